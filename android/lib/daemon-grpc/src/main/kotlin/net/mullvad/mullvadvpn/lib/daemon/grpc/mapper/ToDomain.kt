@@ -664,6 +664,7 @@ internal fun ManagementInterface.CustomProxy.toDomain(): ApiAccessMethod.CustomP
         hasShadowsocks() -> shadowsocks.toDomain()
         hasSocks5Remote() -> socks5Remote.toDomain()
         hasSocks5Local() -> error("Socks5 local not supported")
+        hasHttpConnect() -> error("HTTP CONNECT proxy not supported")
         else -> error("Custom proxy not found")
     }
 
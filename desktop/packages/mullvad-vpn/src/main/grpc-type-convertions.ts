@@ -1,4 +1,5 @@
 import { types as grpcTypes } from 'management-interface';
+import { isIP } from 'net';
 
 import {
   AccessMethod,
@@ -25,6 +26,8 @@ import {
   FeatureIndicator,
   FirewallPolicyError,
   FirewallPolicyErrorType,
+  HttpAuth,
+  HttpProxyTls,
   IBridgeConstraints,
   ICustomList,
   IDevice,
@@ -1120,7 +1123,7 @@ export function convertToCustomProxy(proxy: CustomProxy): grpcTypes.CustomProxy
     }
     case 'socks5-remote': {
       const socks5Remote = new grpcTypes.Socks5Remote();
-      socks5Remote.setIp(proxy.ip);
+      setProxyHost(socks5Remote, proxy.ip);
       socks5Remote.setPort(proxy.port);
       if (proxy.authentication !== undefined) {
         socks5Remote.setAuth(convertToSocksAuth(proxy.authentication));
@@ -1130,18 +1133,43 @@ export function convertToCustomProxy(proxy: CustomProxy): grpcTypes.CustomProxy
     }
     case 'shadowsocks': {
       const shadowsocks = new grpcTypes.Shadowsocks();
-      shadowsocks.setIp(proxy.ip);
+      setProxyHost(shadowsocks, proxy.ip);
       shadowsocks.setPort(proxy.port);
       shadowsocks.setPassword(proxy.password);
       shadowsocks.setCipher(proxy.cipher);
       customProxy.setShadowsocks(shadowsocks);
       break;
     }
+    case 'http-connect': {
+      const httpConnect = new grpcTypes.HttpConnect();
+      setProxyHost(httpConnect, proxy.ip);
+      httpConnect.setPort(proxy.port);
+      if (proxy.authentication !== undefined) {
+        httpConnect.setAuth(convertToHttpAuth(proxy.authentication));
+      }
+      if (proxy.tls !== undefined) {
+        httpConnect.setTls(convertToHttpProxyTls(proxy.tls));
+      }
+      customProxy.setHttpConnect(httpConnect);
+      break;
+    }
   }
 
   return customProxy;
 }
 
+// The `ip` of a custom proxy is its host, which is sent as a hostname unless it is an IP address.
+function setProxyHost(
+  proxy: grpcTypes.Socks5Remote | grpcTypes.Shadowsocks | grpcTypes.HttpConnect,
+  host: string,
+) {
+  if (isIP(host) === 0) {
+    proxy.setHostname(host);
+  } else {
+    proxy.setIp(host);
+  }
+}
+
 function convertToSocksAuth(authentication: SocksAuth): grpcTypes.SocksAuth {
   const auth = new grpcTypes.SocksAuth();
   auth.setUsername(authentication.username);
@@ -1149,6 +1177,21 @@ function convertToSocksAuth(authentication: SocksAuth): grpcTypes.SocksAuth {
   return auth;
 }
 
+function convertToHttpAuth(authentication: HttpAuth): grpcTypes.HttpAuth {
+  const auth = new grpcTypes.HttpAuth();
+  auth.setUsername(authentication.username);
+  auth.setPassword(authentication.password);
+  return auth;
+}
+
+function convertToHttpProxyTls(tls: HttpProxyTls): grpcTypes.HttpProxyTls {
+  const grpcTls = new grpcTypes.HttpProxyTls();
+  if (tls.serverName !== undefined) {
+    grpcTls.setServerName(tls.serverName);
+  }
+  return grpcTls;
+}
+
 function convertFromApiAccessMethodSettings(
   accessMethods: grpcTypes.ApiAccessMethodSettings,
 ): ApiAccessMethodSettings {
@@ -1238,7 +1281,7 @@ function convertFromCustomProxy(proxy: grpcTypes.CustomProxy): CustomProxy {
       const auth = socks5Remote.getAuth();
       return {
         type: 'socks5-remote',
-        ip: socks5Remote.getIp(),
+        ip: convertFromProxyHost(socks5Remote),
         port: socks5Remote.getPort(),
         authentication: auth === undefined ? undefined : convertFromSocksAuth(auth),
       };
@@ -1247,17 +1290,35 @@ function convertFromCustomProxy(proxy: grpcTypes.CustomProxy): CustomProxy {
       const shadowsocks = proxy.getShadowsocks()!;
       return {
         type: 'shadowsocks',
-        ip: shadowsocks.getIp(),
+        ip: convertFromProxyHost(shadowsocks),
         port: shadowsocks.getPort(),
         password: shadowsocks.getPassword(),
         cipher: shadowsocks.getCipher(),
       };
     }
+    case grpcTypes.CustomProxy.ProxyMethodCase.HTTP_CONNECT: {
+      const httpConnect = proxy.getHttpConnect()!;
+      const auth = httpConnect.getAuth();
+      const tls = httpConnect.getTls();
+      return {
+        type: 'http-connect',
+        ip: convertFromProxyHost(httpConnect),
+        port: httpConnect.getPort(),
+        authentication: auth === undefined ? undefined : convertFromHttpAuth(auth),
+        tls: tls === undefined ? undefined : convertFromHttpProxyTls(tls),
+      };
+    }
     case grpcTypes.CustomProxy.ProxyMethodCase.PROXY_METHOD_NOT_SET:
       throw new Error('Custom method not set, which should always be set');
   }
 }
 
+function convertFromProxyHost(
+  proxy: grpcTypes.Socks5Remote | grpcTypes.Shadowsocks | grpcTypes.HttpConnect,
+): string {
+  return proxy.hasHostname() ? proxy.getHostname() : proxy.getIp();
+}
+
 function convertFromSocksAuth(auth: grpcTypes.SocksAuth): SocksAuth {
   return {
     username: auth.getUsername(),
@@ -1265,6 +1326,19 @@ function convertFromSocksAuth(auth: grpcTypes.SocksAuth): SocksAuth {
   };
 }
 
+function convertFromHttpAuth(auth: grpcTypes.HttpAuth): HttpAuth {
+  return {
+    username: auth.getUsername(),
+    password: auth.getPassword(),
+  };
+}
+
+function convertFromHttpProxyTls(tls: grpcTypes.HttpProxyTls): HttpProxyTls {
+  return {
+    serverName: tls.hasServerName() ? tls.getServerName() : undefined,
+  };
+}
+
 export function ensureExists<T>(value: T | undefined, errorMessage: string): T {
   if (value) {
     return value;
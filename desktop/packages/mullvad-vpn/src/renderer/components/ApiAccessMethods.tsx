@@ -338,5 +338,14 @@ function cloneMethod<T extends AccessMethodSetting>(method: T): T {
     clonedMethod.authentication = { ...method.authentication };
   }
 
+  if (method.type === 'http-connect' && clonedMethod.type === 'http-connect') {
+    if (method.authentication !== undefined) {
+      clonedMethod.authentication = { ...method.authentication };
+    }
+    if (method.tls !== undefined) {
+      clonedMethod.tls = { ...method.tls };
+    }
+  }
+
   return clonedMethod;
 }
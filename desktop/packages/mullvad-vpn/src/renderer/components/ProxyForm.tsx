@@ -273,6 +273,9 @@ function EditShadowsocks(props: EditProxyProps<ShadowsocksCustomProxy>) {
         { value: 'xchacha20-ietf-poly1305', label: 'xchacha20-ietf-poly1305' },
         { value: 'aes-128-pmac-siv', label: 'aes-128-pmac-siv' },
         { value: 'aes-256-pmac-siv', label: 'aes-256-pmac-siv' },
+        { value: '2022-blake3-aes-128-gcm', label: '2022-blake3-aes-128-gcm' },
+        { value: '2022-blake3-aes-256-gcm', label: '2022-blake3-aes-256-gcm' },
+        { value: '2022-blake3-chacha20-poly1305', label: '2022-blake3-chacha20-poly1305' },
       ].sort((a, b) => a.label.localeCompare(b.label)),
     [],
   );
@@ -512,6 +512,7 @@ export type Socks5LocalCustomProxy = {
 
 export type Socks5RemoteCustomProxy = {
   type: 'socks5-remote';
+  // IP address or hostname
   ip: string;
   port: number;
   authentication?: SocksAuth;
@@ -519,13 +520,36 @@ export type Socks5RemoteCustomProxy = {
 
 export type ShadowsocksCustomProxy = {
   type: 'shadowsocks';
+  // IP address or hostname
   ip: string;
   port: number;
   password: string;
   cipher: string;
 };
 
-export type CustomProxy = Socks5LocalCustomProxy | Socks5RemoteCustomProxy | ShadowsocksCustomProxy;
+export interface HttpAuth {
+  username: string;
+  password: string;
+}
+
+export interface HttpProxyTls {
+  serverName?: string;
+}
+
+export type HttpConnectCustomProxy = {
+  type: 'http-connect';
+  // IP address or hostname
+  ip: string;
+  port: number;
+  authentication?: HttpAuth;
+  tls?: HttpProxyTls;
+};
+
+export type CustomProxy =
+  | Socks5LocalCustomProxy
+  | Socks5RemoteCustomProxy
+  | ShadowsocksCustomProxy
+  | HttpConnectCustomProxy;
 export type NamedCustomProxy = CustomProxy & { name: string };
 
 export type DirectMethod = { type: 'direct' };
@@ -213,6 +213,8 @@ function newConfig() {
           '=/usr/lib/systemd/system/mullvad-daemon.service',
         distAssets('linux/mullvad-early-boot-blocking.service') +
           '=/usr/lib/systemd/system/mullvad-early-boot-blocking.service',
+        distAssets('linux/net.mullvad.VPN.conf') + '=/usr/share/dbus-1/system.d/',
+        distAssets('linux/net.mullvad.vpn.policy') + '=/usr/share/polkit-1/actions/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-daemon')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-exclude')) + '=/usr/bin/',
@@ -246,6 +248,8 @@ function newConfig() {
           '=/usr/lib/systemd/system/mullvad-daemon.service',
         distAssets('linux/mullvad-early-boot-blocking.service') +
           '=/usr/lib/systemd/system/mullvad-early-boot-blocking.service',
+        distAssets('linux/net.mullvad.VPN.conf') + '=/usr/share/dbus-1/system.d/',
+        distAssets('linux/net.mullvad.vpn.policy') + '=/usr/share/polkit-1/actions/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-daemon')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-exclude')) + '=/usr/bin/',
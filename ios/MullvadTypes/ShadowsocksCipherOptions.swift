@@ -46,6 +46,11 @@ public enum CipherIdentifiers: String, CaseIterable, CustomStringConvertible, Co
     case PMAC_SIV_AES128 = "aes-128-pmac-siv"
     case GPMAC_SIV_AES256 = "aes-256-pmac-siv"
 
+    // AEAD 2022 ciphers.
+    case BLAKE3_GCM_AES128_2022 = "2022-blake3-aes-128-gcm"
+    case BLAKE3_GCM_AES256_2022 = "2022-blake3-aes-256-gcm"
+    case BLAKE3_CHACHA20_POLY1305_2022 = "2022-blake3-chacha20-poly1305"
+
     public var description: String {
         rawValue
     }
@@ -185,18 +185,21 @@ impl AccessModeSelectorHandle {
 
 pub struct AccessModeConnectionModeProvider {
     initial: ApiConnectionMode,
+    /// Name of the access method that the current connection mode was resolved from.
+    access_method: String,
     handle: AccessModeSelectorHandle,
-    change_rx: mpsc::UnboundedReceiver<ApiConnectionMode>,
+    change_rx: mpsc::UnboundedReceiver<ResolvedConnectionMode>,
 }
 
 impl AccessModeConnectionModeProvider {
     pub fn new(
         handle: AccessModeSelectorHandle,
-        initial_connection_mode: ApiConnectionMode,
-        change_rx: mpsc::UnboundedReceiver<ApiConnectionMode>,
+        initial: ResolvedConnectionMode,
+        change_rx: mpsc::UnboundedReceiver<ResolvedConnectionMode>,
     ) -> Result<Self> {
         Ok(Self {
-            initial: initial_connection_mode,
+            initial: initial.connection_mode,
+            access_method: initial.setting.name,
             handle,
             change_rx,
         })
@@ -209,7 +212,11 @@ impl ConnectionModeProvider for AccessModeConnectionModeProvider {
     }
 
     fn receive(&mut self) -> impl std::future::Future<Output = Option<ApiConnectionMode>> + Send {
-        self.change_rx.next()
+        async move {
+            let resolved = self.change_rx.next().await?;
+            self.access_method = resolved.setting.name;
+            Some(resolved.connection_mode)
+        }
     }
 
     fn rotate(&self) -> impl std::future::Future<Output = ()> + Send {
@@ -218,6 +225,10 @@ impl ConnectionModeProvider for AccessModeConnectionModeProvider {
             handle.rotate().await.ok();
         }
     }
+
+    fn access_method(&self) -> Option<String> {
+        Some(self.access_method.clone())
+    }
 }
 
 /// A small actor which takes care of handling the logic around rotating
@@ -236,7 +247,7 @@ pub struct AccessModeSelector<B: AccessMethodResolver> {
     access_method_settings: Settings,
     #[cfg(not(target_os = "ios"))]
     access_method_event_sender: mpsc::UnboundedSender<(AccessMethodEvent, oneshot::Sender<()>)>,
-    connection_mode_provider_sender: mpsc::UnboundedSender<ApiConnectionMode>,
+    connection_mode_provider_sender: mpsc::UnboundedSender<ResolvedConnectionMode>,
     current: ResolvedConnectionMode,
     /// `index` is used to keep track of the [`AccessMethodSetting`] to use.
     index: usize,
@@ -269,7 +280,7 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
 
         let (change_tx, change_rx) = mpsc::unbounded();
 
-        let api_connection_mode = initial_connection_mode.connection_mode.clone();
+        let initial_provider_mode = initial_connection_mode.clone();
 
         let selector = AccessModeSelector {
             #[cfg(feature = "api-override")]
@@ -288,8 +299,11 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
 
         let handle = AccessModeSelectorHandle { cmd_tx };
 
-        let connection_mode_provider =
-            AccessModeConnectionModeProvider::new(handle.clone(), api_connection_mode, change_rx)?;
+        let connection_mode_provider = AccessModeConnectionModeProvider::new(
+            handle.clone(),
+            initial_provider_mode,
+            change_rx,
+        )?;
 
         Ok((handle, connection_mode_provider))
     }
@@ -391,7 +405,7 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
         // Notify REST client
         let _ = self
             .connection_mode_provider_sender
-            .unbounded_send(resolved.connection_mode.clone());
+            .unbounded_send(resolved.clone());
 
         self.current = resolved;
 
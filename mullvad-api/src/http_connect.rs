@@ -0,0 +1,189 @@
+//! Client for HTTP proxies that open tunnels using the `CONNECT` method, optionally over TLS.
+use base64::{prelude::BASE64_STANDARD, Engine};
+use std::{
+    io::{self, ErrorKind},
+    net::SocketAddr,
+    sync::{Arc, LazyLock},
+};
+use talpid_types::net::proxy::HttpAuth;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{self, pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+
+/// Maximum size of the response header that is accepted from the proxy.
+const MAX_RESPONSE_HEADER_SIZE: usize = 8 * 1024;
+
+pub trait ProxyStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ProxyStream for T {}
+
+/// Open a tunnel to `target` through the HTTP proxy that `stream` is connected to. If `tls` is
+/// set, the connection to the proxy is first wrapped in TLS.
+pub async fn connect_via_proxy<S>(
+    stream: S,
+    target: &SocketAddr,
+    auth: Option<&HttpAuth>,
+    tls: Option<ServerName<'static>>,
+) -> io::Result<Box<dyn ProxyStream>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let stream: Box<dyn ProxyStream> = match tls {
+        Some(server_name) => Box::new(connect_tls(stream, server_name).await?),
+        None => Box::new(stream),
+    };
+    connect(stream, target, auth).await
+}
+
+/// Ask the proxy at the other end of `stream` to open a tunnel to `target`. The returned stream is
+/// connected to `target` if this succeeds.
+async fn connect<S>(mut stream: S, target: &SocketAddr, auth: Option<&HttpAuth>) -> io::Result<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream
+        .write_all(connect_request(target, auth).as_bytes())
+        .await?;
+    stream.flush().await?;
+
+    let header = read_response_header(&mut stream).await?;
+    let status = parse_status(&header)?;
+    if !(200..300).contains(&status) {
+        return Err(io::Error::new(
+            ErrorKind::ConnectionRefused,
+            format!("HTTP proxy refused to connect, status {status}"),
+        ));
+    }
+    Ok(stream)
+}
+
+/// Connect to the proxy over TLS, verifying its certificate against `server_name`. Unlike the
+/// connection to the API, this accepts any certificate signed by a publicly trusted CA.
+async fn connect_tls<S>(stream: S, server_name: ServerName<'static>) -> io::Result<TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    static TLS_CONFIG: LazyLock<Arc<ClientConfig>> = LazyLock::new(|| {
+        let root_store = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config =
+            ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .expect("ring crypto-provider should support the default protocol versions")
+                .with_root_certificates(root_store)
+                .with_no_client_auth();
+        Arc::new(config)
+    });
+
+    TlsConnector::from(TLS_CONFIG.clone())
+        .connect(server_name, stream)
+        .await
+}
+
+fn connect_request(target: &SocketAddr, auth: Option<&HttpAuth>) -> String {
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some(auth) = auth {
+        let credentials =
+            BASE64_STANDARD.encode(format!("{}:{}", auth.username(), auth.password()));
+        request.push_str(&format!("Proxy-Authorization: Basic {credentials}\r\n"));
+    }
+    request.push_str("\r\n");
+    request
+}
+
+/// Read the response header, up until and including the empty line that ends it. This reads one
+/// byte at a time, so that nothing sent after the header is consumed.
+async fn read_response_header<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<Vec<u8>> {
+    let mut header = Vec::new();
+    while !header.ends_with(b"\r\n\r\n") {
+        if header.len() >= MAX_RESPONSE_HEADER_SIZE {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "HTTP proxy response header is too large",
+            ));
+        }
+        header.push(stream.read_u8().await?);
+    }
+    Ok(header)
+}
+
+/// Parse the status code from a status line such as `HTTP/1.1 200 Connection established`.
+fn parse_status(header: &[u8]) -> io::Result<u16> {
+    let invalid = || io::Error::new(ErrorKind::InvalidData, "Invalid HTTP proxy response");
+
+    let status_line = header.split(|&b| b == b'\n').next().ok_or_else(invalid)?;
+    let status_line = std::str::from_utf8(status_line).map_err(|_| invalid())?;
+    let mut parts = status_line.split_whitespace();
+    if !parts
+        .next()
+        .is_some_and(|version| version.starts_with("HTTP/1."))
+    {
+        return Err(invalid());
+    }
+    parts
+        .next()
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connect() {
+        let target: SocketAddr = "192.0.2.1:443".parse().unwrap();
+        let auth = HttpAuth::new("user".to_owned(), "hunter2".to_owned()).unwrap();
+        let (client, mut proxy) = tokio::io::duplex(1024);
+
+        let proxy = tokio::spawn(async move {
+            let request = read_response_header(&mut proxy).await.unwrap();
+            proxy
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\nhello")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let mut stream = connect(client, &target, Some(&auth)).await.unwrap();
+        let request = proxy.await.unwrap();
+        assert_eq!(
+            request,
+            "CONNECT 192.0.2.1:443 HTTP/1.1\r\nHost: 192.0.2.1:443\r\n\
+             Proxy-Authorization: Basic dXNlcjpodW50ZXIy\r\n\r\n"
+        );
+
+        // Data following the response header belongs to the tunnel
+        let mut data = [0u8; 5];
+        stream.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_connect_refused() {
+        let target: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        let (client, mut proxy) = tokio::io::duplex(1024);
+
+        tokio::spawn(async move {
+            let _ = read_response_header(&mut proxy).await;
+            let _ = proxy
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await;
+        });
+
+        let error = connect(client, &target, None).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ConnectionRefused);
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status(b"HTTP/1.0 200 OK\r\n\r\n").unwrap(), 200);
+        assert_eq!(parse_status(b"HTTP/1.1 403\r\n\r\n").unwrap(), 403);
+        assert!(parse_status(b"SSH-2.0-OpenSSH\r\n\r\n").is_err());
+        assert!(parse_status(b"HTTP/1.1 abc\r\n\r\n").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
     abortable_stream::{AbortableStream, AbortableStreamHandle},
+    http_connect,
     proxy::{ApiConnection, ApiConnectionMode, ProxyConfig},
     tls_stream::TlsStream,
     DnsResolver,
@@ -39,6 +40,7 @@ use tokio::{
     net::{TcpSocket, TcpStream},
     time::timeout,
 };
+use tokio_rustls::rustls::pki_types::ServerName;
 use tower::Service;
 
 #[cfg(any(feature = "api-override", test))]
@@ -78,6 +80,8 @@ enum InnerConnectionMode {
     Shadowsocks(ShadowsocksConfig),
     /// Connect to the destination via a Socks proxy.
     Socks5(SocksConfig),
+    /// Connect to the destination via an HTTP proxy, using the `CONNECT` method.
+    HttpConnect(HttpConnectConfig),
     /// Connect to the destination via Mullvad Encrypted DNS proxy.
     /// See [`mullvad-encrypted-dns-proxy`] for how the proxy works.
     EncryptedDnsProxy(EncryptedDNSConfig),
@@ -163,6 +167,27 @@ impl InnerConnectionMode {
                 )
                 .await
             }
+            // Set up a tunnel through an HTTP proxy.
+            InnerConnectionMode::HttpConnect(http) => {
+                let first_hop = http.peer;
+                let make_proxy_stream = |tcp_stream| async {
+                    http_connect::connect_via_proxy(tcp_stream, addr, http.auth.as_ref(), http.tls)
+                        .await
+                        .map_err(|error| {
+                            io::Error::new(error.kind(), format!("HTTP proxy error: {error}"))
+                        })
+                };
+                Self::connect_proxied(
+                    first_hop,
+                    hostname,
+                    make_proxy_stream,
+                    #[cfg(target_os = "android")]
+                    socket_bypass_tx,
+                    #[cfg(any(feature = "api-override", test))]
+                    disable_tls,
+                )
+                .await
+            }
             InnerConnectionMode::EncryptedDnsProxy(proxy_config) => {
                 let first_hop = SocketAddr::V4(proxy_config.addr);
                 let make_proxy_stream = |tcp_stream| async {
@@ -251,10 +276,22 @@ struct SocksConfig {
     authentication: Option<proxy::SocksAuth>,
 }
 
+#[derive(Clone)]
+struct HttpConnectConfig {
+    peer: SocketAddr,
+    auth: Option<proxy::HttpAuth>,
+    /// Server name to verify the proxy certificate against, if TLS is used.
+    tls: Option<ServerName<'static>>,
+}
+
 #[derive(thiserror::Error, Debug)]
 enum ProxyConfigError {
     #[error("Unrecognized cipher selected: {0}")]
     InvalidCipher(String),
+    #[error("Invalid TLS server name for HTTP proxy: {0}")]
+    InvalidServerName(String),
+    #[error("Hostname of proxy has not been resolved: {0}")]
+    UnresolvedHostname(String),
 }
 
 impl TryFrom<ApiConnectionMode> for InnerConnectionMode {
@@ -268,7 +305,7 @@ impl TryFrom<ApiConnectionMode> for InnerConnectionMode {
                 ProxyConfig::Shadowsocks(config) => {
                     InnerConnectionMode::Shadowsocks(ShadowsocksConfig {
                         params: ParsedShadowsocksConfig {
-                            peer: config.endpoint,
+                            peer: resolved_peer(&config.endpoint)?,
                             password: config.password,
                             cipher: CipherKind::from_str(&config.cipher)
                                 .map_err(|_| ProxyConfigError::InvalidCipher(config.cipher))?,
@@ -281,9 +318,25 @@ impl TryFrom<ApiConnectionMode> for InnerConnectionMode {
                     authentication: None,
                 }),
                 ProxyConfig::Socks5Remote(config) => InnerConnectionMode::Socks5(SocksConfig {
-                    peer: config.endpoint,
+                    peer: resolved_peer(&config.endpoint)?,
                     authentication: config.auth,
                 }),
+                ProxyConfig::HttpConnect(config) => {
+                    let peer = resolved_peer(&config.endpoint)?;
+                    let tls = config
+                        .tls
+                        .map(|tls| match tls.server_name {
+                            Some(name) => ServerName::try_from(name.clone())
+                                .map_err(|_| ProxyConfigError::InvalidServerName(name)),
+                            None => Ok(ServerName::from(peer.ip())),
+                        })
+                        .transpose()?;
+                    InnerConnectionMode::HttpConnect(HttpConnectConfig {
+                        peer,
+                        auth: config.auth,
+                        tls,
+                    })
+                }
                 ProxyConfig::EncryptedDnsProxy(config) => {
                     InnerConnectionMode::EncryptedDnsProxy(config)
                 }
@@ -292,6 +345,13 @@ impl TryFrom<ApiConnectionMode> for InnerConnectionMode {
     }
 }
 
+/// Proxies are resolved by the access method resolver before they get here.
+fn resolved_peer(address: &proxy::ProxyAddress) -> Result<SocketAddr, ProxyConfigError> {
+    address
+        .socket_addr()
+        .ok_or_else(|| ProxyConfigError::UnresolvedHostname(address.to_string()))
+}
+
 /// A Connector for the `https` scheme.
 #[derive(Clone)]
 pub struct HttpsConnectorWithSni {
@@ -505,3 +565,109 @@ impl Service<Uri> for HttpsConnectorWithSni {
         Box::pin(fut)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use shadowsocks::relay::{socks5::Address, tcprelay::ProxyListener};
+    use std::net::Ipv4Addr;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Start an echo server and a Shadowsocks server in front of it, both on loopback.
+    /// Returns the addresses of the echo server and the Shadowsocks server.
+    async fn start_shadowsocks_server(cipher: &str, password: &str) -> (SocketAddr, SocketAddr) {
+        let echo_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let echo_addr = echo_listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = echo_listener.accept().await {
+                tokio::spawn(async move {
+                    let (mut reader, mut writer) = stream.split();
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+
+        let server_config = ServerConfig::new(
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            password,
+            CipherKind::from_str(cipher).unwrap(),
+        );
+        let proxy_listener =
+            ProxyListener::bind(SsContext::new_shared(ServerType::Server), &server_config)
+                .await
+                .unwrap();
+        let proxy_addr = proxy_listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = proxy_listener.accept().await {
+                tokio::spawn(async move {
+                    let Ok(Address::SocketAddress(target)) = stream.handshake().await else {
+                        return;
+                    };
+                    let mut target = TcpStream::connect(target).await.unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut stream, &mut target).await;
+                });
+            }
+        });
+
+        (echo_addr, proxy_addr)
+    }
+
+    /// Connect to an echo server through a Shadowsocks server on loopback, and make sure that
+    /// data makes it there and back.
+    async fn test_shadowsocks_roundtrip(cipher: &str, password: &str) {
+        let (echo_addr, proxy_addr) = start_shadowsocks_server(cipher, password).await;
+
+        let settings =
+            proxy::Shadowsocks::new(proxy_addr, cipher.to_owned(), password.to_owned()).unwrap();
+        let connection_mode = InnerConnectionMode::try_from(ApiConnectionMode::Proxied(
+            ProxyConfig::from(proxy::CustomProxy::Shadowsocks(settings)),
+        ))
+        .unwrap();
+        let mut connection = connection_mode
+            .connect(
+                "localhost",
+                &echo_addr,
+                #[cfg(target_os = "android")]
+                None,
+                true,
+            )
+            .await
+            .unwrap();
+
+        connection.write_all(b"hello").await.unwrap();
+        let mut response = [0u8; 5];
+        connection.read_exact(&mut response).await.unwrap();
+        assert_eq!(&response, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_shadowsocks_aead() {
+        test_shadowsocks_roundtrip("aes-256-gcm", "hunter2").await;
+    }
+
+    #[tokio::test]
+    async fn test_shadowsocks_2022_aes_128_gcm() {
+        test_shadowsocks_roundtrip("2022-blake3-aes-128-gcm", "AAECAwQFBgcICQoLDA0ODw==").await;
+    }
+
+    #[tokio::test]
+    async fn test_shadowsocks_2022_aes_256_gcm() {
+        test_shadowsocks_roundtrip(
+            "2022-blake3-aes-256-gcm",
+            "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_shadowsocks_2022_chacha20_poly1305() {
+        test_shadowsocks_roundtrip(
+            "2022-blake3-chacha20-poly1305",
+            "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=",
+        )
+        .await;
+    }
+}
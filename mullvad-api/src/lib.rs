@@ -25,6 +25,7 @@ pub mod version;
 
 mod abortable_stream;
 pub mod access_mode;
+mod http_connect;
 mod https_client_with_sni;
 pub mod proxy;
 mod tls_stream;
@@ -329,11 +330,13 @@ impl DnsResolver for NullDnsResolver {
 }
 
 /// A type that helps with the creation of API connections.
+#[derive(Clone)]
 pub struct Runtime {
     handle: tokio::runtime::Handle,
     address_cache: AddressCache,
     api_availability: availability::ApiAvailability,
     endpoint: ApiEndpoint,
+    request_stats: rest::RequestStats,
     #[cfg(target_os = "android")]
     socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
 }
@@ -365,6 +368,7 @@ impl Runtime {
             address_cache: AddressCache::new(endpoint, None),
             api_availability: ApiAvailability::default(),
             endpoint: endpoint.clone(),
+            request_stats: rest::RequestStats::default(),
             #[cfg(target_os = "android")]
             socket_bypass_tx,
         }
@@ -426,6 +430,7 @@ impl Runtime {
             address_cache,
             api_availability,
             endpoint: endpoint.clone(),
+            request_stats: rest::RequestStats::default(),
             #[cfg(target_os = "android")]
             socket_bypass_tx,
         })
@@ -476,6 +481,7 @@ impl Runtime {
             self.api_availability.clone(),
             connection_mode_provider,
             dns_resolver,
+            self.request_stats.clone(),
             #[cfg(target_os = "android")]
             socket_bypass_tx,
             #[cfg(any(feature = "api-override", test))]
@@ -483,6 +489,11 @@ impl Runtime {
         )
     }
 
+    /// Returns the outcomes of the API requests made through this runtime.
+    pub fn request_stats(&self) -> &rest::RequestStats {
+        &self.request_stats
+    }
+
     pub fn handle(&self) -> &tokio::runtime::Handle {
         &self.handle
     }
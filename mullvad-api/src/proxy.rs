@@ -27,6 +27,12 @@ pub trait ConnectionModeProvider: Send {
 
     /// Receive changes to the connection mode, announced by the provider
     fn receive(&mut self) -> impl std::future::Future<Output = Option<ApiConnectionMode>> + Send;
+
+    /// Name of the access method that the current connection mode belongs to, if any. Outcomes
+    /// of requests are only counted in [`crate::rest::RequestStats`] if this is set.
+    fn access_method(&self) -> Option<String> {
+        None
+    }
 }
 
 pub struct StaticConnectionModeProvider {
@@ -66,23 +72,28 @@ pub enum ProxyConfig {
     Shadowsocks(proxy::Shadowsocks),
     Socks5Local(proxy::Socks5Local),
     Socks5Remote(proxy::Socks5Remote),
+    HttpConnect(proxy::HttpConnect),
     EncryptedDnsProxy(mullvad_encrypted_dns_proxy::config::ProxyConfig),
 }
 
 impl ProxyConfig {
-    /// Returns the remote endpoint describing how to reach the proxy.
-    fn get_endpoint(&self) -> Endpoint {
+    /// Returns the remote endpoint describing how to reach the proxy, or `None` if the hostname
+    /// of the proxy has not been resolved.
+    fn get_endpoint(&self) -> Option<Endpoint> {
+        let remote = |address: &proxy::ProxyAddress| {
+            Some(Endpoint::from_socket_address(
+                address.socket_addr()?,
+                TransportProtocol::Tcp,
+            ))
+        };
         match self {
-            ProxyConfig::Shadowsocks(shadowsocks) => {
-                Endpoint::from_socket_address(shadowsocks.endpoint, TransportProtocol::Tcp)
-            }
-            ProxyConfig::Socks5Local(local) => local.remote_endpoint,
-            ProxyConfig::Socks5Remote(remote) => {
-                Endpoint::from_socket_address(remote.endpoint, TransportProtocol::Tcp)
-            }
+            ProxyConfig::Shadowsocks(shadowsocks) => remote(&shadowsocks.endpoint),
+            ProxyConfig::Socks5Local(local) => Some(local.remote_endpoint),
+            ProxyConfig::Socks5Remote(socks) => remote(&socks.endpoint),
+            ProxyConfig::HttpConnect(http) => remote(&http.endpoint),
             ProxyConfig::EncryptedDnsProxy(proxy) => {
                 let addr = SocketAddr::V4(proxy.addr);
-                Endpoint::from_socket_address(addr, TransportProtocol::Tcp)
+                Some(Endpoint::from_socket_address(addr, TransportProtocol::Tcp))
             }
         }
     }
@@ -94,6 +105,7 @@ impl From<proxy::CustomProxy> for ProxyConfig {
             proxy::CustomProxy::Shadowsocks(shadowsocks) => ProxyConfig::Shadowsocks(shadowsocks),
             proxy::CustomProxy::Socks5Local(socks) => ProxyConfig::Socks5Local(socks),
             proxy::CustomProxy::Socks5Remote(socks) => ProxyConfig::Socks5Remote(socks),
+            proxy::CustomProxy::HttpConnect(http) => ProxyConfig::HttpConnect(http),
         }
     }
 }
@@ -165,11 +177,11 @@ impl ApiConnectionMode {
     }
 
     /// Returns the remote endpoint required to reach the API, or `None` for
-    /// `ApiConnectionMode::Direct`.
+    /// `ApiConnectionMode::Direct` and proxies whose hostname has not been resolved.
     pub fn get_endpoint(&self) -> Option<Endpoint> {
         match self {
             ApiConnectionMode::Direct => None,
-            ApiConnectionMode::Proxied(proxy_config) => Some(proxy_config.get_endpoint()),
+            ApiConnectionMode::Proxied(proxy_config) => proxy_config.get_endpoint(),
         }
     }
 
@@ -21,10 +21,11 @@ use hyper_util::client::legacy::connect::Connect;
 use mullvad_types::account::AccountNumber;
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
     convert::Infallible,
     error::Error as StdError,
     str::FromStr,
-    sync::{Arc, Weak},
+    sync::{Arc, Mutex, Weak},
     time::Duration,
 };
 use talpid_types::ErrorExt;
@@ -139,6 +140,41 @@ impl Error {
     }
 }
 
+/// Number of finished API requests per access method. Shared by all request services created by
+/// the same [`crate::Runtime`].
+#[derive(Debug, Clone, Default)]
+pub struct RequestStats(Arc<Mutex<BTreeMap<String, RequestCounts>>>);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestCounts {
+    /// Requests that received a response, regardless of its status code.
+    pub succeeded: u64,
+    /// Requests that did not receive a response, for example due to a network error.
+    pub failed: u64,
+}
+
+impl RequestStats {
+    fn record<T>(&self, access_method: String, result: &Result<T>) {
+        let succeeded = match result {
+            Ok(_) => true,
+            Err(error) if error.is_aborted() => return,
+            Err(_) => false,
+        };
+        let mut stats = self.0.lock().unwrap();
+        let counts = stats.entry(access_method).or_default();
+        if succeeded {
+            counts.succeeded += 1;
+        } else {
+            counts.failed += 1;
+        }
+    }
+
+    /// Returns the request counts, keyed by the name of the access method used.
+    pub fn counts(&self) -> BTreeMap<String, RequestCounts> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
 // TODO: Look into an alternative to using the legacy hyper client `DES-1288`
 type RequestClient =
     hyper_util::client::legacy::Client<HttpsConnectorWithSni, BoxBody<Bytes, Error>>;
@@ -153,6 +189,7 @@ pub(crate) struct RequestService<T: ConnectionModeProvider> {
     connection_mode_provider: T,
     connection_mode_generation: usize,
     api_availability: ApiAvailability,
+    stats: RequestStats,
 }
 
 impl<T: ConnectionModeProvider + 'static> RequestService<T> {
@@ -161,6 +198,7 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
         api_availability: ApiAvailability,
         connection_mode_provider: T,
         dns_resolver: Arc<dyn DnsResolver>,
+        stats: RequestStats,
         #[cfg(target_os = "android")] socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
         #[cfg(any(feature = "api-override", test))] disable_tls: bool,
     ) -> RequestServiceHandle {
@@ -189,6 +227,7 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
             connection_mode_provider,
             connection_mode_generation: 0,
             api_availability,
+            stats,
         };
         let handle = RequestServiceHandle { tx: command_tx };
         tokio::spawn(service.into_future());
@@ -247,10 +286,16 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
             .into_future(self.client.clone(), api_availability.clone());
 
         let connection_mode_generation = self.connection_mode_generation;
+        let access_method = self.connection_mode_provider.access_method();
+        let stats = self.stats.clone();
 
         tokio::spawn(async move {
             let response = request_future.await.map_err(|error| error.map_aborted());
 
+            if let Some(access_method) = access_method {
+                stats.record(access_method, &response);
+            }
+
             // Switch API endpoint if the request failed due to a network error
             if let Err(err) = &response {
                 if err.is_network_error() && !api_availability.is_offline() {
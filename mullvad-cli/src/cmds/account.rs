@@ -5,6 +5,8 @@ use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{account::AccountNumber, device::DeviceState};
 use std::io::{self, Write};
 
+use crate::json;
+
 const NOT_LOGGED_IN_MESSAGE: &str = "Not logged in on any account";
 const REVOKED_MESSAGE: &str = "The current device has been revoked";
 
@@ -104,6 +106,18 @@ impl Account {
 
         let state = rpc.get_device().await?;
 
+        if json::is_enabled() {
+            let account = match state {
+                DeviceState::LoggedIn(device) => {
+                    let data = rpc.get_account_data(device.account_number.clone()).await?;
+                    json::Account::logged_in(&device, &data)
+                }
+                DeviceState::LoggedOut => json::Account::logged_out(),
+                DeviceState::Revoked => json::Account::revoked(rpc.get_account_history().await?),
+            };
+            return json::print(&account);
+        }
+
         match state {
             DeviceState::LoggedIn(device) => {
                 println!("{:<20}{}", "Mullvad account:", device.account_number);
@@ -146,9 +160,14 @@ impl Account {
     ) -> Result<()> {
         let account_number = account_else_current(rpc, account).await?;
         let mut device_list = rpc.list_devices(account_number).await?;
+        device_list.sort_unstable_by_key(|dev| dev.created.timestamp());
+
+        if json::is_enabled() {
+            let devices: Vec<_> = device_list.iter().map(json::Device::from).collect();
+            return json::print(&devices);
+        }
 
         println!("Devices on the account:");
-        device_list.sort_unstable_by_key(|dev| dev.created.timestamp());
         for device in device_list {
             if verbose {
                 println!();
@@ -5,11 +5,14 @@ use talpid_types::net::proxy::CustomProxy;
 
 use clap::{Args, Subcommand};
 
-use super::proxies::{ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd};
+use super::proxies::{
+    HttpConnectAdd, ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd,
+};
+use crate::{json, print_option};
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum ApiAccess {
-    /// Display the current API access method.
+    /// Display the current API access method and the endpoint it connects to.
     Get,
     /// Add a custom API access method
     #[clap(subcommand)]
@@ -71,7 +74,15 @@ impl ApiAccess {
     /// Show all API access methods.
     async fn list() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        for (index, api_access_method) in rpc.get_api_access_methods().await?.iter().enumerate() {
+        let access_methods = rpc.get_api_access_methods().await?;
+        if json::is_enabled() {
+            let access_methods: Vec<_> = access_methods
+                .iter()
+                .map(json::AccessMethod::from)
+                .collect();
+            return json::print(&access_methods);
+        }
+        for (index, api_access_method) in access_methods.iter().enumerate() {
             println!(
                 "{}. {}",
                 index + 1,
@@ -102,7 +113,6 @@ impl ApiAccess {
 
     /// Edit the data of an API access method.
     async fn edit(cmd: EditCustomCommands) -> Result<()> {
-        use talpid_types::net::proxy::{Shadowsocks, Socks5Local, Socks5Remote, SocksAuth};
         let mut rpc = MullvadProxyClient::new().await?;
         let mut api_access_method = Self::get_access_method(&mut rpc, &cmd.item).await?;
 
@@ -111,47 +121,16 @@ impl ApiAccess {
             None => return Err(anyhow!("Can not edit built-in access method")),
             Some(x) => match x.clone() {
                 CustomProxy::Shadowsocks(shadowsocks) => {
-                    let ip = cmd.params.ip.unwrap_or(shadowsocks.endpoint.ip());
-                    let port = cmd.params.port.unwrap_or(shadowsocks.endpoint.port());
-                    let password = cmd.params.password.unwrap_or(shadowsocks.password);
-                    let cipher = cmd.params.cipher.unwrap_or(shadowsocks.cipher);
-                    AccessMethod::from(Shadowsocks::new((ip, port), cipher, password))
+                    AccessMethod::from(cmd.params.merge_shadowsocks(&shadowsocks)?)
                 }
                 CustomProxy::Socks5Local(local) => {
-                    let remote_ip = cmd.params.ip.unwrap_or(local.remote_endpoint.address.ip());
-                    let remote_port = cmd
-                        .params
-                        .port
-                        .unwrap_or(local.remote_endpoint.address.port());
-                    let local_port = cmd.params.local_port.unwrap_or(local.local_port);
-                    let remote_peer_transport_protocol = cmd
-                        .params
-                        .transport_protocol
-                        .unwrap_or(local.remote_endpoint.protocol);
-                    AccessMethod::from(Socks5Local::new_with_transport_protocol(
-                        (remote_ip, remote_port),
-                        local_port,
-                        remote_peer_transport_protocol,
-                    ))
+                    AccessMethod::from(cmd.params.merge_socks_local(&local)?)
                 }
                 CustomProxy::Socks5Remote(remote) => {
-                    let ip = cmd.params.ip.unwrap_or(remote.endpoint.ip());
-                    let port = cmd.params.port.unwrap_or(remote.endpoint.port());
-                    AccessMethod::from(match remote.auth {
-                        None => Socks5Remote::new((ip, port)),
-                        Some(credentials) => {
-                            let username = cmd
-                                .params
-                                .username
-                                .unwrap_or(credentials.username().to_string());
-                            let password = cmd
-                                .params
-                                .password
-                                .unwrap_or(credentials.password().to_string());
-                            let auth = SocksAuth::new(username, password)?;
-                            Socks5Remote::new_with_authentication((ip, port), auth)
-                        }
-                    })
+                    AccessMethod::from(cmd.params.merge_socks_remote(&remote)?)
+                }
+                CustomProxy::HttpConnect(http) => {
+                    AccessMethod::from(cmd.params.merge_http_connect(&http)?)
                 }
             },
         };
@@ -232,9 +211,14 @@ impl ApiAccess {
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let current = rpc.get_current_api_access_method().await?;
+        if json::is_enabled() {
+            return json::print(&json::AccessMethod::from(&current));
+        }
+        let endpoint = rpc.get_current_api_endpoint().await?;
         let mut access_method_formatter = pp::ApiAccessMethodFormatter::new(&current);
         access_method_formatter.settings.write_enabled = false;
         println!("{}", access_method_formatter);
+        print_option!("Endpoint", endpoint);
         Ok(())
     }
 
@@ -266,6 +250,17 @@ pub enum AddCustomCommands {
         #[clap(flatten)]
         add: ShadowsocksAdd,
     },
+    /// Configure an HTTP proxy which supports the CONNECT method
+    Http {
+        /// An easy to remember name for this custom proxy
+        name: String,
+        /// Disable the use of this custom access method. It has to be manually
+        /// enabled at a later stage to be used when accessing the Mullvad API.
+        #[arg(default_value_t = false, short, long)]
+        disabled: bool,
+        #[clap(flatten)]
+        add: HttpConnectAdd,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -298,6 +293,7 @@ impl AddCustomCommands {
     fn name(&self) -> &str {
         match self {
             AddCustomCommands::Shadowsocks { name, .. }
+            | AddCustomCommands::Http { name, .. }
             | AddCustomCommands::Socks5(AddSocks5Commands::Remote { name, .. })
             | AddCustomCommands::Socks5(AddSocks5Commands::Local { name, .. }) => name,
         }
@@ -306,6 +302,7 @@ impl AddCustomCommands {
     fn enabled(&self) -> bool {
         match self {
             AddCustomCommands::Shadowsocks { disabled, .. }
+            | AddCustomCommands::Http { disabled, .. }
             | AddCustomCommands::Socks5(AddSocks5Commands::Remote { disabled, .. })
             | AddCustomCommands::Socks5(AddSocks5Commands::Local { disabled, .. }) => !disabled,
         }
@@ -363,7 +360,7 @@ pub struct EditParams {
 /// we define them in a hidden-away module.
 mod conversions {
     use super::{AddCustomCommands, AddSocks5Commands};
-    use crate::cmds::proxies::{Error, SocksAuthentication};
+    use crate::cmds::proxies::Error;
     use mullvad_types::access_method as daemon_types;
     use talpid_types::net::proxy as talpid_types;
 
@@ -379,27 +376,15 @@ mod conversions {
                             add.transport_protocol,
                         ),
                     )),
-                    AddSocks5Commands::Remote { add, .. } => {
-                        Ok(daemon_types::AccessMethod::from(match add.authentication {
-                            Some(SocksAuthentication { username, password }) => {
-                                let auth = talpid_types::SocksAuth::new(username, password)?;
-                                talpid_types::Socks5Remote::new_with_authentication(
-                                    (add.remote_ip, add.remote_port),
-                                    auth,
-                                )
-                            }
-                            None => {
-                                talpid_types::Socks5Remote::new((add.remote_ip, add.remote_port))
-                            }
-                        }))
-                    }
+                    AddSocks5Commands::Remote { add, .. } => Ok(daemon_types::AccessMethod::from(
+                        talpid_types::Socks5Remote::try_from(add)?,
+                    )),
                 },
                 AddCustomCommands::Shadowsocks { add, .. } => Ok(daemon_types::AccessMethod::from(
-                    talpid_types::Shadowsocks::new(
-                        (add.remote_ip, add.remote_port),
-                        add.cipher,
-                        add.password,
-                    ),
+                    talpid_types::Shadowsocks::try_from(add)?,
+                )),
+                AddCustomCommands::Http { add, .. } => Ok(daemon_types::AccessMethod::from(
+                    talpid_types::HttpConnect::try_from(add)?,
                 )),
             }
         }
@@ -9,12 +9,12 @@ use mullvad_types::{
     },
     relay_list::RelayEndpointData,
 };
-use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remote};
+use talpid_types::net::proxy::{CustomProxy, HttpConnect, Shadowsocks, Socks5Local, Socks5Remote};
 
 use crate::cmds::proxies::pp::CustomProxyFormatter;
 
 use super::{
-    proxies::{ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd},
+    proxies::{HttpConnectAdd, ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd},
     relay::resolve_location_constraint,
     relay_constraints::LocationArgs,
 };
@@ -104,6 +104,13 @@ pub enum AddCustomCommands {
         #[clap(flatten)]
         add: ShadowsocksAdd,
     },
+    /// Configure an HTTP proxy which supports the CONNECT method. Only plain
+    /// `http` URLs are supported, since OpenVPN can not connect to the proxy
+    /// over TLS.
+    Http {
+        #[clap(flatten)]
+        add: HttpConnectAdd,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -302,10 +309,14 @@ impl Bridge {
         };
 
         match custom_bridge {
-            CustomProxy::Shadowsocks(ss) => *ss = edit.merge_shadowsocks(ss),
-            CustomProxy::Socks5Local(local) => *local = edit.merge_socks_local(local),
+            CustomProxy::Shadowsocks(ss) => *ss = edit.merge_shadowsocks(ss)?,
+            CustomProxy::Socks5Local(local) => *local = edit.merge_socks_local(local)?,
             CustomProxy::Socks5Remote(remote) => *remote = edit.merge_socks_remote(remote)?,
+            CustomProxy::HttpConnect(http) => *http = edit.merge_http_connect(http)?,
         };
+        if custom_bridge.hostname().is_some() {
+            bail!("Custom bridges must be given by IP address, not by hostname");
+        }
 
         rpc.set_bridge_settings(settings.bridge_settings)
             .await
@@ -339,7 +350,7 @@ impl Bridge {
         let mut rpc = MullvadProxyClient::new().await?;
         let mut settings = rpc.get_settings().await?;
 
-        settings.bridge_settings.custom = Some(match set_commands {
+        let custom_bridge = match set_commands {
             AddCustomCommands::Socks5(AddSocks5Commands::Local { add }) => {
                 CustomProxy::Socks5Local(Socks5Local::from(add))
             }
@@ -347,9 +358,20 @@ impl Bridge {
                 CustomProxy::Socks5Remote(Socks5Remote::try_from(add)?)
             }
             AddCustomCommands::Shadowsocks { add } => {
-                CustomProxy::Shadowsocks(Shadowsocks::from(add))
+                CustomProxy::Shadowsocks(Shadowsocks::try_from(add)?)
+            }
+            AddCustomCommands::Http { add } => {
+                if add.url.tls {
+                    bail!("Connecting to an HTTP proxy over TLS is not supported for bridges");
+                }
+                CustomProxy::HttpConnect(HttpConnect::try_from(add)?)
             }
-        });
+        };
+        if custom_bridge.hostname().is_some() {
+            bail!("Custom bridges must be given by IP address, not by hostname");
+        }
+
+        settings.bridge_settings.custom = Some(custom_bridge);
 
         settings.bridge_settings.bridge_type = BridgeType::Custom;
 
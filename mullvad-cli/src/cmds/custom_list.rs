@@ -1,4 +1,5 @@
 use super::{relay::resolve_location_constraint, relay_constraints::LocationArgs};
+use crate::json;
 use anyhow::{anyhow, bail, Result};
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
@@ -86,8 +87,13 @@ impl CustomList {
     /// Print all custom lists.
     async fn list() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
+        let custom_lists = rpc.get_settings().await?.custom_lists;
+        if json::is_enabled() {
+            let custom_lists: Vec<_> = custom_lists.iter().map(json::CustomList::from).collect();
+            return json::print(&custom_lists);
+        }
         let cache = rpc.get_relay_locations().await?;
-        for custom_list in rpc.get_settings().await?.custom_lists {
+        for custom_list in custom_lists {
             Self::print_custom_list(&custom_list, &cache)
         }
         Ok(())
@@ -98,6 +104,9 @@ impl CustomList {
     async fn get(name: String) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let custom_list = find_list_by_name(&mut rpc, &name).await?;
+        if json::is_enabled() {
+            return json::print(&json::CustomList::from(&custom_list));
+        }
         let cache = rpc.get_relay_locations().await?;
         Self::print_custom_list_content(&custom_list, &cache);
         Ok(())
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::{
+    constraints::Constraint,
+    data_usage::{usage_in_period, DataUsageQuota, QuotaPeriod},
+};
+
+use crate::format::format_bytes;
+
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Subcommand, Debug)]
+pub enum DataUsage {
+    /// Display the data usage during the current day and month
+    Get,
+
+    /// List the data usage per day, relay and protocol
+    List,
+
+    /// Manage the data usage quota
+    #[clap(subcommand)]
+    Quota(Quota),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Quota {
+    /// Display the current data usage quota
+    Get,
+
+    /// Change the data usage quota
+    #[clap(arg_required_else_help = true)]
+    Set {
+        /// The period over which data usage is counted
+        #[arg(long)]
+        period: Option<QuotaPeriod>,
+
+        /// Notify when the usage during the period exceeds this many MiB, or 'any'
+        #[arg(long)]
+        soft_limit: Option<Constraint<u64>>,
+
+        /// Disconnect when the usage during the period exceeds this many MiB, or 'any'
+        #[arg(long)]
+        hard_limit: Option<Constraint<u64>>,
+    },
+}
+
+impl DataUsage {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            DataUsage::Get => Self::get().await,
+            DataUsage::List => Self::list().await,
+            DataUsage::Quota(Quota::Get) => Self::get_quota().await,
+            DataUsage::Quota(Quota::Set {
+                period,
+                soft_limit,
+                hard_limit,
+            }) => Self::set_quota(period, soft_limit, hard_limit).await,
+        }
+    }
+
+    async fn get() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let entries = rpc.get_data_usage().await?;
+        let quota = rpc.get_settings().await?.data_usage_quota;
+        let today = chrono::Local::now().date_naive();
+
+        for (label, period) in [
+            ("Today", QuotaPeriod::Day),
+            ("This month", QuotaPeriod::Month),
+        ] {
+            let usage = usage_in_period(&entries, period, today);
+            println!(
+                "{label:<12}sent {}, received {}",
+                format_bytes(usage.tx_bytes),
+                format_bytes(usage.rx_bytes),
+            );
+        }
+        print_quota(&quota);
+        Ok(())
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut entries = rpc.get_data_usage().await?;
+        entries
+            .sort_by(|a, b| (a.date, &a.relay, &a.protocol).cmp(&(b.date, &b.relay, &b.protocol)));
+        for entry in entries {
+            println!(
+                "{}  {:<20}{:<16}sent {}, received {}",
+                entry.date,
+                entry.relay,
+                entry.protocol,
+                format_bytes(entry.usage.tx_bytes),
+                format_bytes(entry.usage.rx_bytes),
+            );
+        }
+        Ok(())
+    }
+
+    async fn get_quota() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        print_quota(&rpc.get_settings().await?.data_usage_quota);
+        Ok(())
+    }
+
+    async fn set_quota(
+        period: Option<QuotaPeriod>,
+        soft_limit: Option<Constraint<u64>>,
+        hard_limit: Option<Constraint<u64>>,
+    ) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut quota = rpc.get_settings().await?.data_usage_quota;
+        let to_bytes = |limit: Constraint<u64>| limit.option().map(|mib| mib.saturating_mul(MIB));
+
+        if let Some(period) = period {
+            quota.period = period;
+        }
+        if let Some(soft_limit) = soft_limit {
+            quota.soft_limit = to_bytes(soft_limit);
+        }
+        if let Some(hard_limit) = hard_limit {
+            quota.hard_limit = to_bytes(hard_limit);
+        }
+
+        rpc.set_data_usage_quota(quota).await?;
+        println!("Updated data usage quota");
+        Ok(())
+    }
+}
+
+fn print_quota(quota: &DataUsageQuota) {
+    let format_limit = |limit: Option<u64>| match limit {
+        Some(limit) => format!("{} per {}", format_bytes(limit), quota.period),
+        None => "any".to_owned(),
+    };
+    println!("{:<12}{}", "Soft limit", format_limit(quota.soft_limit));
+    println!("{:<12}{}", "Hard limit", format_limit(quota.hard_limit));
+}
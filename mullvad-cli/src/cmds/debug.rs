@@ -1,4 +1,5 @@
 use anyhow::Result;
+use futures::StreamExt;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
@@ -12,6 +13,13 @@ pub enum DebugCommands {
     /// Relay
     #[clap(subcommand)]
     Relay(RelayDebugCommands),
+    /// Print packets blocked by the firewall as they are blocked, starting with the most recent
+    /// ones. Logging is only enabled while this command is running, and packets are never stored
+    /// on disk. Only supported on Linux.
+    FirewallLog,
+    /// Print all recorded changes to the settings, oldest first, along with what made them.
+    /// Secrets are redacted.
+    AuditLog,
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -66,6 +74,31 @@ impl DebugCommands {
                 println!("{relay} is now marked as active");
                 Ok(())
             }
+            DebugCommands::FirewallLog => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                let mut packets = rpc.firewall_log_listen().await?;
+                while let Some(packet) = packets.next().await {
+                    let packet = packet?;
+                    let timestamp = chrono::DateTime::<chrono::Local>::from(packet.timestamp);
+                    println!("{} {packet}", timestamp.format("%Y-%m-%d %H:%M:%S%.3f"));
+                }
+                Ok(())
+            }
+            DebugCommands::AuditLog => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                for entry in rpc.get_settings_audit_log().await? {
+                    let timestamp = chrono::DateTime::<chrono::Local>::from(entry.timestamp);
+                    println!(
+                        "{} {}",
+                        timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+                        entry.source
+                    );
+                    for change in entry.changes {
+                        println!("    {}: {} -> {}", change.path, change.old, change.new);
+                    }
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -4,6 +4,8 @@ use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::settings::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
 use std::net::IpAddr;
 
+use crate::json;
+
 #[derive(Subcommand, Debug)]
 pub enum Dns {
     /// Display the current DNS settings
@@ -89,6 +91,10 @@ impl Dns {
         let mut rpc = MullvadProxyClient::new().await?;
         let options = rpc.get_settings().await?.tunnel_options.dns_options;
 
+        if json::is_enabled() {
+            return json::print(&json::Dns::from(&options));
+        }
+
         match options.state {
             DnsState::Default => {
                 println!("Custom DNS: no");
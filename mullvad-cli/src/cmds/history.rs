@@ -0,0 +1,74 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use clap::Args;
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::session_history::Session;
+
+use crate::{format::format_bytes, json};
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// Only show this many of the most recent sessions
+    #[arg(long, short = 'n')]
+    limit: Option<usize>,
+}
+
+pub async fn handle(args: HistoryArgs) -> Result<()> {
+    let mut rpc = MullvadProxyClient::new().await?;
+    let mut sessions = rpc.get_session_history().await?;
+    if let Some(limit) = args.limit {
+        sessions.drain(..sessions.len().saturating_sub(limit));
+    }
+
+    if json::is_enabled() {
+        let sessions: Vec<_> = sessions.iter().map(json::Session::from).collect();
+        return json::print(&sessions);
+    }
+    if sessions.is_empty() {
+        println!("No sessions");
+    }
+    for session in &sessions {
+        print_session(session);
+    }
+    Ok(())
+}
+
+fn print_session(session: &Session) {
+    let format_time = |time: DateTime<Utc>| {
+        time.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    };
+    let end = match (&session.end, &session.end_reason) {
+        (Some(end), _) => format_time(*end),
+        (None, None) => "now".to_owned(),
+        (None, Some(_)) => "unknown".to_owned(),
+    };
+    println!("{} - {end}", format_time(session.start));
+
+    let relay = session.relay.as_deref().unwrap_or("unknown relay");
+    let location = match (&session.city, &session.country) {
+        (Some(city), Some(country)) => format!(" in {city}, {country}"),
+        (None, Some(country)) => format!(" in {country}"),
+        _ => String::new(),
+    };
+    let mut tunnel = session.tunnel_type.to_string();
+    if let Some(obfuscation) = &session.obfuscation {
+        tunnel += &format!(" over {obfuscation}");
+    }
+    println!("    {relay}{location} using {tunnel}");
+
+    let attempts = match session.connect_attempts {
+        1 => "1 connection attempt".to_owned(),
+        attempts => format!("{attempts} connection attempts"),
+    };
+    println!(
+        "    {attempts}, sent {}, received {}",
+        format_bytes(session.usage.tx_bytes),
+        format_bytes(session.usage.rx_bytes),
+    );
+    match &session.end_reason {
+        Some(reason) => println!("    Ended: {reason}"),
+        None => println!("    Ongoing"),
+    }
+}
@@ -7,8 +7,10 @@ pub mod auto_connect;
 pub mod beta_program;
 pub mod bridge;
 pub mod custom_list;
+pub mod data_usage;
 pub mod debug;
 pub mod dns;
+pub mod history;
 pub mod lan;
 pub mod lockdown;
 pub mod obfuscation;
@@ -1,7 +1,13 @@
 use clap::Args;
-use std::net::{IpAddr, SocketAddr};
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
 use talpid_types::net::{
-    proxy::{Shadowsocks, Socks5Local, Socks5Remote, SocksAuth, SHADOWSOCKS_CIPHERS},
+    proxy::{
+        HttpAuth, HttpConnect, HttpProxyTls, ProxyAddress, ProxyHost, Shadowsocks, Socks5Local,
+        Socks5Remote, SocksAuth, SHADOWSOCKS_CIPHERS,
+    },
     Endpoint, TransportProtocol,
 };
 
@@ -9,6 +15,10 @@ use talpid_types::net::{
 pub enum Error {
     #[error(transparent)]
     InvalidAuth(#[from] talpid_types::net::proxy::Error),
+    #[error("A TLS server name can only be set for proxies that are connected to over https")]
+    TlsServerNameWithoutTls,
+    #[error("Local SOCKS5 proxies must be given by IP address, not by hostname")]
+    LocalProxyHostname,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -44,8 +54,9 @@ impl From<Socks5LocalAdd> for Socks5Local {
 // We do not support setting the protocol as anything other than tcp for remote socks5 servers
 #[derive(Args, Debug, Clone)]
 pub struct Socks5RemoteAdd {
-    /// The IP of the remote proxy server
-    pub remote_ip: IpAddr,
+    /// The IP or hostname of the remote proxy server. Hostnames are looked up
+    /// each time the proxy is connected to, and are not supported for bridges
+    pub remote_host: ProxyHost,
     /// The port of the remote proxy server
     pub remote_port: u16,
 
@@ -56,39 +67,141 @@ pub struct Socks5RemoteAdd {
 impl TryFrom<Socks5RemoteAdd> for Socks5Remote {
     type Error = Error;
     fn try_from(add: Socks5RemoteAdd) -> Result<Self, Self::Error> {
-        Ok(Self {
-            endpoint: SocketAddr::new(add.remote_ip, add.remote_port),
-            auth: add
-                .authentication
-                .map(|auth| SocksAuth::new(auth.username, auth.password))
-                .transpose()?,
+        let endpoint = ProxyAddress::new(add.remote_host, add.remote_port);
+        Ok(match add.authentication {
+            Some(auth) => Socks5Remote::new_with_authentication(
+                endpoint,
+                SocksAuth::new(auth.username, auth.password)?,
+            ),
+            None => Socks5Remote::new(endpoint),
         })
     }
 }
 
 #[derive(Args, Debug, Clone)]
 pub struct ShadowsocksAdd {
-    /// The IP of the remote Shadowsocks-proxy
-    pub remote_ip: IpAddr,
+    /// The IP or hostname of the remote Shadowsocks-proxy. Hostnames are looked
+    /// up each time the proxy is connected to, and are not supported for bridges
+    pub remote_host: ProxyHost,
     /// Port on which the remote Shadowsocks-proxy listens for traffic
     pub remote_port: u16,
-    /// Password for authentication
+    /// Password for authentication. For the `2022-blake3-*` ciphers, this is
+    /// the base64 encoded pre-shared key
     pub password: String,
     /// Cipher to use
     #[arg(long, value_parser = SHADOWSOCKS_CIPHERS)]
     pub cipher: String,
 }
 
-impl From<ShadowsocksAdd> for Shadowsocks {
-    fn from(add: ShadowsocksAdd) -> Self {
-        Self {
-            endpoint: SocketAddr::new(add.remote_ip, add.remote_port),
-            password: add.password,
-            cipher: add.cipher,
+impl TryFrom<ShadowsocksAdd> for Shadowsocks {
+    type Error = Error;
+    fn try_from(add: ShadowsocksAdd) -> Result<Self, Self::Error> {
+        Ok(Shadowsocks::new(
+            ProxyAddress::new(add.remote_host, add.remote_port),
+            add.cipher,
+            add.password,
+        )?)
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct HttpConnectAdd {
+    /// URL of the HTTP proxy, such as `http://192.0.2.1:8080` or
+    /// `https://proxy.example.com`. Use the `https` scheme to connect to the
+    /// proxy over TLS. The port defaults to 80 for `http` and 443 for `https`.
+    /// Hostnames are looked up each time the proxy is connected to, and are
+    /// not supported for bridges.
+    pub url: HttpProxyUrl,
+    /// Name to verify the TLS certificate of the proxy against. By default, the
+    /// certificate is verified against the host in the URL.
+    #[arg(long)]
+    pub tls_server_name: Option<String>,
+
+    #[clap(flatten)]
+    pub authentication: Option<HttpAuthentication>,
+}
+
+impl TryFrom<HttpConnectAdd> for HttpConnect {
+    type Error = Error;
+    fn try_from(add: HttpConnectAdd) -> Result<Self, Self::Error> {
+        let tls = match (add.url.tls, add.tls_server_name) {
+            (true, server_name) => Some(HttpProxyTls { server_name }),
+            (false, None) => None,
+            (false, Some(_)) => return Err(Error::TlsServerNameWithoutTls),
+        };
+        Ok(HttpConnect {
+            endpoint: ProxyAddress::new(add.url.host, add.url.port),
+            auth: add
+                .authentication
+                .map(|auth| HttpAuth::new(auth.username, auth.password))
+                .transpose()?,
+            tls,
+        })
+    }
+}
+
+/// URL of an HTTP proxy, of the form `http[s]://<host>[:<port>]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpProxyUrl {
+    pub host: ProxyHost,
+    pub port: u16,
+    /// Whether the proxy is connected to over TLS
+    pub tls: bool,
+}
+
+impl FromStr for HttpProxyUrl {
+    type Err = String;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let (tls, default_port, authority) = if let Some(authority) = url.strip_prefix("http://") {
+            (false, 80, authority)
+        } else if let Some(authority) = url.strip_prefix("https://") {
+            (true, 443, authority)
+        } else {
+            return Err("The URL must start with http:// or https://".to_owned());
+        };
+        let authority = authority.strip_suffix('/').unwrap_or(authority);
+        let invalid = || format!("Invalid proxy address: {authority}");
+
+        if let Ok(endpoint) = authority.parse::<SocketAddr>() {
+            return Ok(HttpProxyUrl {
+                host: ProxyHost::Ip(endpoint.ip()),
+                port: endpoint.port(),
+                tls,
+            });
+        }
+        let ip = authority
+            .strip_prefix('[')
+            .and_then(|ip| ip.strip_suffix(']'))
+            .unwrap_or(authority);
+        if let Ok(ip) = ip.parse::<IpAddr>() {
+            return Ok(HttpProxyUrl {
+                host: ProxyHost::Ip(ip),
+                port: default_port,
+                tls,
+            });
         }
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, default_port),
+        };
+        let host = host.parse().map_err(|_| invalid())?;
+        Ok(HttpProxyUrl { host, port, tls })
     }
 }
 
+#[derive(Args, Debug, Clone)]
+#[group(requires_all = ["username", "password"])] // https://github.com/clap-rs/clap/issues/5092
+pub struct HttpAuthentication {
+    /// Username for basic authentication against the HTTP proxy
+    #[arg(short, long, required = false)]
+    pub username: String,
+    /// Password for basic authentication against the HTTP proxy
+    #[arg(short, long, required = false)]
+    pub password: String,
+}
+
 #[derive(Args, Debug, Clone)]
 #[group(requires_all = ["username", "password"])] // https://github.com/clap-rs/clap/issues/5092
 pub struct SocksAuthentication {
@@ -102,19 +215,20 @@ pub struct SocksAuthentication {
 
 #[derive(Args, Debug, Clone)]
 pub struct ProxyEditParams {
-    /// Username for authentication \[Socks5 (Remote proxy)\]
+    /// Username for authentication \[Socks5 (Remote proxy), HTTP\]
     #[arg(long)]
     pub username: Option<String>,
-    /// Password for authentication \[Socks5 (Remote proxy), Shadowsocks\]
+    /// Password for authentication \[Socks5 (Remote proxy), Shadowsocks, HTTP\]
     #[arg(long)]
     pub password: Option<String>,
     /// Cipher to use \[Shadowsocks\]
     #[arg(value_parser = SHADOWSOCKS_CIPHERS, long)]
     pub cipher: Option<String>,
-    /// The IP of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks\]
-    #[arg(long)]
-    pub ip: Option<IpAddr>,
-    /// The port of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks\]
+    /// The IP or hostname of the remote proxy server \[Socks5 (Local & Remote
+    /// proxy), Shadowsocks, HTTP\]. Only IPs are supported for local proxies
+    #[arg(long, alias = "ip")]
+    pub host: Option<ProxyHost>,
+    /// The port of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks, HTTP\]
     #[arg(long)]
     pub port: Option<u16>,
     /// The port that the server on localhost is listening on \[Socks5 (Local proxy)\]
@@ -123,54 +237,96 @@ pub struct ProxyEditParams {
     /// The transport protocol used by the remote proxy \[Socks5 (Local proxy)\]
     #[arg(long)]
     pub transport_protocol: Option<TransportProtocol>,
+    /// Name to verify the TLS certificate of the proxy against \[HTTP (over TLS)\]
+    #[arg(long)]
+    pub tls_server_name: Option<String>,
 }
 
 impl ProxyEditParams {
-    pub fn merge_socks_local(self, local: &Socks5Local) -> Socks5Local {
-        let remote_ip = self.ip.unwrap_or(local.remote_endpoint.address.ip());
+    pub fn merge_socks_local(self, local: &Socks5Local) -> Result<Socks5Local, Error> {
+        let remote_ip = match self.host {
+            Some(ProxyHost::Ip(ip)) => ip,
+            Some(ProxyHost::Hostname(_)) => return Err(Error::LocalProxyHostname),
+            None => local.remote_endpoint.address.ip(),
+        };
         let remote_port = self.port.unwrap_or(local.remote_endpoint.address.port());
         let local_port = self.local_port.unwrap_or(local.local_port);
         let remote_peer_transport_protocol = self
             .transport_protocol
             .unwrap_or(local.remote_endpoint.protocol);
-        Socks5Local::new_with_transport_protocol(
+        Ok(Socks5Local::new_with_transport_protocol(
             (remote_ip, remote_port),
             local_port,
             remote_peer_transport_protocol,
-        )
+        ))
     }
 
     pub fn merge_socks_remote(self, remote: &Socks5Remote) -> Result<Socks5Remote, Error> {
-        let ip = self.ip.unwrap_or(remote.endpoint.ip());
-        let port = self.port.unwrap_or(remote.endpoint.port());
-        let config = match &remote.auth {
+        let endpoint = self.merge_address(&remote.endpoint);
+        Ok(match &remote.auth {
             None => match (self.username, self.password) {
                 (Some(username), Some(password)) => {
                     let auth = SocksAuth::new(username, password)?;
-                    Socks5Remote::new_with_authentication((ip, port), auth)
+                    Socks5Remote::new_with_authentication(endpoint, auth)
                 }
-                (None, None) => Socks5Remote::new((ip, port)),
+                (None, None) => Socks5Remote::new(endpoint),
                 _ => {
                     println!("Remote SOCKS5 proxy does not have a username and password set already, so you must provide both or neither when you edit.");
-                    Socks5Remote::new((ip, port))
+                    Socks5Remote::new(endpoint)
                 }
             },
             Some(credentials) => {
                 let username = self.username.unwrap_or(credentials.username().to_string());
                 let password = self.password.unwrap_or(credentials.password().to_string());
                 let auth = SocksAuth::new(username, password)?;
-                Socks5Remote::new_with_authentication((ip, port), auth)
+                Socks5Remote::new_with_authentication(endpoint, auth)
+            }
+        })
+    }
+
+    pub fn merge_http_connect(self, http: &HttpConnect) -> Result<HttpConnect, Error> {
+        let endpoint = self.merge_address(&http.endpoint);
+        let auth = match &http.auth {
+            None => match (self.username, self.password) {
+                (Some(username), Some(password)) => Some(HttpAuth::new(username, password)?),
+                (None, None) => None,
+                _ => {
+                    println!("HTTP proxy does not have a username and password set already, so you must provide both or neither when you edit.");
+                    None
+                }
+            },
+            Some(credentials) => {
+                let username = self.username.unwrap_or(credentials.username().to_string());
+                let password = self.password.unwrap_or(credentials.password().to_string());
+                Some(HttpAuth::new(username, password)?)
             }
         };
-        Ok(config)
+        let tls = match (&http.tls, self.tls_server_name) {
+            (Some(_), Some(server_name)) => Some(HttpProxyTls {
+                server_name: Some(server_name),
+            }),
+            (tls, None) => tls.clone(),
+            (None, Some(_)) => return Err(Error::TlsServerNameWithoutTls),
+        };
+        Ok(HttpConnect {
+            endpoint,
+            auth,
+            tls,
+        })
     }
 
-    pub fn merge_shadowsocks(self, shadowsocks: &Shadowsocks) -> Shadowsocks {
-        let ip = self.ip.unwrap_or(shadowsocks.endpoint.ip());
-        let port = self.port.unwrap_or(shadowsocks.endpoint.port());
+    pub fn merge_shadowsocks(self, shadowsocks: &Shadowsocks) -> Result<Shadowsocks, Error> {
+        let endpoint = self.merge_address(&shadowsocks.endpoint);
         let password = self.password.unwrap_or(shadowsocks.password.clone());
         let cipher = self.cipher.unwrap_or(shadowsocks.cipher.clone());
-        Shadowsocks::new((ip, port), cipher, password)
+        Ok(Shadowsocks::new(endpoint, cipher, password)?)
+    }
+
+    fn merge_address(&self, address: &ProxyAddress) -> ProxyAddress {
+        ProxyAddress::new(
+            self.host.clone().unwrap_or_else(|| address.host.clone()),
+            self.port.unwrap_or(address.port),
+        )
     }
 }
 
@@ -212,6 +368,24 @@ pub mod pp {
                     print_option!("Local port", local.local_port);
                     Ok(())
                 }
+                CustomProxy::HttpConnect(http) => {
+                    print_option!("Protocol", "HTTP CONNECT");
+                    print_option!("Peer", http.endpoint);
+                    if let Some(tls) = &http.tls {
+                        print_option!(
+                            "TLS",
+                            match &tls.server_name {
+                                Some(server_name) => format!("yes [{server_name}]"),
+                                None => "yes".to_owned(),
+                            }
+                        );
+                    }
+                    if let Some(credentials) = &http.auth {
+                        print_option!("Username", credentials.username());
+                        print_option!("Password", credentials.password());
+                    }
+                    Ok(())
+                }
             }
         }
     }
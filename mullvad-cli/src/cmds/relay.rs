@@ -1,5 +1,6 @@
 use anyhow::{anyhow, bail, Context, Result};
-use clap::Subcommand;
+use clap::{Args, Subcommand};
+use ipnetwork::IpNetwork;
 use itertools::Itertools;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
@@ -11,11 +12,12 @@ use mullvad_types::{
         RelaySettings, TransportPort, WireguardConstraints,
     },
     relay_list::{RelayEndpointData, RelayListCountry},
-    ConnectionConfig, CustomTunnelEndpoint,
+    wg_quick, ConnectionConfig, CustomTunnelEndpoint,
 };
 use std::{
     collections::HashMap,
-    io::BufRead,
+    fs::File,
+    io::{read_to_string, stdin, BufRead, BufReader},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
 };
 use talpid_types::net::{
@@ -23,7 +25,7 @@ use talpid_types::net::{
 };
 
 use super::{relay_constraints::LocationArgs, BooleanOption};
-use crate::{cmds::receive_confirmation, print_option};
+use crate::{cmds::receive_confirmation, json, print_option};
 
 #[derive(Subcommand, Debug)]
 pub enum Relay {
@@ -196,24 +198,61 @@ pub enum SetCustomCommands {
     #[clap(arg_required_else_help = true)]
     Wireguard {
         /// Hostname or IP
-        host: String,
+        #[arg(required_unless_present = "from_file")]
+        host: Option<String>,
         /// Remote port
-        port: u16,
+        #[arg(required_unless_present = "from_file")]
+        port: Option<u16>,
         /// Base64 encoded public key of remote peer
-        #[arg(value_parser = wireguard::PublicKey::from_base64)]
-        peer_pubkey: wireguard::PublicKey,
+        #[arg(
+            value_parser = wireguard::PublicKey::from_base64,
+            required_unless_present = "from_file"
+        )]
+        peer_pubkey: Option<wireguard::PublicKey>,
         /// IP addresses of local tunnel interface
-        #[arg(required = true, num_args = 1..)]
+        #[arg(required_unless_present = "from_file", num_args = 1..)]
         tunnel_ip: Vec<IpAddr>,
-        /// IPv4 gateway address
-        #[arg(long)]
-        v4_gateway: Ipv4Addr,
-        /// IPv6 gateway address
+        /// IPv4 gateway address. When importing a config, this overrides the gateway found
+        /// in it
+        #[arg(long, required_unless_present = "from_file")]
+        v4_gateway: Option<Ipv4Addr>,
+        /// IPv6 gateway address. When importing a config, this overrides the gateway found
+        /// in it
         #[arg(long)]
         v6_gateway: Option<Ipv6Addr>,
+        #[command(flatten)]
+        peer_options: CustomWireguardPeerOptions,
+        /// Import the relay from a wg-quick config instead. If this is "-", read the config
+        /// from standard input
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "host",
+                "port",
+                "peer_pubkey",
+                "tunnel_ip",
+                "preshared_key",
+                "keepalive",
+                "allowed_ip",
+            ]
+        )]
+        from_file: Option<String>,
     },
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct CustomWireguardPeerOptions {
+    /// Read a base64 encoded preshared key from standard input, after the private key
+    #[arg(long)]
+    preshared_key: bool,
+    /// Interval in seconds at which to send keepalive packets to the relay
+    #[arg(long)]
+    keepalive: Option<u16>,
+    /// Networks to route through the relay. Defaults to all networks
+    #[arg(long, num_args = 1..)]
+    allowed_ip: Vec<IpNetwork>,
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum OverrideCommands {
     /// Show current custom fields for servers
@@ -274,6 +313,13 @@ impl Relay {
         let settings = rpc.get_settings().await?;
         let relay_settings = settings.relay_settings;
 
+        if json::is_enabled() {
+            return json::print(&json::RelayConstraints::new(
+                &relay_settings,
+                &settings.custom_lists,
+            ));
+        }
+
         match relay_settings {
             RelaySettings::CustomTunnelEndpoint(endpoint) => {
                 println!("Custom endpoint: {endpoint}")
@@ -345,14 +391,24 @@ impl Relay {
     async fn list() -> Result<()> {
         let mut countries = get_active_relays().await?;
         countries.sort_by(|c1, c2| natord::compare_ignore_case(&c1.name, &c2.name));
-        for mut country in countries {
+        for country in &mut countries {
             country
                 .cities
                 .sort_by(|c1, c2| natord::compare_ignore_case(&c1.name, &c2.name));
-            println!("{} ({})", country.name, country.code);
-            for mut city in country.cities {
+            for city in &mut country.cities {
                 city.relays
                     .sort_by(|r1, r2| natord::compare_ignore_case(&r1.hostname, &r2.hostname));
+            }
+        }
+
+        if json::is_enabled() {
+            let countries: Vec<_> = countries.iter().map(json::RelayListCountry::from).collect();
+            return json::print(&countries);
+        }
+
+        for country in countries {
+            println!("{} ({})", country.name, country.code);
+            for city in country.cities {
                 println!(
                     "\t{} ({}) @ {:.5}°N, {:.5}°W",
                     city.name, city.code, city.latitude, city.longitude
@@ -458,6 +514,12 @@ impl Relay {
             } => {
                 Self::read_custom_openvpn_relay(host, port, username, password, transport_protocol)
             }
+            SetCustomCommands::Wireguard {
+                v4_gateway,
+                v6_gateway,
+                from_file: Some(source),
+                ..
+            } => Self::import_custom_wireguard_relay(source, v4_gateway, v6_gateway).await?,
             SetCustomCommands::Wireguard {
                 host,
                 port,
@@ -465,7 +527,17 @@ impl Relay {
                 tunnel_ip,
                 v4_gateway,
                 v6_gateway,
+                peer_options,
+                from_file: None,
             } => {
+                let (Some(host), Some(port), Some(peer_pubkey), Some(v4_gateway)) =
+                    (host, port, peer_pubkey, v4_gateway)
+                else {
+                    bail!(
+                        "HOST, PORT, PEER_PUBKEY and V4_GATEWAY are required unless --from-file is \
+                         given"
+                    );
+                };
                 Self::read_custom_wireguard_relay(
                     host,
                     port,
@@ -473,6 +545,7 @@ impl Relay {
                     tunnel_ip,
                     v4_gateway,
                     v6_gateway,
+                    peer_options,
                 )
                 .await?
             }
@@ -511,24 +584,24 @@ impl Relay {
         tunnel_ip: Vec<IpAddr>,
         ipv4_gateway: Ipv4Addr,
         ipv6_gateway: Option<Ipv6Addr>,
+        peer_options: CustomWireguardPeerOptions,
     ) -> Result<CustomTunnelEndpoint> {
-        println!("Reading private key from standard input");
-
-        let private_key_str = tokio::task::spawn_blocking(|| {
-            let mut private_key_str = String::new();
-            let _ = std::io::stdin().lock().read_line(&mut private_key_str);
-            let private_key_str = private_key_str.trim().to_owned();
-            if private_key_str.is_empty() {
-                eprintln!("Expected to read private key from standard input");
-            }
-            private_key_str
-        })
-        .await
-        .unwrap();
-
+        let private_key_str = Self::read_key_from_stdin("private key").await;
         let private_key =
             wireguard::PrivateKey::from_base64(&private_key_str).context("Invalid private key")?;
 
+        let psk = if peer_options.preshared_key {
+            let psk_str = Self::read_key_from_stdin("preshared key").await;
+            Some(wireguard::PresharedKey::from_base64(&psk_str).context("Invalid preshared key")?)
+        } else {
+            None
+        };
+        let allowed_ips = if peer_options.allowed_ip.is_empty() {
+            all_of_the_internet()
+        } else {
+            peer_options.allowed_ip
+        };
+
         Ok(CustomTunnelEndpoint {
             host,
             config: ConnectionConfig::Wireguard(wireguard::ConnectionConfig {
@@ -538,9 +611,10 @@ impl Relay {
                 },
                 peer: wireguard::PeerConfig {
                     public_key: peer_pubkey,
-                    allowed_ips: all_of_the_internet(),
+                    allowed_ips,
                     endpoint: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port),
-                    psk: None,
+                    psk,
+                    persistent_keepalive: peer_options.keepalive,
                     constant_packet_size: false,
                 },
                 exit_peer: None,
@@ -553,6 +627,47 @@ impl Relay {
         })
     }
 
+    async fn read_key_from_stdin(name: &'static str) -> String {
+        println!("Reading {name} from standard input");
+
+        tokio::task::spawn_blocking(move || {
+            let mut key_str = String::new();
+            let _ = std::io::stdin().lock().read_line(&mut key_str);
+            let key_str = key_str.trim().to_owned();
+            if key_str.is_empty() {
+                eprintln!("Expected to read {name} from standard input");
+            }
+            key_str
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Read a custom WireGuard relay from a wg-quick config.
+    ///
+    /// * If `source` is "-", read the config from standard input
+    /// * Otherwise, interpret `source` as a filepath and read from the provided file
+    async fn import_custom_wireguard_relay(
+        source: String,
+        ipv4_gateway: Option<Ipv4Addr>,
+        ipv6_gateway: Option<Ipv6Addr>,
+    ) -> Result<CustomTunnelEndpoint> {
+        let config = tokio::task::spawn_blocking(move || match source.as_str() {
+            "-" => read_to_string(BufReader::new(stdin())).context("Failed to read from stdin"),
+            _ => read_to_string(File::open(&source)?)
+                .context(format!("Failed to read from path: {source}")),
+        })
+        .await
+        .unwrap()?;
+
+        let parsed = wg_quick::parse(&config, ipv4_gateway, ipv6_gateway)
+            .context("Failed to parse WireGuard config")?;
+        for key in parsed.ignored_keys {
+            eprintln!("Warning: Ignoring unsupported key '{key}'");
+        }
+        Ok(parsed.endpoint)
+    }
+
     async fn set_location(location_constraint_args: LocationArgs) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let relay_settings = rpc.get_settings().await?.get_relay_settings();
@@ -2,6 +2,8 @@ use anyhow::Result;
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 
+use crate::json;
+
 /// Manage split tunneling. To launch applications outside the tunnel, use the program
 /// 'mullvad-exclude' instead of this command
 #[derive(Subcommand, Debug)]
@@ -17,6 +19,11 @@ pub enum SplitTunnel {
 }
 
 impl SplitTunnel {
+    /// Whether the command can print its output as JSON.
+    pub fn supports_json(&self) -> bool {
+        matches!(self, SplitTunnel::List)
+    }
+
     pub async fn handle(self) -> Result<()> {
         match self {
             SplitTunnel::List => {
@@ -25,6 +32,10 @@ impl SplitTunnel {
                     .get_split_tunnel_processes()
                     .await?;
 
+                if json::is_enabled() {
+                    return json::print(&json::SplitTunnelProcesses { pids });
+                }
+
                 println!("Excluded PIDs:");
                 for pid in &pids {
                     println!("{pid}");
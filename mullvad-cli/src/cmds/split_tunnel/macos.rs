@@ -5,6 +5,7 @@ use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 
 use super::super::BooleanOption;
+use crate::json;
 
 /// Set options for applications to exclude from the tunnel.
 #[derive(Subcommand, Debug)]
@@ -28,12 +29,21 @@ pub enum App {
 }
 
 impl SplitTunnel {
+    /// Whether the command can print its output as JSON.
+    pub fn supports_json(&self) -> bool {
+        matches!(self, SplitTunnel::Get)
+    }
+
     pub async fn handle(self) -> Result<()> {
         match self {
             SplitTunnel::Get => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let settings = rpc.get_settings().await?.split_tunnel;
 
+                if json::is_enabled() {
+                    return json::print(&json::SplitTunnel::new(&settings));
+                }
+
                 let enable_exclusions = BooleanOption::from(settings.enable_exclusions);
 
                 println!("Split tunneling state: {enable_exclusions}");
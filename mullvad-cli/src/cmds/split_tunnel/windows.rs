@@ -8,6 +8,7 @@ use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 
 use super::super::BooleanOption;
+use crate::json;
 
 /// Set options for applications to exclude from the tunnel.
 #[derive(Subcommand, Debug)]
@@ -37,12 +38,27 @@ pub enum App {
 }
 
 impl SplitTunnel {
+    /// Whether the command can print its output as JSON.
+    pub fn supports_json(&self) -> bool {
+        matches!(self, SplitTunnel::Get { .. })
+    }
+
     pub async fn handle(self) -> Result<()> {
         match self {
             SplitTunnel::Get { list_processes } => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let settings = rpc.get_settings().await?.split_tunnel;
 
+                if json::is_enabled() {
+                    let mut split_tunnel = json::SplitTunnel::new(&settings);
+                    if list_processes {
+                        let processes = rpc.get_excluded_processes().await?;
+                        split_tunnel.processes =
+                            Some(processes.iter().map(json::ExcludedProcess::from).collect());
+                    }
+                    return json::print(&split_tunnel);
+                }
+
                 let enable_exclusions = BooleanOption::from(settings.enable_exclusions);
 
                 println!("Split tunneling state: {enable_exclusions}");
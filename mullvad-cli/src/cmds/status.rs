@@ -4,31 +4,43 @@ use futures::StreamExt;
 use mullvad_management_interface::{client::DaemonEvent, MullvadProxyClient};
 use mullvad_types::{device::DeviceState, states::TunnelState};
 use serde::Serialize;
-use std::fmt::Debug;
+use std::{fmt::Debug, time::Duration};
+use talpid_types::net::stats::TunnelStats;
 
 use crate::format;
 
 #[derive(Subcommand, Debug, PartialEq)]
 pub enum Status {
-    /// Listen for tunnel state changes
+    /// Listen for tunnel state changes, detected leaks and changes in connectivity
     Listen,
 }
 
 #[derive(Args, Debug)]
 pub struct StatusArgs {
     /// Enable verbose output
-    #[arg(long, short = 'v')]
+    #[arg(long, short = 'v', conflicts_with = "json")]
     verbose: bool,
 
     /// Enable debug output
     #[arg(long, short = 'd', conflicts_with_all = ["verbose", "json"])]
     debug: bool,
 
-    /// Format output as JSON
-    #[arg(long, short = 'j', conflicts_with_all = ["verbose", "debug"])]
-    json: bool,
+    /// Format output as JSON. Set by the global `--json` flag
+    #[arg(skip)]
+    pub json: bool,
+
+    /// Show traffic statistics of the current tunnel
+    #[arg(long)]
+    stats: bool,
+
+    /// Keep printing tunnel statistics, including throughput, every second
+    #[arg(long, requires = "stats")]
+    watch: bool,
 }
 
+/// How often statistics are sampled with `--watch`.
+const STATS_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
 impl Status {
     pub async fn listen(
         mut rpc: MullvadProxyClient,
@@ -68,6 +80,30 @@ impl Status {
                 DaemonEvent::NewAccessMethod(access_method) => {
                     print_debug_or_json(&args, "New access method", &access_method)?;
                 }
+                DaemonEvent::DataQuotaExceeded(event) => {
+                    print_debug_or_json(&args, "Data quota exceeded", &event)?;
+                }
+                DaemonEvent::MtuChanged(mtu) => {
+                    print_debug_or_json(&args, "Tunnel MTU changed", &mtu)?;
+                }
+                DaemonEvent::LeakDetected(leak) => {
+                    if args.debug || args.json {
+                        print_debug_or_json(&args, "Leak detected", &leak)?;
+                    } else {
+                        println!("Leak detected: {leak}");
+                    }
+                }
+                DaemonEvent::ConnectivityDegraded(degraded) => {
+                    if args.debug || args.json {
+                        print_debug_or_json(&args, "Tunnel connectivity degraded", &degraded)?;
+                    } else if degraded {
+                        println!(
+                            "Tunnel connectivity degraded: no traffic received from the relay"
+                        );
+                    } else {
+                        println!("Tunnel connectivity restored");
+                    }
+                }
             }
         }
         Ok(())
@@ -75,6 +111,10 @@ impl Status {
 }
 
 pub async fn handle(cmd: Option<Status>, args: StatusArgs) -> Result<()> {
+    if args.watch && cmd.is_some() {
+        anyhow::bail!("--watch cannot be combined with a subcommand");
+    }
+
     let mut rpc = MullvadProxyClient::new().await?;
     let state = rpc.get_tunnel_state().await?;
     let device = rpc.get_device().await?;
@@ -90,12 +130,39 @@ pub async fn handle(cmd: Option<Status>, args: StatusArgs) -> Result<()> {
         format::print_state(&state, None, args.verbose);
     }
 
+    if args.stats {
+        if args.watch {
+            let mut stats_stream = rpc.tunnel_stats_listen(STATS_WATCH_INTERVAL).await?;
+            while let Some(stats) = stats_stream.next().await {
+                print_tunnel_stats(&args, &stats?)?;
+            }
+        } else {
+            match rpc.get_tunnel_stats().await? {
+                Some(stats) => print_tunnel_stats(&args, &stats)?,
+                None if args.json => println!("null"),
+                None => println!("Tunnel statistics are unavailable"),
+            }
+        }
+    }
+
     if cmd == Some(Status::Listen) {
         Status::listen(rpc, args, state).await?;
     }
     Ok(())
 }
 
+fn print_tunnel_stats(args: &StatusArgs, stats: &TunnelStats) -> Result<()> {
+    if args.debug {
+        println!("Tunnel stats: {stats:#?}");
+    } else if args.json {
+        let json = serde_json::to_string(stats).context("Failed to format output as JSON")?;
+        println!("{json}");
+    } else {
+        format::print_tunnel_stats(stats);
+    }
+    Ok(())
+}
+
 fn print_account_logged_out(state: &TunnelState, device: &DeviceState) {
     match state {
         TunnelState::Connecting { .. } | TunnelState::Connected { .. } | TunnelState::Error(_) => {
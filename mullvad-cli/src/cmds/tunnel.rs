@@ -1,13 +1,17 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
-    wireguard::{QuantumResistantState, RotationInterval, DEFAULT_ROTATION_INTERVAL},
+    wireguard::{
+        QuantumResistantState, RekeyInterval, RotationInterval, DEFAULT_ROTATION_INTERVAL,
+    },
 };
+use std::{net::Ipv4Addr, time::Duration};
+use talpid_types::net::wireguard::{ConnectivityCheckOptions, DaitaLevel, WireguardBackend};
 
 use super::BooleanOption;
-use crate::print_option;
+use crate::{json, print_option};
 
 #[derive(Subcommand, Debug)]
 pub enum Tunnel {
@@ -17,6 +21,14 @@ pub enum Tunnel {
     /// Set tunnel options
     #[clap(subcommand)]
     Set(TunnelOptions),
+
+    /// Print the config of the current WireGuard tunnel in the wg-quick format
+    ExportWireguard {
+        /// Include the private key instead of redacting it. Only root and members of the admin
+        /// group may do this
+        #[arg(long)]
+        include_secrets: bool,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -38,15 +50,33 @@ pub enum TunnelOptions {
         /// Configure quantum-resistant key exchange
         #[arg(long)]
         quantum_resistant: Option<QuantumResistantState>,
+        /// How often to negotiate a new quantum-resistant PSK without reconnecting. Number of
+        /// hours, or 'any' to only negotiate it when connecting
+        #[arg(long)]
+        quantum_resistant_rekey_interval: Option<Constraint<RekeyInterval>>,
         /// Configure whether to enable DAITA
         #[arg(long)]
         daita: Option<BooleanOption>,
         /// Configure whether to enable DAITA direct only
         #[arg(long)]
         daita_direct_only: Option<BooleanOption>,
+        /// DAITA level to request from the relay, from 1 to 10, or 'default' to let the relay
+        /// decide. Higher levels provide more protection but use more bandwidth
+        #[arg(long)]
+        daita_level: Option<DaitaLevel>,
         /// The key rotation interval. Number of hours, or 'any'
         #[arg(long)]
         rotation_interval: Option<Constraint<RotationInterval>>,
+        /// WireGuard implementation to try first: 'auto', 'kernel', 'wireguard-go', or
+        /// 'boringtun'. Other available implementations are used if it fails
+        #[arg(long)]
+        backend: Option<WireguardBackend>,
+        /// Configure whether to connect to a new relay before disconnecting from the current one.
+        /// Only used if multihop and obfuscation are disabled. With quantum resistance or DAITA,
+        /// traffic is paused while keys are negotiated with the new relay.
+        /// Only supported on Linux and macOS
+        #[arg(long)]
+        make_before_break: Option<BooleanOption>,
         /// Rotate WireGuard key
         #[clap(subcommand)]
         rotate_key: Option<RotateKey>,
@@ -55,6 +85,33 @@ pub enum TunnelOptions {
     /// Enable or disable IPv6 in the tunnel
     #[clap(arg_required_else_help = true)]
     Ipv6 { state: BooleanOption },
+
+    /// Tune when the WireGuard connectivity monitor considers the tunnel to be dead.
+    /// Durations are given in seconds
+    #[clap(arg_required_else_help = true)]
+    Connectivity {
+        /// Start pinging if outgoing traffic is not answered within this time (1-60)
+        #[arg(long, value_parser = parse_seconds)]
+        rx_timeout: Option<Duration>,
+        /// Start pinging if no traffic is sent or received within this time (10-600)
+        #[arg(long, value_parser = parse_seconds)]
+        traffic_timeout: Option<Duration>,
+        /// Reconnect if nothing is received within this time after the first ping (3-120)
+        #[arg(long, value_parser = parse_seconds)]
+        ping_timeout: Option<Duration>,
+        /// Time between pings. At most half of the ping timeout (0.5-30)
+        #[arg(long, value_parser = parse_seconds)]
+        ping_interval: Option<Duration>,
+        /// Initial timeout for establishing a connection. At most the ping timeout (1-60)
+        #[arg(long, value_parser = parse_seconds)]
+        establish_timeout: Option<Duration>,
+        /// IPv4 address inside the tunnel to ping, or 'any' to ping the tunnel gateway
+        #[arg(long)]
+        ping_target: Option<Constraint<Ipv4Addr>>,
+        /// Reset all parameters to their defaults
+        #[arg(long, exclusive = true)]
+        reset: bool,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -68,13 +125,31 @@ impl Tunnel {
         match self {
             Tunnel::Get => Self::get().await,
             Tunnel::Set(options) => Self::set(options).await,
+            Tunnel::ExportWireguard { include_secrets } => {
+                Self::export_wireguard(include_secrets).await
+            }
         }
     }
 
+    async fn export_wireguard(include_secrets: bool) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let config = rpc
+            .get_wireguard_config(include_secrets)
+            .await?
+            .ok_or_else(|| anyhow!("Not connecting or connected to a WireGuard relay"))?;
+        print!("{config}");
+        Ok(())
+    }
+
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let tunnel_options = rpc.get_settings().await?.tunnel_options;
 
+        if json::is_enabled() {
+            let key = rpc.get_wireguard_key().await?;
+            return json::print(&json::Tunnel::new(&tunnel_options, &key));
+        }
+
         println!("OpenVPN options");
 
         print_option!(
@@ -100,8 +175,21 @@ impl Tunnel {
             "Quantum resistance",
             tunnel_options.wireguard.quantum_resistant,
         );
+        print_option!(
+            "Quantum-resistant re-key interval",
+            match tunnel_options.wireguard.quantum_resistant_rekey_interval {
+                Some(interval) => interval.to_string(),
+                None => "unset".to_string(),
+            },
+        );
 
         print_option!("DAITA", tunnel_options.wireguard.daita.enabled);
+        print_option!("DAITA level", tunnel_options.wireguard.daita.level);
+        print_option!("Backend", tunnel_options.wireguard.backend);
+        print_option!(
+            "Make before break",
+            tunnel_options.wireguard.make_before_break,
+        );
 
         let key = rpc.get_wireguard_key().await?;
         print_option!("Public key", key.key,);
@@ -117,6 +205,27 @@ impl Tunnel {
             },
         );
 
+        let connectivity = &tunnel_options.wireguard.connectivity;
+        print_option!(
+            "Connectivity monitor",
+            format_args!(
+                "rx timeout {}s, traffic timeout {}s, ping timeout {}s, ping interval {}s, \
+                 establish timeout {}s",
+                connectivity.rx_timeout.as_secs_f64(),
+                connectivity.traffic_timeout.as_secs_f64(),
+                connectivity.ping_timeout.as_secs_f64(),
+                connectivity.ping_interval.as_secs_f64(),
+                connectivity.establish_timeout.as_secs_f64(),
+            ),
+        );
+        print_option!(
+            "Ping target",
+            match connectivity.ping_target {
+                Some(target) => target.to_string(),
+                None => "gateway".to_string(),
+            },
+        );
+
         println!("Generic options");
 
         print_option!(
@@ -137,22 +246,50 @@ impl Tunnel {
             TunnelOptions::Wireguard {
                 mtu,
                 quantum_resistant,
+                quantum_resistant_rekey_interval,
                 daita,
                 daita_direct_only,
+                daita_level,
                 rotation_interval,
+                backend,
+                make_before_break,
                 rotate_key,
             } => {
                 Self::handle_wireguard(
                     mtu,
                     quantum_resistant,
+                    quantum_resistant_rekey_interval,
                     daita,
                     daita_direct_only,
+                    daita_level,
                     rotation_interval,
+                    backend,
+                    make_before_break,
                     rotate_key,
                 )
                 .await
             }
             TunnelOptions::Ipv6 { state } => Self::handle_ipv6(state).await,
+            TunnelOptions::Connectivity {
+                rx_timeout,
+                traffic_timeout,
+                ping_timeout,
+                ping_interval,
+                establish_timeout,
+                ping_target,
+                reset,
+            } => {
+                Self::handle_connectivity(
+                    rx_timeout,
+                    traffic_timeout,
+                    ping_timeout,
+                    ping_interval,
+                    establish_timeout,
+                    ping_target,
+                    reset,
+                )
+                .await
+            }
         }
     }
 
@@ -163,6 +300,47 @@ impl Tunnel {
         Ok(())
     }
 
+    async fn handle_connectivity(
+        rx_timeout: Option<Duration>,
+        traffic_timeout: Option<Duration>,
+        ping_timeout: Option<Duration>,
+        ping_interval: Option<Duration>,
+        establish_timeout: Option<Duration>,
+        ping_target: Option<Constraint<Ipv4Addr>>,
+        reset: bool,
+    ) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = if reset {
+            ConnectivityCheckOptions::default()
+        } else {
+            rpc.get_settings()
+                .await?
+                .tunnel_options
+                .wireguard
+                .connectivity
+        };
+
+        let updates = [
+            (&mut options.rx_timeout, rx_timeout),
+            (&mut options.traffic_timeout, traffic_timeout),
+            (&mut options.ping_timeout, ping_timeout),
+            (&mut options.ping_interval, ping_interval),
+            (&mut options.establish_timeout, establish_timeout),
+        ];
+        for (option, value) in updates {
+            if let Some(value) = value {
+                *option = value;
+            }
+        }
+        if let Some(target) = ping_target {
+            options.ping_target = target.option();
+        }
+
+        rpc.set_connectivity_check_options(options).await?;
+        println!("Connectivity monitor parameters have been updated");
+        Ok(())
+    }
+
     async fn handle_openvpn(mssfix: Option<Constraint<u16>>) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
 
@@ -174,12 +352,17 @@ impl Tunnel {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handle_wireguard(
         mtu: Option<Constraint<u16>>,
         quantum_resistant: Option<QuantumResistantState>,
+        quantum_resistant_rekey_interval: Option<Constraint<RekeyInterval>>,
         daita: Option<BooleanOption>,
         daita_direct_only: Option<BooleanOption>,
+        daita_level: Option<DaitaLevel>,
         rotation_interval: Option<Constraint<RotationInterval>>,
+        backend: Option<WireguardBackend>,
+        make_before_break: Option<BooleanOption>,
         rotate_key: Option<RotateKey>,
     ) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
@@ -194,6 +377,19 @@ impl Tunnel {
             println!("Quantum resistant setting has been updated");
         }
 
+        if let Some(interval) = quantum_resistant_rekey_interval {
+            match interval {
+                Constraint::Only(interval) => {
+                    rpc.set_quantum_resistant_rekey_interval(interval).await?;
+                    println!("Set quantum-resistant re-key interval to {interval}");
+                }
+                Constraint::Any => {
+                    rpc.reset_quantum_resistant_rekey_interval().await?;
+                    println!("Disabled quantum-resistant re-keying");
+                }
+            }
+        }
+
         if let Some(enable_daita) = daita {
             rpc.set_enable_daita(*enable_daita).await?;
             println!("DAITA setting has been updated");
@@ -205,6 +401,11 @@ impl Tunnel {
             println!("Direct only setting has been updated");
         }
 
+        if let Some(level) = daita_level {
+            rpc.set_daita_level(level).await?;
+            println!("DAITA level has been updated");
+        }
+
         if let Some(interval) = rotation_interval {
             match interval {
                 Constraint::Only(interval) => {
@@ -221,6 +422,17 @@ impl Tunnel {
             }
         }
 
+        if let Some(backend) = backend {
+            rpc.set_wireguard_backend(backend).await?;
+            println!("WireGuard backend has been updated");
+        }
+
+        if let Some(make_before_break) = make_before_break {
+            rpc.set_wireguard_make_before_break(*make_before_break)
+                .await?;
+            println!("Make before break setting has been updated");
+        }
+
         if matches!(rotate_key, Some(RotateKey::RotateKey)) {
             rpc.rotate_wireguard_key().await?;
             println!("Rotated WireGuard key");
@@ -229,3 +441,8 @@ impl Tunnel {
         Ok(())
     }
 }
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value.parse().map_err(|_| "not a number".to_string())?;
+    Duration::try_from_secs_f64(seconds).map_err(|error| error.to_string())
+}
@@ -6,7 +6,7 @@ use mullvad_types::{
     states::TunnelState,
 };
 use talpid_types::{
-    net::{Endpoint, TunnelEndpoint},
+    net::{stats::TunnelStats, Endpoint, TunnelEndpoint},
     tunnel::{ActionAfterDisconnect, ErrorState},
 };
 
@@ -145,6 +145,72 @@ pub fn print_state(state: &TunnelState, previous_state: Option<&TunnelState>, ve
     }
 }
 
+pub fn print_tunnel_stats(stats: &TunnelStats) {
+    println!("Tunnel statistics");
+    for peer in &stats.peers {
+        print_option!("Peer", peer.public_key);
+        print_option!("Sent", format_traffic(peer.tx_bytes, peer.tx_rate));
+        print_option!("Received", format_traffic(peer.rx_bytes, peer.rx_rate));
+        let last_handshake = match peer.last_handshake {
+            Some(last_handshake) => {
+                let age = stats
+                    .timestamp
+                    .duration_since(last_handshake)
+                    .unwrap_or_default();
+                format!("{} s ago", age.as_secs())
+            }
+            None => "never".to_owned(),
+        };
+        print_option!("Last handshake", last_handshake);
+    }
+    if let Some(rtt) = stats.ping_rtt_upper_bound {
+        print_option!(
+            "Ping round-trip time upper bound",
+            format!("{} ms", rtt.as_millis())
+        );
+    }
+    if let Some(obfuscator) = &stats.obfuscator {
+        print_option!(
+            "Obfuscator sent",
+            format!(
+                "{} ({} packets)",
+                format_bytes(obfuscator.tx_bytes),
+                obfuscator.tx_packets
+            )
+        );
+        print_option!(
+            "Obfuscator received",
+            format!(
+                "{} ({} packets)",
+                format_bytes(obfuscator.rx_bytes),
+                obfuscator.rx_packets
+            )
+        );
+    }
+}
+
+fn format_traffic(bytes: u64, rate: Option<f64>) -> String {
+    match rate {
+        Some(rate) => format!("{} ({}/s)", format_bytes(bytes), format_bytes(rate as u64)),
+        None => format_bytes(bytes),
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
 fn connection_information(
     endpoint: Option<&TunnelEndpoint>,
     location: Option<&GeoIpLocation>,
@@ -169,6 +235,23 @@ fn connection_information(
         .filter(|_| verbose)
         .map(|endpoint| endpoint.tunnel_type.to_string());
     info.insert("Tunnel type", tunnel_type_fmt);
+    let daita_fmt = endpoint
+        .filter(|_| verbose)
+        .and_then(|endpoint| endpoint.daita_parameters)
+        .map(|daita| {
+            format!(
+                "level {}, max padding {:.0}%, max blocking {:.0}%",
+                daita.level,
+                daita.max_padding_frac * 100.0,
+                daita.max_blocking_frac * 100.0,
+            )
+        });
+    info.insert("DAITA", daita_fmt);
+    let wireguard_backend_fmt = endpoint
+        .filter(|_| verbose)
+        .and_then(|endpoint| endpoint.wireguard_backend)
+        .map(|backend| backend.to_string());
+    info.insert("WireGuard backend", wireguard_backend_fmt);
 
     info.insert("Visible location", location.map(format_location));
     let features_fmt = feature_indicators
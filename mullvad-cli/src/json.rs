@@ -0,0 +1,894 @@
+//! Machine-readable output, enabled by the global `--json` flag.
+//!
+//! The types in this module define the JSON schema of each command that supports `--json`. They
+//! are built from `mullvad-types`, but kept separate from them so that the output only changes
+//! when the schema here does. The schemas are covered by snapshot tests.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use mullvad_types::{
+    access_method::{self, AccessMethodSetting, BuiltInAccessMethod},
+    account::AccountData,
+    constraints::Constraint,
+    custom_list::{self, CustomListsSettings},
+    device::{self, AccountAndDevice},
+    relay_constraints::{self, GeographicLocationConstraint, Ownership, RelaySettings},
+    relay_list::{self, RelayEndpointData},
+    session_history::{self, SessionEndReason},
+    settings::{DnsOptions, DnsState, TunnelOptions},
+    wireguard::PublicKey,
+    ConnectionConfig,
+};
+use serde::Serialize;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use talpid_types::net::{
+    proxy::CustomProxy, IpVersion, ObfuscationType, TransportProtocol, TunnelType,
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Make commands print JSON instead of human-readable text.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Print `value` as pretty-printed JSON.
+pub fn print(value: &impl Serialize) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).context("Failed to format output as JSON")?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Output of `account get`.
+#[derive(Serialize, Debug)]
+pub struct Account {
+    pub state: AccountState,
+    pub account_number: Option<String>,
+    pub account_id: Option<String>,
+    pub expiry: Option<DateTime<Utc>>,
+    pub device: Option<Device>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountState {
+    LoggedIn,
+    LoggedOut,
+    Revoked,
+}
+
+impl Account {
+    pub fn logged_in(device: &AccountAndDevice, data: &AccountData) -> Self {
+        Account {
+            state: AccountState::LoggedIn,
+            account_number: Some(device.account_number.clone()),
+            account_id: Some(data.id.clone()),
+            expiry: Some(data.expiry),
+            device: Some(Device::from(&device.device)),
+        }
+    }
+
+    pub fn logged_out() -> Self {
+        Account {
+            state: AccountState::LoggedOut,
+            account_number: None,
+            account_id: None,
+            expiry: None,
+            device: None,
+        }
+    }
+
+    /// The device was revoked. `account_number` is the most recently used account, if any.
+    pub fn revoked(account_number: Option<String>) -> Self {
+        Account {
+            state: AccountState::Revoked,
+            account_number,
+            ..Self::logged_out()
+        }
+    }
+}
+
+/// A device, as listed by `account get` and `account list-devices`.
+#[derive(Serialize, Debug)]
+pub struct Device {
+    pub id: String,
+    pub name: String,
+    pub public_key: String,
+    pub created: DateTime<Utc>,
+}
+
+impl From<&device::Device> for Device {
+    fn from(device: &device::Device) -> Self {
+        Device {
+            id: device.id.clone(),
+            name: device.pretty_name(),
+            public_key: device.pubkey.to_string(),
+            created: device.created,
+        }
+    }
+}
+
+/// Output of `dns get`.
+#[derive(Serialize, Debug)]
+pub struct Dns {
+    pub custom: bool,
+    pub content_blockers: ContentBlockers,
+    pub custom_servers: Vec<IpAddr>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ContentBlockers {
+    pub ads: bool,
+    pub trackers: bool,
+    pub malware: bool,
+    pub adult_content: bool,
+    pub gambling: bool,
+    pub social_media: bool,
+}
+
+impl From<&DnsOptions> for Dns {
+    fn from(options: &DnsOptions) -> Self {
+        let blockers = &options.default_options;
+        Dns {
+            custom: options.state == DnsState::Custom,
+            content_blockers: ContentBlockers {
+                ads: blockers.block_ads,
+                trackers: blockers.block_trackers,
+                malware: blockers.block_malware,
+                adult_content: blockers.block_adult_content,
+                gambling: blockers.block_gambling,
+                social_media: blockers.block_social_media,
+            },
+            custom_servers: options.custom_options.addresses.clone(),
+        }
+    }
+}
+
+/// Output of `tunnel get`.
+#[derive(Serialize, Debug)]
+pub struct Tunnel {
+    pub openvpn: OpenVpnOptions,
+    pub wireguard: WireguardOptions,
+    pub ipv6: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OpenVpnOptions {
+    pub mssfix: Option<u16>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WireguardOptions {
+    pub mtu: Option<u16>,
+    pub quantum_resistant: String,
+    pub quantum_resistant_rekey_interval_secs: Option<u64>,
+    pub daita: bool,
+    pub daita_direct_only: bool,
+    pub daita_level: String,
+    pub backend: String,
+    pub make_before_break: bool,
+    pub public_key: String,
+    pub key_created: DateTime<Utc>,
+    pub rotation_interval_secs: Option<u64>,
+    pub connectivity: ConnectivityOptions,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ConnectivityOptions {
+    pub rx_timeout_secs: f64,
+    pub traffic_timeout_secs: f64,
+    pub ping_timeout_secs: f64,
+    pub ping_interval_secs: f64,
+    pub establish_timeout_secs: f64,
+    /// Address pinged inside the tunnel, or `None` for the tunnel gateway.
+    pub ping_target: Option<Ipv4Addr>,
+}
+
+impl Tunnel {
+    pub fn new(options: &TunnelOptions, key: &PublicKey) -> Self {
+        let wireguard = &options.wireguard;
+        let connectivity = &wireguard.connectivity;
+        Tunnel {
+            openvpn: OpenVpnOptions {
+                mssfix: options.openvpn.mssfix,
+            },
+            wireguard: WireguardOptions {
+                mtu: wireguard.mtu,
+                quantum_resistant: wireguard.quantum_resistant.to_string(),
+                quantum_resistant_rekey_interval_secs: wireguard
+                    .quantum_resistant_rekey_interval
+                    .map(|interval| interval.as_duration().as_secs()),
+                daita: wireguard.daita.enabled,
+                daita_direct_only: !wireguard.daita.use_multihop_if_necessary,
+                daita_level: wireguard.daita.level.to_string(),
+                backend: wireguard.backend.to_string(),
+                make_before_break: wireguard.make_before_break,
+                public_key: key.key.to_string(),
+                key_created: key.created,
+                rotation_interval_secs: wireguard
+                    .rotation_interval
+                    .map(|interval| interval.as_duration().as_secs()),
+                connectivity: ConnectivityOptions {
+                    rx_timeout_secs: connectivity.rx_timeout.as_secs_f64(),
+                    traffic_timeout_secs: connectivity.traffic_timeout.as_secs_f64(),
+                    ping_timeout_secs: connectivity.ping_timeout.as_secs_f64(),
+                    ping_interval_secs: connectivity.ping_interval.as_secs_f64(),
+                    establish_timeout_secs: connectivity.establish_timeout.as_secs_f64(),
+                    ping_target: connectivity.ping_target,
+                },
+            },
+            ipv6: options.generic.enable_ipv6,
+        }
+    }
+}
+
+/// Output of `relay get`.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RelayConstraints {
+    Normal {
+        location: Option<LocationConstraint>,
+        providers: Option<Vec<String>>,
+        /// Either "mullvad_owned" or "rented".
+        ownership: Option<&'static str>,
+        /// Either "wireguard" or "openvpn".
+        tunnel_protocol: &'static str,
+        openvpn: OpenVpnConstraints,
+        wireguard: WireguardConstraints,
+    },
+    CustomTunnelEndpoint {
+        host: String,
+        /// Either "wireguard" or "openvpn".
+        tunnel_type: &'static str,
+        address: SocketAddr,
+        transport_protocol: TransportProtocol,
+    },
+}
+
+/// A location constraint, as used by `relay get`.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LocationConstraint {
+    Location(Location),
+    CustomList {
+        id: String,
+        /// `None` if the custom list no longer exists.
+        name: Option<String>,
+    },
+}
+
+#[derive(Serialize, Debug)]
+pub struct OpenVpnConstraints {
+    pub port: Option<u16>,
+    pub transport_protocol: Option<TransportProtocol>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WireguardConstraints {
+    pub port: Option<u16>,
+    /// Either "ipv4" or "ipv6".
+    pub ip_version: Option<&'static str>,
+    pub multihop: bool,
+    pub entry_location: Option<LocationConstraint>,
+}
+
+impl RelayConstraints {
+    pub fn new(settings: &RelaySettings, custom_lists: &CustomListsSettings) -> Self {
+        let constraints = match settings {
+            RelaySettings::Normal(constraints) => constraints,
+            RelaySettings::CustomTunnelEndpoint(custom) => {
+                let endpoint = custom.endpoint();
+                return RelayConstraints::CustomTunnelEndpoint {
+                    host: custom.host.clone(),
+                    tunnel_type: match custom.config {
+                        ConnectionConfig::OpenVpn(_) => "openvpn",
+                        ConnectionConfig::Wireguard(_) => "wireguard",
+                    },
+                    address: endpoint.address,
+                    transport_protocol: endpoint.protocol,
+                };
+            }
+        };
+        let location = |location: &Constraint<relay_constraints::LocationConstraint>| {
+            location
+                .as_ref()
+                .option()
+                .map(|location| LocationConstraint::new(location, custom_lists))
+        };
+        let openvpn_port = constraints.openvpn_constraints.port.option();
+        let wireguard = &constraints.wireguard_constraints;
+        RelayConstraints::Normal {
+            location: location(&constraints.location),
+            providers: constraints.providers.as_ref().option().map(|providers| {
+                let mut providers: Vec<_> = providers.providers().iter().cloned().collect();
+                providers.sort();
+                providers
+            }),
+            ownership: constraints
+                .ownership
+                .option()
+                .map(|ownership| match ownership {
+                    Ownership::MullvadOwned => "mullvad_owned",
+                    Ownership::Rented => "rented",
+                }),
+            tunnel_protocol: tunnel_type(constraints.tunnel_protocol),
+            openvpn: OpenVpnConstraints {
+                port: openvpn_port.and_then(|port| port.port.option()),
+                transport_protocol: openvpn_port.map(|port| port.protocol),
+            },
+            wireguard: WireguardConstraints {
+                port: wireguard.port.option(),
+                ip_version: wireguard.ip_version.option().map(|version| match version {
+                    IpVersion::V4 => "ipv4",
+                    IpVersion::V6 => "ipv6",
+                }),
+                multihop: wireguard.multihop(),
+                entry_location: location(&wireguard.entry_location),
+            },
+        }
+    }
+}
+
+impl LocationConstraint {
+    fn new(
+        location: &relay_constraints::LocationConstraint,
+        custom_lists: &CustomListsSettings,
+    ) -> Self {
+        match location {
+            relay_constraints::LocationConstraint::Location(location) => {
+                LocationConstraint::Location(Location::from(location))
+            }
+            relay_constraints::LocationConstraint::CustomList { list_id } => {
+                LocationConstraint::CustomList {
+                    id: list_id.to_string(),
+                    name: custom_lists
+                        .iter()
+                        .find(|list| &list.id == list_id)
+                        .map(|list| list.name.clone()),
+                }
+            }
+        }
+    }
+}
+
+fn tunnel_type(tunnel_type: TunnelType) -> &'static str {
+    match tunnel_type {
+        TunnelType::OpenVpn => "openvpn",
+        TunnelType::Wireguard => "wireguard",
+    }
+}
+
+/// A country in the output of `relay list`.
+#[derive(Serialize, Debug)]
+pub struct RelayListCountry {
+    pub name: String,
+    pub code: String,
+    pub cities: Vec<RelayListCity>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RelayListCity {
+    pub name: String,
+    pub code: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub relays: Vec<Relay>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Relay {
+    pub hostname: String,
+    pub ipv4_addr_in: Ipv4Addr,
+    pub ipv6_addr_in: Option<Ipv6Addr>,
+    /// Either "wireguard", "openvpn" or "bridge".
+    pub tunnel_type: &'static str,
+    pub provider: String,
+    pub owned: bool,
+}
+
+impl From<&relay_list::RelayListCountry> for RelayListCountry {
+    fn from(country: &relay_list::RelayListCountry) -> Self {
+        RelayListCountry {
+            name: country.name.clone(),
+            code: country.code.clone(),
+            cities: country.cities.iter().map(RelayListCity::from).collect(),
+        }
+    }
+}
+
+impl From<&relay_list::RelayListCity> for RelayListCity {
+    fn from(city: &relay_list::RelayListCity) -> Self {
+        RelayListCity {
+            name: city.name.clone(),
+            code: city.code.clone(),
+            latitude: city.latitude,
+            longitude: city.longitude,
+            relays: city.relays.iter().map(Relay::from).collect(),
+        }
+    }
+}
+
+impl From<&relay_list::Relay> for Relay {
+    fn from(relay: &relay_list::Relay) -> Self {
+        Relay {
+            hostname: relay.hostname.clone(),
+            ipv4_addr_in: relay.ipv4_addr_in,
+            ipv6_addr_in: relay.ipv6_addr_in,
+            tunnel_type: match relay.endpoint_data {
+                RelayEndpointData::Openvpn => "openvpn",
+                RelayEndpointData::Wireguard(_) => "wireguard",
+                RelayEndpointData::Bridge => "bridge",
+            },
+            provider: relay.provider.clone(),
+            owned: relay.owned,
+        }
+    }
+}
+
+/// An API access method, as listed by `api-access list` and `api-access get`.
+#[derive(Serialize, Debug)]
+pub struct AccessMethod {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub access_method: AccessMethodType,
+}
+
+/// How the API is reached. Passwords and keys of custom proxies are left out.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AccessMethodType {
+    Direct,
+    Bridge,
+    EncryptedDnsProxy,
+    Shadowsocks {
+        peer: String,
+        cipher: String,
+    },
+    Socks5Local {
+        peer: String,
+        transport_protocol: TransportProtocol,
+        local_port: u16,
+    },
+    Socks5Remote {
+        peer: String,
+        authenticated: bool,
+    },
+    HttpConnect {
+        peer: String,
+        authenticated: bool,
+        tls: bool,
+        tls_server_name: Option<String>,
+    },
+}
+
+impl From<&AccessMethodSetting> for AccessMethod {
+    fn from(setting: &AccessMethodSetting) -> Self {
+        AccessMethod {
+            id: setting.get_id().to_string(),
+            name: setting.name.clone(),
+            enabled: setting.enabled,
+            access_method: AccessMethodType::from(&setting.access_method),
+        }
+    }
+}
+
+impl From<&access_method::AccessMethod> for AccessMethodType {
+    fn from(method: &access_method::AccessMethod) -> Self {
+        match method {
+            access_method::AccessMethod::BuiltIn(BuiltInAccessMethod::Direct) => {
+                AccessMethodType::Direct
+            }
+            access_method::AccessMethod::BuiltIn(BuiltInAccessMethod::Bridge) => {
+                AccessMethodType::Bridge
+            }
+            access_method::AccessMethod::BuiltIn(BuiltInAccessMethod::EncryptedDnsProxy) => {
+                AccessMethodType::EncryptedDnsProxy
+            }
+            access_method::AccessMethod::Custom(CustomProxy::Shadowsocks(shadowsocks)) => {
+                AccessMethodType::Shadowsocks {
+                    peer: shadowsocks.endpoint.to_string(),
+                    cipher: shadowsocks.cipher.clone(),
+                }
+            }
+            access_method::AccessMethod::Custom(CustomProxy::Socks5Local(local)) => {
+                AccessMethodType::Socks5Local {
+                    peer: local.remote_endpoint.address.to_string(),
+                    transport_protocol: local.remote_endpoint.protocol,
+                    local_port: local.local_port,
+                }
+            }
+            access_method::AccessMethod::Custom(CustomProxy::Socks5Remote(remote)) => {
+                AccessMethodType::Socks5Remote {
+                    peer: remote.endpoint.to_string(),
+                    authenticated: remote.auth.is_some(),
+                }
+            }
+            access_method::AccessMethod::Custom(CustomProxy::HttpConnect(http)) => {
+                AccessMethodType::HttpConnect {
+                    peer: http.endpoint.to_string(),
+                    authenticated: http.auth.is_some(),
+                    tls: http.tls.is_some(),
+                    tls_server_name: http.tls.as_ref().and_then(|tls| tls.server_name.clone()),
+                }
+            }
+        }
+    }
+}
+
+/// A custom list, as listed by `custom-list list`.
+#[derive(Serialize, Debug)]
+pub struct CustomList {
+    pub id: String,
+    pub name: String,
+    pub locations: Vec<Location>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Location {
+    pub country: String,
+    pub city: Option<String>,
+    pub hostname: Option<String>,
+}
+
+impl From<&custom_list::CustomList> for CustomList {
+    fn from(list: &custom_list::CustomList) -> Self {
+        CustomList {
+            id: list.id.to_string(),
+            name: list.name.clone(),
+            locations: list.locations.iter().map(Location::from).collect(),
+        }
+    }
+}
+
+impl From<&GeographicLocationConstraint> for Location {
+    fn from(location: &GeographicLocationConstraint) -> Self {
+        let (country, city, hostname) = match location {
+            GeographicLocationConstraint::Country(country) => (country, None, None),
+            GeographicLocationConstraint::City(country, city) => (country, Some(city), None),
+            GeographicLocationConstraint::Hostname(country, city, hostname) => {
+                (country, Some(city), Some(hostname))
+            }
+        };
+        Location {
+            country: country.clone(),
+            city: city.cloned(),
+            hostname: hostname.cloned(),
+        }
+    }
+}
+
+/// A session, as listed by `history`.
+#[derive(Serialize, Debug)]
+pub struct Session {
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub relay: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    /// Either "wireguard" or "openvpn".
+    pub tunnel_type: &'static str,
+    /// Either "udp2tcp", "shadowsocks" or "quic".
+    pub obfuscation: Option<&'static str>,
+    pub connect_attempts: u32,
+    /// Either "disconnected", "error" or "interrupted", or `None` if the session is ongoing.
+    pub end_reason: Option<&'static str>,
+    /// Why the tunnel entered the error state, if `end_reason` is "error".
+    pub error: Option<String>,
+    pub usage: DataUsage,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DataUsage {
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+}
+
+impl From<&session_history::Session> for Session {
+    fn from(session: &session_history::Session) -> Self {
+        Session {
+            start: session.start,
+            end: session.end,
+            relay: session.relay.clone(),
+            country: session.country.clone(),
+            city: session.city.clone(),
+            tunnel_type: tunnel_type(session.tunnel_type),
+            obfuscation: session.obfuscation.map(|obfuscation| match obfuscation {
+                ObfuscationType::Udp2Tcp => "udp2tcp",
+                ObfuscationType::Shadowsocks => "shadowsocks",
+                ObfuscationType::Quic => "quic",
+            }),
+            connect_attempts: session.connect_attempts,
+            end_reason: session.end_reason.as_ref().map(|reason| match reason {
+                SessionEndReason::Disconnected => "disconnected",
+                SessionEndReason::Error(_) => "error",
+                SessionEndReason::Interrupted => "interrupted",
+            }),
+            error: match &session.end_reason {
+                Some(SessionEndReason::Error(cause)) => Some(cause.to_string()),
+                _ => None,
+            },
+            usage: DataUsage {
+                tx_bytes: session.usage.tx_bytes,
+                rx_bytes: session.usage.rx_bytes,
+            },
+        }
+    }
+}
+
+/// Output of `split-tunnel list`.
+#[cfg(target_os = "linux")]
+#[derive(Serialize, Debug)]
+pub struct SplitTunnelProcesses {
+    pub pids: Vec<i32>,
+}
+
+/// Output of `split-tunnel get`.
+#[cfg(any(windows, target_os = "macos"))]
+#[derive(Serialize, Debug)]
+pub struct SplitTunnel {
+    pub enabled: bool,
+    pub apps: Vec<String>,
+    /// Processes that are currently excluded, if requested with `--list-processes`.
+    #[cfg(windows)]
+    pub processes: Option<Vec<ExcludedProcess>>,
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+impl SplitTunnel {
+    pub fn new(settings: &mullvad_types::settings::SplitTunnelSettings) -> Self {
+        let mut apps: Vec<_> = settings
+            .apps
+            .iter()
+            .map(|app| app.display().to_string())
+            .collect();
+        apps.sort();
+        SplitTunnel {
+            enabled: settings.enable_exclusions,
+            apps,
+            #[cfg(windows)]
+            processes: None,
+        }
+    }
+}
+
+#[cfg(windows)]
+#[derive(Serialize, Debug)]
+pub struct ExcludedProcess {
+    pub pid: u32,
+    pub image: String,
+    /// Whether the process is excluded because its parent is.
+    pub inherited: bool,
+}
+
+#[cfg(windows)]
+impl From<&talpid_types::split_tunnel::ExcludedProcess> for ExcludedProcess {
+    fn from(process: &talpid_types::split_tunnel::ExcludedProcess) -> Self {
+        ExcludedProcess {
+            pid: process.pid,
+            image: process.image.display().to_string(),
+            inherited: process.inherited,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::{
+        relay_constraints::RelayConstraints as Constraints, relay_list::WireguardRelayEndpointData,
+        session_history::Session as HistorySession,
+    };
+    use talpid_types::net::{
+        proxy::{HttpAuth, HttpConnect, HttpProxyTls, Shadowsocks},
+        wireguard, TunnelType,
+    };
+
+    fn time() -> DateTime<Utc> {
+        "2025-01-02T03:04:05Z".parse().unwrap()
+    }
+
+    fn device() -> device::Device {
+        device::Device {
+            id: "d1b5d6a0-0f4d-4fa5-9a43-0e4c4f3b8a2e".to_owned(),
+            name: "happy seagull".to_owned(),
+            pubkey: wireguard::PublicKey::from_base64(
+                "sD6BrFbAYMu0r2oqk+VjzYYcNlwEJdQyLAB1etuhaEw=",
+            )
+            .unwrap(),
+            hijack_dns: false,
+            created: time(),
+        }
+    }
+
+    #[test]
+    fn test_account() {
+        let device = AccountAndDevice {
+            account_number: "1234123412341234".to_owned(),
+            device: device(),
+        };
+        let data = AccountData {
+            id: "a9f3c1e2-5b6d-4c7e-8f90-1a2b3c4d5e6f".to_owned(),
+            expiry: time(),
+        };
+        insta::assert_json_snapshot!("account_logged_in", Account::logged_in(&device, &data));
+        insta::assert_json_snapshot!("account_logged_out", Account::logged_out());
+        insta::assert_json_snapshot!(
+            "account_revoked",
+            Account::revoked(Some("1234123412341234".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_dns() {
+        let mut options = DnsOptions::default();
+        options.default_options.block_ads = true;
+        options.custom_options.addresses = vec!["192.0.2.53".parse().unwrap()];
+        insta::assert_json_snapshot!(Dns::from(&options));
+    }
+
+    #[test]
+    fn test_tunnel() {
+        let mut options = TunnelOptions::default();
+        options.generic.enable_ipv6 = false;
+        let key = PublicKey {
+            key: device().pubkey,
+            created: time(),
+        };
+        insta::assert_json_snapshot!(Tunnel::new(&options, &key));
+    }
+
+    #[test]
+    fn test_relay_constraints() {
+        let mut custom_lists = CustomListsSettings::default();
+        let mut list = custom_list::CustomList::new("Nordics".to_owned()).unwrap();
+        list.id = "7c1e0f3a-9d2b-4e5f-8a6b-1c2d3e4f5a6b".parse().unwrap();
+        let list_id = list.id;
+        custom_lists.add(list).unwrap();
+
+        let mut constraints = Constraints {
+            location: Constraint::Only(relay_constraints::LocationConstraint::CustomList {
+                list_id,
+            }),
+            ..Default::default()
+        };
+        constraints.wireguard_constraints.use_multihop(true);
+        constraints.wireguard_constraints.entry_location = Constraint::Only(
+            GeographicLocationConstraint::City("se".to_owned(), "got".to_owned()).into(),
+        );
+        let relay_settings = RelaySettings::Normal(constraints);
+        insta::assert_json_snapshot!(RelayConstraints::new(&relay_settings, &custom_lists));
+    }
+
+    #[test]
+    fn test_relay_list() {
+        let relay = relay_list::Relay {
+            hostname: "se-got-wg-001".to_owned(),
+            ipv4_addr_in: "192.0.2.1".parse().unwrap(),
+            ipv6_addr_in: Some("2001:db8::1".parse().unwrap()),
+            overridden_ipv4: false,
+            overridden_ipv6: false,
+            include_in_country: true,
+            active: true,
+            owned: true,
+            provider: "31173".to_owned(),
+            weight: 100,
+            endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
+                public_key: device().pubkey,
+                daita: true,
+                shadowsocks_extra_addr_in: vec![],
+            }),
+            location: mullvad_types::location::Location {
+                country: "Sweden".to_owned(),
+                country_code: "se".to_owned(),
+                city: "Gothenburg".to_owned(),
+                city_code: "got".to_owned(),
+                latitude: 57.70887,
+                longitude: 11.97456,
+            },
+        };
+        let country = relay_list::RelayListCountry {
+            name: "Sweden".to_owned(),
+            code: "se".to_owned(),
+            cities: vec![relay_list::RelayListCity {
+                name: "Gothenburg".to_owned(),
+                code: "got".to_owned(),
+                latitude: 57.70887,
+                longitude: 11.97456,
+                relays: vec![relay],
+            }],
+        };
+        insta::assert_json_snapshot!(vec![RelayListCountry::from(&country)]);
+    }
+
+    #[test]
+    fn test_access_methods() {
+        let direct = AccessMethodSetting::with_id(
+            access_method::Id::from_string("5a8f2a4e-2f6b-4c33-9a4e-2b0f1d1c6d7e".to_owned())
+                .unwrap(),
+            "Direct".to_owned(),
+            true,
+            access_method::AccessMethod::BuiltIn(BuiltInAccessMethod::Direct),
+        );
+        let shadowsocks = AccessMethodSetting::with_id(
+            access_method::Id::from_string("0c3f0b6e-77f5-4b5e-8d1a-5a0c1b2d3e4f".to_owned())
+                .unwrap(),
+            "My proxy".to_owned(),
+            false,
+            access_method::AccessMethod::from(
+                Shadowsocks::new(
+                    ("192.0.2.2".parse::<IpAddr>().unwrap(), 443),
+                    "aes-256-gcm".to_owned(),
+                    "password".to_owned(),
+                )
+                .unwrap(),
+            ),
+        );
+        let http = AccessMethodSetting::with_id(
+            access_method::Id::from_string("9b2d4c6e-1a3f-4e5d-8c7b-6a5f4e3d2c1b".to_owned())
+                .unwrap(),
+            "My HTTP proxy".to_owned(),
+            true,
+            access_method::AccessMethod::from(CustomProxy::HttpConnect(HttpConnect {
+                endpoint: "proxy.example.com:8443".parse().unwrap(),
+                auth: Some(HttpAuth::new("user".to_owned(), "password".to_owned()).unwrap()),
+                tls: Some(HttpProxyTls::default()),
+            })),
+        );
+        insta::assert_json_snapshot!(vec![
+            AccessMethod::from(&direct),
+            AccessMethod::from(&shadowsocks),
+            AccessMethod::from(&http),
+        ]);
+    }
+
+    #[test]
+    fn test_custom_list() {
+        let mut list = custom_list::CustomList::new("Nordics".to_owned()).unwrap();
+        list.id = "7c1e0f3a-9d2b-4e5f-8a6b-1c2d3e4f5a6b".parse().unwrap();
+        list.locations
+            .insert(GeographicLocationConstraint::Country("no".to_owned()));
+        list.locations
+            .insert(GeographicLocationConstraint::Hostname(
+                "se".to_owned(),
+                "got".to_owned(),
+                "se-got-wg-001".to_owned(),
+            ));
+        insta::assert_json_snapshot!(CustomList::from(&list));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_split_tunnel() {
+        insta::assert_json_snapshot!(SplitTunnelProcesses {
+            pids: vec![1234, 5678]
+        });
+    }
+
+    #[test]
+    fn test_history() {
+        let session = HistorySession {
+            start: time(),
+            end: Some(time() + chrono::Duration::minutes(30)),
+            relay: Some("se-got-wg-001".to_owned()),
+            country: Some("Sweden".to_owned()),
+            city: Some("Gothenburg".to_owned()),
+            tunnel_type: TunnelType::Wireguard,
+            obfuscation: None,
+            connect_attempts: 2,
+            end_reason: Some(SessionEndReason::Disconnected),
+            usage: mullvad_types::data_usage::DataUsage {
+                tx_bytes: 1024,
+                rx_bytes: 4096,
+            },
+        };
+        insta::assert_json_snapshot!(vec![Session::from(&session)]);
+    }
+}
@@ -1,8 +1,9 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand};
 
 mod cmds;
 mod format;
+mod json;
 use cmds::*;
 
 pub const BIN_NAME: &str = env!("CARGO_BIN_NAME");
@@ -10,7 +11,17 @@ pub const BIN_NAME: &str = env!("CARGO_BIN_NAME");
 #[derive(Debug, Parser)]
 #[command(author, version = mullvad_version::VERSION, about, long_about = None)]
 #[command(propagate_version = true)]
-enum Cli {
+struct Cli {
+    /// Format output as JSON. Only supported by commands that display information
+    #[arg(long, short = 'j', global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
     /// Control and display information about your Mullvad account
     #[clap(subcommand)]
     Account(account::Account),
@@ -34,10 +45,17 @@ enum Cli {
     #[clap(subcommand, hide = true)]
     Debug(debug::DebugCommands),
 
+    /// Display data usage and manage the data usage quota
+    #[clap(subcommand)]
+    DataUsage(data_usage::DataUsage),
+
     /// Configure DNS servers to use when connected
     #[clap(subcommand)]
     Dns(dns::Dns),
 
+    /// Display the history of tunnel sessions
+    History(history::HistoryArgs),
+
     /// Control the allow local network sharing setting
     #[clap(subcommand)]
     Lan(lan::Lan),
@@ -158,32 +176,45 @@ async fn main() -> Result<()> {
     #[cfg(unix)]
     handle_sigpipe().unwrap();
 
-    match Cli::parse() {
-        Cli::Account(cmd) => cmd.handle().await,
-        Cli::Bridge(cmd) => cmd.handle().await,
-        Cli::Connect { wait } => tunnel_state::connect(wait).await,
-        Cli::Reconnect { wait } => tunnel_state::reconnect(wait).await,
-        Cli::Debug(cmd) => cmd.handle().await,
-        Cli::Disconnect { wait } => tunnel_state::disconnect(wait).await,
-        Cli::AutoConnect(cmd) => cmd.handle().await,
-        Cli::BetaProgram(cmd) => cmd.handle().await,
-        Cli::LockdownMode(cmd) => cmd.handle().await,
-        Cli::Dns(cmd) => cmd.handle().await,
-        Cli::Lan(cmd) => cmd.handle().await,
-        Cli::Obfuscation(cmd) => cmd.handle().await,
-        Cli::ApiAccess(cmd) => cmd.handle().await,
-        Cli::Version => version::print().await,
-        Cli::FactoryReset => reset::handle().await,
-        Cli::Relay(cmd) => cmd.handle().await,
-        Cli::Tunnel(cmd) => cmd.handle().await,
-        Cli::SplitTunnel(cmd) => cmd.handle().await,
-        Cli::Status { cmd, args } => status::handle(cmd, args).await,
-        Cli::CustomList(cmd) => cmd.handle().await,
-        Cli::ImportSettings { file } => patch::import(file).await,
-        Cli::ExportSettings { file } => patch::export(file).await,
+    let cli = Cli::parse();
+    if cli.json {
+        if !cli.command.supports_json() {
+            bail!("This command does not support --json");
+        }
+        json::enable();
+    }
+
+    match cli.command {
+        Command::Account(cmd) => cmd.handle().await,
+        Command::Bridge(cmd) => cmd.handle().await,
+        Command::Connect { wait } => tunnel_state::connect(wait).await,
+        Command::Reconnect { wait } => tunnel_state::reconnect(wait).await,
+        Command::Debug(cmd) => cmd.handle().await,
+        Command::Disconnect { wait } => tunnel_state::disconnect(wait).await,
+        Command::AutoConnect(cmd) => cmd.handle().await,
+        Command::BetaProgram(cmd) => cmd.handle().await,
+        Command::LockdownMode(cmd) => cmd.handle().await,
+        Command::DataUsage(cmd) => cmd.handle().await,
+        Command::Dns(cmd) => cmd.handle().await,
+        Command::History(args) => history::handle(args).await,
+        Command::Lan(cmd) => cmd.handle().await,
+        Command::Obfuscation(cmd) => cmd.handle().await,
+        Command::ApiAccess(cmd) => cmd.handle().await,
+        Command::Version => version::print().await,
+        Command::FactoryReset => reset::handle().await,
+        Command::Relay(cmd) => cmd.handle().await,
+        Command::Tunnel(cmd) => cmd.handle().await,
+        Command::SplitTunnel(cmd) => cmd.handle().await,
+        Command::Status { cmd, mut args } => {
+            args.json = cli.json;
+            status::handle(cmd, args).await
+        }
+        Command::CustomList(cmd) => cmd.handle().await,
+        Command::ImportSettings { file } => patch::import(file).await,
+        Command::ExportSettings { file } => patch::export(file).await,
 
         #[cfg(all(unix, not(target_os = "android")))]
-        Cli::ShellCompletions { shell, dir } => {
+        Command::ShellCompletions { shell, dir } => {
             use anyhow::Context;
             use clap::CommandFactory;
 
@@ -196,6 +227,30 @@ async fn main() -> Result<()> {
     }
 }
 
+impl Command {
+    /// Whether the command can print its output as JSON.
+    fn supports_json(&self) -> bool {
+        match self {
+            Command::Account(cmd) => matches!(
+                cmd,
+                account::Account::Get { .. } | account::Account::ListDevices { .. }
+            ),
+            Command::ApiAccess(cmd) => matches!(
+                cmd,
+                api_access::ApiAccess::Get | api_access::ApiAccess::List
+            ),
+            Command::CustomList(cmd) => matches!(cmd, custom_list::CustomList::List { .. }),
+            Command::Dns(cmd) => matches!(cmd, dns::Dns::Get),
+            Command::History(_) => true,
+            Command::Relay(cmd) => matches!(cmd, relay::Relay::Get | relay::Relay::List),
+            Command::SplitTunnel(cmd) => cmd.supports_json(),
+            Command::Status { .. } => true,
+            Command::Tunnel(cmd) => matches!(cmd, tunnel::Tunnel::Get),
+            _ => false,
+        }
+    }
+}
+
 /// Install the default signal handler for `SIGPIPE`.
 ///
 /// By default, Rust replaces it with an empty handler because reasons: https://github.com/rust-lang/rust/issues/119980
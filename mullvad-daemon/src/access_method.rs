@@ -2,6 +2,7 @@ use crate::{settings, Daemon};
 use mullvad_api::{access_mode, proxy::ApiConnectionMode, rest, ApiProxy};
 use mullvad_types::{
     access_method::{self, AccessMethod, AccessMethodSetting},
+    audit_log::AuditSource,
     settings::Settings,
 };
 
@@ -39,11 +40,14 @@ impl Daemon {
         name: String,
         enabled: bool,
         access_method: AccessMethod,
+        source: AuditSource,
     ) -> Result<access_method::Id, Error> {
         let access_method_setting = AccessMethodSetting::new(name, enabled, access_method);
         let id = access_method_setting.get_id();
         self.settings
-            .update(|settings| settings.api_access_methods.append(access_method_setting))
+            .update(source, |settings| {
+                settings.api_access_methods.append(access_method_setting)
+            })
             .await?;
         Ok(id)
     }
@@ -52,9 +56,10 @@ impl Daemon {
     pub async fn remove_access_method(
         &mut self,
         access_method: access_method::Id,
+        source: AuditSource,
     ) -> Result<(), Error> {
         self.settings
-            .try_update(|settings| -> Result<(), Error> {
+            .try_update(source, |settings| -> Result<(), Error> {
                 settings.api_access_methods.remove(&access_method)?;
                 Ok(())
             })
@@ -75,22 +80,35 @@ impl Daemon {
     /// [`AccessMethodSetting`] is enabled, it is eligible to be part of the
     /// automatic selection of access methods that the Daemon will perform at
     /// start up or if the current access method starts failing.
+    ///
+    /// The access method is selected in the background, and `reply` is called
+    /// once it has been. Selecting an access method resolves it, which may
+    /// require the daemon to let DNS resolvers through the firewall.
     pub async fn use_api_access_method(
         &mut self,
         access_method: access_method::Id,
-    ) -> Result<(), Error> {
-        self.settings
-            .update(|settings| {
+        source: AuditSource,
+        reply: impl FnOnce(Result<(), Error>) + Send + 'static,
+    ) {
+        let result = self
+            .settings
+            .update(source, |settings| {
                 settings.api_access_methods.update(
                     |setting| setting.get_id() == access_method,
                     |setting| setting.enable(),
                 );
             })
-            .await?;
-        self.access_mode_handler
-            .use_access_method(access_method)
-            .await?;
-        Ok(())
+            .await;
+        if let Err(error) = result {
+            reply(Err(error.into()));
+            return;
+        }
+
+        let access_mode_handler = self.access_mode_handler.clone();
+        tokio::spawn(async move {
+            let result = access_mode_handler.use_access_method(access_method).await;
+            reply(result.map_err(Error::from));
+        });
     }
 
     pub fn get_api_access_method(
@@ -111,9 +129,10 @@ impl Daemon {
     pub async fn update_access_method(
         &mut self,
         access_method_update: AccessMethodSetting,
+        source: AuditSource,
     ) -> Result<(), Error> {
         self.settings
-            .update(|settings: &mut Settings| {
+            .update(source, |settings: &mut Settings| {
                 let target = access_method_update.get_id();
                 settings.api_access_methods.update(
                     |access_method| access_method.get_id() == target,
@@ -126,9 +145,12 @@ impl Daemon {
     }
 
     /// Remove all custom [`AccessMethodSetting`].
-    pub async fn clear_custom_api_access_methods(&mut self) -> Result<(), Error> {
+    pub async fn clear_custom_api_access_methods(
+        &mut self,
+        source: AuditSource,
+    ) -> Result<(), Error> {
         self.settings
-            .update(|settings: &mut Settings| {
+            .update(source, |settings: &mut Settings| {
                 settings.api_access_methods.clear_custom();
             })
             .await?;
@@ -193,10 +215,11 @@ impl Daemon {
 
     /// Create an [`ApiProxy`] which will perform all REST requests against one
     /// specific endpoint `connection_mode`.
-    pub fn create_limited_api_proxy(&mut self, connection_mode: ApiConnectionMode) -> ApiProxy {
-        let rest_handle = self
-            .api_runtime
-            .mullvad_rest_handle(connection_mode.into_provider());
+    pub fn create_limited_api_proxy(
+        api_runtime: &mullvad_api::Runtime,
+        connection_mode: ApiConnectionMode,
+    ) -> ApiProxy {
+        let rest_handle = api_runtime.mullvad_rest_handle(connection_mode.into_provider());
         ApiProxy::new(rest_handle)
     }
 
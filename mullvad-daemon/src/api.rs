@@ -1,17 +1,27 @@
-use std::net::SocketAddr;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+};
 
 #[cfg(target_os = "android")]
 use crate::DaemonCommand;
 #[cfg(target_os = "android")]
 use crate::DaemonEventSender;
+#[cfg(not(target_os = "android"))]
+use futures::channel::oneshot;
 use futures::{channel::mpsc, StreamExt};
+#[cfg(not(target_os = "android"))]
+use mullvad_api::access_mode::AccessMethodEvent;
 use mullvad_api::AddressCache;
 use mullvad_api::{
     access_mode::AccessMethodResolver,
     availability::ApiAvailability,
     proxy::{ApiConnectionMode, ProxyConfig},
 };
-use mullvad_encrypted_dns_proxy::state::EncryptedDnsProxyState;
+use mullvad_encrypted_dns_proxy::{
+    config_resolver::{self, Nameserver},
+    state::EncryptedDnsProxyState,
+};
 use mullvad_management_interface::async_trait;
 use mullvad_relay_selector::RelaySelector;
 use mullvad_types::access_method::{AccessMethod, BuiltInAccessMethod};
@@ -22,10 +32,46 @@ use talpid_types::net::Endpoint;
 use talpid_types::net::TransportProtocol;
 use talpid_types::net::{proxy::CustomProxy, AllowedClients, Connectivity};
 
+/// How long to wait for the firewall to let through a DNS resolver before querying it anyway.
+#[cfg(not(target_os = "android"))]
+const ALLOW_RESOLVER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+/// How long to wait for the system resolver to resolve the hostname of a custom proxy.
+const SYSTEM_RESOLVER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 pub struct DaemonAccessMethodResolver {
     relay_selector: RelaySelector,
     encrypted_dns_proxy_cache: EncryptedDnsProxyState,
     address_cache: AddressCache,
+    /// Addresses that the hostnames of custom proxies were last resolved to.
+    proxy_hosts: HashMap<String, ResolvedHost>,
+    #[cfg(not(target_os = "android"))]
+    access_method_event_sender: mpsc::UnboundedSender<(AccessMethodEvent, oneshot::Sender<()>)>,
+}
+
+#[derive(Default)]
+struct ResolvedHost {
+    addrs: Vec<IpAddr>,
+    /// Index of the address to use the next time the host is resolved.
+    next: usize,
+}
+
+impl ResolvedHost {
+    /// Store the result of a new lookup. The position in the addresses is kept unless they have
+    /// changed.
+    fn update(&mut self, mut addrs: Vec<IpAddr>) {
+        // The order of the records may differ between lookups
+        addrs.sort();
+        if self.addrs != addrs {
+            *self = ResolvedHost { addrs, next: 0 };
+        }
+    }
+
+    /// Return the address to use, and move on to the next one.
+    fn next_addr(&mut self) -> Option<IpAddr> {
+        let addr = *self.addrs.get(self.next % self.addrs.len().max(1))?;
+        self.next = self.next.wrapping_add(1);
+        Some(addr)
+    }
 }
 
 impl DaemonAccessMethodResolver {
@@ -33,11 +79,125 @@ impl DaemonAccessMethodResolver {
         relay_selector: RelaySelector,
         encrypted_dns_proxy_cache: EncryptedDnsProxyState,
         address_cache: AddressCache,
+        #[cfg(not(target_os = "android"))] access_method_event_sender: mpsc::UnboundedSender<(
+            AccessMethodEvent,
+            oneshot::Sender<()>,
+        )>,
     ) -> Self {
         Self {
             relay_selector,
             encrypted_dns_proxy_cache,
             address_cache,
+            proxy_hosts: HashMap::new(),
+            #[cfg(not(target_os = "android"))]
+            access_method_event_sender,
+        }
+    }
+
+    /// Resolve the hostname of a custom proxy. The hostname is looked up again every time, and
+    /// successive calls cycle through its addresses, so that all of them are tried if the API
+    /// remains unreachable. If the lookup fails, the addresses from the last successful lookup are
+    /// used.
+    async fn resolve_proxy_host(&mut self, hostname: &str) -> Option<IpAddr> {
+        if let Some(addrs) = self.lookup_host(hostname).await {
+            self.proxy_hosts
+                .entry(hostname.to_owned())
+                .or_default()
+                .update(addrs);
+        }
+
+        let addr = self.proxy_hosts.get_mut(hostname)?.next_addr()?;
+        log::debug!("Using address {addr} for proxy {hostname}");
+        Some(addr)
+    }
+
+    /// Look up `hostname`. The bootstrap DoH resolvers, which are public resolvers queried by IP,
+    /// are tried first. Each resolver is let through the firewall before it is queried, so that
+    /// this works in the blocked state as well.
+    ///
+    /// If none of them can resolve the hostname, the system resolver is used instead. It uses the
+    /// DNS servers of the tunnel while connected, which are the custom DNS servers if set, and the
+    /// DNS servers of the network otherwise. These are only reachable if the firewall allows it.
+    async fn lookup_host(&self, hostname: &str) -> Option<Vec<IpAddr>> {
+        if let Some(addrs) = self.lookup_host_doh(hostname).await {
+            return Some(addrs);
+        }
+
+        log::debug!("Resolving proxy hostname {hostname} using the system resolver");
+        match tokio::time::timeout(
+            SYSTEM_RESOLVER_TIMEOUT,
+            tokio::net::lookup_host((hostname, 0)),
+        )
+        .await
+        {
+            Ok(Ok(addrs)) => {
+                let addrs: Vec<_> = addrs.map(|addr| addr.ip()).collect();
+                if addrs.is_empty() {
+                    log::warn!("Proxy hostname {hostname} has no addresses");
+                    return None;
+                }
+                Some(addrs)
+            }
+            Ok(Err(error)) => {
+                log::warn!(
+                    "Failed to resolve proxy hostname {hostname} using the system resolver: {error}"
+                );
+                None
+            }
+            Err(_) => {
+                log::warn!(
+                    "Timed out resolving proxy hostname {hostname} using the system resolver"
+                );
+                None
+            }
+        }
+    }
+
+    /// Look up `hostname` using the bootstrap DoH resolvers.
+    async fn lookup_host_doh(&self, hostname: &str) -> Option<Vec<IpAddr>> {
+        for nameserver in config_resolver::default_resolvers() {
+            for addr in nameserver.addr {
+                #[cfg(not(target_os = "android"))]
+                self.allow_resolver(addr).await;
+
+                let resolver = Nameserver {
+                    name: nameserver.name.clone(),
+                    addr: vec![addr],
+                };
+                match config_resolver::resolve_host(&[resolver], hostname).await {
+                    Ok(addrs) if !addrs.is_empty() => return Some(addrs),
+                    Ok(_) => log::warn!("Proxy hostname {hostname} has no addresses"),
+                    Err(error) => log::warn!(
+                        "Failed to resolve proxy hostname {hostname} using {} ({addr}): {error}",
+                        nameserver.name
+                    ),
+                }
+            }
+        }
+        None
+    }
+
+    /// Allow the daemon to reach the DoH resolver at `addr`. This replaces the allowed API
+    /// endpoint, so whoever resolves the access method must let the API endpoint through the
+    /// firewall again afterwards, whether or not it could be resolved.
+    #[cfg(not(target_os = "android"))]
+    async fn allow_resolver(&self, addr: IpAddr) {
+        let endpoint = AllowedEndpoint {
+            endpoint: Endpoint::new(addr, config_resolver::RESOLVER_PORT, TransportProtocol::Tcp),
+            clients: allowed_clients(&ApiConnectionMode::Direct),
+        };
+        let event = AccessMethodEvent::Allow { endpoint };
+        // Access methods may be resolved before the daemon handles events, such as at startup, so
+        // do not wait for the firewall to be updated indefinitely.
+        match tokio::time::timeout(
+            ALLOW_RESOLVER_TIMEOUT,
+            event.send(self.access_method_event_sender.clone()),
+        )
+        .await
+        {
+            Ok(Ok(())) => (),
+            Ok(Err(error)) => log::warn!("Failed to allow DNS resolver {addr}: {error}"),
+            Err(_) => log::debug!("Timed out waiting for DNS resolver {addr} to be allowed"),
         }
     }
 }
@@ -76,7 +236,17 @@ impl AccessMethodResolver for DaemonAccessMethodResolver {
                     ApiConnectionMode::Proxied(ProxyConfig::from(edp))
                 }
                 AccessMethod::Custom(config) => {
-                    ApiConnectionMode::Proxied(ProxyConfig::from(config.clone()))
+                    let config = match config.hostname() {
+                        Some(hostname) => {
+                            let Some(addr) = self.resolve_proxy_host(hostname).await else {
+                                log::warn!("Could not resolve proxy hostname {hostname}");
+                                return None;
+                            };
+                            config.resolve(addr)
+                        }
+                        None => config.clone(),
+                    };
+                    ApiConnectionMode::Proxied(ProxyConfig::from(config))
                 }
             }
         };
@@ -175,3 +345,33 @@ pub(crate) fn forward_offline_state(
         }
     });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolved_host_cycles_addresses() {
+        let a = IpAddr::from([192, 0, 2, 1]);
+        let b = IpAddr::from([192, 0, 2, 2]);
+        let c = IpAddr::from([192, 0, 2, 3]);
+
+        let mut host = ResolvedHost::default();
+        assert_eq!(host.next_addr(), None);
+
+        host.update(vec![b, a]);
+        assert_eq!(host.next_addr(), Some(a));
+        // Connecting to `a` failed, so the next attempt uses a new address even if the lookup
+        // returns the same records in a different order
+        host.update(vec![a, b]);
+        assert_eq!(host.next_addr(), Some(b));
+        // The addresses of the last lookup are reused if the lookup fails
+        assert_eq!(host.next_addr(), Some(a));
+
+        // Start over when the addresses change
+        host.update(vec![c, b]);
+        assert_eq!(host.next_addr(), Some(b));
+        assert_eq!(host.next_addr(), Some(c));
+        assert_eq!(host.next_addr(), Some(b));
+    }
+}
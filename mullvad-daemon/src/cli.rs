@@ -1,5 +1,10 @@
 use clap::{Args, Parser};
+#[cfg(target_os = "linux")]
+use mullvad_daemon::dbus_interface;
+use mullvad_daemon::metrics::ListenAddress;
 use std::sync::LazyLock;
+#[cfg(target_os = "linux")]
+use talpid_core::tunnel_state_machine::LinuxNetworkingIdentifiers;
 
 static ENV_DESC: LazyLock<String> = LazyLock::new(|| {
     format!(
@@ -38,6 +43,89 @@ struct Cli {
 
     #[command(flatten)]
     command: CommandFlags,
+
+    #[cfg(target_os = "linux")]
+    #[command(flatten)]
+    linux_ids: LinuxNetworkingArgs,
+
+    /// Export a D-Bus service for observing and controlling the daemon on the given bus
+    #[cfg(target_os = "linux")]
+    #[arg(long, env = "MULLVAD_DBUS_BUS", value_enum)]
+    dbus_bus: Option<dbus_interface::Bus>,
+
+    /// Serve Prometheus metrics at /metrics on a loopback address, given as <ip>:<port>, or on a
+    /// Unix socket, given as unix:<path>
+    #[arg(long, env = "MULLVAD_METRICS_LISTEN")]
+    metrics_listen: Option<ListenAddress>,
+}
+
+/// Identifiers used for policy routing and packet marking. These must not collide with routing
+/// tables, routing rules or marks used by other software on the host.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Args)]
+struct LinuxNetworkingArgs {
+    /// Routing table ID used to route traffic through the tunnel
+    #[arg(
+        long,
+        env = "MULLVAD_TUNNEL_TABLE_ID",
+        value_parser = parse_table_id,
+        default_value_t = mullvad_types::TUNNEL_TABLE_ID,
+    )]
+    tunnel_table_id: u32,
+
+    /// Firewall mark used to identify traffic that bypasses the tunnel
+    #[arg(
+        long,
+        env = "MULLVAD_TUNNEL_FWMARK",
+        value_parser = parse_mark,
+        default_value_t = mullvad_types::TUNNEL_FWMARK,
+    )]
+    tunnel_fwmark: u32,
+
+    /// Connection tracking mark used for connections excluded from the tunnel
+    #[arg(
+        long,
+        env = "MULLVAD_SPLIT_TUNNEL_MARK",
+        value_parser = parse_mark,
+        default_value_t = talpid_core::split_tunnel::DEFAULT_MARK,
+    )]
+    split_tunnel_mark: u32,
+
+    /// Class ID of the net_cls cgroup containing processes excluded from the tunnel
+    #[arg(
+        long,
+        env = "MULLVAD_SPLIT_TUNNEL_CLASSID",
+        value_parser = parse_mark,
+        default_value_t = talpid_core::split_tunnel::DEFAULT_NET_CLS_CLASSID,
+    )]
+    split_tunnel_classid: u32,
+}
+
+/// Parse a nonzero integer given either in decimal or in hexadecimal with a `0x` prefix.
+#[cfg(target_os = "linux")]
+fn parse_mark(value: &str) -> Result<u32, String> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|error| error.to_string())?;
+
+    if parsed == 0 {
+        return Err("value must be nonzero".to_string());
+    }
+    Ok(parsed)
+}
+
+#[cfg(target_os = "linux")]
+fn parse_table_id(value: &str) -> Result<u32, String> {
+    /// Tables reserved by the kernel: `default`, `main` and `local`.
+    const RESERVED_TABLES: [u32; 3] = [253, 254, 255];
+
+    let table_id = parse_mark(value)?;
+    if RESERVED_TABLES.contains(&table_id) {
+        return Err(format!("table {table_id} is reserved"));
+    }
+    Ok(table_id)
 }
 
 #[derive(Debug, Args)]
@@ -71,6 +159,14 @@ pub struct Config {
     pub log_stdout_timestamps: bool,
 
     pub command: Command,
+
+    #[cfg(target_os = "linux")]
+    pub linux_ids: LinuxNetworkingIdentifiers,
+
+    #[cfg(target_os = "linux")]
+    pub dbus_bus: Option<dbus_interface::Bus>,
+
+    pub metrics_address: Option<ListenAddress>,
 }
 
 #[derive(Debug)]
@@ -137,5 +233,15 @@ fn create_config() -> Config {
         log_to_file: !app.disable_log_to_file,
         log_stdout_timestamps: !app.disable_stdout_timestamps,
         command: app.command.into(),
+        #[cfg(target_os = "linux")]
+        linux_ids: LinuxNetworkingIdentifiers {
+            fwmark: app.linux_ids.tunnel_fwmark,
+            table_id: app.linux_ids.tunnel_table_id,
+            split_tunnel_mark: app.linux_ids.split_tunnel_mark,
+            net_cls_classid: app.linux_ids.split_tunnel_classid,
+        },
+        #[cfg(target_os = "linux")]
+        dbus_bus: app.dbus_bus,
+        metrics_address: app.metrics_listen,
     }
 }
@@ -1,6 +1,7 @@
 use crate::{Daemon, Error};
 use mullvad_relay_selector::SelectorConfig;
 use mullvad_types::{
+    audit_log::AuditSource,
     constraints::Constraint,
     custom_list::{CustomList, Id},
     relay_constraints::{BridgeState, LocationConstraint, RelaySettings, ResolvedBridgeSettings},
@@ -11,12 +12,16 @@ impl Daemon {
     /// Create a new custom list.
     ///
     /// Returns an error if the name is not unique.
-    pub async fn create_custom_list(&mut self, name: String) -> Result<Id, crate::Error> {
+    pub async fn create_custom_list(
+        &mut self,
+        name: String,
+        source: AuditSource,
+    ) -> Result<Id, crate::Error> {
         let new_list = CustomList::new(name).map_err(crate::Error::CustomListError)?;
         let id = new_list.id;
 
         self.settings
-            .try_update(|settings| settings.custom_lists.add(new_list))
+            .try_update(source, |settings| settings.custom_lists.add(new_list))
             .await
             .map_err(Error::SettingsError)?;
 
@@ -26,10 +31,10 @@ impl Daemon {
     /// Update a custom list.
     ///
     /// Returns an error if the list doesn't exist.
-    pub async fn delete_custom_list(&mut self, id: Id) -> Result<(), Error> {
+    pub async fn delete_custom_list(&mut self, id: Id, source: AuditSource) -> Result<(), Error> {
         let settings_changed = self
             .settings
-            .try_update(|settings| {
+            .try_update(source, |settings| {
                 // NOTE: Not using swap remove because it would make user output slightly
                 // more confusing and the cost is so small.
                 settings.custom_lists.remove(&id)
@@ -56,11 +61,15 @@ impl Daemon {
     /// Returns an error if...
     /// - there is no existing list with the same ID,
     /// - or the existing list has a different name.
-    pub async fn update_custom_list(&mut self, new_list: CustomList) -> Result<(), Error> {
+    pub async fn update_custom_list(
+        &mut self,
+        new_list: CustomList,
+        source: AuditSource,
+    ) -> Result<(), Error> {
         let list_id = new_list.id;
         let settings_changed = self
             .settings
-            .try_update(|settings| settings.custom_lists.update(new_list))
+            .try_update(source, |settings| settings.custom_lists.update(new_list))
             .await
             .map_err(Error::SettingsError);
 
@@ -79,10 +88,10 @@ impl Daemon {
     }
 
     /// Remove all custom lists.
-    pub async fn clear_custom_lists(&mut self) -> Result<(), Error> {
+    pub async fn clear_custom_lists(&mut self, source: AuditSource) -> Result<(), Error> {
         let settings_changed = self
             .settings
-            .update(|settings| {
+            .update(source, |settings| {
                 settings.custom_lists.clear();
             })
             .await
@@ -0,0 +1,543 @@
+//! Keeps a persistent ledger of how much traffic has passed through the tunnel, and enforces the
+//! data usage quota.
+//!
+//! Traffic is sampled from the tunnel statistics while connected and is bucketed by local date,
+//! relay and tunnel protocol. The ledger is stored in the cache directory.
+
+use chrono::{Local, NaiveDate};
+use futures::{
+    channel::{mpsc as futures_mpsc, oneshot},
+    FutureExt,
+};
+use mullvad_types::data_usage::{
+    usage_in_period, DataUsage, DataUsageEntry, DataUsageQuota, QuotaExceeded,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Weak,
+    time::Duration,
+};
+use talpid_core::{mpsc::Sender, tunnel_state_machine::TunnelCommand};
+use talpid_types::{
+    net::{stats::TunnelStats, wireguard::PublicKey},
+    ErrorExt,
+};
+use tokio::{
+    fs,
+    io::{self, AsyncWriteExt},
+    sync::mpsc,
+};
+
+use crate::DaemonEventSender;
+
+const DATA_USAGE_FILE: &str = "data-usage.json";
+/// How often to sample the tunnel statistics while connected.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How often to write the ledger to disk while connected.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// Entries older than this are removed from the ledger.
+const MAX_ENTRY_AGE_DAYS: u64 = 400;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to read data usage ledger")]
+    Read(#[source] io::Error),
+
+    #[error("Failed to parse data usage ledger")]
+    Parse(#[source] serde_json::Error),
+
+    #[error("Failed to serialize data usage ledger")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Failed to write data usage ledger")]
+    Write(#[source] io::Error),
+}
+
+type TunnelCommandSender = Weak<futures_mpsc::UnboundedSender<TunnelCommand>>;
+
+/// Describes the tunnel that traffic is currently attributed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TunnelInfo {
+    /// Hostname of the relay, or its address if the hostname is unknown.
+    pub relay: String,
+    /// Tunnel protocol, e.g. "WireGuard/UDP".
+    pub protocol: String,
+}
+
+enum Request {
+    SetTunnel(Option<TunnelInfo>),
+    SetQuota(DataUsageQuota),
+    Get(oneshot::Sender<Vec<DataUsageEntry>>),
+}
+
+/// Handle to an actor that accumulates data usage and checks it against the quota.
+pub struct DataUsageLedger {
+    request_tx: mpsc::UnboundedSender<Request>,
+}
+
+/// [DataUsageLedger] internal task state.
+struct Task {
+    request_rx: mpsc::UnboundedReceiver<Request>,
+    tunnel_command_tx: TunnelCommandSender,
+    event_tx: DaemonEventSender<QuotaExceeded>,
+    path: PathBuf,
+    ledger: Ledger,
+    quota: QuotaState,
+    tunnel: Option<TunnelInfo>,
+    /// Last observed counters of each peer in the current tunnel.
+    peers: HashMap<PublicKey, DataUsage>,
+}
+
+impl DataUsageLedger {
+    pub fn new(
+        cache_dir: &Path,
+        tunnel_command_tx: TunnelCommandSender,
+        event_tx: DaemonEventSender<QuotaExceeded>,
+        quota: DataUsageQuota,
+    ) -> Self {
+        let (request_tx, request_rx) = mpsc::unbounded_channel();
+
+        let task = Task {
+            request_rx,
+            tunnel_command_tx,
+            event_tx,
+            path: cache_dir.join(DATA_USAGE_FILE),
+            ledger: Ledger::default(),
+            quota: QuotaState::new(quota),
+            tunnel: None,
+            peers: HashMap::new(),
+        };
+        tokio::spawn(task.run());
+
+        DataUsageLedger { request_tx }
+    }
+
+    /// Attribute traffic to the given tunnel, or stop accounting if `None`.
+    pub fn set_tunnel(&self, tunnel: Option<TunnelInfo>) {
+        self.send(Request::SetTunnel(tunnel));
+    }
+
+    pub fn set_quota(&self, quota: DataUsageQuota) {
+        self.send(Request::SetQuota(quota));
+    }
+
+    /// Return all entries in the ledger.
+    pub fn get(&self, tx: oneshot::Sender<Vec<DataUsageEntry>>) {
+        self.send(Request::Get(tx));
+    }
+
+    fn send(&self, request: Request) {
+        if self.request_tx.send(request).is_err() {
+            log::error!("Data usage task has stopped");
+        }
+    }
+}
+
+impl Task {
+    async fn run(mut self) {
+        match Ledger::load(&self.path).await {
+            Ok(ledger) => self.ledger = ledger,
+            Err(Error::Read(error)) if error.kind() == io::ErrorKind::NotFound => (),
+            Err(error) => log::error!("{}", error.display_chain()),
+        }
+        self.ledger.prune(today());
+
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        let mut save = tokio::time::interval(SAVE_INTERVAL);
+
+        loop {
+            futures::select! {
+                request = self.request_rx.recv().fuse() => {
+                    let Some(request) = request else {
+                        break; // The DataUsageLedger handle was dropped.
+                    };
+                    self.on_request(request).await;
+                }
+                _ = poll.tick().fuse() => {
+                    if self.tunnel.is_some() {
+                        self.update().await;
+                    }
+                }
+                _ = save.tick().fuse() => self.save().await,
+            }
+        }
+
+        self.save().await;
+    }
+
+    async fn on_request(&mut self, request: Request) {
+        match request {
+            Request::SetTunnel(tunnel) => {
+                if tunnel == self.tunnel {
+                    return;
+                }
+                if self.tunnel.is_some() {
+                    // Count whatever passed through the old tunnel since the last sample.
+                    self.update().await;
+                }
+                self.peers.clear();
+                self.quota.hard_notified = false;
+                self.tunnel = tunnel;
+                if self.tunnel.is_none() {
+                    self.save().await;
+                }
+            }
+            Request::SetQuota(quota) => {
+                self.quota = QuotaState::new(quota);
+                self.check_quota();
+            }
+            Request::Get(tx) => {
+                let _ = tx.send(self.ledger.entries.clone());
+            }
+        }
+    }
+
+    /// Sample the tunnel statistics and add the traffic since the last sample to the ledger.
+    async fn update(&mut self) {
+        let Some(stats) = self.get_tunnel_stats().await else {
+            return;
+        };
+        let Some(tunnel) = &self.tunnel else {
+            return;
+        };
+        let usage = usage_delta(&mut self.peers, &stats);
+        if usage.total() > 0 {
+            self.ledger
+                .add(today(), &tunnel.relay, &tunnel.protocol, usage);
+            self.check_quota();
+        }
+    }
+
+    async fn get_tunnel_stats(&self) -> Option<TunnelStats> {
+        let command_tx = self.tunnel_command_tx.upgrade()?;
+        let (tx, rx) = oneshot::channel();
+        command_tx
+            .unbounded_send(TunnelCommand::GetTunnelStats(tx))
+            .ok()?;
+        drop(command_tx);
+        rx.await.ok().flatten()
+    }
+
+    fn check_quota(&mut self) {
+        if let Some(event) = self
+            .quota
+            .check(&self.ledger, today(), self.tunnel.is_some())
+        {
+            if event.hard {
+                log::warn!(
+                    "Data usage of {} bytes this {} exceeds the hard limit of {} bytes",
+                    event.usage,
+                    event.period,
+                    event.limit,
+                );
+            } else {
+                log::info!(
+                    "Data usage of {} bytes this {} exceeds the soft limit of {} bytes",
+                    event.usage,
+                    event.period,
+                    event.limit,
+                );
+            }
+            let _ = self.event_tx.send(event);
+        }
+    }
+
+    async fn save(&mut self) {
+        if !self.ledger.dirty {
+            return;
+        }
+        self.ledger.prune(today());
+        match self.ledger.save(&self.path).await {
+            Ok(()) => self.ledger.dirty = false,
+            Err(error) => log::error!("{}", error.display_chain()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Ledger {
+    entries: Vec<DataUsageEntry>,
+    /// Whether there are changes that have not been written to disk.
+    dirty: bool,
+}
+
+impl Ledger {
+    async fn load(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path).await.map_err(Error::Read)?;
+        let entries = serde_json::from_str(&content).map_err(Error::Parse)?;
+        Ok(Ledger {
+            entries,
+            dirty: false,
+        })
+    }
+
+    async fn save(&self, path: &Path) -> Result<(), Error> {
+        let buffer = serde_json::to_string(&self.entries).map_err(Error::Serialize)?;
+        let mut file = mullvad_fs::AtomicFile::new(path)
+            .await
+            .map_err(Error::Write)?;
+        file.write_all(buffer.as_bytes())
+            .await
+            .map_err(Error::Write)?;
+        file.finalize().await.map_err(Error::Write)
+    }
+
+    fn add(&mut self, date: NaiveDate, relay: &str, protocol: &str, usage: DataUsage) {
+        let existing = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.date == date && entry.relay == relay && entry.protocol == protocol);
+        match existing {
+            Some(entry) => entry.usage += usage,
+            None => self.entries.push(DataUsageEntry {
+                date,
+                relay: relay.to_owned(),
+                protocol: protocol.to_owned(),
+                usage,
+            }),
+        }
+        self.dirty = true;
+    }
+
+    /// Remove entries that are too old to be of interest.
+    fn prune(&mut self, today: NaiveDate) {
+        let Some(oldest) = today.checked_sub_days(chrono::Days::new(MAX_ENTRY_AGE_DAYS)) else {
+            return;
+        };
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.date >= oldest);
+        self.dirty |= self.entries.len() != len;
+    }
+}
+
+struct QuotaState {
+    quota: DataUsageQuota,
+    /// Start of the period in which listeners were notified about the soft limit.
+    soft_notified: Option<NaiveDate>,
+    /// Whether listeners were notified about the hard limit for the current tunnel.
+    hard_notified: bool,
+}
+
+impl QuotaState {
+    fn new(quota: DataUsageQuota) -> Self {
+        QuotaState {
+            quota,
+            soft_notified: None,
+            hard_notified: false,
+        }
+    }
+
+    /// Return an event if a limit is exceeded that listeners have not yet been told about.
+    ///
+    /// The soft limit is reported once per period. The hard limit is reported once per tunnel,
+    /// so that the tunnel is brought down again if it is reconnected.
+    fn check(
+        &mut self,
+        ledger: &Ledger,
+        today: NaiveDate,
+        connected: bool,
+    ) -> Option<QuotaExceeded> {
+        let period = self.quota.period;
+        let usage = usage_in_period(&ledger.entries, period, today).total();
+        let exceeded = |limit: Option<u64>| limit.filter(|&limit| usage > limit);
+
+        if let Some(limit) = exceeded(self.quota.hard_limit) {
+            if connected && !self.hard_notified {
+                self.hard_notified = true;
+                return Some(QuotaExceeded {
+                    period,
+                    limit,
+                    usage,
+                    hard: true,
+                });
+            }
+        }
+        if let Some(limit) = exceeded(self.quota.soft_limit) {
+            let period_start = period.start(today);
+            if self.soft_notified != Some(period_start) {
+                self.soft_notified = Some(period_start);
+                return Some(QuotaExceeded {
+                    period,
+                    limit,
+                    usage,
+                    hard: false,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Update the per-peer counters and return the traffic since the previous sample.
+///
+/// In a multihop tunnel, all traffic passes through the entry peer, so the largest difference is
+/// used rather than the sum. Counters that went backwards belong to a reconfigured peer and are
+/// counted from zero.
+pub(crate) fn usage_delta(
+    peers: &mut HashMap<PublicKey, DataUsage>,
+    stats: &TunnelStats,
+) -> DataUsage {
+    let mut delta = DataUsage::default();
+    for peer in &stats.peers {
+        let current = DataUsage {
+            tx_bytes: peer.tx_bytes,
+            rx_bytes: peer.rx_bytes,
+        };
+        let previous = peers
+            .insert(peer.public_key.clone(), current)
+            .unwrap_or_default();
+        let diff = |new: u64, old: u64| new.checked_sub(old).unwrap_or(new);
+        delta.tx_bytes = delta
+            .tx_bytes
+            .max(diff(current.tx_bytes, previous.tx_bytes));
+        delta.rx_bytes = delta
+            .rx_bytes
+            .max(diff(current.rx_bytes, previous.rx_bytes));
+    }
+    delta
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::data_usage::QuotaPeriod;
+    use std::time::SystemTime;
+    use talpid_types::net::{stats::PeerStats, wireguard::PrivateKey};
+
+    fn stats(peers: &[(&PublicKey, u64, u64)]) -> TunnelStats {
+        TunnelStats {
+            timestamp: SystemTime::now(),
+            peers: peers
+                .iter()
+                .map(|&(public_key, tx_bytes, rx_bytes)| PeerStats {
+                    public_key: public_key.clone(),
+                    tx_bytes,
+                    rx_bytes,
+                    last_handshake: None,
+                    tx_rate: None,
+                    rx_rate: None,
+                })
+                .collect(),
+            ping_rtt_upper_bound: None,
+            obfuscator: None,
+        }
+    }
+
+    #[test]
+    fn test_usage_delta() {
+        let entry = PrivateKey::new_from_random().public_key();
+        let exit = PrivateKey::new_from_random().public_key();
+        let mut peers = HashMap::new();
+
+        let delta = usage_delta(&mut peers, &stats(&[(&entry, 100, 200), (&exit, 90, 190)]));
+        assert_eq!(
+            delta,
+            DataUsage {
+                tx_bytes: 100,
+                rx_bytes: 200
+            }
+        );
+
+        let delta = usage_delta(&mut peers, &stats(&[(&entry, 150, 300), (&exit, 130, 280)]));
+        assert_eq!(
+            delta,
+            DataUsage {
+                tx_bytes: 50,
+                rx_bytes: 100
+            }
+        );
+
+        // The counters were reset
+        let delta = usage_delta(&mut peers, &stats(&[(&entry, 10, 20)]));
+        assert_eq!(
+            delta,
+            DataUsage {
+                tx_bytes: 10,
+                rx_bytes: 20
+            }
+        );
+    }
+
+    #[test]
+    fn test_ledger_add_and_prune() {
+        let today: NaiveDate = "2024-06-15".parse().unwrap();
+        let usage = DataUsage {
+            tx_bytes: 1,
+            rx_bytes: 2,
+        };
+        let mut ledger = Ledger::default();
+
+        ledger.add(today, "se-got-wg-001", "WireGuard/UDP", usage);
+        ledger.add(today, "se-got-wg-001", "WireGuard/UDP", usage);
+        ledger.add(today, "se-got-wg-002", "WireGuard/UDP", usage);
+        ledger.add(
+            "2023-01-01".parse().unwrap(),
+            "se-got-wg-001",
+            "WireGuard/UDP",
+            usage,
+        );
+        assert_eq!(ledger.entries.len(), 3);
+        assert_eq!(ledger.entries[0].usage.total(), 6);
+
+        ledger.prune(today);
+        assert_eq!(ledger.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_quota_check() {
+        let today: NaiveDate = "2024-06-15".parse().unwrap();
+        let mut ledger = Ledger::default();
+        let mut state = QuotaState::new(DataUsageQuota {
+            period: QuotaPeriod::Month,
+            soft_limit: Some(100),
+            hard_limit: Some(200),
+        });
+
+        ledger.add(
+            today,
+            "relay",
+            "WireGuard/UDP",
+            DataUsage {
+                tx_bytes: 60,
+                rx_bytes: 60,
+            },
+        );
+        let event = state.check(&ledger, today, true).unwrap();
+        assert!(!event.hard);
+        assert_eq!(event.usage, 120);
+        // The soft limit is only reported once per period
+        assert_eq!(state.check(&ledger, today, true), None);
+
+        ledger.add(
+            today,
+            "relay",
+            "WireGuard/UDP",
+            DataUsage {
+                tx_bytes: 100,
+                rx_bytes: 0,
+            },
+        );
+        let event = state.check(&ledger, today, true).unwrap();
+        assert!(event.hard);
+        assert_eq!(event.limit, 200);
+        assert_eq!(state.check(&ledger, today, true), None);
+
+        // A new period resets the soft limit notification
+        let next_month = "2024-07-01".parse().unwrap();
+        ledger.add(
+            next_month,
+            "relay",
+            "WireGuard/UDP",
+            DataUsage {
+                tx_bytes: 101,
+                rx_bytes: 0,
+            },
+        );
+        assert!(!state.check(&ledger, next_month, false).unwrap().hard);
+    }
+}
@@ -0,0 +1,633 @@
+//! Optional D-Bus service that lets desktop environments and status bars observe and control the
+//! daemon without speaking gRPC.
+//!
+//! The service is exported as [`BUS_NAME`] at [`OBJECT_PATH`], implementing [`INTERFACE`]. The
+//! tunnel state, current relay and location are exposed as properties, and changes to them are
+//! announced using `org.freedesktop.DBus.Properties.PropertiesChanged`. On the system bus, all
+//! methods are authorized using polkit. The polkit actions are defined in
+//! `dist-assets/linux/net.mullvad.vpn.policy`.
+//!
+//! Method calls are forwarded to the daemon as [`DaemonCommand`]s. The responses are awaited on
+//! the Tokio runtime, so that a slow daemon does not block the service from handling other calls.
+
+use crate::{DaemonCommand, DaemonCommandSender};
+use dbus_crossroads::{Context, Crossroads, IfaceBuilder, MethodErr};
+use futures::channel::oneshot;
+use mullvad_types::{
+    audit_log::AuditSource,
+    relay_constraints::{GeographicLocationConstraint, LocationConstraint},
+    states::{TargetState, TunnelState},
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use talpid_dbus::dbus::{
+    arg::{AppendAll, RefArg, Variant},
+    blocking::{
+        stdintf::org_freedesktop_dbus::{PropertiesPropertiesChanged, RequestNameReply},
+        Proxy, SyncConnection,
+    },
+    channel::{BusType, Channel, MatchingReceiver, Sender},
+    message::{MatchRule, SignalArgs},
+};
+use talpid_types::ErrorExt;
+
+/// Well-known name of the service.
+pub const BUS_NAME: &str = "net.mullvad.VPN";
+/// Path of the only object exported by the service.
+pub const OBJECT_PATH: &str = "/net/mullvad/VPN";
+/// Interface implemented by the object at [`OBJECT_PATH`].
+pub const INTERFACE: &str = "net.mullvad.VPN1";
+
+/// Polkit action required to connect, disconnect or reconnect.
+const CONTROL_TUNNEL_ACTION: &str = "net.mullvad.vpn.control-tunnel";
+/// Polkit action required to change the relay location.
+const SET_LOCATION_ACTION: &str = "net.mullvad.vpn.set-location";
+
+const DBUS_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the thread serving the service waits for incoming messages at a time. Replies sent
+/// from the Tokio runtime are queued until the thread wakes up, so this bounds their latency.
+const SERVE_INTERVAL: Duration = Duration::from_millis(100);
+/// How long to wait for polkit, which includes the time it takes the user to authenticate.
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(120);
+/// `AllowUserInteraction` flag of `CheckAuthorization`.
+const ALLOW_USER_INTERACTION: u32 = 1;
+
+const ACCESS_DENIED: &str = "org.freedesktop.DBus.Error.AccessDenied";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to connect to D-Bus")]
+    Connect(#[source] talpid_dbus::dbus::Error),
+
+    #[error("Failed to request the name {BUS_NAME}")]
+    RequestName(#[source] talpid_dbus::dbus::Error),
+
+    #[error("The name {BUS_NAME} is already owned by another process")]
+    NameTaken,
+}
+
+/// Bus to export the service on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Bus {
+    /// The system bus. Calls are authorized using polkit.
+    System,
+    /// The session bus of the user running the daemon. Calls are not authorized, so this is
+    /// only meant for testing.
+    Session,
+}
+
+/// Values of the properties exported by the service.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Properties {
+    tunnel_state: String,
+    relay: String,
+    country: String,
+    city: String,
+}
+
+impl Properties {
+    fn from_tunnel_state(state: &TunnelState) -> Self {
+        let (name, location) = match state {
+            TunnelState::Disconnected { location, .. } => ("disconnected", location.as_ref()),
+            TunnelState::Connecting { location, .. } => ("connecting", location.as_ref()),
+            TunnelState::Connected { location, .. } => ("connected", location.as_ref()),
+            TunnelState::Disconnecting(_) => ("disconnecting", None),
+            TunnelState::Error(_) => ("error", None),
+        };
+        Self {
+            tunnel_state: name.to_owned(),
+            relay: location
+                .and_then(|location| location.hostname.clone())
+                .unwrap_or_default(),
+            country: location
+                .map(|location| location.country.clone())
+                .unwrap_or_default(),
+            city: location
+                .and_then(|location| location.city.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the D-Bus name and new value of every property that differs from `old`.
+    fn changes(&self, old: &Properties) -> HashMap<String, Variant<Box<dyn RefArg>>> {
+        [
+            ("TunnelState", &old.tunnel_state, &self.tunnel_state),
+            ("Relay", &old.relay, &self.relay),
+            ("Country", &old.country, &self.country),
+            ("City", &old.city, &self.city),
+        ]
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(name, _, new)| {
+            let value: Box<dyn RefArg> = Box::new(new.clone());
+            (name.to_owned(), Variant(value))
+        })
+        .collect()
+    }
+}
+
+/// Handle to the D-Bus service. The service runs on a separate thread for as long as the daemon
+/// is running.
+pub struct DbusInterface {
+    connection: Arc<SyncConnection>,
+    properties: Arc<Mutex<Properties>>,
+}
+
+impl DbusInterface {
+    pub fn start(bus: Bus, command_sender: DaemonCommandSender) -> Result<Self, Error> {
+        let bus_type = match bus {
+            Bus::System => BusType::System,
+            Bus::Session => BusType::Session,
+        };
+        let channel = Channel::get_private(bus_type).map_err(Error::Connect)?;
+        let call_channel = Channel::get_private(bus_type).map_err(Error::Connect)?;
+        Self::start_on_channels(channel, call_channel, bus == Bus::System, command_sender)
+    }
+
+    /// Export the service on an already registered connection. `call_channel` is used for
+    /// calling polkit and the bus while handling method calls. Calls are only authorized if
+    /// `authorize` is set. Must be called from within a Tokio runtime.
+    fn start_on_channels(
+        channel: Channel,
+        call_channel: Channel,
+        authorize: bool,
+        command_sender: DaemonCommandSender,
+    ) -> Result<Self, Error> {
+        let connection = Arc::new(SyncConnection::from(channel));
+        match connection
+            .request_name(BUS_NAME, false, false, true)
+            .map_err(Error::RequestName)?
+        {
+            RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner => (),
+            RequestNameReply::InQueue | RequestNameReply::Exists => return Err(Error::NameTaken),
+        }
+
+        let properties = Arc::new(Mutex::new(Properties::default()));
+        let service = Service {
+            call_connection: Arc::new(SyncConnection::from(call_channel)),
+            properties: properties.clone(),
+            command_sender,
+            authorize,
+        };
+
+        let runtime = tokio::runtime::Handle::current();
+        let mut crossroads = Crossroads::new();
+        crossroads.set_async_support(Some((
+            connection.clone(),
+            Box::new(move |future| {
+                runtime.spawn(future);
+            }),
+        )));
+        let interface = crossroads.register(INTERFACE, register_interface);
+        crossroads.insert(OBJECT_PATH, &[interface], service);
+        let crossroads = Mutex::new(crossroads);
+        connection.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |message, connection| {
+                let _ = crossroads
+                    .lock()
+                    .expect("D-Bus service lock poisoned")
+                    .handle_message(message, connection);
+                true
+            }),
+        );
+
+        let serve_connection = connection.clone();
+        thread::spawn(move || loop {
+            if let Err(error) = serve_connection.process(SERVE_INTERVAL) {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Stopping D-Bus service due to an error")
+                );
+                break;
+            }
+        });
+
+        Ok(Self {
+            connection,
+            properties,
+        })
+    }
+
+    /// Update the properties from the new tunnel state, and signal those that changed.
+    pub fn notify_tunnel_state(&self, state: &TunnelState) {
+        let new_properties = Properties::from_tunnel_state(state);
+        let mut properties = self.properties.lock().expect("D-Bus service lock poisoned");
+        let changed_properties = new_properties.changes(&properties);
+        if changed_properties.is_empty() {
+            return;
+        }
+        *properties = new_properties;
+
+        let signal = PropertiesPropertiesChanged {
+            interface_name: INTERFACE.to_owned(),
+            changed_properties,
+            invalidated_properties: vec![],
+        };
+        if self
+            .connection
+            .send(signal.to_emit_message(&OBJECT_PATH.into()))
+            .is_err()
+        {
+            log::error!("Failed to signal changed D-Bus properties");
+        }
+    }
+}
+
+fn register_interface(builder: &mut IfaceBuilder<Service>) {
+    builder
+        .property("TunnelState")
+        .get(|_, service| Ok(service.properties().tunnel_state));
+    builder
+        .property("Relay")
+        .get(|_, service| Ok(service.properties().relay));
+    builder
+        .property("Country")
+        .get(|_, service| Ok(service.properties().country));
+    builder
+        .property("City")
+        .get(|_, service| Ok(service.properties().city));
+
+    builder.method_with_cr_async("Connect", (), ("changed",), |context, crossroads, ()| {
+        let caller = caller(&context, crossroads);
+        reply(context, async move {
+            let changed = caller?
+                .authorize(CONTROL_TUNNEL_ACTION)
+                .await?
+                .call(|tx| DaemonCommand::SetTargetState(tx, TargetState::Secured))
+                .await?;
+            Ok((changed,))
+        })
+    });
+    builder.method_with_cr_async("Disconnect", (), ("changed",), |context, crossroads, ()| {
+        let caller = caller(&context, crossroads);
+        reply(context, async move {
+            let changed = caller?
+                .authorize(CONTROL_TUNNEL_ACTION)
+                .await?
+                .call(|tx| DaemonCommand::SetTargetState(tx, TargetState::Unsecured))
+                .await?;
+            Ok((changed,))
+        })
+    });
+    builder.method_with_cr_async(
+        "Reconnect",
+        (),
+        ("reconnecting",),
+        |context, crossroads, ()| {
+            let caller = caller(&context, crossroads);
+            reply(context, async move {
+                let reconnecting = caller?
+                    .authorize(CONTROL_TUNNEL_ACTION)
+                    .await?
+                    .call(DaemonCommand::Reconnect)
+                    .await?;
+                Ok((reconnecting,))
+            })
+        },
+    );
+    builder.method_with_cr_async(
+        "SetLocation",
+        ("location",),
+        (),
+        |context, crossroads, (location,): (String,)| {
+            let caller = caller(&context, crossroads);
+            reply(context, async move {
+                caller?
+                    .authorize(SET_LOCATION_ACTION)
+                    .await?
+                    .set_location(&location)
+                    .await
+            })
+        },
+    );
+}
+
+/// Return the caller of the method in `context`.
+fn caller(context: &Context, crossroads: &mut Crossroads) -> Result<Caller, MethodErr> {
+    let service = crossroads
+        .data_mut::<Service>(context.path())
+        .ok_or_else(|| MethodErr::no_path(context.path()))?;
+    let sender = context
+        .message()
+        .sender()
+        .ok_or_else(|| MethodErr::failed("Unknown sender"))?
+        .to_string();
+    Ok(Caller {
+        service: service.clone(),
+        sender,
+        method: format!("{INTERFACE}.{}", context.method()),
+    })
+}
+
+/// Reply to the method call in `context` once `result` is ready.
+async fn reply<OA: AppendAll>(
+    mut context: Context,
+    result: impl Future<Output = Result<OA, MethodErr>>,
+) -> PhantomData<OA> {
+    let result = result.await;
+    context.reply(result)
+}
+
+/// State shared by the method and property handlers.
+#[derive(Clone)]
+struct Service {
+    /// Connection for making blocking calls from outside the thread that serves the service.
+    /// Replies would otherwise be consumed by that thread.
+    call_connection: Arc<SyncConnection>,
+    properties: Arc<Mutex<Properties>>,
+    command_sender: DaemonCommandSender,
+    authorize: bool,
+}
+
+impl Service {
+    fn properties(&self) -> Properties {
+        self.properties
+            .lock()
+            .expect("D-Bus service lock poisoned")
+            .clone()
+    }
+}
+
+/// Client that called a method, identified by its unique bus name.
+struct Caller {
+    service: Service,
+    sender: String,
+    method: String,
+}
+
+impl Caller {
+    /// Check that the caller is allowed to perform `action`, and return a client for sending
+    /// commands to the daemon on its behalf. Since the user may be asked to authenticate, this is
+    /// done on a blocking thread.
+    async fn authorize(self, action: &'static str) -> Result<Client, MethodErr> {
+        tokio::task::spawn_blocking(move || self.authorize_blocking(action))
+            .await
+            .map_err(|_| MethodErr::failed("Failed to check authorization"))?
+    }
+
+    fn authorize_blocking(self, action: &str) -> Result<Client, MethodErr> {
+        if self.service.authorize {
+            self.check_authorization(action)?;
+        }
+
+        let source = AuditSource::Rpc {
+            uid: self.bus_call("GetConnectionUnixUser").ok(),
+            pid: self
+                .bus_call("GetConnectionUnixProcessID")
+                .ok()
+                .map(|pid: u32| pid as i32),
+            method: self.method,
+        };
+        Ok(Client {
+            command_sender: self.service.command_sender,
+            source,
+        })
+    }
+
+    /// Ask polkit whether the caller may perform `action`, allowing polkit to ask the user to
+    /// authenticate.
+    fn check_authorization(&self, action: &str) -> Result<(), MethodErr> {
+        let proxy = Proxy::new(
+            "org.freedesktop.PolicyKit1",
+            "/org/freedesktop/PolicyKit1/Authority",
+            AUTHORIZATION_TIMEOUT,
+            &*self.service.call_connection,
+        );
+        let subject_name: Box<dyn RefArg> = Box::new(self.sender.clone());
+        let subject = (
+            "system-bus-name",
+            HashMap::from([("name", Variant(subject_name))]),
+        );
+        let details: HashMap<&str, &str> = HashMap::new();
+        let ((is_authorized, _is_challenge, _details),): ((bool, bool, HashMap<String, String>),) =
+            proxy
+                .method_call(
+                    "org.freedesktop.PolicyKit1.Authority",
+                    "CheckAuthorization",
+                    (subject, action, details, ALLOW_USER_INTERACTION, ""),
+                )
+                .map_err(|error| {
+                    log::error!("Failed to check polkit authorization: {error}");
+                    MethodErr::failed("Failed to check authorization")
+                })?;
+
+        if is_authorized {
+            Ok(())
+        } else {
+            Err(MethodErr::from((
+                ACCESS_DENIED,
+                format!("Not authorized to perform {action}"),
+            )))
+        }
+    }
+
+    /// Call a method of the bus itself that takes the unique bus name of the caller and returns
+    /// a `u32`.
+    fn bus_call(&self, method: &str) -> Result<u32, talpid_dbus::dbus::Error> {
+        Proxy::new(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            DBUS_TIMEOUT,
+            &*self.service.call_connection,
+        )
+        .method_call("org.freedesktop.DBus", method, (&self.sender,))
+        .map(|(value,)| value)
+    }
+}
+
+/// Sends commands to the daemon on behalf of an authorized caller.
+struct Client {
+    command_sender: DaemonCommandSender,
+    source: AuditSource,
+}
+
+impl Client {
+    /// Set the relay location to a country code, a country and city code such as `se-got`, or
+    /// a relay hostname.
+    async fn set_location(&self, location: &str) -> Result<(), MethodErr> {
+        if location.is_empty() {
+            return Err(MethodErr::invalid_arg(location));
+        }
+        let location: GeographicLocationConstraint = location
+            .parse()
+            .map_err(|_| MethodErr::invalid_arg(location))?;
+
+        self.call(|tx| DaemonCommand::SetRelayLocation(tx, LocationConstraint::from(location)))
+            .await?
+            .map_err(|error| MethodErr::failed(&error.display_chain()))
+    }
+
+    /// Send a command to the daemon and wait for the response.
+    async fn call<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> DaemonCommand,
+    ) -> Result<T, MethodErr> {
+        let (tx, rx) = oneshot::channel();
+        self.command_sender
+            .send_with_source(command(tx), self.source.clone())
+            .map_err(|_| MethodErr::failed("The daemon is unavailable"))?;
+        rx.await
+            .map_err(|_| MethodErr::failed("The daemon did not respond"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test_util::relay_location, DaemonCommandChannel, InternalDaemonEvent};
+    use futures::{executor::block_on, StreamExt};
+    use mullvad_types::location::GeoIpLocation;
+    use std::{
+        io::{self, BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+    use talpid_dbus::dbus::blocking::{stdintf::org_freedesktop_dbus::Properties as _, Connection};
+
+    /// A private session bus, which is stopped when dropped.
+    struct PrivateBus {
+        process: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        /// Start a bus, or return `None` if `dbus-daemon` is not installed.
+        fn start() -> Option<Self> {
+            let mut process = match Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+            {
+                Ok(process) => process,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => return None,
+                Err(error) => panic!("Failed to start dbus-daemon: {error}"),
+            };
+            let mut address = String::new();
+            BufReader::new(process.stdout.take().unwrap())
+                .read_line(&mut address)
+                .expect("Failed to read bus address");
+            Some(Self {
+                process,
+                address: address.trim().to_owned(),
+            })
+        }
+
+        fn channel(&self) -> Channel {
+            let mut channel = Channel::open_private(&self.address).unwrap();
+            channel.register().unwrap();
+            channel
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.process.kill();
+            let _ = self.process.wait();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_private_session_bus() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("Skipping test since dbus-daemon is not installed");
+            return;
+        };
+
+        let command_channel = DaemonCommandChannel::new();
+        let interface = DbusInterface::start_on_channels(
+            bus.channel(),
+            bus.channel(),
+            false,
+            command_channel.sender(),
+        )
+        .unwrap();
+        let (_event_sender, mut commands) = command_channel.destructure();
+
+        // Handle the commands of the test, like the daemon would
+        let daemon = thread::spawn(move || {
+            let source = match block_on(commands.next()) {
+                Some(InternalDaemonEvent::Command(
+                    DaemonCommand::SetTargetState(tx, TargetState::Secured),
+                    source,
+                )) => {
+                    tx.send(true).unwrap();
+                    source
+                }
+                _ => panic!("Expected a command to connect"),
+            };
+            match block_on(commands.next()) {
+                Some(InternalDaemonEvent::Command(
+                    DaemonCommand::SetRelayLocation(tx, location),
+                    _,
+                )) => {
+                    tx.send(Ok(())).unwrap();
+                    (source, location)
+                }
+                _ => panic!("Expected a command to set the location"),
+            }
+        });
+
+        let client = Connection::from(bus.channel());
+        let proxy = client.with_proxy(BUS_NAME, OBJECT_PATH, DBUS_TIMEOUT);
+        let (changed,): (bool,) = proxy.method_call(INTERFACE, "Connect", ()).unwrap();
+        assert!(changed);
+        let () = proxy
+            .method_call(INTERFACE, "SetLocation", ("se-got",))
+            .unwrap();
+        let (source, location) = daemon.join().unwrap();
+        assert_eq!(
+            location,
+            LocationConstraint::from(GeographicLocationConstraint::city("se", "got"))
+        );
+        match source {
+            AuditSource::Rpc { method, uid, .. } => {
+                assert_eq!(method, "net.mullvad.VPN1.Connect");
+                assert_eq!(uid, Some(nix::unistd::getuid().as_raw()));
+            }
+            source => panic!("Unexpected audit source: {source:?}"),
+        }
+
+        let changes = Arc::new(Mutex::new(vec![]));
+        let signal_changes = changes.clone();
+        proxy
+            .match_signal(
+                move |signal: PropertiesPropertiesChanged, _: &Connection, _: &_| {
+                    let mut names: Vec<_> = signal.changed_properties.into_keys().collect();
+                    names.sort();
+                    signal_changes.lock().unwrap().push(names);
+                    true
+                },
+            )
+            .unwrap();
+
+        interface.notify_tunnel_state(&TunnelState::Disconnected {
+            location: Some(GeoIpLocation {
+                mullvad_exit_ip: false,
+                hostname: None,
+                ..relay_location()
+            }),
+            locked_down: false,
+        });
+
+        while changes.lock().unwrap().is_empty() {
+            client.process(DBUS_TIMEOUT).unwrap();
+        }
+        assert_eq!(
+            *changes.lock().unwrap(),
+            [["City", "Country", "TunnelState"]]
+        );
+
+        let state: String = proxy.get(INTERFACE, "TunnelState").unwrap();
+        assert_eq!(state, "disconnected");
+        let city: String = proxy.get(INTERFACE, "City").unwrap();
+        assert_eq!(city, "Gothenburg");
+        let relay: String = proxy.get(INTERFACE, "Relay").unwrap();
+        assert_eq!(relay, "");
+    }
+}
@@ -0,0 +1,170 @@
+//! Collects packets blocked by the firewall for as long as someone is listening.
+//!
+//! Logging is disabled in the firewall whenever there are no listeners, and blocked packets are
+//! only ever kept in memory.
+
+use futures::{
+    channel::{mpsc as futures_mpsc, oneshot},
+    FutureExt,
+};
+use std::{collections::VecDeque, future, sync::Weak, time::Duration};
+use talpid_core::{firewall::nflog, tunnel_state_machine::TunnelCommand};
+use talpid_types::net::firewall_log::BlockedPacket;
+use tokio::sync::mpsc;
+
+use crate::ResponseTx;
+
+/// Number of recently blocked packets sent to new listeners.
+const MAX_RECENT_PACKETS: usize = 100;
+/// How often to check whether listeners have gone away.
+const LISTENER_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to start listening for blocked packets.
+    #[error("Failed to listen for blocked packets")]
+    Listen(#[source] nflog::Error),
+}
+
+type TunnelCommandSender = Weak<futures_mpsc::UnboundedSender<TunnelCommand>>;
+type ListenRequest = ResponseTx<mpsc::UnboundedReceiver<BlockedPacket>, Error>;
+type PacketListener = (
+    nflog::BlockedPacketListener,
+    mpsc::UnboundedReceiver<BlockedPacket>,
+);
+
+/// Handle to an actor that enables firewall logging and fans out blocked packets to listeners.
+pub struct FirewallLog {
+    request_tx: mpsc::UnboundedSender<ListenRequest>,
+}
+
+/// [FirewallLog] internal task state.
+struct Task {
+    request_rx: mpsc::UnboundedReceiver<ListenRequest>,
+    tunnel_command_tx: TunnelCommandSender,
+    listeners: Vec<mpsc::UnboundedSender<BlockedPacket>>,
+    recent: VecDeque<BlockedPacket>,
+    packet_listener: Option<PacketListener>,
+}
+
+impl FirewallLog {
+    pub fn new(tunnel_command_tx: TunnelCommandSender) -> Self {
+        let (request_tx, request_rx) = mpsc::unbounded_channel();
+
+        let task = Task {
+            request_rx,
+            tunnel_command_tx,
+            listeners: vec![],
+            recent: VecDeque::new(),
+            packet_listener: None,
+        };
+        tokio::spawn(task.run());
+
+        FirewallLog { request_tx }
+    }
+
+    /// Start receiving blocked packets, beginning with the most recent ones, if any.
+    pub fn listen(&self, tx: ListenRequest) {
+        if self.request_tx.send(tx).is_err() {
+            log::error!("Firewall log task has stopped");
+        }
+    }
+}
+
+impl Task {
+    async fn run(mut self) {
+        let mut listener_check = tokio::time::interval(LISTENER_CHECK_INTERVAL);
+
+        loop {
+            futures::select! {
+                request = self.request_rx.recv().fuse() => {
+                    let Some(request) = request else {
+                        break; // The FirewallLog handle was dropped.
+                    };
+                    self.on_listen(request).await;
+                }
+                packet = next_packet(&mut self.packet_listener).fuse() => match packet {
+                    Some(packet) => self.on_packet(packet),
+                    None => {
+                        log::error!("Stopped receiving blocked packets");
+                        self.listeners.clear();
+                    }
+                },
+                _ = listener_check.tick().fuse() => {
+                    self.listeners.retain(|listener| !listener.is_closed());
+                }
+            }
+
+            if self.listeners.is_empty() && self.packet_listener.is_some() {
+                self.stop_logging().await;
+            }
+        }
+
+        if self.packet_listener.is_some() {
+            self.stop_logging().await;
+        }
+    }
+
+    async fn on_listen(&mut self, response_tx: ListenRequest) {
+        if self.packet_listener.is_none() {
+            if let Err(error) = self.start_logging().await {
+                let _ = response_tx.send(Err(error));
+                return;
+            }
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        for packet in &self.recent {
+            let _ = tx.send(packet.clone());
+        }
+        self.listeners.push(tx);
+        let _ = response_tx.send(Ok(rx));
+    }
+
+    fn on_packet(&mut self, packet: BlockedPacket) {
+        if self.recent.len() >= MAX_RECENT_PACKETS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(packet.clone());
+        self.listeners
+            .retain(|listener| listener.send(packet.clone()).is_ok());
+    }
+
+    async fn start_logging(&mut self) -> Result<(), Error> {
+        let (packet_tx, packet_rx) = mpsc::unbounded_channel();
+        let listener = nflog::BlockedPacketListener::start(move |packet| {
+            let _ = packet_tx.send(packet);
+        })
+        .map_err(Error::Listen)?;
+        self.packet_listener = Some((listener, packet_rx));
+        self.set_firewall_logging(true).await;
+        Ok(())
+    }
+
+    async fn stop_logging(&mut self) {
+        self.set_firewall_logging(false).await;
+        self.packet_listener = None;
+        self.recent.clear();
+    }
+
+    async fn set_firewall_logging(&self, enabled: bool) {
+        let Some(command_tx) = self.tunnel_command_tx.upgrade() else {
+            return;
+        };
+        let (tx, rx) = oneshot::channel();
+        let sent = command_tx
+            .unbounded_send(TunnelCommand::LogBlockedPackets(enabled, tx))
+            .is_ok();
+        drop(command_tx);
+        if sent {
+            let _ = rx.await;
+        }
+    }
+}
+
+async fn next_packet(packet_listener: &mut Option<PacketListener>) -> Option<BlockedPacket> {
+    match packet_listener {
+        Some((_, packet_rx)) => packet_rx.recv().await,
+        None => future::pending().await,
+    }
+}
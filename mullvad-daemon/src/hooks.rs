@@ -0,0 +1,333 @@
+//! Runs user-provided executables whenever the tunnel state changes.
+//!
+//! Hooks are executables in [`HOOKS_DIR`] inside the settings directory. They are run one at a
+//! time, in alphabetical order, and never block the tunnel state machine. Information about the
+//! new state is passed as environment variables. Output is written to the daemon log, and hooks
+//! that do not finish within [`HOOK_TIMEOUT`] are killed.
+//!
+//! On Unix, the directory and hooks must be owned by root and must not be writable by anyone
+//! else. Hooks that do not fulfill this are skipped.
+
+use mullvad_types::states::TunnelState;
+use std::{
+    ffi::OsStr,
+    io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use talpid_types::ErrorExt;
+use tokio::{process::Command, sync::mpsc};
+
+/// Name of the directory containing the hooks, relative to the settings directory.
+pub const HOOKS_DIR: &str = "hooks.d";
+
+/// Hooks that run for longer than this are killed.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+    #[error("Failed to read hooks directory")]
+    ReadDir(#[source] io::Error),
+
+    #[error("Failed to start hook")]
+    Spawn(#[source] io::Error),
+
+    #[error("Hook did not finish within {} seconds", HOOK_TIMEOUT.as_secs())]
+    Timeout,
+}
+
+type Environment = Vec<(&'static str, String)>;
+
+/// Handle to an actor that runs the hooks.
+pub struct HookRunner {
+    tx: mpsc::UnboundedSender<Environment>,
+}
+
+impl HookRunner {
+    pub fn new(dir: PathBuf) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(dir, rx));
+        HookRunner { tx }
+    }
+
+    /// Run all hooks for a transition to `state`, once the hooks for earlier transitions have
+    /// finished.
+    pub fn on_tunnel_state(&self, state: &TunnelState) {
+        if self.tx.send(environment(state)).is_err() {
+            log::error!("Hook runner has stopped");
+        }
+    }
+}
+
+async fn run(dir: PathBuf, mut rx: mpsc::UnboundedReceiver<Environment>) {
+    while let Some(env) = rx.recv().await {
+        let hooks = match find_hooks(&dir).await {
+            Ok(hooks) => hooks,
+            Err(error) => {
+                log::error!("{}", error.display_chain());
+                continue;
+            }
+        };
+        for hook in hooks {
+            if let Err(error) = run_hook(&hook, &env).await {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg(&format!("Hook {} failed", hook.display()))
+                );
+            }
+        }
+    }
+}
+
+/// Returns the hooks in `dir`, sorted by name.
+async fn find_hooks(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => return Err(Error::ReadDir(error)),
+    };
+    if !is_trusted(dir).await {
+        log::warn!(
+            "Not running hooks since {} may be modified by other users than root",
+            dir.display()
+        );
+        return Ok(vec![]);
+    }
+
+    let mut hooks = vec![];
+    while let Some(entry) = entries.next_entry().await.map_err(Error::ReadDir)? {
+        let path = entry.path();
+        let hidden = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .is_none_or(|name| name.starts_with('.'));
+        if hidden || !is_executable(&path).await {
+            continue;
+        }
+        if !is_trusted(&path).await {
+            log::warn!(
+                "Skipping hook {} since it may be modified by other users than root",
+                path.display()
+            );
+            continue;
+        }
+        hooks.push(path);
+    }
+    hooks.sort();
+    Ok(hooks)
+}
+
+#[cfg(unix)]
+async fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(windows)]
+async fn is_executable(path: &Path) -> bool {
+    let is_file = tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.is_file());
+    let extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(str::to_ascii_lowercase);
+    is_file && matches!(extension.as_deref(), Some("exe" | "bat" | "cmd"))
+}
+
+/// Returns whether `path` is owned by root and not writable by anyone else.
+#[cfg(unix)]
+async fn is_trusted(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| is_trusted_owner_and_mode(metadata.uid(), metadata.mode()))
+}
+
+#[cfg(unix)]
+fn is_trusted_owner_and_mode(uid: u32, mode: u32) -> bool {
+    uid == 0 && mode & 0o022 == 0
+}
+
+/// The settings directory is only writable by administrators on Windows.
+#[cfg(windows)]
+async fn is_trusted(_path: &Path) -> bool {
+    true
+}
+
+async fn run_hook(hook: &Path, env: &Environment) -> Result<(), Error> {
+    log::debug!("Running hook {}", hook.display());
+
+    let child = Command::new(hook)
+        .envs(env.iter().map(|(key, value)| (*key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(Error::Spawn)?;
+
+    let output = tokio::time::timeout(HOOK_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| Error::Timeout)?
+        .map_err(Error::Spawn)?;
+
+    let name = hook
+        .file_name()
+        .unwrap_or(hook.as_os_str())
+        .to_string_lossy();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        log::info!("[{name}] {line}");
+    }
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        log::warn!("[{name}] {line}");
+    }
+    if !output.status.success() {
+        log::warn!("Hook {name} exited with {}", output.status);
+    }
+    Ok(())
+}
+
+/// Returns the environment variables passed to hooks for a transition to `state`.
+fn environment(state: &TunnelState) -> Environment {
+    let state_name = match state {
+        TunnelState::Disconnected { .. } => "disconnected",
+        TunnelState::Connecting { .. } => "connecting",
+        TunnelState::Connected { .. } => "connected",
+        TunnelState::Disconnecting(_) => "disconnecting",
+        TunnelState::Error(_) => "error",
+    };
+    let mut env = vec![("MULLVAD_TUNNEL_STATE", state_name.to_owned())];
+
+    if let TunnelState::Connecting {
+        endpoint, location, ..
+    }
+    | TunnelState::Connected {
+        endpoint, location, ..
+    } = state
+    {
+        if let Some(hostname) = location
+            .as_ref()
+            .and_then(|location| location.hostname.clone())
+        {
+            env.push(("MULLVAD_RELAY_HOSTNAME", hostname));
+        }
+        env.push((
+            "MULLVAD_RELAY_ENDPOINT",
+            endpoint.endpoint.address.to_string(),
+        ));
+        if let Some(interface) = &endpoint.tunnel_interface {
+            env.push(("MULLVAD_TUNNEL_INTERFACE", interface.clone()));
+        }
+        if !endpoint.tunnel_ips.is_empty() {
+            let ips: Vec<_> = endpoint.tunnel_ips.iter().map(IpAddr::to_string).collect();
+            env.push(("MULLVAD_TUNNEL_IPS", ips.join(",")));
+        }
+    }
+
+    env
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{relay_endpoint, relay_location};
+    use mullvad_types::features::FeatureIndicators;
+    use talpid_types::{net::TunnelEndpoint, tunnel::ActionAfterDisconnect};
+
+    #[test]
+    fn test_environment() {
+        let env = environment(&TunnelState::Disconnecting(ActionAfterDisconnect::Nothing));
+        assert_eq!(env, [("MULLVAD_TUNNEL_STATE", "disconnecting".to_owned())]);
+
+        let state = TunnelState::Connected {
+            endpoint: TunnelEndpoint {
+                tunnel_interface: Some("wg0-mullvad".to_owned()),
+                tunnel_ips: vec!["10.64.0.2".parse().unwrap(), "fc00::2".parse().unwrap()],
+                ..relay_endpoint()
+            },
+            location: Some(relay_location()),
+            feature_indicators: FeatureIndicators::default(),
+        };
+        let env = environment(&state);
+        let expected = [
+            ("MULLVAD_TUNNEL_STATE", "connected"),
+            ("MULLVAD_RELAY_HOSTNAME", "se-got-wg-001"),
+            ("MULLVAD_RELAY_ENDPOINT", "192.0.2.1:51820"),
+            ("MULLVAD_TUNNEL_INTERFACE", "wg0-mullvad"),
+            ("MULLVAD_TUNNEL_IPS", "10.64.0.2,fc00::2"),
+        ]
+        .map(|(key, value)| (key, value.to_owned()));
+        assert_eq!(env, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_trusted_owner_and_mode() {
+        assert!(is_trusted_owner_and_mode(0, 0o100755));
+        assert!(is_trusted_owner_and_mode(0, 0o40700));
+        // Not owned by root
+        assert!(!is_trusted_owner_and_mode(1000, 0o100755));
+        // Writable by the group or by anyone
+        assert!(!is_trusted_owner_and_mode(0, 0o100775));
+        assert!(!is_trusted_owner_and_mode(0, 0o100757));
+    }
+
+    #[cfg(unix)]
+    fn create_file(dir: &Path, name: &str, contents: &str, mode: u32) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    /// Hooks are only run if they are executable, not hidden, and trusted, i.e. owned by root
+    /// and not writable by anyone else. The same goes for the directory.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_find_hooks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let set_dir_mode = |mode| {
+            std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(mode)).unwrap()
+        };
+        set_dir_mode(0o755);
+
+        let second = create_file(dir.path(), "20-second", "", 0o755);
+        let first = create_file(dir.path(), "10-first", "", 0o700);
+        create_file(dir.path(), ".hidden", "", 0o755);
+        create_file(dir.path(), "not-executable", "", 0o644);
+        create_file(dir.path(), "group-writable", "", 0o775);
+        std::fs::create_dir(dir.path().join("directory")).unwrap();
+
+        // Nothing is trusted unless it is owned by root
+        let is_root = nix::unistd::getuid().is_root();
+        let expected = if is_root { vec![first, second] } else { vec![] };
+        assert_eq!(find_hooks(dir.path()).await.unwrap(), expected);
+
+        set_dir_mode(0o777);
+        assert_eq!(find_hooks(dir.path()).await.unwrap(), Vec::<PathBuf>::new());
+
+        let missing = dir.path().join("missing");
+        assert_eq!(find_hooks(&missing).await.unwrap(), Vec::<PathBuf>::new());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(start_paused = true)]
+    async fn test_hook_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook = create_file(dir.path(), "hook", "#!/bin/sh\nsleep 60\n", 0o755);
+
+        let result = run_hook(&hook, &vec![]).await;
+        assert!(matches!(result, Err(Error::Timeout)), "{result:?}");
+    }
+}
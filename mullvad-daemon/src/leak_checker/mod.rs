@@ -15,6 +15,8 @@ struct Task {
     events_rx: mpsc::UnboundedReceiver<TaskEvent>,
     route_manager: RouteManagerHandle,
     callbacks: Vec<Box<dyn LeakCheckerCallback>>,
+    #[cfg(target_os = "linux")]
+    fwmark: u32,
 }
 
 enum TaskEvent {
@@ -36,13 +38,15 @@ pub trait LeakCheckerCallback: Send + 'static {
 }
 
 impl LeakChecker {
-    pub fn new(route_manager: RouteManagerHandle) -> Self {
+    pub fn new(route_manager: RouteManagerHandle, #[cfg(target_os = "linux")] fwmark: u32) -> Self {
         let (task_event_tx, events_rx) = mpsc::unbounded_channel();
 
         let task = Task {
             events_rx,
             route_manager,
             callbacks: vec![],
+            #[cfg(target_os = "linux")]
+            fwmark,
         };
 
         tokio::task::spawn(task.run());
@@ -94,11 +98,19 @@ impl Task {
 
             let ping_destination = tunnel.endpoint;
             let route_manager = self.route_manager.clone();
+            #[cfg(target_os = "linux")]
+            let fwmark = self.fwmark;
             let leak_test = async {
                 // Give the connection a little time to settle before starting the test.
                 tokio::time::sleep(Duration::from_millis(5000)).await;
 
-                check_for_leaks(&route_manager, ping_destination).await
+                check_for_leaks(
+                    &route_manager,
+                    ping_destination,
+                    #[cfg(target_os = "linux")]
+                    fwmark,
+                )
+                .await
             };
 
             // Make sure the tunnel state doesn't change while we're doing the leak test.
@@ -166,6 +178,7 @@ async fn check_for_leaks(
 async fn check_for_leaks(
     route_manager: &RouteManagerHandle,
     destination: Endpoint,
+    #[cfg(target_os = "linux")] fwmark: u32,
 ) -> anyhow::Result<Option<LeakInfo>> {
     use anyhow::{anyhow, Context};
     use mullvad_leak_checker::{traceroute::TracerouteOpt, LeakStatus};
@@ -174,7 +187,7 @@ async fn check_for_leaks(
     let interface = {
         // By setting FWMARK, we are effectively getting the same route as when using split tunneling.
         let route = route_manager
-            .get_destination_route(destination.address.ip(), Some(mullvad_types::TUNNEL_FWMARK))
+            .get_destination_route(destination.address.ip(), Some(fwmark))
             .await
             .context("Failed to get route to relay")?
             .ok_or(anyhow!("No route to relay"))?;
@@ -258,3 +271,17 @@ where
         }
     }
 }
+
+/// Convert [LeakInfo] to the type reported to management interface clients.
+pub fn to_leak_event(info: LeakInfo) -> mullvad_types::leak::LeakInfo {
+    match info {
+        LeakInfo::NodeReachableOnInterface {
+            reachable_nodes,
+            interface,
+        } => mullvad_types::leak::LeakInfo::NodeReachableOnInterface {
+            interface: interface.to_string(),
+            reachable_nodes,
+        },
+        LeakInfo::AmIMullvad { ip } => mullvad_types::leak::LeakInfo::AmIMullvad { ip },
+    }
+}
@@ -9,23 +9,35 @@ mod api_address_updater;
 #[cfg(not(target_os = "android"))]
 mod cleanup;
 mod custom_list;
+mod data_usage;
+#[cfg(target_os = "linux")]
+pub mod dbus_interface;
 pub mod device;
 mod dns;
 pub mod exception_logging;
+#[cfg(target_os = "linux")]
+pub mod firewall_log;
 mod geoip;
+#[cfg(not(target_os = "android"))]
+mod hooks;
 mod leak_checker;
 pub mod logging;
 #[cfg(target_os = "macos")]
 mod macos;
 pub mod management_interface;
+#[cfg(not(target_os = "android"))]
+pub mod metrics;
 mod migrations;
 mod relay_list;
 #[cfg(not(target_os = "android"))]
 pub mod rpc_uniqueness_check;
 pub mod runtime;
+mod session_history;
 pub mod settings;
 pub mod shutdown;
 mod target_state;
+#[cfg(all(test, not(target_os = "android")))]
+mod test_util;
 mod tunnel;
 pub mod version;
 mod version_check;
@@ -53,19 +65,25 @@ use mullvad_types::wireguard::DaitaSettings;
 use mullvad_types::{
     access_method::{AccessMethod, AccessMethodSetting},
     account::{AccountData, AccountNumber, VoucherSubmission},
+    audit_log::{AuditEntry, AuditSource},
     auth_failed::AuthFailed,
+    constraints::Constraint,
     custom_list::CustomList,
+    data_usage::{DataUsageEntry, DataUsageQuota, QuotaExceeded},
     device::{Device, DeviceEvent, DeviceEventCause, DeviceId, DeviceState, RemoveDeviceEvent},
     features::{compute_feature_indicators, FeatureIndicator, FeatureIndicators},
     location::{GeoIpLocation, LocationEventData},
     relay_constraints::{
-        BridgeSettings, BridgeState, BridgeType, ObfuscationSettings, RelayOverride, RelaySettings,
+        BridgeSettings, BridgeState, BridgeType, LocationConstraint, ObfuscationSettings,
+        RelayOverride, RelaySettings,
     },
     relay_list::RelayList,
+    session_history::Session,
     settings::{DnsOptions, Settings},
     states::{Secured, TargetState, TargetStateStrict, TunnelState},
     version::{AppVersion, AppVersionInfo},
-    wireguard::{PublicKey, QuantumResistantState, RotationInterval},
+    wg_quick,
+    wireguard::{PublicKey, QuantumResistantState, RekeyInterval, RotationInterval},
 };
 use relay_list::{RelayListUpdater, RelayListUpdaterHandle, RELAYS_FILENAME};
 use settings::SettingsPersister;
@@ -91,8 +109,11 @@ use talpid_types::android::AndroidContext;
 #[cfg(target_os = "windows")]
 use talpid_types::split_tunnel::ExcludedProcess;
 use talpid_types::{
-    net::{IpVersion, TunnelType},
-    tunnel::{ErrorStateCause, TunnelStateTransition},
+    net::{
+        proxy::{CustomProxy, HttpConnect},
+        IpVersion, TunnelParameters, TunnelType,
+    },
+    tunnel::{ErrorStateCause, TunnelNotification, TunnelStateTransition},
     ErrorExt,
 };
 use tokio::io;
@@ -200,6 +221,12 @@ pub enum Error {
     ApiConnectionModeError(#[source] mullvad_api::access_mode::Error),
     #[error("No custom bridge has been specified")]
     NoCustomProxySaved,
+    #[error("Custom bridges must be specified by IP address, not by hostname")]
+    CustomBridgeHostname,
+    #[error("Custom bridges can not connect to HTTP proxies over TLS")]
+    CustomBridgeHttpTls,
+    #[error("The relay location can not be set while a custom relay is in use")]
+    CustomRelayInUse,
 
     #[cfg(target_os = "macos")]
     #[error("Failed to set exclusion group")]
@@ -222,6 +249,17 @@ pub enum DaemonCommand {
     Reconnect(oneshot::Sender<bool>),
     /// Request the current state.
     GetState(oneshot::Sender<TunnelState>),
+    /// Sample the traffic statistics of the current tunnel, if there is one.
+    GetTunnelStats(oneshot::Sender<Option<talpid_types::net::stats::TunnelStats>>),
+    /// Export the config of the current WireGuard tunnel in the wg-quick format, if there is one.
+    /// The private key is only included if the flag is set.
+    GetWireguardConfig(oneshot::Sender<Option<String>>, bool),
+    /// Return the data usage ledger
+    GetDataUsage(oneshot::Sender<Vec<DataUsageEntry>>),
+    /// Set the data usage quota
+    SetDataUsageQuota(ResponseTx<(), settings::Error>, DataUsageQuota),
+    /// Return the history of tunnel sessions, oldest first
+    GetSessionHistory(oneshot::Sender<Vec<Session>>),
     CreateNewAccount(ResponseTx<String, Error>),
     /// Request the metadata for an account.
     GetAccountData(
@@ -255,6 +293,8 @@ pub enum DaemonCommand {
     RemoveDevice(ResponseTx<(), Error>, AccountNumber, DeviceId),
     /// Place constraints on the type of tunnel and relay
     SetRelaySettings(ResponseTx<(), settings::Error>, RelaySettings),
+    /// Set the location constraint, leaving the other relay constraints as they are
+    SetRelayLocation(ResponseTx<(), Error>, LocationConstraint),
     /// Set the allow LAN setting.
     SetAllowLan(ResponseTx<(), settings::Error>, bool),
     /// Set the beta program setting.
@@ -274,12 +314,31 @@ pub enum DaemonCommand {
     SetEnableIpv6(ResponseTx<(), settings::Error>, bool),
     /// Set whether to enable PQ PSK exchange in the tunnel
     SetQuantumResistantTunnel(ResponseTx<(), settings::Error>, QuantumResistantState),
+    /// Set how often to negotiate a new PQ-safe PSK while connected
+    SetQuantumResistantRekeyInterval(ResponseTx<(), settings::Error>, Option<RekeyInterval>),
+    /// Set the parameters of the WireGuard connectivity monitor
+    SetConnectivityCheckOptions(
+        ResponseTx<(), settings::Error>,
+        talpid_types::net::wireguard::ConnectivityCheckOptions,
+    ),
+    /// Set the preferred WireGuard implementation
+    SetWireguardBackend(
+        ResponseTx<(), settings::Error>,
+        talpid_types::net::wireguard::WireguardBackend,
+    ),
+    /// Set whether to connect to a new relay before disconnecting from the current one
+    SetWireguardMakeBeforeBreak(ResponseTx<(), settings::Error>, bool),
     /// Set DAITA settings for the tunnel
     #[cfg(daita)]
     SetEnableDaita(ResponseTx<(), settings::Error>, bool),
     #[cfg(daita)]
     SetDaitaUseMultihopIfNecessary(ResponseTx<(), settings::Error>, bool),
     #[cfg(daita)]
+    SetDaitaLevel(
+        ResponseTx<(), settings::Error>,
+        talpid_types::net::wireguard::DaitaLevel,
+    ),
+    #[cfg(daita)]
     SetDaitaSettings(ResponseTx<(), settings::Error>, DaitaSettings),
     /// Set DNS options or servers to use
     SetDnsOptions(ResponseTx<(), settings::Error>, DnsOptions),
@@ -325,6 +384,8 @@ pub enum DaemonCommand {
     ClearCustomApiAccessMethods(ResponseTx<(), Error>),
     /// Get the currently used API access method
     GetCurrentAccessMethod(ResponseTx<AccessMethodSetting, Error>),
+    /// Get the endpoint that the current API access method resolved to
+    GetCurrentApiEndpoint(ResponseTx<talpid_types::net::Endpoint, Error>),
     /// Test an API access method
     TestApiAccessMethodById(ResponseTx<bool, Error>, mullvad_types::access_method::Id),
     /// Test a custom API access method
@@ -402,14 +463,24 @@ pub enum DaemonCommand {
         relay: String,
         tx: oneshot::Sender<()>,
     },
+    /// Listen for packets blocked by the firewall
+    #[cfg(target_os = "linux")]
+    FirewallLogListen(
+        ResponseTx<
+            tokio::sync::mpsc::UnboundedReceiver<talpid_types::net::firewall_log::BlockedPacket>,
+            firewall_log::Error,
+        >,
+    ),
+    /// Return all recorded changes to the settings
+    GetSettingsAuditLog(ResponseTx<Vec<AuditEntry>, settings::audit::Error>),
 }
 
 /// All events that can happen in the daemon. Sent from various threads and exposed interfaces.
 pub(crate) enum InternalDaemonEvent {
     /// Tunnel has changed state.
     TunnelStateTransition(TunnelStateTransition),
-    /// A command sent to the daemon.
-    Command(DaemonCommand),
+    /// A command sent to the daemon, and what sent it.
+    Command(DaemonCommand, AuditSource),
     /// Daemon shutdown triggered by a signal, ctrl-c or similar.
     /// The boolean should indicate whether the shutdown was user-initiated.
     TriggerShutdown(bool),
@@ -430,9 +501,17 @@ pub(crate) enum InternalDaemonEvent {
     SettingsChanged,
     /// The split tunnel paths or state were updated.
     #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-    ExcludedPathsEvent(ExcludedPathsUpdate, oneshot::Sender<Result<(), Error>>),
+    ExcludedPathsEvent(
+        ExcludedPathsUpdate,
+        AuditSource,
+        oneshot::Sender<Result<(), Error>>,
+    ),
     /// A network leak was detected.
     LeakDetected(LeakInfo),
+    /// The data usage exceeded a limit of the quota.
+    DataQuotaExceeded(QuotaExceeded),
+    /// The active tunnel reported an event that did not cause a state transition.
+    TunnelNotification(TunnelNotification),
 }
 
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
@@ -449,7 +528,7 @@ impl From<TunnelStateTransition> for InternalDaemonEvent {
 
 impl From<DaemonCommand> for InternalDaemonEvent {
     fn from(command: DaemonCommand) -> Self {
-        InternalDaemonEvent::Command(command)
+        InternalDaemonEvent::Command(command, AuditSource::Daemon)
     }
 }
 
@@ -459,6 +538,18 @@ impl From<AppVersionInfo> for InternalDaemonEvent {
     }
 }
 
+impl From<QuotaExceeded> for InternalDaemonEvent {
+    fn from(event: QuotaExceeded) -> Self {
+        InternalDaemonEvent::DataQuotaExceeded(event)
+    }
+}
+
+impl From<TunnelNotification> for InternalDaemonEvent {
+    fn from(notification: TunnelNotification) -> Self {
+        InternalDaemonEvent::TunnelNotification(notification)
+    }
+}
+
 impl From<AccountEvent> for InternalDaemonEvent {
     fn from(event: AccountEvent) -> Self {
         InternalDaemonEvent::DeviceEvent(event)
@@ -514,8 +605,21 @@ pub struct DaemonCommandSender(Arc<mpsc::UnboundedSender<InternalDaemonEvent>>);
 
 impl DaemonCommandSender {
     pub fn send(&self, command: DaemonCommand) -> Result<(), Error> {
+        self.send_with_source(
+            command,
+            settings::audit::source_from_caller(mullvad_management_interface::current_caller()),
+        )
+    }
+
+    /// Send a command on behalf of `source` rather than the current gRPC caller. Any settings
+    /// changes made by the command are attributed to `source` in the audit log.
+    pub(crate) fn send_with_source(
+        &self,
+        command: DaemonCommand,
+        source: AuditSource,
+    ) -> Result<(), Error> {
         self.0
-            .unbounded_send(InternalDaemonEvent::Command(command))
+            .unbounded_send(InternalDaemonEvent::Command(command, source))
             .map_err(|_| Error::DaemonUnavailable)
     }
 
@@ -631,6 +735,16 @@ pub struct Daemon {
     volume_update_tx: mpsc::UnboundedSender<()>,
     location_handler: GeoIpHandler,
     leak_checker: LeakChecker,
+    data_usage: data_usage::DataUsageLedger,
+    session_history: session_history::SessionHistory,
+    #[cfg(target_os = "linux")]
+    firewall_log: firewall_log::FirewallLog,
+    #[cfg(not(target_os = "android"))]
+    hooks: hooks::HookRunner,
+    #[cfg(not(target_os = "android"))]
+    metrics: Option<metrics::Metrics>,
+    #[cfg(target_os = "linux")]
+    dbus_interface: Option<dbus_interface::DbusInterface>,
     cache_dir: PathBuf,
 }
 pub struct DaemonConfig {
@@ -642,6 +756,15 @@ pub struct DaemonConfig {
     pub endpoint: ApiEndpoint,
     #[cfg(target_os = "android")]
     pub android_context: AndroidContext,
+    /// Routing table ID and firewall marks used by the tunnel.
+    #[cfg(target_os = "linux")]
+    pub linux_ids: tunnel_state_machine::LinuxNetworkingIdentifiers,
+    /// Bus to export the D-Bus service on, if any.
+    #[cfg(target_os = "linux")]
+    pub dbus_bus: Option<dbus_interface::Bus>,
+    /// Address to serve metrics on, if any.
+    #[cfg(not(target_os = "android"))]
+    pub metrics_address: Option<metrics::ListenAddress>,
 }
 
 impl Daemon {
@@ -657,6 +780,18 @@ impl Daemon {
             ManagementInterfaceServer::start(command_sender, config.rpc_socket_path)
                 .map_err(Error::ManagementInterfaceError)?;
 
+        #[cfg(target_os = "linux")]
+        let dbus_interface = config.dbus_bus.and_then(|bus| {
+            dbus_interface::DbusInterface::start(bus, daemon_command_channel.sender())
+                .inspect_err(|error| {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to start D-Bus service")
+                    );
+                })
+                .ok()
+        });
+
         let (internal_event_tx, internal_event_rx) = daemon_command_channel.destructure();
 
         #[cfg(target_os = "android")]
@@ -683,18 +818,20 @@ impl Daemon {
         let api_availability = api_runtime.availability_handle();
         api_availability.suspend();
 
-        let migration_data = migrations::migrate_all(&config.cache_dir, &config.settings_dir)
-            .await
-            .unwrap_or_else(|error| {
-                log::error!(
-                    "{}",
-                    error.display_chain_with_msg("Failed to migrate settings or cache")
-                );
-                None
-            });
+        let audit_log = settings::audit::AuditLog::new(config.log_dir.as_deref());
+        let migration_data =
+            migrations::migrate_all(&config.cache_dir, &config.settings_dir, &audit_log)
+                .await
+                .unwrap_or_else(|error| {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to migrate settings or cache")
+                    );
+                    None
+                });
 
         let settings_event_listener = management_interface.notifier().clone();
-        let mut settings = SettingsPersister::load(&config.settings_dir).await;
+        let mut settings = SettingsPersister::load(&config.settings_dir, audit_log).await;
         settings.register_change_listener(move |settings| {
             // Notify management interface server of changes to the settings
             settings_event_listener.notify_settings(settings.to_owned());
@@ -715,11 +852,15 @@ impl Daemon {
                 .set_config(SelectorConfig::from_settings(settings));
         });
 
+        // Shared by the resolver and the selector, so that their events arrive in order
+        let access_method_event_sender = internal_event_tx.to_unbounded_sender();
         let encrypted_dns_proxy_cache = EncryptedDnsProxyState::default();
         let method_resolver = DaemonAccessMethodResolver::new(
             relay_selector.clone(),
             encrypted_dns_proxy_cache,
             api_runtime.address_cache().clone(),
+            #[cfg(not(target_os = "android"))]
+            access_method_event_sender.clone(),
         );
 
         let (access_mode_handler, access_mode_provider) =
@@ -728,7 +869,7 @@ impl Daemon {
                 settings.api_access_methods.clone(),
                 #[cfg(feature = "api-override")]
                 config.endpoint.clone(),
-                internal_event_tx.to_unbounded_sender(),
+                access_method_event_sender,
             )
             .await
             .map_err(Error::ApiConnectionModeError)?;
@@ -806,6 +947,8 @@ impl Daemon {
             account_manager.clone(),
             relay_selector.clone(),
             settings.tunnel_options.clone(),
+            #[cfg(target_os = "linux")]
+            config.linux_ids.fwmark,
         );
 
         let param_gen = parameters_generator.clone();
@@ -828,9 +971,9 @@ impl Daemon {
 
         let route_manager = RouteManagerHandle::spawn(
             #[cfg(target_os = "linux")]
-            mullvad_types::TUNNEL_FWMARK,
+            config.linux_ids.fwmark,
             #[cfg(target_os = "linux")]
-            mullvad_types::TUNNEL_TABLE_ID,
+            config.linux_ids.table_id,
             #[cfg(target_os = "android")]
             config.android_context.clone(),
         )
@@ -859,6 +1002,7 @@ impl Daemon {
             config.log_dir,
             config.resource_dir.clone(),
             internal_event_tx.to_specialized_sender(),
+            internal_event_tx.to_specialized_sender(),
             offline_state_tx,
             route_manager.clone(),
             #[cfg(target_os = "windows")]
@@ -868,10 +1012,7 @@ impl Daemon {
             #[cfg(target_os = "android")]
             connectivity_listener.clone(),
             #[cfg(target_os = "linux")]
-            tunnel_state_machine::LinuxNetworkingIdentifiers {
-                fwmark: mullvad_types::TUNNEL_FWMARK,
-                table_id: mullvad_types::TUNNEL_TABLE_ID,
-            },
+            config.linux_ids,
         )
         .await
         .map_err(Error::TunnelError)?;
@@ -913,7 +1054,11 @@ impl Daemon {
         );
 
         let leak_checker = {
-            let mut leak_checker = LeakChecker::new(route_manager);
+            let mut leak_checker = LeakChecker::new(
+                route_manager,
+                #[cfg(target_os = "linux")]
+                config.linux_ids.fwmark,
+            );
             let internal_event_tx = internal_event_tx.clone();
             leak_checker.add_leak_callback(move |info| {
                 internal_event_tx
@@ -923,6 +1068,45 @@ impl Daemon {
             leak_checker
         };
 
+        let data_usage = data_usage::DataUsageLedger::new(
+            &config.cache_dir,
+            Arc::downgrade(tunnel_state_machine_handle.command_tx()),
+            internal_event_tx.to_specialized_sender(),
+            settings.data_usage_quota,
+        );
+
+        let session_history = session_history::SessionHistory::new(
+            &config.cache_dir,
+            Arc::downgrade(tunnel_state_machine_handle.command_tx()),
+        );
+
+        #[cfg(target_os = "linux")]
+        let firewall_log = firewall_log::FirewallLog::new(Arc::downgrade(
+            tunnel_state_machine_handle.command_tx(),
+        ));
+
+        #[cfg(not(target_os = "android"))]
+        let hooks = hooks::HookRunner::new(config.settings_dir.join(hooks::HOOKS_DIR));
+
+        #[cfg(not(target_os = "android"))]
+        let metrics = match config.metrics_address {
+            Some(address) => metrics::Metrics::start(
+                address,
+                Arc::downgrade(tunnel_state_machine_handle.command_tx()),
+                api_runtime.request_stats().clone(),
+                relay_selector.clone(),
+            )
+            .await
+            .inspect_err(|error| {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to start metrics endpoint")
+                );
+            })
+            .ok(),
+            None => None,
+        };
+
         let daemon = Daemon {
             tunnel_state: TunnelState::Disconnected {
                 location: None,
@@ -931,7 +1115,8 @@ impl Daemon {
             },
             target_state,
             #[cfg(target_os = "linux")]
-            exclude_pids: split_tunnel::PidManager::new().map_err(Error::InitSplitTunneling)?,
+            exclude_pids: split_tunnel::PidManager::new(config.linux_ids.net_cls_classid)
+                .map_err(Error::InitSplitTunneling)?,
             rx: internal_event_rx,
             tx: internal_event_tx,
             reconnection_job: None,
@@ -954,9 +1139,22 @@ impl Daemon {
             volume_update_tx,
             location_handler,
             leak_checker,
+            data_usage,
+            session_history,
+            #[cfg(target_os = "linux")]
+            firewall_log,
+            #[cfg(not(target_os = "android"))]
+            hooks,
+            #[cfg(not(target_os = "android"))]
+            metrics,
+            #[cfg(target_os = "linux")]
+            dbus_interface,
             cache_dir: config.cache_dir,
         };
 
+        #[cfg(target_os = "linux")]
+        daemon.notify_dbus_interface();
+
         api_availability.unsuspend();
 
         #[cfg(target_os = "macos")]
@@ -1068,7 +1266,9 @@ impl Daemon {
             TunnelStateTransition(transition) => {
                 self.handle_tunnel_state_transition(transition).await;
             }
-            Command(command) => self.handle_command(command).await,
+            Command(command, source) => {
+                self.handle_command(command, source).await;
+            }
             TriggerShutdown(user_init_shutdown) => {
                 self.on_trigger_shutdown(user_init_shutdown);
                 should_stop = true;
@@ -1087,11 +1287,18 @@ impl Daemon {
                 self.update_feature_indicators_on_settings_changed();
             }
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-            ExcludedPathsEvent(update, tx) => self.handle_new_excluded_paths(update, tx).await,
+            ExcludedPathsEvent(update, source, tx) => {
+                self.handle_new_excluded_paths(update, source, tx).await
+            }
             LeakDetected(leak_info) => {
                 log::warn!("Network leak detected! Please contact Mullvad support.");
-                log::warn!("{leak_info:?}")
+                log::warn!("{leak_info:?}");
+                self.management_interface
+                    .notifier()
+                    .notify_leak_detected(leak_checker::to_leak_event(leak_info));
             }
+            DataQuotaExceeded(event) => self.handle_data_quota_exceeded(event).await,
+            TunnelNotification(notification) => self.handle_tunnel_notification(notification),
         }
         should_stop
     }
@@ -1194,7 +1401,31 @@ impl Daemon {
             _ => {}
         }
 
+        self.data_usage.set_tunnel(match &tunnel_state {
+            TunnelState::Connected {
+                endpoint, location, ..
+            } => Some(data_usage::TunnelInfo {
+                relay: location
+                    .as_ref()
+                    .and_then(|location| location.hostname.clone())
+                    .unwrap_or_else(|| endpoint.endpoint.address.ip().to_string()),
+                protocol: format!("{}/{}", endpoint.tunnel_type, endpoint.endpoint.protocol),
+            }),
+            _ => None,
+        });
+        self.session_history.on_tunnel_state(&tunnel_state);
+
+        #[cfg(not(target_os = "android"))]
+        {
+            self.hooks.on_tunnel_state(&tunnel_state);
+            if let Some(metrics) = &self.metrics {
+                metrics.on_tunnel_state(&tunnel_state);
+            }
+        }
+
         self.tunnel_state = tunnel_state.clone();
+        #[cfg(target_os = "linux")]
+        self.notify_dbus_interface();
         self.management_interface
             .notifier()
             .notify_new_state(tunnel_state);
@@ -1236,6 +1467,35 @@ impl Daemon {
         self.location_handler.send_geo_location_request(use_ipv6);
     }
 
+    /// Forwards an exceeded data usage limit to clients, and disconnects if it was the hard
+    /// limit.
+    async fn handle_data_quota_exceeded(&mut self, event: QuotaExceeded) {
+        self.management_interface
+            .notifier()
+            .notify_data_quota_exceeded(event);
+        if event.hard {
+            log::info!("Disconnecting because the hard data usage limit was exceeded");
+            self.set_target_state(TargetState::Unsecured).await;
+        }
+    }
+
+    fn handle_tunnel_notification(&mut self, notification: TunnelNotification) {
+        match notification {
+            TunnelNotification::MtuChanged(mtu) => {
+                log::info!("Tunnel MTU changed to {mtu}");
+                self.management_interface.notifier().notify_mtu_changed(mtu);
+            }
+            TunnelNotification::ConnectivityDegraded => self
+                .management_interface
+                .notifier()
+                .notify_connectivity_changed(true),
+            TunnelNotification::ConnectivityRestored => self
+                .management_interface
+                .notifier()
+                .notify_connectivity_changed(false),
+        }
+    }
+
     /// Receives and handles the geographical exit location received from am.i.mullvad.net, i.e. the
     /// [`InternalDaemonEvent::LocationEvent`] event.
     fn handle_location_event(&mut self, location_data: LocationEventData) {
@@ -1267,11 +1527,21 @@ impl Daemon {
             _ => return,
         };
 
+        #[cfg(target_os = "linux")]
+        self.notify_dbus_interface();
         self.management_interface
             .notifier()
             .notify_new_state(self.tunnel_state.clone());
     }
 
+    /// Update the properties of the D-Bus service, if it is running, from the tunnel state.
+    #[cfg(target_os = "linux")]
+    fn notify_dbus_interface(&self) {
+        if let Some(dbus_interface) = &self.dbus_interface {
+            dbus_interface.notify_tunnel_state(&self.tunnel_state);
+        }
+    }
+
     /// Update the set of feature indicators based on the new settings.
     fn update_feature_indicators_on_settings_changed(&mut self) {
         // Updated settings may affect the feature indicators, even if they don't change the tunnel
@@ -1348,7 +1618,7 @@ impl Daemon {
         }
     }
 
-    async fn handle_command(&mut self, command: DaemonCommand) {
+    async fn handle_command(&mut self, command: DaemonCommand, source: AuditSource) {
         use self::DaemonCommand::*;
         if self.tunnel_state.is_disconnected() {
             self.api_handle.availability.reset_inactivity_timer();
@@ -1358,6 +1628,13 @@ impl Daemon {
             SetTargetState(tx, state) => self.on_set_target_state(tx, state).await,
             Reconnect(tx) => self.on_reconnect(tx),
             GetState(tx) => self.on_get_state(tx),
+            GetTunnelStats(tx) => self.on_get_tunnel_stats(tx),
+            GetWireguardConfig(tx, include_secrets) => {
+                self.on_get_wireguard_config(tx, include_secrets)
+            }
+            GetDataUsage(tx) => self.data_usage.get(tx),
+            GetSessionHistory(tx) => self.session_history.get(tx),
+            SetDataUsageQuota(tx, quota) => self.on_set_data_usage_quota(tx, quota, source).await,
             CreateNewAccount(tx) => self.on_create_new_account(tx),
             GetAccountData(tx, account_number) => self.on_get_account_data(tx, account_number),
             GetWwwAuthToken(tx) => self.on_get_www_auth_token(tx).await,
@@ -1374,68 +1651,112 @@ impl Daemon {
             }
             GetAccountHistory(tx) => self.on_get_account_history(tx),
             ClearAccountHistory(tx) => self.on_clear_account_history(tx).await,
-            SetRelaySettings(tx, update) => self.on_set_relay_settings(tx, update).await,
-            SetAllowLan(tx, allow_lan) => self.on_set_allow_lan(tx, allow_lan).await,
-            SetShowBetaReleases(tx, enabled) => self.on_set_show_beta_releases(tx, enabled).await,
+            SetRelaySettings(tx, update) => self.on_set_relay_settings(tx, update, source).await,
+            SetRelayLocation(tx, location) => {
+                self.on_set_relay_location(tx, location, source).await
+            }
+            SetAllowLan(tx, allow_lan) => self.on_set_allow_lan(tx, allow_lan, source).await,
+            SetShowBetaReleases(tx, enabled) => {
+                self.on_set_show_beta_releases(tx, enabled, source).await
+            }
             #[cfg(not(target_os = "android"))]
             SetBlockWhenDisconnected(tx, block_when_disconnected) => {
-                self.on_set_block_when_disconnected(tx, block_when_disconnected)
+                self.on_set_block_when_disconnected(tx, block_when_disconnected, source)
                     .await
             }
-            SetAutoConnect(tx, auto_connect) => self.on_set_auto_connect(tx, auto_connect).await,
-            SetOpenVpnMssfix(tx, mssfix_arg) => self.on_set_openvpn_mssfix(tx, mssfix_arg).await,
+            SetAutoConnect(tx, auto_connect) => {
+                self.on_set_auto_connect(tx, auto_connect, source).await
+            }
+            SetOpenVpnMssfix(tx, mssfix_arg) => {
+                self.on_set_openvpn_mssfix(tx, mssfix_arg, source).await
+            }
             SetBridgeSettings(tx, bridge_settings) => {
-                self.on_set_bridge_settings(tx, bridge_settings).await
+                self.on_set_bridge_settings(tx, bridge_settings, source)
+                    .await
+            }
+            SetBridgeState(tx, bridge_state) => {
+                self.on_set_bridge_state(tx, bridge_state, source).await
+            }
+            SetEnableIpv6(tx, enable_ipv6) => {
+                self.on_set_enable_ipv6(tx, enable_ipv6, source).await
             }
-            SetBridgeState(tx, bridge_state) => self.on_set_bridge_state(tx, bridge_state).await,
-            SetEnableIpv6(tx, enable_ipv6) => self.on_set_enable_ipv6(tx, enable_ipv6).await,
             SetQuantumResistantTunnel(tx, quantum_resistant_state) => {
-                self.on_set_quantum_resistant_tunnel(tx, quantum_resistant_state)
+                self.on_set_quantum_resistant_tunnel(tx, quantum_resistant_state, source)
+                    .await
+            }
+            SetQuantumResistantRekeyInterval(tx, interval) => {
+                self.on_set_quantum_resistant_rekey_interval(tx, interval, source)
+                    .await
+            }
+            SetConnectivityCheckOptions(tx, options) => {
+                self.on_set_connectivity_check_options(tx, options, source)
+                    .await
+            }
+            SetWireguardBackend(tx, backend) => {
+                self.on_set_wireguard_backend(tx, backend, source).await
+            }
+            SetWireguardMakeBeforeBreak(tx, enabled) => {
+                self.on_set_wireguard_make_before_break(tx, enabled, source)
                     .await
             }
             #[cfg(daita)]
-            SetEnableDaita(tx, value) => self.on_set_daita_enabled(tx, value).await,
+            SetEnableDaita(tx, value) => self.on_set_daita_enabled(tx, value, source).await,
             #[cfg(daita)]
             SetDaitaUseMultihopIfNecessary(tx, value) => {
-                self.on_set_daita_use_multihop_if_necessary(tx, value).await
+                self.on_set_daita_use_multihop_if_necessary(tx, value, source)
+                    .await
             }
             #[cfg(daita)]
+            SetDaitaLevel(tx, level) => self.on_set_daita_level(tx, level, source).await,
+            #[cfg(daita)]
             SetDaitaSettings(tx, daita_settings) => {
-                self.on_set_daita_settings(tx, daita_settings).await
+                self.on_set_daita_settings(tx, daita_settings, source).await
+            }
+            SetDnsOptions(tx, dns_servers) => {
+                self.on_set_dns_options(tx, dns_servers, source).await
             }
-            SetDnsOptions(tx, dns_servers) => self.on_set_dns_options(tx, dns_servers).await,
             SetRelayOverride(tx, relay_override) => {
-                self.on_set_relay_override(tx, relay_override).await
+                self.on_set_relay_override(tx, relay_override, source).await
             }
-            ClearAllRelayOverrides(tx) => self.on_clear_all_relay_overrides(tx).await,
-            SetWireguardMtu(tx, mtu) => self.on_set_wireguard_mtu(tx, mtu).await,
+            ClearAllRelayOverrides(tx) => self.on_clear_all_relay_overrides(tx, source).await,
+            SetWireguardMtu(tx, mtu) => self.on_set_wireguard_mtu(tx, mtu, source).await,
             SetWireguardRotationInterval(tx, interval) => {
-                self.on_set_wireguard_rotation_interval(tx, interval).await
+                self.on_set_wireguard_rotation_interval(tx, interval, source)
+                    .await
             }
             GetSettings(tx) => self.on_get_settings(tx),
-            ResetSettings(tx) => self.on_reset_settings(tx).await,
+            ResetSettings(tx) => self.on_reset_settings(tx, source).await,
             RotateWireguardKey(tx) => self.on_rotate_wireguard_key(tx),
             GetWireguardKey(tx) => self.on_get_wireguard_key(tx).await,
-            CreateCustomList(tx, name) => self.on_create_custom_list(tx, name).await,
-            DeleteCustomList(tx, id) => self.on_delete_custom_list(tx, id).await,
-            UpdateCustomList(tx, update) => self.on_update_custom_list(tx, update).await,
-            ClearCustomLists(tx) => self.on_clear_custom_lists(tx).await,
+            CreateCustomList(tx, name) => self.on_create_custom_list(tx, name, source).await,
+            DeleteCustomList(tx, id) => self.on_delete_custom_list(tx, id, source).await,
+            UpdateCustomList(tx, update) => self.on_update_custom_list(tx, update, source).await,
+            ClearCustomLists(tx) => self.on_clear_custom_lists(tx, source).await,
             GetVersionInfo(tx) => self.on_get_version_info(tx),
             AddApiAccessMethod(tx, name, enabled, access_method) => {
-                self.on_add_access_method(tx, name, enabled, access_method)
+                self.on_add_access_method(tx, name, enabled, access_method, source)
                     .await
             }
-            RemoveApiAccessMethod(tx, method) => self.on_remove_api_access_method(tx, method).await,
-            UpdateApiAccessMethod(tx, method) => self.on_update_api_access_method(tx, method).await,
-            ClearCustomApiAccessMethods(tx) => self.on_clear_custom_api_access_methods(tx).await,
+            RemoveApiAccessMethod(tx, method) => {
+                self.on_remove_api_access_method(tx, method, source).await
+            }
+            UpdateApiAccessMethod(tx, method) => {
+                self.on_update_api_access_method(tx, method, source).await
+            }
+            ClearCustomApiAccessMethods(tx) => {
+                self.on_clear_custom_api_access_methods(tx, source).await
+            }
             GetCurrentAccessMethod(tx) => self.on_get_current_api_access_method(tx),
-            SetApiAccessMethod(tx, method) => self.on_set_api_access_method(tx, method).await,
-            TestApiAccessMethodById(tx, method) => self.on_test_api_access_method(tx, method).await,
+            GetCurrentApiEndpoint(tx) => self.on_get_current_api_endpoint(tx),
+            SetApiAccessMethod(tx, method) => {
+                self.on_set_api_access_method(tx, method, source).await
+            }
+            TestApiAccessMethodById(tx, method) => self.on_test_api_access_method(tx, method),
             TestCustomApiAccessMethod(tx, proxy) => self.on_test_proxy_as_access_method(tx, proxy),
             IsPerformingPostUpgrade(tx) => self.on_is_performing_post_upgrade(tx),
             GetCurrentVersion(tx) => self.on_get_current_version(tx),
             #[cfg(not(target_os = "android"))]
-            FactoryReset(tx) => self.on_factory_reset(tx).await,
+            FactoryReset(tx) => self.on_factory_reset(tx, source).await,
             #[cfg(target_os = "linux")]
             GetSplitTunnelProcesses(tx) => self.on_get_split_tunnel_processes(tx),
             #[cfg(target_os = "linux")]
@@ -1445,19 +1766,19 @@ impl Daemon {
             #[cfg(target_os = "linux")]
             ClearSplitTunnelProcesses(tx) => self.on_clear_split_tunnel_processes(tx),
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-            AddSplitTunnelApp(tx, app) => self.on_add_split_tunnel_app(tx, app),
+            AddSplitTunnelApp(tx, app) => self.on_add_split_tunnel_app(tx, app, source),
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-            RemoveSplitTunnelApp(tx, path) => self.on_remove_split_tunnel_app(tx, path),
+            RemoveSplitTunnelApp(tx, path) => self.on_remove_split_tunnel_app(tx, path, source),
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-            ClearSplitTunnelApps(tx) => self.on_clear_split_tunnel_apps(tx),
+            ClearSplitTunnelApps(tx) => self.on_clear_split_tunnel_apps(tx, source),
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-            SetSplitTunnelState(tx, enabled) => self.on_set_split_tunnel_state(tx, enabled),
+            SetSplitTunnelState(tx, enabled) => self.on_set_split_tunnel_state(tx, enabled, source),
             #[cfg(windows)]
             GetSplitTunnelProcesses(tx) => self.on_get_split_tunnel_processes(tx),
             #[cfg(target_os = "windows")]
             CheckVolumes(tx) => self.on_check_volumes(tx),
             SetObfuscationSettings(tx, settings) => {
-                self.on_set_obfuscation_settings(tx, settings).await
+                self.on_set_obfuscation_settings(tx, settings, source).await
             }
             PrepareRestart(shutdown) => self.on_prepare_restart(shutdown),
             #[cfg(target_os = "android")]
@@ -1468,11 +1789,14 @@ impl Daemon {
             VerifyPlayPurchase(tx, play_purchase) => {
                 self.on_verify_play_purchase(tx, play_purchase)
             }
-            ApplyJsonSettings(tx, blob) => self.on_apply_json_settings(tx, blob).await,
+            ApplyJsonSettings(tx, blob) => self.on_apply_json_settings(tx, blob, source).await,
             ExportJsonSettings(tx) => self.on_export_json_settings(tx),
             GetFeatureIndicators(tx) => self.on_get_feature_indicators(tx),
             DisableRelay { relay, tx } => self.on_toggle_relay(relay, false, tx),
             EnableRelay { relay, tx } => self.on_toggle_relay(relay, true, tx),
+            #[cfg(target_os = "linux")]
+            FirewallLogListen(tx) => self.on_firewall_log_listen(tx),
+            GetSettingsAuditLog(tx) => self.on_get_settings_audit_log(tx),
         }
     }
 
@@ -1655,6 +1979,7 @@ impl Daemon {
     async fn handle_new_excluded_paths(
         &mut self,
         update: ExcludedPathsUpdate,
+        source: AuditSource,
         tx: ResponseTx<(), Error>,
     ) {
         let save_result = match update {
@@ -1663,7 +1988,9 @@ impl Daemon {
                     self.settings.settings().split_tunnel.enable_exclusions;
                 let save_result = self
                     .settings
-                    .update(move |settings| settings.split_tunnel.enable_exclusions = state)
+                    .update(source, move |settings| {
+                        settings.split_tunnel.enable_exclusions = state
+                    })
                     .await
                     .map_err(Error::SettingsError);
                 // If the user enables split tunneling without also enabling Full Disk Access
@@ -1690,7 +2017,7 @@ impl Daemon {
             }
             ExcludedPathsUpdate::SetPaths(paths) => self
                 .settings
-                .update(move |settings| settings.split_tunnel.apps = paths)
+                .update(source, move |settings| settings.split_tunnel.apps = paths)
                 .await
                 .map_err(Error::SettingsError),
         };
@@ -1720,6 +2047,53 @@ impl Daemon {
         Self::oneshot_send(tx, self.tunnel_state.clone(), "current state");
     }
 
+    fn on_get_tunnel_stats(
+        &self,
+        tx: oneshot::Sender<Option<talpid_types::net::stats::TunnelStats>>,
+    ) {
+        self.send_tunnel_command(TunnelCommand::GetTunnelStats(tx));
+    }
+
+    fn on_get_wireguard_config(&self, tx: oneshot::Sender<Option<String>>, include_secrets: bool) {
+        let (parameters_tx, parameters_rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::GetTunnelParameters(parameters_tx));
+        tokio::spawn(async move {
+            let config = match parameters_rx.await {
+                Ok(Some(TunnelParameters::Wireguard(parameters))) => {
+                    let config = &parameters.connection;
+                    let host = config.peer.endpoint.ip().to_string();
+                    Some(wg_quick::serialize(&host, config, include_secrets))
+                }
+                _ => None,
+            };
+            Self::oneshot_send(tx, config, "get_wireguard_config response");
+        });
+    }
+
+    async fn on_set_data_usage_quota(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        quota: DataUsageQuota,
+        source: AuditSource,
+    ) {
+        match self
+            .settings
+            .update(source, move |settings| settings.data_usage_quota = quota)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.data_usage.set_quota(quota);
+                }
+                Self::oneshot_send(tx, Ok(()), "set_data_usage_quota response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_data_usage_quota response");
+            }
+        }
+    }
+
     fn on_is_performing_post_upgrade(&self, tx: oneshot::Sender<bool>) {
         let performing_post_upgrade = !self.migration_complete.is_complete();
         Self::oneshot_send(tx, performing_post_upgrade, "performing post upgrade");
@@ -1967,7 +2341,7 @@ impl Daemon {
     }
 
     #[cfg(not(target_os = "android"))]
-    async fn on_factory_reset(&mut self, tx: ResponseTx<(), Error>) {
+    async fn on_factory_reset(&mut self, tx: ResponseTx<(), Error>, source: AuditSource) {
         let mut last_error = None;
 
         if let Err(error) = self.account_manager.logout().await {
@@ -1985,7 +2359,15 @@ impl Daemon {
             last_error = Some("Failed to clear account history");
         }
 
-        if let Err(e) = self.settings.reset().await {
+        if let Err(error) = self.session_history.clear().await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to clear session history")
+            );
+            last_error = Some("Failed to clear session history");
+        }
+
+        if let Err(e) = self.settings.reset(source).await {
             log::error!("Failed to reset settings: {}", e);
             last_error = Some("Failed to reset settings");
         }
@@ -2052,6 +2434,7 @@ impl Daemon {
         response_msg: &'static str,
         settings: Settings,
         update: ExcludedPathsUpdate,
+        source: AuditSource,
     ) {
         let new_list = match update {
             ExcludedPathsUpdate::SetPaths(ref paths) => {
@@ -2106,12 +2489,12 @@ impl Daemon {
                     }
                 }
 
-                let _ = daemon_tx.send(InternalDaemonEvent::ExcludedPathsEvent(update, tx));
+                let _ = daemon_tx.send(InternalDaemonEvent::ExcludedPathsEvent(update, source, tx));
             });
         } else {
             let _ = self
                 .tx
-                .send(InternalDaemonEvent::ExcludedPathsEvent(update, tx));
+                .send(InternalDaemonEvent::ExcludedPathsEvent(update, source, tx));
         }
     }
 
@@ -2123,6 +2506,7 @@ impl Daemon {
         _response_msg: &'static str,
         settings: Settings,
         update: ExcludedPathsUpdate,
+        source: AuditSource,
     ) {
         let tunnel_list = match update {
             ExcludedPathsUpdate::SetPaths(ref paths) if settings.split_tunnel.enable_exclusions => {
@@ -2161,12 +2545,17 @@ impl Daemon {
                     log::error!("The tunnel failed to return a result");
                 }
             }
-            let _ = daemon_tx.send(InternalDaemonEvent::ExcludedPathsEvent(update, tx));
+            let _ = daemon_tx.send(InternalDaemonEvent::ExcludedPathsEvent(update, source, tx));
         });
     }
 
     #[cfg(any(target_os = "windows", target_os = "macos", target_os = "android"))]
-    fn on_add_split_tunnel_app(&mut self, tx: ResponseTx<(), Error>, app: SplitApp) {
+    fn on_add_split_tunnel_app(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        app: SplitApp,
+        source: AuditSource,
+    ) {
         let settings = self.settings.to_settings();
 
         let excluded_apps = {
@@ -2180,11 +2569,17 @@ impl Daemon {
             "add_split_tunnel_app response",
             settings,
             ExcludedPathsUpdate::SetPaths(excluded_apps),
+            source,
         );
     }
 
     #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-    fn on_remove_split_tunnel_app(&mut self, tx: ResponseTx<(), Error>, app: impl Into<SplitApp>) {
+    fn on_remove_split_tunnel_app(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        app: impl Into<SplitApp>,
+        source: AuditSource,
+    ) {
         let settings = self.settings.to_settings();
 
         let excluded_apps = {
@@ -2198,11 +2593,12 @@ impl Daemon {
             "remove_split_tunnel_app response",
             settings,
             ExcludedPathsUpdate::SetPaths(excluded_apps),
+            source,
         );
     }
 
     #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-    fn on_clear_split_tunnel_apps(&mut self, tx: ResponseTx<(), Error>) {
+    fn on_clear_split_tunnel_apps(&mut self, tx: ResponseTx<(), Error>, source: AuditSource) {
         let settings = self.settings.to_settings();
         let new_list = HashSet::new();
         self.set_split_tunnel_paths(
@@ -2210,17 +2606,24 @@ impl Daemon {
             "clear_split_tunnel_apps response",
             settings,
             ExcludedPathsUpdate::SetPaths(new_list),
+            source,
         );
     }
 
     #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-    fn on_set_split_tunnel_state(&mut self, tx: ResponseTx<(), Error>, state: bool) {
+    fn on_set_split_tunnel_state(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        state: bool,
+        source: AuditSource,
+    ) {
         let settings = self.settings.to_settings();
         self.set_split_tunnel_paths(
             tx,
             "set_split_tunnel_state response",
             settings,
             ExcludedPathsUpdate::SetState(state),
+            source,
         );
     }
 
@@ -2249,10 +2652,11 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         update: RelaySettings,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.set_relay_settings(update))
+            .update(source, move |settings| settings.set_relay_settings(update))
             .await
         {
             Ok(settings_changed) => {
@@ -2269,10 +2673,57 @@ impl Daemon {
         }
     }
 
-    async fn on_set_allow_lan(&mut self, tx: ResponseTx<(), settings::Error>, allow_lan: bool) {
+    async fn on_set_relay_location(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        location: LocationConstraint,
+        source: AuditSource,
+    ) {
+        // Commands are handled one at a time, so the relay settings can't change before the update
+        if !matches!(self.settings.relay_settings, RelaySettings::Normal(_)) {
+            Self::oneshot_send(
+                tx,
+                Err(Error::CustomRelayInUse),
+                "set_relay_location response",
+            );
+            return;
+        }
+        match self
+            .settings
+            .update(source, move |settings| {
+                if let RelaySettings::Normal(constraints) = &mut settings.relay_settings {
+                    constraints.location = Constraint::Only(location);
+                }
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_relay_location response");
+                if settings_changed {
+                    log::info!("Initiating tunnel restart because the relay location changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_relay_location response",
+                );
+            }
+        }
+    }
+
+    async fn on_set_allow_lan(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        allow_lan: bool,
+        source: AuditSource,
+    ) {
         match self
             .settings
-            .update(move |settings| settings.allow_lan = allow_lan)
+            .update(source, move |settings| settings.allow_lan = allow_lan)
             .await
         {
             Ok(settings_changed) => {
@@ -2298,10 +2749,13 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         enabled: bool,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.show_beta_releases = enabled)
+            .update(source, move |settings| {
+                settings.show_beta_releases = enabled
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2323,10 +2777,13 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         block_when_disconnected: bool,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.block_when_disconnected = block_when_disconnected)
+            .update(source, move |settings| {
+                settings.block_when_disconnected = block_when_disconnected
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2352,10 +2809,11 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         auto_connect: bool,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.auto_connect = auto_connect)
+            .update(source, move |settings| settings.auto_connect = auto_connect)
             .await
         {
             Ok(_settings_changed) => {
@@ -2372,10 +2830,13 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         mssfix: Option<u16>,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.tunnel_options.openvpn.mssfix = mssfix)
+            .update(source, move |settings| {
+                settings.tunnel_options.openvpn.mssfix = mssfix
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2398,6 +2859,7 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), Error>,
         new_settings: BridgeSettings,
+        source: AuditSource,
     ) {
         if new_settings.custom.is_none() && new_settings.bridge_type == BridgeType::Custom {
             log::info!("Tried to select custom bridge but no custom bridge settings exist");
@@ -2408,10 +2870,36 @@ impl Daemon {
             );
             return;
         }
+        if let Some(hostname) = new_settings
+            .custom
+            .as_ref()
+            .and_then(|proxy| proxy.hostname())
+        {
+            log::info!("Tried to use proxy with hostname {hostname} as custom bridge");
+            Self::oneshot_send(
+                tx,
+                Err(Error::CustomBridgeHostname),
+                "set_bridge_settings response",
+            );
+            return;
+        }
+        if let Some(CustomProxy::HttpConnect(HttpConnect { tls: Some(_), .. })) =
+            &new_settings.custom
+        {
+            log::info!("Tried to use HTTP proxy with TLS as custom bridge");
+            Self::oneshot_send(
+                tx,
+                Err(Error::CustomBridgeHttpTls),
+                "set_bridge_settings response",
+            );
+            return;
+        }
 
         match self
             .settings
-            .update(move |settings| settings.bridge_settings = new_settings)
+            .update(source, move |settings| {
+                settings.bridge_settings = new_settings
+            })
             .await
         {
             Ok(settings_changes) => {
@@ -2441,10 +2929,13 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         new_settings: ObfuscationSettings,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.obfuscation_settings = new_settings)
+            .update(source, move |settings| {
+                settings.obfuscation_settings = new_settings
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2467,10 +2958,11 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         bridge_state: BridgeState,
+        source: AuditSource,
     ) {
         let result = match self
             .settings
-            .update(move |settings| settings.bridge_state = bridge_state)
+            .update(source, move |settings| settings.bridge_state = bridge_state)
             .await
         {
             Ok(settings_changed) => {
@@ -2491,10 +2983,17 @@ impl Daemon {
         Self::oneshot_send(tx, result, "on_set_bridge_state response");
     }
 
-    async fn on_set_enable_ipv6(&mut self, tx: ResponseTx<(), settings::Error>, enable_ipv6: bool) {
+    async fn on_set_enable_ipv6(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        enable_ipv6: bool,
+        source: AuditSource,
+    ) {
         match self
             .settings
-            .update(|settings| settings.tunnel_options.generic.enable_ipv6 = enable_ipv6)
+            .update(source, |settings| {
+                settings.tunnel_options.generic.enable_ipv6 = enable_ipv6
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2515,10 +3014,11 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         quantum_resistant: QuantumResistantState,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(|settings| {
+            .update(source, |settings| {
                 settings.tunnel_options.wireguard.quantum_resistant = quantum_resistant
             })
             .await
@@ -2538,11 +3038,128 @@ impl Daemon {
         }
     }
 
+    async fn on_set_quantum_resistant_rekey_interval(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        interval: Option<RekeyInterval>,
+        source: AuditSource,
+    ) {
+        match self
+            .settings
+            .update(source, move |settings| {
+                settings
+                    .tunnel_options
+                    .wireguard
+                    .quantum_resistant_rekey_interval = interval
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_quantum_resistant_rekey_interval response");
+                if settings_changed && self.get_target_tunnel_type() == Some(TunnelType::Wireguard)
+                {
+                    log::info!("Reconnecting because the PQ re-key interval changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_quantum_resistant_rekey_interval response");
+            }
+        }
+    }
+
+    async fn on_set_connectivity_check_options(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        options: talpid_types::net::wireguard::ConnectivityCheckOptions,
+        source: AuditSource,
+    ) {
+        match self
+            .settings
+            .update(source, move |settings| {
+                settings.tunnel_options.wireguard.connectivity = options
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_connectivity_check_options response");
+                if settings_changed && self.get_target_tunnel_type() == Some(TunnelType::Wireguard)
+                {
+                    log::info!("Reconnecting because the connectivity monitor parameters changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_connectivity_check_options response");
+            }
+        }
+    }
+
+    async fn on_set_wireguard_backend(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        backend: talpid_types::net::wireguard::WireguardBackend,
+        source: AuditSource,
+    ) {
+        match self
+            .settings
+            .update(source, move |settings| {
+                settings.tunnel_options.wireguard.backend = backend
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_wireguard_backend response");
+                if settings_changed && self.get_target_tunnel_type() == Some(TunnelType::Wireguard)
+                {
+                    log::info!("Reconnecting because the WireGuard backend changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_wireguard_backend response");
+            }
+        }
+    }
+
+    async fn on_set_wireguard_make_before_break(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        enabled: bool,
+        source: AuditSource,
+    ) {
+        match self
+            .settings
+            .update(source, move |settings| {
+                settings.tunnel_options.wireguard.make_before_break = enabled
+            })
+            .await
+        {
+            Ok(_) => {
+                // The setting only affects how the next relay change is performed, so there is
+                // no need to reconnect.
+                Self::oneshot_send(tx, Ok(()), "set_wireguard_make_before_break response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_wireguard_make_before_break response");
+            }
+        }
+    }
+
     #[cfg(daita)]
-    async fn on_set_daita_enabled(&mut self, tx: ResponseTx<(), settings::Error>, value: bool) {
+    async fn on_set_daita_enabled(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        value: bool,
+        source: AuditSource,
+    ) {
         let result = self
             .settings
-            .update(|settings| {
+            .update(source, |settings| {
                 settings.tunnel_options.wireguard.daita.enabled = value;
             })
             .await;
@@ -2573,10 +3190,11 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         value: bool,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(|settings| {
+            .update(source, |settings| {
                 settings
                     .tunnel_options
                     .wireguard
@@ -2608,15 +3226,55 @@ impl Daemon {
         }
     }
 
+    #[cfg(daita)]
+    async fn on_set_daita_level(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        level: talpid_types::net::wireguard::DaitaLevel,
+        source: AuditSource,
+    ) {
+        match self
+            .settings
+            .update(source, |settings| {
+                settings.tunnel_options.wireguard.daita.level = level
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_daita_level response");
+
+                let RelaySettings::Normal(constraints) = &self.settings.relay_settings else {
+                    return; // DAITA is not supported for custom relays
+                };
+
+                let wireguard_enabled = constraints.tunnel_protocol == TunnelType::Wireguard;
+
+                let daita_enabled = self.settings.tunnel_options.wireguard.daita.enabled;
+
+                if settings_changed && wireguard_enabled && daita_enabled {
+                    log::info!("Reconnecting because DAITA settings changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_daita_level response");
+            }
+        }
+    }
+
     #[cfg(daita)]
     async fn on_set_daita_settings(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         daita_settings: DaitaSettings,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(|settings| settings.tunnel_options.wireguard.daita = daita_settings)
+            .update(source, |settings| {
+                settings.tunnel_options.wireguard.daita = daita_settings
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2637,10 +3295,13 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         dns_options: DnsOptions,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.tunnel_options.dns_options = dns_options)
+            .update(source, move |settings| {
+                settings.tunnel_options.dns_options = dns_options
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2669,10 +3330,13 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         relay_override: RelayOverride,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.set_relay_override(relay_override))
+            .update(source, move |settings| {
+                settings.set_relay_override(relay_override)
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2688,10 +3352,14 @@ impl Daemon {
         }
     }
 
-    async fn on_clear_all_relay_overrides(&mut self, tx: ResponseTx<(), settings::Error>) {
+    async fn on_clear_all_relay_overrides(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        source: AuditSource,
+    ) {
         match self
             .settings
-            .update(move |settings| settings.relay_overrides.clear())
+            .update(source, move |settings| settings.relay_overrides.clear())
             .await
         {
             Ok(settings_changed) => {
@@ -2711,10 +3379,13 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         mtu: Option<u16>,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.tunnel_options.wireguard.mtu = mtu)
+            .update(source, move |settings| {
+                settings.tunnel_options.wireguard.mtu = mtu
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2739,10 +3410,13 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::Error>,
         interval: Option<RotationInterval>,
+        source: AuditSource,
     ) {
         match self
             .settings
-            .update(move |settings| settings.tunnel_options.wireguard.rotation_interval = interval)
+            .update(source, move |settings| {
+                settings.tunnel_options.wireguard.rotation_interval = interval
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2791,8 +3465,9 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<mullvad_types::custom_list::Id, Error>,
         name: String,
+        source: AuditSource,
     ) {
-        let result = self.create_custom_list(name).await;
+        let result = self.create_custom_list(name, source).await;
         Self::oneshot_send(tx, result, "create_custom_list response");
     }
 
@@ -2800,18 +3475,24 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), Error>,
         id: mullvad_types::custom_list::Id,
+        source: AuditSource,
     ) {
-        let result = self.delete_custom_list(id).await;
+        let result = self.delete_custom_list(id, source).await;
         Self::oneshot_send(tx, result, "delete_custom_list response");
     }
 
-    async fn on_update_custom_list(&mut self, tx: ResponseTx<(), Error>, new_list: CustomList) {
-        let result = self.update_custom_list(new_list).await;
+    async fn on_update_custom_list(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        new_list: CustomList,
+        source: AuditSource,
+    ) {
+        let result = self.update_custom_list(new_list, source).await;
         Self::oneshot_send(tx, result, "update_custom_list response");
     }
 
-    async fn on_clear_custom_lists(&mut self, tx: ResponseTx<(), Error>) {
-        let result = self.clear_custom_lists().await;
+    async fn on_clear_custom_lists(&mut self, tx: ResponseTx<(), Error>, source: AuditSource) {
+        let result = self.clear_custom_lists(source).await;
         Self::oneshot_send(tx, result, "clear_custom_lists response");
     }
 
@@ -2821,9 +3502,10 @@ impl Daemon {
         name: String,
         enabled: bool,
         access_method: AccessMethod,
+        source: AuditSource,
     ) {
         let result = self
-            .add_access_method(name, enabled, access_method)
+            .add_access_method(name, enabled, access_method, source)
             .await
             .map_err(Error::AccessMethodError);
         Self::oneshot_send(tx, result, "add_api_access_method response");
@@ -2833,9 +3515,10 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), Error>,
         api_access_method: mullvad_types::access_method::Id,
+        source: AuditSource,
     ) {
         let result = self
-            .remove_access_method(api_access_method)
+            .remove_access_method(api_access_method, source)
             .await
             .map_err(Error::AccessMethodError);
         Self::oneshot_send(tx, result, "remove_api_access_method response");
@@ -2845,29 +3528,35 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), Error>,
         access_method: mullvad_types::access_method::Id,
+        source: AuditSource,
     ) {
-        let result = self
-            .use_api_access_method(access_method)
-            .await
-            .map_err(Error::AccessMethodError);
-        Self::oneshot_send(tx, result, "set_api_access_method response");
+        self.use_api_access_method(access_method, source, |result| {
+            let result = result.map_err(Error::AccessMethodError);
+            Self::oneshot_send(tx, result, "set_api_access_method response")
+        })
+        .await;
     }
 
     async fn on_update_api_access_method(
         &mut self,
         tx: ResponseTx<(), Error>,
         method: AccessMethodSetting,
+        source: AuditSource,
     ) {
         let result = self
-            .update_access_method(method)
+            .update_access_method(method, source)
             .await
             .map_err(Error::AccessMethodError);
         Self::oneshot_send(tx, result, "update_api_access_method response");
     }
 
-    async fn on_clear_custom_api_access_methods(&mut self, tx: ResponseTx<(), Error>) {
+    async fn on_clear_custom_api_access_methods(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        source: AuditSource,
+    ) {
         let result = self
-            .clear_custom_api_access_methods()
+            .clear_custom_api_access_methods(source)
             .await
             .map_err(Error::AccessMethodError);
         Self::oneshot_send(tx, result, "clear_custom_api_access_methods response");
@@ -2885,38 +3574,32 @@ impl Daemon {
         });
     }
 
+    fn on_get_current_api_endpoint(&mut self, tx: ResponseTx<talpid_types::net::Endpoint, Error>) {
+        let handle = self.access_mode_handler.clone();
+        tokio::spawn(async move {
+            let result = handle
+                .get_current()
+                .await
+                .map(|current| current.endpoint.endpoint)
+                .map_err(Error::ApiConnectionModeError);
+            Self::oneshot_send(tx, result, "get_current_api_endpoint response");
+        });
+    }
+
     fn on_test_proxy_as_access_method(
         &mut self,
         tx: ResponseTx<bool, Error>,
         proxy: talpid_types::net::proxy::CustomProxy,
     ) {
-        use mullvad_api::proxy::{ApiConnectionMode, ProxyConfig};
-        use talpid_types::net::AllowedEndpoint;
-
-        let connection_mode = ApiConnectionMode::Proxied(ProxyConfig::from(proxy.clone()));
-        let api_proxy = self.create_limited_api_proxy(connection_mode.clone());
-        let proxy_endpoint = AllowedEndpoint {
-            endpoint: proxy.get_remote_endpoint().endpoint,
-            clients: api::allowed_clients(&connection_mode),
-        };
-
-        let daemon_event_sender = self.tx.to_specialized_sender();
-        let access_method_selector = self.access_mode_handler.clone();
-        tokio::spawn(async move {
-            let result = Self::test_access_method(
-                proxy_endpoint,
-                access_method_selector,
-                daemon_event_sender,
-                api_proxy,
-            )
-            .await
-            .map_err(Error::AccessMethodError);
-
-            Self::oneshot_send(tx, result, "on_test_proxy_as_access_method response");
+        // Resolve the proxy like a saved access method, since it may have a hostname to look up
+        let access_method =
+            AccessMethodSetting::new("Custom proxy".to_owned(), true, AccessMethod::from(proxy));
+        self.test_api_access_method(access_method, |response| {
+            Self::oneshot_send(tx, response, "on_test_proxy_as_access_method response")
         });
     }
 
-    async fn on_test_api_access_method(
+    fn on_test_api_access_method(
         &mut self,
         tx: ResponseTx<bool, Error>,
         access_method: mullvad_types::access_method::Id,
@@ -2924,39 +3607,47 @@ impl Daemon {
         let reply =
             |response| Self::oneshot_send(tx, response, "on_test_api_access_method response");
 
-        let access_method = match self.get_api_access_method(access_method) {
-            Ok(x) => x,
-            Err(err) => {
-                reply(Err(Error::AccessMethodError(err)));
-                return;
-            }
-        };
-
-        let test_subject = match self
-            .access_mode_handler
-            .resolve(access_method.clone())
-            .await
-        {
-            Ok(Some(test_subject)) => test_subject,
-            Ok(None) => {
-                let error =
-                    Error::ApiConnectionModeError(mullvad_api::access_mode::Error::Resolve {
-                        access_method: access_method.access_method,
-                    });
-                reply(Err(error));
-                return;
-            }
-            Err(err) => {
-                reply(Err(Error::ApiConnectionModeError(err)));
-                return;
-            }
-        };
+        match self.get_api_access_method(access_method) {
+            Ok(access_method) => self.test_api_access_method(access_method, reply),
+            Err(err) => reply(Err(Error::AccessMethodError(err))),
+        }
+    }
 
-        let api_proxy = self.create_limited_api_proxy(test_subject.connection_mode);
+    /// Resolve and test `access_method` in the background. Resolving an access method may require
+    /// the daemon to let DNS resolvers through the firewall, so it must not block the daemon.
+    fn test_api_access_method(
+        &mut self,
+        access_method: AccessMethodSetting,
+        reply: impl FnOnce(Result<bool, Error>) + Send + 'static,
+    ) {
+        let api_runtime = self.api_runtime.clone();
         let daemon_event_sender = self.tx.to_specialized_sender();
         let access_method_selector = self.access_mode_handler.clone();
 
         tokio::spawn(async move {
+            let test_subject = match access_method_selector.resolve(access_method.clone()).await {
+                Ok(Some(test_subject)) => test_subject,
+                Ok(None) => {
+                    // Resolving a proxy hostname lets DNS resolvers through the firewall in place
+                    // of the API endpoint
+                    #[cfg(not(target_os = "android"))]
+                    Self::allow_current_api_endpoint(&access_method_selector, &daemon_event_sender)
+                        .await;
+                    let error =
+                        Error::ApiConnectionModeError(mullvad_api::access_mode::Error::Resolve {
+                            access_method: access_method.access_method,
+                        });
+                    reply(Err(error));
+                    return;
+                }
+                Err(err) => {
+                    reply(Err(Error::ApiConnectionModeError(err)));
+                    return;
+                }
+            };
+
+            let api_proxy =
+                Self::create_limited_api_proxy(&api_runtime, test_subject.connection_mode);
             let result = Self::test_access_method(
                 test_subject.endpoint,
                 access_method_selector,
@@ -2979,12 +3670,36 @@ impl Daemon {
         });
     }
 
+    /// Let the endpoint of the current API access method through the firewall again.
+    #[cfg(not(target_os = "android"))]
+    async fn allow_current_api_endpoint(
+        access_mode_handler: &mullvad_api::access_mode::AccessModeSelectorHandle,
+        daemon_event_sender: &DaemonEventSender<(AccessMethodEvent, oneshot::Sender<()>)>,
+    ) {
+        let result = async {
+            let endpoint = access_mode_handler.get_current().await?.endpoint;
+            AccessMethodEvent::Allow { endpoint }
+                .send(daemon_event_sender.to_unbounded_sender())
+                .await
+        };
+        if let Err(error) = result.await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to allow the current API endpoint")
+            );
+        }
+    }
+
     fn on_get_settings(&self, tx: oneshot::Sender<Settings>) {
         Self::oneshot_send(tx, self.settings.to_settings(), "get_settings response");
     }
 
-    async fn on_reset_settings(&mut self, tx: ResponseTx<(), settings::Error>) {
-        let result = self.settings.reset().await;
+    async fn on_reset_settings(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        source: AuditSource,
+    ) {
+        let result = self.settings.reset(source).await;
         Self::oneshot_send(tx, result, "reset_settings response");
 
         // TODO: All of the functions below should probably be handled by settings observers
@@ -3129,8 +3844,9 @@ impl Daemon {
         &mut self,
         tx: ResponseTx<(), settings::patch::Error>,
         blob: String,
+        source: AuditSource,
     ) {
-        let result = settings::patch::merge_validate_patch(&mut self.settings, &blob).await;
+        let result = settings::patch::merge_validate_patch(&mut self.settings, &blob, source).await;
         if result.is_ok() {
             self.reconnect_tunnel();
         }
@@ -3205,6 +3921,27 @@ impl Daemon {
         Self::oneshot_send(tx, (), "on_toggle_relay response");
     }
 
+    /// Start streaming packets blocked by the firewall. Logging is enabled in the firewall for as
+    /// long as there are listeners.
+    #[cfg(target_os = "linux")]
+    fn on_firewall_log_listen(
+        &self,
+        tx: ResponseTx<
+            tokio::sync::mpsc::UnboundedReceiver<talpid_types::net::firewall_log::BlockedPacket>,
+            firewall_log::Error,
+        >,
+    ) {
+        self.firewall_log.listen(tx);
+    }
+
+    fn on_get_settings_audit_log(&self, tx: ResponseTx<Vec<AuditEntry>, settings::audit::Error>) {
+        let audit_log = self.settings.audit_log().clone();
+        tokio::spawn(async move {
+            let result = audit_log.read().await;
+            Self::oneshot_send(tx, result, "get_settings_audit_log response");
+        });
+    }
+
     /// Set the target state of the client. If it changed trigger the operations needed to
     /// progress towards that state.
     /// Returns a bool representing whether a state change was initiated.
@@ -70,7 +70,7 @@ async fn run() -> Result<(), String> {
         cli::Command::InitializeEarlyBootFirewall => {
             init_early_boot_logging(config);
 
-            crate::early_boot_firewall::initialize_firewall()
+            crate::early_boot_firewall::initialize_firewall(config.linux_ids)
                 .await
                 .map_err(|err| format!("{err}"))
         }
@@ -220,6 +220,11 @@ async fn create_daemon(log_dir: Option<PathBuf>) -> Result<Daemon, String> {
             cache_dir,
             rpc_socket_path,
             endpoint: mullvad_api::ApiEndpoint::from_env_vars(),
+            #[cfg(target_os = "linux")]
+            linux_ids: cli::get_config().linux_ids,
+            #[cfg(target_os = "linux")]
+            dbus_bus: cli::get_config().dbus_bus,
+            metrics_address: cli::get_config().metrics_address.clone(),
         },
         DaemonCommandChannel::new(),
     )
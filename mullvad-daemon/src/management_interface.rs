@@ -10,6 +10,8 @@ use mullvad_management_interface::{
 };
 use mullvad_types::{
     account::AccountNumber,
+    data_usage::{DataUsageQuota, QuotaExceeded},
+    leak::LeakInfo,
     relay_constraints::{
         BridgeSettings, BridgeState, ObfuscationSettings, RelayOverride, RelaySettings,
     },
@@ -17,7 +19,7 @@ use mullvad_types::{
     settings::{DnsOptions, Settings},
     states::{TargetState, TunnelState},
     version,
-    wireguard::{RotationInterval, RotationIntervalError},
+    wireguard::{RekeyInterval, RekeyIntervalError, RotationInterval, RotationIntervalError},
 };
 use std::{
     path::Path,
@@ -25,11 +27,20 @@ use std::{
     sync::{Arc, Mutex},
     time::Duration,
 };
-use talpid_types::ErrorExt;
+use talpid_types::{
+    net::{
+        firewall_log::BlockedPacket,
+        stats::TunnelStats,
+        wireguard::{ConnectivityCheckOptions, WireguardBackend},
+    },
+    ErrorExt,
+};
 use tokio::time::timeout;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 const RPC_SERVER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+/// Shortest interval at which tunnel stats may be sampled by a listener.
+const MIN_TUNNEL_STATS_INTERVAL: Duration = Duration::from_millis(100);
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -40,12 +51,23 @@ pub enum Error {
 
 struct ManagementServiceImpl {
     daemon_tx: DaemonCommandSender,
-    subscriptions: Arc<Mutex<Vec<EventsListenerSender>>>,
+    subscriptions: Arc<Mutex<Vec<EventsListener>>>,
+}
+
+/// Subscriber to daemon events.
+struct EventsListener {
+    tx: EventsListenerSender,
+    /// Whether the subscriber may see secrets, such as passwords and private keys.
+    is_admin: bool,
 }
 
 pub type ServiceResult<T> = std::result::Result<Response<T>, Status>;
 type EventsListenerReceiver = UnboundedReceiverStream<Result<types::DaemonEvent, Status>>;
 type EventsListenerSender = tokio::sync::mpsc::UnboundedSender<Result<types::DaemonEvent, Status>>;
+type FirewallLogReceiver = futures::stream::Map<
+    UnboundedReceiverStream<BlockedPacket>,
+    fn(BlockedPacket) -> Result<types::BlockedPacket, Status>,
+>;
 
 const INVALID_VOUCHER_MESSAGE: &str = "This voucher code is invalid";
 const USED_VOUCHER_MESSAGE: &str = "This voucher code has already been used";
@@ -54,6 +76,8 @@ const USED_VOUCHER_MESSAGE: &str = "This voucher code has already been used";
 impl ManagementService for ManagementServiceImpl {
     type GetSplitTunnelProcessesStream = UnboundedReceiverStream<Result<i32, Status>>;
     type EventsListenStream = EventsListenerReceiver;
+    type FirewallLogListenStream = FirewallLogReceiver;
+    type TunnelStatsListenStream = UnboundedReceiverStream<Result<types::TunnelStats, Status>>;
 
     // Control and get the tunnel state
     //
@@ -92,14 +116,132 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(types::TunnelState::from(state)))
     }
 
+    async fn get_tunnel_stats(&self, _: Request<()>) -> ServiceResult<types::TunnelStats> {
+        log::debug!("get_tunnel_stats");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetTunnelStats(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(|stats| Response::new(types::TunnelStats::from(stats)))
+            .ok_or_else(|| Status::not_found("Tunnel statistics are unavailable"))
+    }
+
+    async fn get_wireguard_config(&self, request: Request<bool>) -> ServiceResult<String> {
+        let is_admin = mullvad_management_interface::is_admin(&request);
+        let include_secrets = request.into_inner();
+        log::debug!("get_wireguard_config({include_secrets})");
+        if include_secrets && !is_admin {
+            return Err(Status::permission_denied(
+                "Only root and members of the admin group may export secrets",
+            ));
+        }
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetWireguardConfig(tx, include_secrets))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .ok_or_else(|| Status::not_found("There is no WireGuard tunnel"))
+    }
+
+    async fn tunnel_stats_listen(
+        &self,
+        request: Request<types::Duration>,
+    ) -> ServiceResult<Self::TunnelStatsListenStream> {
+        log::debug!("tunnel_stats_listen");
+        let interval = Duration::try_from(request.into_inner())
+            .map_err(|_| Status::invalid_argument("Invalid interval"))?;
+        if interval < MIN_TUNNEL_STATS_INTERVAL {
+            return Err(Status::invalid_argument(format!(
+                "Interval must be at least {} ms",
+                MIN_TUNNEL_STATS_INTERVAL.as_millis()
+            )));
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let daemon_tx = self.daemon_tx.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut previous: Option<TunnelStats> = None;
+
+            while !tx.is_closed() {
+                interval.tick().await;
+
+                let (stats_tx, stats_rx) = oneshot::channel();
+                if daemon_tx
+                    .send(DaemonCommand::GetTunnelStats(stats_tx))
+                    .is_err()
+                {
+                    break;
+                }
+                let Ok(Some(mut stats)) = stats_rx.await else {
+                    previous = None;
+                    continue;
+                };
+                if let Some(previous) = &previous {
+                    stats.compute_rates(previous);
+                }
+                previous = Some(stats.clone());
+
+                if tx.send(Ok(types::TunnelStats::from(stats))).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+
+    async fn get_data_usage(&self, _: Request<()>) -> ServiceResult<types::DataUsage> {
+        log::debug!("get_data_usage");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetDataUsage(tx))?;
+        let entries = self.wait_for_result(rx).await?;
+        Ok(Response::new(types::DataUsage {
+            entries: entries
+                .into_iter()
+                .map(types::data_usage::Entry::from)
+                .collect(),
+        }))
+    }
+
+    async fn get_session_history(&self, _: Request<()>) -> ServiceResult<types::SessionHistory> {
+        log::debug!("get_session_history");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetSessionHistory(tx))?;
+        let sessions = self.wait_for_result(rx).await?;
+        Ok(Response::new(types::SessionHistory {
+            sessions: sessions
+                .into_iter()
+                .map(types::session_history::Session::from)
+                .collect(),
+        }))
+    }
+
+    async fn set_data_usage_quota(
+        &self,
+        request: Request<types::DataUsageQuota>,
+    ) -> ServiceResult<()> {
+        let quota =
+            DataUsageQuota::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
+        log::debug!("set_data_usage_quota({:?})", quota);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetDataUsageQuota(tx, quota))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     // Control the daemon and receive events
     //
 
-    async fn events_listen(&self, _: Request<()>) -> ServiceResult<Self::EventsListenStream> {
+    async fn events_listen(&self, request: Request<()>) -> ServiceResult<Self::EventsListenStream> {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 
         let mut subscriptions = self.subscriptions.lock().unwrap();
-        subscriptions.push(tx);
+        subscriptions.push(EventsListener {
+            tx,
+            is_admin: mullvad_management_interface::is_admin(&request),
+        });
 
         Ok(Response::new(UnboundedReceiverStream::new(rx)))
     }
@@ -238,13 +380,16 @@ impl ManagementService for ManagementServiceImpl {
     // Settings
     //
 
-    async fn get_settings(&self, _: Request<()>) -> ServiceResult<types::Settings> {
+    async fn get_settings(&self, request: Request<()>) -> ServiceResult<types::Settings> {
         log::debug!("get_settings");
+        let is_admin = mullvad_management_interface::is_admin(&request);
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetSettings(tx))?;
-        self.wait_for_result(rx)
-            .await
-            .map(|settings| Response::new(types::Settings::from(&settings)))
+        let mut settings = self.wait_for_result(rx).await?;
+        if !is_admin {
+            settings.redact_secrets();
+        }
+        Ok(Response::new(types::Settings::from(&settings)))
     }
 
     async fn reset_settings(&self, _: Request<()>) -> ServiceResult<()> {
@@ -351,6 +496,73 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn set_quantum_resistant_rekey_interval(
+        &self,
+        request: Request<types::Duration>,
+    ) -> ServiceResult<()> {
+        let interval: RekeyInterval = Duration::try_from(request.into_inner())
+            .map_err(|_| Status::invalid_argument("unexpected negative re-key interval"))?
+            .try_into()
+            .map_err(|error: RekeyIntervalError| Status::invalid_argument(error.display_chain()))?;
+
+        log::debug!("set_quantum_resistant_rekey_interval({:?})", interval);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetQuantumResistantRekeyInterval(
+            tx,
+            Some(interval),
+        ))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn reset_quantum_resistant_rekey_interval(&self, _: Request<()>) -> ServiceResult<()> {
+        log::debug!("reset_quantum_resistant_rekey_interval");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetQuantumResistantRekeyInterval(tx, None))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_connectivity_check_options(
+        &self,
+        request: Request<types::ConnectivityCheckOptions>,
+    ) -> ServiceResult<()> {
+        let options = ConnectivityCheckOptions::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+        if !options.is_valid() {
+            return Err(Status::invalid_argument(
+                "Connectivity monitor parameters are out of bounds",
+            ));
+        }
+        log::debug!("set_connectivity_check_options({:?})", options);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetConnectivityCheckOptions(tx, options))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_wireguard_backend(
+        &self,
+        request: Request<types::WireguardBackend>,
+    ) -> ServiceResult<()> {
+        let backend =
+            WireguardBackend::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
+        log::debug!("set_wireguard_backend({backend})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetWireguardBackend(tx, backend))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_wireguard_make_before_break(&self, request: Request<bool>) -> ServiceResult<()> {
+        let enabled = request.into_inner();
+        log::debug!("set_wireguard_make_before_break({enabled})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetWireguardMakeBeforeBreak(tx, enabled))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     #[cfg(daita)]
     async fn set_enable_daita(&self, request: Request<bool>) -> ServiceResult<()> {
         let daita_enabled = request.into_inner();
@@ -374,12 +586,26 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    #[cfg(daita)]
+    async fn set_daita_level(&self, request: Request<u32>) -> ServiceResult<()> {
+        let level = u8::try_from(request.into_inner())
+            .ok()
+            .and_then(talpid_types::net::wireguard::DaitaLevel::new)
+            .ok_or_else(|| Status::invalid_argument("invalid DAITA level"))?;
+        log::debug!("set_daita_level({level})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetDaitaLevel(tx, level))?;
+        self.wait_for_result(rx).await?.map(Response::new)?;
+        Ok(Response::new(()))
+    }
+
     #[cfg(daita)]
     async fn set_daita_settings(
         &self,
         request: Request<types::DaitaSettings>,
     ) -> ServiceResult<()> {
-        let state = mullvad_types::wireguard::DaitaSettings::from(request.into_inner());
+        let state = mullvad_types::wireguard::DaitaSettings::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
 
         log::debug!("set_daita_settings({state:?})");
         let (tx, rx) = oneshot::channel();
@@ -398,6 +624,11 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    #[cfg(not(daita))]
+    async fn set_daita_level(&self, _: Request<u32>) -> ServiceResult<()> {
+        Ok(Response::new(()))
+    }
+
     #[cfg(not(daita))]
     async fn set_daita_settings(&self, _: Request<types::DaitaSettings>) -> ServiceResult<()> {
         Ok(Response::new(()))
@@ -759,14 +990,30 @@ impl ManagementService for ManagementServiceImpl {
     /// connect to the Mullvad API.
     async fn get_current_api_access_method(
         &self,
-        _: Request<()>,
+        request: Request<()>,
     ) -> ServiceResult<types::AccessMethodSetting> {
         log::debug!("get_current_api_access_method");
+        let is_admin = mullvad_management_interface::is_admin(&request);
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetCurrentAccessMethod(tx))?;
+        let mut access_method = self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
+        if !is_admin {
+            access_method.redact_secrets();
+        }
+        Ok(Response::new(types::AccessMethodSetting::from(
+            access_method,
+        )))
+    }
+
+    /// Return the endpoint which the current access method resolved to, i.e. the address that the
+    /// daemon connects to in order to reach the Mullvad API.
+    async fn get_current_api_endpoint(&self, _: Request<()>) -> ServiceResult<types::Endpoint> {
+        log::debug!("get_current_api_endpoint");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetCurrentApiEndpoint(tx))?;
         self.wait_for_result(rx)
             .await?
-            .map(types::AccessMethodSetting::from)
+            .map(types::Endpoint::from)
             .map(Response::new)
             .map_err(map_daemon_error)
     }
@@ -1115,6 +1362,49 @@ impl ManagementService for ManagementServiceImpl {
         self.wait_for_result(rx).await?;
         Ok(Response::new(()))
     }
+
+    async fn firewall_log_listen(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<Self::FirewallLogListenStream> {
+        #[cfg(target_os = "linux")]
+        {
+            log::debug!("firewall_log_listen");
+            let (tx, rx) = oneshot::channel();
+            self.send_command_to_daemon(DaemonCommand::FirewallLogListen(tx))?;
+            let packets = self
+                .wait_for_result(rx)
+                .await?
+                .map_err(|error| Status::failed_precondition(error.display_chain()))?;
+            let to_proto: fn(BlockedPacket) -> Result<types::BlockedPacket, Status> =
+                |packet| Ok(types::BlockedPacket::from(packet));
+            Ok(Response::new(
+                UnboundedReceiverStream::new(packets).map(to_proto),
+            ))
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err(Status::unimplemented(
+                "Logging blocked packets is only supported on Linux",
+            ))
+        }
+    }
+
+    async fn get_settings_audit_log(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::SettingsAuditLog> {
+        log::debug!("get_settings_audit_log");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetSettingsAuditLog(tx))?;
+        let entries = self
+            .wait_for_result(rx)
+            .await?
+            .map_err(|error| Status::internal(error.display_chain()))?;
+        Ok(Response::new(types::SettingsAuditLog {
+            entries: entries.into_iter().map(Into::into).collect(),
+        }))
+    }
 }
 
 impl ManagementServiceImpl {
@@ -1148,7 +1438,7 @@ impl ManagementInterfaceServer {
         daemon_tx: DaemonCommandSender,
         rpc_socket_path: impl AsRef<Path>,
     ) -> Result<ManagementInterfaceServer, Error> {
-        let subscriptions = Arc::<Mutex<Vec<EventsListenerSender>>>::default();
+        let subscriptions = Arc::<Mutex<Vec<EventsListener>>>::default();
         // NOTE: It is important that the channel buffer size is kept at 0. When sending a signal
         // to abort the gRPC server, the sender can be awaited to know when the gRPC server has
         // received and started processing the shutdown signal.
@@ -1210,13 +1500,25 @@ impl ManagementInterfaceServer {
 /// A handle that allows broadcasting messages to all subscribers of the management interface.
 #[derive(Clone)]
 pub struct ManagementInterfaceEventBroadcaster {
-    subscriptions: Arc<Mutex<Vec<EventsListenerSender>>>,
+    subscriptions: Arc<Mutex<Vec<EventsListener>>>,
 }
 
 impl ManagementInterfaceEventBroadcaster {
     fn notify(&self, value: types::DaemonEvent) {
+        self.notify_by_role(value.clone(), value);
+    }
+
+    /// Send `value` to admins, and `redacted_value` to all other subscribers.
+    fn notify_by_role(&self, value: types::DaemonEvent, redacted_value: types::DaemonEvent) {
         let mut subscriptions = self.subscriptions.lock().unwrap();
-        subscriptions.retain(|tx| tx.send(Ok(value.clone())).is_ok());
+        subscriptions.retain(|listener| {
+            let value = if listener.is_admin {
+                &value
+            } else {
+                &redacted_value
+            };
+            listener.tx.send(Ok(value.clone())).is_ok()
+        });
     }
 
     /// Notify that the tunnel state changed.
@@ -1235,11 +1537,14 @@ impl ManagementInterfaceEventBroadcaster {
     /// Sends settings to all `settings` subscribers of the management interface.
     pub(crate) fn notify_settings(&self, settings: Settings) {
         log::debug!("Broadcasting new settings");
-        self.notify(types::DaemonEvent {
+        let mut redacted_settings = settings.clone();
+        redacted_settings.redact_secrets();
+        let event = |settings: &Settings| types::DaemonEvent {
             event: Some(daemon_event::Event::Settings(types::Settings::from(
-                &settings,
+                settings,
             ))),
-        })
+        };
+        self.notify_by_role(event(&settings), event(&redacted_settings))
     }
 
     /// Notify that the relay list changed.
@@ -1294,9 +1599,52 @@ impl ManagementInterfaceEventBroadcaster {
         new_access_method: mullvad_types::access_method::AccessMethodSetting,
     ) {
         log::debug!("Broadcasting access method event");
-        self.notify(types::DaemonEvent {
+        let mut redacted_access_method = new_access_method.clone();
+        redacted_access_method.redact_secrets();
+        let event = |access_method| types::DaemonEvent {
             event: Some(daemon_event::Event::NewAccessMethod(
-                types::AccessMethodSetting::from(new_access_method),
+                types::AccessMethodSetting::from(access_method),
+            )),
+        };
+        self.notify_by_role(event(new_access_method), event(redacted_access_method))
+    }
+
+    /// Notify that the data usage exceeded a limit of the quota.
+    pub(crate) fn notify_data_quota_exceeded(&self, event: QuotaExceeded) {
+        log::debug!("Broadcasting data quota exceeded event");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::QuotaExceeded(
+                types::QuotaExceeded::from(event),
+            )),
+        })
+    }
+
+    /// Notify that the MTU of the active tunnel has changed.
+    pub(crate) fn notify_mtu_changed(&self, mtu: u16) {
+        log::debug!("Broadcasting MTU changed event");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::MtuChanged(types::MtuChanged {
+                mtu: u32::from(mtu),
+            })),
+        })
+    }
+
+    /// Notify that traffic was found to leak outside the tunnel.
+    pub(crate) fn notify_leak_detected(&self, info: LeakInfo) {
+        log::debug!("Broadcasting leak detected event");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::LeakDetected(
+                types::LeakDetected::from(info),
+            )),
+        })
+    }
+
+    /// Notify that the active tunnel stopped or started receiving traffic from the relay.
+    pub(crate) fn notify_connectivity_changed(&self, degraded: bool) {
+        log::debug!("Broadcasting connectivity changed event");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::ConnectivityChanged(
+                types::ConnectivityChanged { degraded },
             )),
         })
     }
@@ -1324,6 +1672,10 @@ fn map_daemon_error(error: crate::Error) -> Status {
             Status::unauthenticated(error.to_string())
         }
         DaemonError::VersionCheckError(error) => map_version_check_error(error),
+        DaemonError::CustomBridgeHostname | DaemonError::CustomBridgeHttpTls => {
+            Status::invalid_argument(error.to_string())
+        }
+        DaemonError::CustomRelayInUse => Status::failed_precondition(error.to_string()),
         error => Status::unknown(error.to_string()),
     }
 }
@@ -1407,3 +1759,105 @@ fn map_protobuf_type_err(err: types::FromProtobufTypeError) -> Status {
         types::FromProtobufTypeError::InvalidArgument(err) => Status::invalid_argument(err),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::access_method::{AccessMethod, AccessMethodSetting};
+    use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Remote, SocksAuth};
+
+    const PRIVATE_KEY: &str = "mPue6Xt0pdz4NRAhfQSp/SLKo7kV7DW+2zvBq0N9iUI=";
+    const PRESHARED_KEY: &str = "FuSj0/+LUiM/0hWUlGxN+oTUd/jvB6XIJ6pOyB4Z4hQ=";
+    const PASSWORD: &str = "hunter2";
+
+    type EventsRx = tokio::sync::mpsc::UnboundedReceiver<Result<types::DaemonEvent, Status>>;
+
+    /// Settings with a custom WireGuard relay, a custom bridge and a custom API access method,
+    /// all of which contain secrets.
+    fn settings_with_secrets() -> Settings {
+        let config = format!(
+            "[Interface]\nPrivateKey = {PRIVATE_KEY}\nAddress = 10.64.0.2/32\n\n\
+             [Peer]\nPublicKey = 7svBwGBefP7KVmH/yes+pZCfO6uSOYeGieYYa1+kZ0E=\n\
+             PresharedKey = {PRESHARED_KEY}\nAllowedIPs = 0.0.0.0/0\nEndpoint = 192.0.2.1:51820\n"
+        );
+        let relay = mullvad_types::wg_quick::parse(&config, Some([10, 64, 0, 1].into()), None)
+            .unwrap()
+            .endpoint;
+
+        let auth = SocksAuth::new("user".to_owned(), PASSWORD.to_owned()).unwrap();
+        let bridge = Socks5Remote::new_with_authentication(([192, 0, 2, 2], 1080), auth);
+        let shadowsocks = Shadowsocks::new(
+            ([192, 0, 2, 3], 443),
+            "aes-256-gcm".to_owned(),
+            PASSWORD.to_owned(),
+        )
+        .unwrap();
+
+        let mut settings = Settings::default();
+        settings.set_relay_settings(RelaySettings::CustomTunnelEndpoint(relay));
+        settings.bridge_settings.custom = Some(CustomProxy::from(bridge));
+        settings.api_access_methods.append(AccessMethodSetting::new(
+            "proxy".to_owned(),
+            true,
+            AccessMethod::Custom(CustomProxy::Shadowsocks(shadowsocks)),
+        ));
+        settings
+    }
+
+    /// Subscribe to events as an admin and as a regular user.
+    fn subscribe(broadcaster: &ManagementInterfaceEventBroadcaster) -> (EventsRx, EventsRx) {
+        let (admin_tx, admin_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (user_tx, user_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut subscriptions = broadcaster.subscriptions.lock().unwrap();
+        subscriptions.push(EventsListener {
+            tx: admin_tx,
+            is_admin: true,
+        });
+        subscriptions.push(EventsListener {
+            tx: user_tx,
+            is_admin: false,
+        });
+        (admin_rx, user_rx)
+    }
+
+    fn received_settings(rx: &mut EventsRx) -> Settings {
+        let event = rx.try_recv().unwrap().unwrap().event;
+        let Some(daemon_event::Event::Settings(settings)) = event else {
+            panic!("Expected a settings event, got {event:?}");
+        };
+        Settings::try_from(settings).expect("redacted settings must still be valid")
+    }
+
+    /// Users that are not admins must not be able to read any secrets from the settings.
+    #[test]
+    fn test_user_cannot_read_secrets() {
+        let broadcaster = ManagementInterfaceEventBroadcaster {
+            subscriptions: Default::default(),
+        };
+        let (mut admin_rx, mut user_rx) = subscribe(&broadcaster);
+        let settings = settings_with_secrets();
+
+        broadcaster.notify_settings(settings.clone());
+
+        let admin_settings = serde_json::to_string(&received_settings(&mut admin_rx)).unwrap();
+        let user_settings = received_settings(&mut user_rx);
+        let serialized = serde_json::to_string(&user_settings).unwrap();
+        for secret in [PRIVATE_KEY, PRESHARED_KEY, PASSWORD] {
+            assert!(
+                admin_settings.contains(secret),
+                "{secret} was withheld from admin"
+            );
+            assert!(!serialized.contains(secret), "{secret} was not redacted");
+        }
+        // Everything but the secrets is still visible
+        assert_eq!(user_settings.bridge_settings.custom, {
+            let mut bridge = settings.bridge_settings.custom.clone();
+            bridge.as_mut().unwrap().redact_secrets();
+            bridge
+        });
+        assert_eq!(
+            user_settings.api_access_methods.cardinality(),
+            settings.api_access_methods.cardinality()
+        );
+    }
+}
@@ -0,0 +1,576 @@
+//! Opt-in endpoint exporting tunnel health metrics in the Prometheus text format.
+//!
+//! The endpoint listens on a loopback TCP address or a Unix socket, as given to the daemon on the
+//! command line, and serves the metrics at `/metrics`. It is never enabled through the settings.
+
+use futures::channel::{mpsc as futures_mpsc, oneshot};
+use mullvad_api::rest::{RequestCounts, RequestStats};
+use mullvad_relay_selector::RelaySelector;
+use mullvad_types::states::TunnelState;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    io,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+#[cfg(unix)]
+use std::{os::unix::fs::PermissionsExt, path::PathBuf};
+use talpid_core::tunnel_state_machine::TunnelCommand;
+use talpid_types::{net::stats::TunnelStats, ErrorExt};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Upper bounds of the buckets of the connect duration histogram, in seconds.
+const CONNECT_DURATION_BUCKETS: [f64; 9] = [0.5, 1.0, 2.0, 3.0, 5.0, 10.0, 20.0, 30.0, 60.0];
+
+const TUNNEL_STATES: [&str; 5] = [
+    "disconnected",
+    "connecting",
+    "connected",
+    "disconnecting",
+    "error",
+];
+
+/// How long to wait for the tunnel state machine to return the tunnel statistics.
+const TUNNEL_STATS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Requests whose head is larger than this are rejected.
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to listen on {0}")]
+    Bind(ListenAddress, #[source] io::Error),
+}
+
+/// Where to serve the metrics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddress {
+    /// A TCP socket on a loopback address.
+    Tcp(SocketAddr),
+    /// A Unix socket. Only root may connect to it.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddress {
+    type Err = String;
+
+    /// Parses either `<ip>:<port>`, where the IP must be a loopback address, or `unix:<path>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err("missing socket path".to_owned());
+            }
+            return Ok(ListenAddress::Unix(PathBuf::from(path)));
+        }
+
+        let address: SocketAddr = s
+            .parse()
+            .map_err(|_| format!("expected <ip>:<port> or unix:<path>, got '{s}'"))?;
+        if !address.ip().is_loopback() {
+            return Err(format!("{} is not a loopback address", address.ip()));
+        }
+        Ok(ListenAddress::Tcp(address))
+    }
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddress::Tcp(address) => address.fmt(f),
+            #[cfg(unix)]
+            ListenAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+type TunnelCommandSender = Weak<futures_mpsc::UnboundedSender<TunnelCommand>>;
+
+/// Handle to the metrics endpoint. The endpoint is served for as long as the daemon is running.
+pub struct Metrics {
+    tunnel: Arc<Mutex<TunnelMetrics>>,
+}
+
+/// Sources of the metrics that are not kept by [`TunnelMetrics`].
+struct Sources {
+    tunnel: Arc<Mutex<TunnelMetrics>>,
+    tunnel_command_tx: TunnelCommandSender,
+    request_stats: RequestStats,
+    relay_selector: RelaySelector,
+}
+
+impl Metrics {
+    pub async fn start(
+        address: ListenAddress,
+        tunnel_command_tx: TunnelCommandSender,
+        request_stats: RequestStats,
+        relay_selector: RelaySelector,
+    ) -> Result<Self, Error> {
+        let tunnel = Arc::new(Mutex::new(TunnelMetrics::new()));
+        let sources = Arc::new(Sources {
+            tunnel: tunnel.clone(),
+            tunnel_command_tx,
+            request_stats,
+            relay_selector,
+        });
+
+        match &address {
+            ListenAddress::Tcp(socket_address) => {
+                let listener = tokio::net::TcpListener::bind(socket_address)
+                    .await
+                    .map_err(|error| Error::Bind(address.clone(), error))?;
+                tokio::spawn(async move {
+                    loop {
+                        match listener.accept().await {
+                            Ok((stream, _)) => {
+                                tokio::spawn(handle_connection(stream, sources.clone()));
+                            }
+                            Err(error) => log_accept_error(error),
+                        }
+                    }
+                });
+            }
+            #[cfg(unix)]
+            ListenAddress::Unix(path) => {
+                let _ = tokio::fs::remove_file(path).await;
+                let listener = tokio::net::UnixListener::bind(path)
+                    .map_err(|error| Error::Bind(address.clone(), error))?;
+                std::fs::set_permissions(path, PermissionsExt::from_mode(0o600))
+                    .map_err(|error| Error::Bind(address.clone(), error))?;
+                tokio::spawn(async move {
+                    loop {
+                        match listener.accept().await {
+                            Ok((stream, _)) => {
+                                tokio::spawn(handle_connection(stream, sources.clone()));
+                            }
+                            Err(error) => log_accept_error(error),
+                        }
+                    }
+                });
+            }
+        }
+        log::info!("Serving metrics on {address}");
+
+        Ok(Self { tunnel })
+    }
+
+    /// Update the tunnel metrics after a transition to `state`.
+    pub fn on_tunnel_state(&self, state: &TunnelState) {
+        self.tunnel
+            .lock()
+            .unwrap()
+            .on_tunnel_state(state, Instant::now());
+    }
+}
+
+fn log_accept_error(error: io::Error) {
+    log::error!(
+        "{}",
+        error.display_chain_with_msg("Failed to accept metrics connection")
+    );
+}
+
+/// Answer a single HTTP request and close the connection.
+async fn handle_connection(stream: impl AsyncRead + AsyncWrite + Unpin, sources: Arc<Sources>) {
+    let mut stream = BufReader::new(stream);
+    let mut head = String::new();
+    let mut limited_stream = (&mut stream).take(MAX_REQUEST_SIZE);
+    loop {
+        let mut line = String::new();
+        match limited_stream.read_line(&mut line).await {
+            // The connection was closed or the request is too large
+            Ok(0) => return,
+            Ok(_) => (),
+            Err(error) => {
+                log::debug!("Failed to read metrics request: {error}");
+                return;
+            }
+        }
+        let end_of_head = line == "\r\n" || line == "\n";
+        head.push_str(&line);
+        if end_of_head {
+            break;
+        }
+    }
+
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let response = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = sources.render().await;
+            format!(
+                "HTTP/1.1 200 OK\r\n\
+                 Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+                 Content-Length: {}\r\n\
+                 Connection: close\r\n\r\n{body}",
+                body.len()
+            )
+        }
+        (Some("GET"), Some(_)) => {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned()
+        }
+        _ => "HTTP/1.1 405 Method Not Allowed\r\nAllow: GET\r\nContent-Length: 0\r\n\
+              Connection: close\r\n\r\n"
+            .to_owned(),
+    };
+
+    let stream = stream.get_mut();
+    if let Err(error) = stream.write_all(response.as_bytes()).await {
+        log::debug!("Failed to write metrics response: {error}");
+    }
+    let _ = stream.shutdown().await;
+}
+
+impl Sources {
+    async fn render(&self) -> String {
+        let tunnel_stats = self.tunnel_stats().await;
+        // The relay list has never been loaded if it was last updated at the epoch
+        let relay_list_age = Some(self.relay_selector.last_updated())
+            .filter(|last_updated| *last_updated != UNIX_EPOCH)
+            .and_then(|last_updated| SystemTime::now().duration_since(last_updated).ok());
+        let tunnel = self.tunnel.lock().unwrap().clone();
+        render(
+            &tunnel,
+            Instant::now(),
+            tunnel_stats.as_ref(),
+            &self.request_stats.counts(),
+            relay_list_age,
+        )
+    }
+
+    async fn tunnel_stats(&self) -> Option<TunnelStats> {
+        let tunnel_command_tx = self.tunnel_command_tx.upgrade()?;
+        let (tx, rx) = oneshot::channel();
+        tunnel_command_tx
+            .unbounded_send(TunnelCommand::GetTunnelStats(tx))
+            .ok()?;
+        tokio::time::timeout(TUNNEL_STATS_TIMEOUT, rx)
+            .await
+            .ok()?
+            .ok()?
+    }
+}
+
+/// Metrics derived from the sequence of tunnel states.
+#[derive(Debug, Clone)]
+struct TunnelMetrics {
+    state: &'static str,
+    state_since: Instant,
+    relay: Option<String>,
+    reconnects: u64,
+    /// When the current connection attempt started, if the tunnel is connecting.
+    connecting_since: Option<Instant>,
+    connect_durations: Histogram,
+}
+
+impl TunnelMetrics {
+    fn new() -> Self {
+        Self {
+            state: "disconnected",
+            state_since: Instant::now(),
+            relay: None,
+            reconnects: 0,
+            connecting_since: None,
+            connect_durations: Histogram::new(&CONNECT_DURATION_BUCKETS),
+        }
+    }
+
+    fn on_tunnel_state(&mut self, state: &TunnelState, now: Instant) {
+        let (name, location) = match state {
+            TunnelState::Disconnected { .. } => ("disconnected", None),
+            TunnelState::Connecting { location, .. } => ("connecting", location.as_ref()),
+            TunnelState::Connected { location, .. } => ("connected", location.as_ref()),
+            TunnelState::Disconnecting(_) => ("disconnecting", None),
+            TunnelState::Error(_) => ("error", None),
+        };
+
+        match state {
+            TunnelState::Connecting { .. } => {
+                // Every attempt that does not start from the disconnected state is a reconnect
+                if self.state != "disconnected" {
+                    self.reconnects += 1;
+                }
+                self.connecting_since = Some(now);
+            }
+            TunnelState::Connected { .. } => {
+                if let Some(since) = self.connecting_since.take() {
+                    self.connect_durations
+                        .observe(now.saturating_duration_since(since));
+                }
+            }
+            _ => self.connecting_since = None,
+        }
+
+        self.state = name;
+        self.state_since = now;
+        self.relay = location.and_then(|location| location.hostname.clone());
+    }
+}
+
+/// A Prometheus histogram of durations.
+#[derive(Debug, Clone)]
+struct Histogram {
+    buckets: &'static [f64],
+    /// Number of observations per bucket, not including those in lower buckets.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(bucket) = self.buckets.iter().position(|bound| seconds <= *bound) {
+            self.counts[bucket] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+fn render(
+    tunnel: &TunnelMetrics,
+    now: Instant,
+    tunnel_stats: Option<&TunnelStats>,
+    requests: &BTreeMap<String, RequestCounts>,
+    relay_list_age: Option<Duration>,
+) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "mullvad_tunnel_state",
+        "gauge",
+        "Current tunnel state.",
+    );
+    for state in TUNNEL_STATES {
+        let value = u8::from(state == tunnel.state);
+        let _ = writeln!(out, "mullvad_tunnel_state{{state=\"{state}\"}} {value}");
+    }
+
+    header(
+        &mut out,
+        "mullvad_tunnel_state_duration_seconds",
+        "gauge",
+        "Time spent in the current tunnel state.",
+    );
+    let _ = writeln!(
+        out,
+        "mullvad_tunnel_state_duration_seconds {}",
+        now.saturating_duration_since(tunnel.state_since)
+            .as_secs_f64()
+    );
+
+    header(
+        &mut out,
+        "mullvad_tunnel_reconnects_total",
+        "counter",
+        "Number of connection attempts not preceded by a disconnect.",
+    );
+    let _ = writeln!(out, "mullvad_tunnel_reconnects_total {}", tunnel.reconnects);
+
+    header(
+        &mut out,
+        "mullvad_tunnel_connect_duration_seconds",
+        "histogram",
+        "Time from starting a connection attempt until the tunnel was connected.",
+    );
+    let histogram = &tunnel.connect_durations;
+    let mut cumulative = 0;
+    for (bound, count) in histogram.buckets.iter().zip(&histogram.counts) {
+        cumulative += count;
+        let _ = writeln!(
+            out,
+            "mullvad_tunnel_connect_duration_seconds_bucket{{le=\"{bound}\"}} {cumulative}"
+        );
+    }
+    let _ = writeln!(
+        out,
+        "mullvad_tunnel_connect_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+        histogram.count
+    );
+    let _ = writeln!(
+        out,
+        "mullvad_tunnel_connect_duration_seconds_sum {}",
+        histogram.sum
+    );
+    let _ = writeln!(
+        out,
+        "mullvad_tunnel_connect_duration_seconds_count {}",
+        histogram.count
+    );
+
+    header(
+        &mut out,
+        "mullvad_tunnel_relay_info",
+        "gauge",
+        "Relay used by the tunnel, while connecting or connected.",
+    );
+    if let Some(relay) = &tunnel.relay {
+        let _ = writeln!(
+            out,
+            "mullvad_tunnel_relay_info{{hostname=\"{}\"}} 1",
+            escape_label(relay)
+        );
+    }
+
+    if let Some(stats) = tunnel_stats {
+        let tx_bytes: u64 = stats.peers.iter().map(|peer| peer.tx_bytes).sum();
+        let rx_bytes: u64 = stats.peers.iter().map(|peer| peer.rx_bytes).sum();
+        header(
+            &mut out,
+            "mullvad_tunnel_transmitted_bytes_total",
+            "counter",
+            "Bytes sent through the current tunnel.",
+        );
+        let _ = writeln!(out, "mullvad_tunnel_transmitted_bytes_total {tx_bytes}");
+        header(
+            &mut out,
+            "mullvad_tunnel_received_bytes_total",
+            "counter",
+            "Bytes received through the current tunnel.",
+        );
+        let _ = writeln!(out, "mullvad_tunnel_received_bytes_total {rx_bytes}");
+    }
+
+    header(
+        &mut out,
+        "mullvad_api_requests_total",
+        "counter",
+        "Finished API requests per access method.",
+    );
+    for (access_method, counts) in requests {
+        let access_method = escape_label(access_method);
+        for (result, count) in [("success", counts.succeeded), ("failure", counts.failed)] {
+            let _ = writeln!(
+                out,
+                "mullvad_api_requests_total{{access_method=\"{access_method}\",result=\"{result}\"}} {count}"
+            );
+        }
+    }
+
+    if let Some(age) = relay_list_age {
+        header(
+            &mut out,
+            "mullvad_relay_list_age_seconds",
+            "gauge",
+            "Time since the relay list was last updated.",
+        );
+        let _ = writeln!(out, "mullvad_relay_list_age_seconds {}", age.as_secs());
+    }
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::tunnel_state;
+    use talpid_types::tunnel::ActionAfterDisconnect;
+
+    #[test]
+    fn test_tunnel_metrics() {
+        let start = Instant::now();
+        let mut tunnel = TunnelMetrics::new();
+
+        tunnel.on_tunnel_state(&tunnel_state(false), start);
+        tunnel.on_tunnel_state(&tunnel_state(true), start + Duration::from_millis(1500));
+        assert_eq!(tunnel.reconnects, 0);
+        assert_eq!(tunnel.connect_durations.count, 1);
+        assert_eq!(tunnel.connect_durations.counts[2], 1);
+        assert_eq!(tunnel.relay.as_deref(), Some("se-got-wg-001"));
+
+        // Reconnecting without disconnecting first
+        let reconnect_start = start + Duration::from_secs(60);
+        tunnel.on_tunnel_state(
+            &TunnelState::Disconnecting(ActionAfterDisconnect::Reconnect),
+            reconnect_start,
+        );
+        assert_eq!(tunnel.relay, None);
+        tunnel.on_tunnel_state(&tunnel_state(false), reconnect_start);
+        tunnel.on_tunnel_state(&tunnel_state(false), reconnect_start);
+        tunnel.on_tunnel_state(
+            &tunnel_state(true),
+            reconnect_start + Duration::from_secs(100),
+        );
+        assert_eq!(tunnel.reconnects, 2);
+        assert_eq!(tunnel.connect_durations.count, 2);
+        assert_eq!(tunnel.connect_durations.sum, 101.5);
+        assert_eq!(tunnel.connect_durations.counts.iter().sum::<u64>(), 1);
+
+        let requests = BTreeMap::from([(
+            "My \"proxy\"".to_owned(),
+            RequestCounts {
+                succeeded: 3,
+                failed: 1,
+            },
+        )]);
+        let output = render(
+            &tunnel,
+            reconnect_start + Duration::from_secs(110),
+            None,
+            &requests,
+            Some(Duration::from_secs(3600)),
+        );
+        for line in [
+            "mullvad_tunnel_state{state=\"connected\"} 1",
+            "mullvad_tunnel_state{state=\"connecting\"} 0",
+            "mullvad_tunnel_state_duration_seconds 10",
+            "mullvad_tunnel_reconnects_total 2",
+            "mullvad_tunnel_connect_duration_seconds_bucket{le=\"2\"} 1",
+            "mullvad_tunnel_connect_duration_seconds_bucket{le=\"60\"} 1",
+            "mullvad_tunnel_connect_duration_seconds_bucket{le=\"+Inf\"} 2",
+            "mullvad_tunnel_connect_duration_seconds_sum 101.5",
+            "mullvad_tunnel_relay_info{hostname=\"se-got-wg-001\"} 1",
+            "mullvad_api_requests_total{access_method=\"My \\\"proxy\\\"\",result=\"success\"} 3",
+            "mullvad_api_requests_total{access_method=\"My \\\"proxy\\\"\",result=\"failure\"} 1",
+            "mullvad_relay_list_age_seconds 3600",
+        ] {
+            assert!(output.lines().any(|l| l == line), "missing line: {line}");
+        }
+        assert!(!output.contains("mullvad_tunnel_transmitted_bytes_total"));
+    }
+
+    #[test]
+    fn test_parse_listen_address() {
+        assert_eq!(
+            "127.0.0.1:9090".parse(),
+            Ok(ListenAddress::Tcp("127.0.0.1:9090".parse().unwrap()))
+        );
+        assert_eq!(
+            "[::1]:9090".parse(),
+            Ok(ListenAddress::Tcp("[::1]:9090".parse().unwrap()))
+        );
+        assert!("0.0.0.0:9090".parse::<ListenAddress>().is_err());
+        assert!("localhost".parse::<ListenAddress>().is_err());
+        #[cfg(unix)]
+        assert_eq!(
+            "unix:/run/mullvad-metrics.sock".parse(),
+            Ok(ListenAddress::Unix("/run/mullvad-metrics.sock".into()))
+        );
+    }
+}
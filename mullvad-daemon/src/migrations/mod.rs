@@ -31,6 +31,8 @@
 //! 1. Implement the migration and add adequate tests.
 //! 1. Add to the changelog: "Settings format updated to `vY`"
 
+use crate::settings::audit::AuditLog;
+use mullvad_types::audit_log::AuditSource;
 use std::{
     path::Path,
     sync::{
@@ -124,7 +126,13 @@ pub struct Directories<'path> {
     settings_dir: &'path Path,
 }
 
-pub async fn migrate_all(cache_dir: &Path, settings_dir: &Path) -> Result<Option<MigrationData>> {
+/// Migrate the settings and cache to the current format. Changes to the settings are recorded in
+/// `audit_log`.
+pub async fn migrate_all(
+    cache_dir: &Path,
+    settings_dir: &Path,
+    audit_log: &AuditLog,
+) -> Result<Option<MigrationData>> {
     #[cfg(windows)]
     windows::migrate_after_windows_update(settings_dir)
         .await
@@ -170,6 +178,10 @@ pub async fn migrate_all(cache_dir: &Path, settings_dir: &Path) -> Result<Option
 
     log::debug!("Migrated settings. Wrote settings to {}", path.display());
 
+    audit_log
+        .record(AuditSource::Migration, &old_settings, &settings)
+        .await;
+
     Ok(migration_data)
 }
 
@@ -241,8 +241,9 @@ fn migrate_bridge_settings(settings: &mut serde_json::Value) -> Result<()> {
             normal: BridgeConstraints::default(),
             custom: Some(CustomProxy::Socks5Remote(Socks5Remote {
                 endpoint: extract_str(custom_bridge_remote.get("address"))?
-                    .parse()
-                    .map_err(|_| Error::InvalidSettingsContent)?,
+                    .parse::<SocketAddr>()
+                    .map_err(|_| Error::InvalidSettingsContent)?
+                    .into(),
                 auth: custom_bridge_remote.get("auth").and_then(|auth| {
                     let username = auth.get("username")?.to_string();
                     let password = auth.get("password")?.to_string();
@@ -260,8 +261,9 @@ fn migrate_bridge_settings(settings: &mut serde_json::Value) -> Result<()> {
             normal: BridgeConstraints::default(),
             custom: Some(CustomProxy::Shadowsocks(Shadowsocks {
                 endpoint: extract_str(custom_bridge_shadowsocks.get("peer"))?
-                    .parse()
-                    .map_err(|_| Error::InvalidSettingsContent)?,
+                    .parse::<SocketAddr>()
+                    .map_err(|_| Error::InvalidSettingsContent)?
+                    .into(),
                 password: extract_str(custom_bridge_shadowsocks.get("password"))?.to_string(),
                 cipher: extract_str(custom_bridge_shadowsocks.get("cipher"))?.to_string(),
             })),
@@ -0,0 +1,501 @@
+//! Keeps a bounded, persistent history of tunnel sessions.
+//!
+//! A session starts when the tunnel begins connecting and ends when the tunnel is disconnected or
+//! enters the error state. The history is stored in the cache directory. The ongoing session is
+//! stored as well, so that it is kept, and marked as interrupted, if the daemon stops unexpectedly.
+
+use chrono::{DateTime, Utc};
+use futures::{
+    channel::{mpsc as futures_mpsc, oneshot},
+    FutureExt,
+};
+use mullvad_types::{
+    data_usage::DataUsage,
+    location::GeoIpLocation,
+    session_history::{Session, SessionEndReason},
+    states::TunnelState,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Weak,
+    time::Duration,
+};
+use talpid_core::tunnel_state_machine::TunnelCommand;
+use talpid_types::{
+    net::{wireguard::PublicKey, ObfuscationType, TunnelEndpoint, TunnelType},
+    ErrorExt,
+};
+use tokio::{
+    fs,
+    io::{self, AsyncWriteExt},
+    sync::mpsc,
+};
+
+use crate::data_usage::usage_delta;
+
+const SESSION_HISTORY_FILE: &str = "session-history.json";
+/// How often to sample the tunnel statistics while connected.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How long to wait for the tunnel state machine to return the tunnel statistics.
+const TUNNEL_STATS_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often to write the history to disk during a session.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// The oldest sessions are removed once there are more than this.
+const MAX_SESSIONS: usize = 500;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to read session history")]
+    Read(#[source] io::Error),
+
+    #[error("Failed to parse session history")]
+    Parse(#[source] serde_json::Error),
+
+    #[error("Failed to serialize session history")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Failed to write session history")]
+    Write(#[source] io::Error),
+
+    #[error("Failed to remove session history")]
+    Remove(#[source] io::Error),
+
+    #[error("Session history task has stopped")]
+    Stopped,
+}
+
+type TunnelCommandSender = Weak<futures_mpsc::UnboundedSender<TunnelCommand>>;
+
+/// Relay and tunnel parameters of a connection attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RelayInfo {
+    relay: Option<String>,
+    country: Option<String>,
+    city: Option<String>,
+    tunnel_type: TunnelType,
+    obfuscation: Option<ObfuscationType>,
+}
+
+impl RelayInfo {
+    fn new(endpoint: &TunnelEndpoint, location: Option<&GeoIpLocation>) -> Self {
+        RelayInfo {
+            relay: location.and_then(|location| location.hostname.clone()),
+            country: location.map(|location| location.country.clone()),
+            city: location.and_then(|location| location.city.clone()),
+            tunnel_type: endpoint.tunnel_type,
+            obfuscation: endpoint
+                .obfuscation
+                .as_ref()
+                .map(|obfuscation| obfuscation.obfuscation_type),
+        }
+    }
+
+    fn apply_to(self, session: &mut Session) {
+        session.relay = self.relay;
+        session.country = self.country;
+        session.city = self.city;
+        session.tunnel_type = self.tunnel_type;
+        session.obfuscation = self.obfuscation;
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Event {
+    Connecting(RelayInfo),
+    Connected(RelayInfo),
+    Ended(SessionEndReason),
+}
+
+impl Event {
+    fn from_tunnel_state(state: &TunnelState) -> Option<Self> {
+        match state {
+            TunnelState::Connecting {
+                endpoint, location, ..
+            } => Some(Event::Connecting(RelayInfo::new(
+                endpoint,
+                location.as_ref(),
+            ))),
+            TunnelState::Connected {
+                endpoint, location, ..
+            } => Some(Event::Connected(RelayInfo::new(
+                endpoint,
+                location.as_ref(),
+            ))),
+            TunnelState::Disconnected { .. } => Some(Event::Ended(SessionEndReason::Disconnected)),
+            TunnelState::Error(error_state) => Some(Event::Ended(SessionEndReason::Error(
+                error_state.cause().clone(),
+            ))),
+            TunnelState::Disconnecting(_) => None,
+        }
+    }
+}
+
+enum Request {
+    Event(Event),
+    Get(oneshot::Sender<Vec<Session>>),
+    Clear(oneshot::Sender<Result<(), Error>>),
+}
+
+/// Handle to an actor that records tunnel sessions.
+pub struct SessionHistory {
+    request_tx: mpsc::UnboundedSender<Request>,
+}
+
+/// [SessionHistory] internal task state.
+struct Task {
+    request_rx: mpsc::UnboundedReceiver<Request>,
+    tunnel_command_tx: TunnelCommandSender,
+    path: PathBuf,
+    history: History,
+    connected: bool,
+    /// Last observed counters of each peer in the current tunnel.
+    peers: HashMap<PublicKey, DataUsage>,
+}
+
+impl SessionHistory {
+    pub fn new(cache_dir: &Path, tunnel_command_tx: TunnelCommandSender) -> Self {
+        let (request_tx, request_rx) = mpsc::unbounded_channel();
+
+        let task = Task {
+            request_rx,
+            tunnel_command_tx,
+            path: cache_dir.join(SESSION_HISTORY_FILE),
+            history: History::default(),
+            connected: false,
+            peers: HashMap::new(),
+        };
+        tokio::spawn(task.run());
+
+        SessionHistory { request_tx }
+    }
+
+    /// Update the ongoing session, or start or end one, given the new tunnel state.
+    pub fn on_tunnel_state(&self, state: &TunnelState) {
+        if let Some(event) = Event::from_tunnel_state(state) {
+            self.send(Request::Event(event));
+        }
+    }
+
+    /// Return all sessions, oldest first.
+    pub fn get(&self, tx: oneshot::Sender<Vec<Session>>) {
+        self.send(Request::Get(tx));
+    }
+
+    /// Remove all sessions, including the ongoing one, from memory and disk.
+    pub async fn clear(&self) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        self.send(Request::Clear(tx));
+        rx.await.map_err(|_| Error::Stopped)?
+    }
+
+    fn send(&self, request: Request) {
+        if self.request_tx.send(request).is_err() {
+            log::error!("Session history task has stopped");
+        }
+    }
+}
+
+impl Task {
+    async fn run(mut self) {
+        match History::load(&self.path).await {
+            Ok(history) => self.history = history,
+            Err(Error::Read(error)) if error.kind() == io::ErrorKind::NotFound => (),
+            Err(error) => log::error!("{}", error.display_chain()),
+        }
+
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        let mut save = tokio::time::interval(SAVE_INTERVAL);
+
+        loop {
+            futures::select! {
+                request = self.request_rx.recv().fuse() => {
+                    let Some(request) = request else {
+                        break; // The SessionHistory handle was dropped.
+                    };
+                    self.on_request(request).await;
+                }
+                _ = poll.tick().fuse() => {
+                    if self.connected {
+                        self.update().await;
+                    }
+                }
+                _ = save.tick().fuse() => self.save().await,
+            }
+        }
+
+        self.save().await;
+    }
+
+    async fn on_request(&mut self, request: Request) {
+        match request {
+            Request::Event(event) => {
+                if self.connected {
+                    // Count whatever passed through the old tunnel since the last sample.
+                    self.update().await;
+                }
+                self.peers.clear();
+                self.connected = matches!(event, Event::Connected(_));
+                let ended = matches!(event, Event::Ended(_));
+                self.history.on_event(event, Utc::now());
+                if ended {
+                    self.save().await;
+                }
+            }
+            Request::Get(tx) => {
+                let _ = tx.send(self.history.sessions.clone());
+            }
+            Request::Clear(tx) => {
+                self.history = History::default();
+                self.peers.clear();
+                let result = match fs::remove_file(&self.path).await {
+                    Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                        Err(Error::Remove(error))
+                    }
+                    _ => Ok(()),
+                };
+                let _ = tx.send(result);
+            }
+        }
+    }
+
+    /// Sample the tunnel statistics and add the traffic since the last sample to the ongoing
+    /// session.
+    async fn update(&mut self) {
+        let Some(command_tx) = self.tunnel_command_tx.upgrade() else {
+            return;
+        };
+        let (tx, rx) = oneshot::channel();
+        if command_tx
+            .unbounded_send(TunnelCommand::GetTunnelStats(tx))
+            .is_err()
+        {
+            return;
+        }
+        drop(command_tx);
+        if let Ok(Ok(Some(stats))) = tokio::time::timeout(TUNNEL_STATS_TIMEOUT, rx).await {
+            let usage = usage_delta(&mut self.peers, &stats);
+            self.history.add_usage(usage);
+        }
+    }
+
+    async fn save(&mut self) {
+        if !self.history.dirty {
+            return;
+        }
+        match self.history.save(&self.path).await {
+            Ok(()) => self.history.dirty = false,
+            Err(error) => log::error!("{}", error.display_chain()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct History {
+    /// All sessions, oldest first. Only the last one may be ongoing.
+    sessions: Vec<Session>,
+    /// Whether there are changes that have not been written to disk.
+    dirty: bool,
+}
+
+impl History {
+    async fn load(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path).await.map_err(Error::Read)?;
+        Self::parse(&content)
+    }
+
+    /// Parse stored sessions. Sessions that never ended were interrupted.
+    fn parse(content: &str) -> Result<Self, Error> {
+        let mut sessions: Vec<Session> = serde_json::from_str(content).map_err(Error::Parse)?;
+
+        let mut dirty = false;
+        for session in &mut sessions {
+            if session.end_reason.is_none() {
+                session.end_reason = Some(SessionEndReason::Interrupted);
+                dirty = true;
+            }
+        }
+        Ok(History { sessions, dirty })
+    }
+
+    async fn save(&self, path: &Path) -> Result<(), Error> {
+        let buffer = serde_json::to_string(&self.sessions).map_err(Error::Serialize)?;
+        let mut file = mullvad_fs::AtomicFile::new(path)
+            .await
+            .map_err(Error::Write)?;
+        file.write_all(buffer.as_bytes())
+            .await
+            .map_err(Error::Write)?;
+        file.finalize().await.map_err(Error::Write)
+    }
+
+    fn ongoing(&mut self) -> Option<&mut Session> {
+        self.sessions
+            .last_mut()
+            .filter(|session| session.end_reason.is_none())
+    }
+
+    fn on_event(&mut self, event: Event, now: DateTime<Utc>) {
+        match event {
+            Event::Connecting(info) => match self.ongoing() {
+                Some(session) => {
+                    session.connect_attempts += 1;
+                    info.apply_to(session);
+                }
+                None => self.start(info, now),
+            },
+            Event::Connected(info) => match self.ongoing() {
+                Some(session) => info.apply_to(session),
+                None => self.start(info, now),
+            },
+            Event::Ended(reason) => match self.ongoing() {
+                Some(session) => {
+                    session.end = Some(now);
+                    session.end_reason = Some(reason);
+                }
+                None => return,
+            },
+        }
+        self.dirty = true;
+    }
+
+    fn start(&mut self, info: RelayInfo, now: DateTime<Utc>) {
+        let mut session = Session {
+            start: now,
+            end: None,
+            relay: None,
+            country: None,
+            city: None,
+            tunnel_type: info.tunnel_type,
+            obfuscation: None,
+            connect_attempts: 1,
+            end_reason: None,
+            usage: DataUsage::default(),
+        };
+        info.apply_to(&mut session);
+        self.sessions.push(session);
+
+        let excess = self.sessions.len().saturating_sub(MAX_SESSIONS);
+        self.sessions.drain(..excess);
+    }
+
+    fn add_usage(&mut self, usage: DataUsage) {
+        if usage.total() == 0 {
+            return;
+        }
+        if let Some(session) = self.ongoing() {
+            session.usage += usage;
+            self.dirty = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use talpid_types::tunnel::ErrorStateCause;
+
+    fn relay(hostname: &str) -> RelayInfo {
+        RelayInfo {
+            relay: Some(hostname.to_owned()),
+            country: Some("Sweden".to_owned()),
+            city: Some("Gothenburg".to_owned()),
+            tunnel_type: TunnelType::Wireguard,
+            obfuscation: None,
+        }
+    }
+
+    #[test]
+    fn test_sessions() {
+        let now = Utc::now();
+        let mut history = History::default();
+
+        // Disconnecting without a session does nothing
+        history.on_event(Event::Ended(SessionEndReason::Disconnected), now);
+        assert!(history.sessions.is_empty());
+
+        history.on_event(Event::Connecting(relay("se-got-wg-001")), now);
+        history.on_event(Event::Connecting(relay("se-got-wg-002")), now);
+        history.on_event(Event::Connected(relay("se-got-wg-002")), now);
+        history.add_usage(DataUsage {
+            tx_bytes: 10,
+            rx_bytes: 20,
+        });
+        history.on_event(
+            Event::Ended(SessionEndReason::Error(ErrorStateCause::IsOffline)),
+            now,
+        );
+
+        history.on_event(Event::Connecting(relay("se-got-wg-003")), now);
+        history.add_usage(DataUsage {
+            tx_bytes: 1,
+            rx_bytes: 1,
+        });
+
+        assert_eq!(history.sessions.len(), 2);
+        let first = &history.sessions[0];
+        assert_eq!(first.connect_attempts, 2);
+        assert_eq!(first.relay.as_deref(), Some("se-got-wg-002"));
+        assert_eq!(first.usage.total(), 30);
+        assert_eq!(first.end, Some(now));
+        assert!(matches!(
+            first.end_reason,
+            Some(SessionEndReason::Error(ErrorStateCause::IsOffline))
+        ));
+
+        let second = &history.sessions[1];
+        assert_eq!(second.connect_attempts, 1);
+        assert_eq!(second.usage.total(), 2);
+        assert!(second.end_reason.is_none());
+    }
+
+    #[test]
+    fn test_max_sessions() {
+        let now = Utc::now();
+        let mut history = History::default();
+        for _ in 0..MAX_SESSIONS + 10 {
+            history.on_event(Event::Connecting(relay("se-got-wg-001")), now);
+            history.on_event(Event::Ended(SessionEndReason::Disconnected), now);
+        }
+        assert_eq!(history.sessions.len(), MAX_SESSIONS);
+    }
+
+    /// Updating must not hang if the tunnel state machine does not return the statistics.
+    #[tokio::test(start_paused = true)]
+    async fn test_update_timeout() {
+        let (command_tx, mut command_rx) = futures_mpsc::unbounded();
+        let command_tx = Arc::new(command_tx);
+        let (_request_tx, request_rx) = mpsc::unbounded_channel();
+        let mut task = Task {
+            request_rx,
+            tunnel_command_tx: Arc::downgrade(&command_tx),
+            path: PathBuf::new(),
+            history: History::default(),
+            connected: true,
+            peers: HashMap::new(),
+        };
+
+        task.update().await;
+
+        // The request is still pending, so the sender of the response is alive
+        assert!(matches!(
+            command_rx.try_recv(),
+            Ok(TunnelCommand::GetTunnelStats(_))
+        ));
+    }
+
+    #[test]
+    fn test_interrupted_session() {
+        let mut history = History::default();
+        history.on_event(Event::Connecting(relay("se-got-wg-001")), Utc::now());
+        let content = serde_json::to_string(&history.sessions).unwrap();
+
+        let history = History::parse(&content).unwrap();
+        assert!(history.dirty);
+        assert!(matches!(
+            history.sessions[0].end_reason,
+            Some(SessionEndReason::Interrupted)
+        ));
+    }
+}
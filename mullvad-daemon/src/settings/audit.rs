@@ -0,0 +1,264 @@
+//! Log of all changes made to the settings, and what made them.
+//!
+//! Each change is stored as a line of JSON in a file next to the daemon log. Secrets and
+//! personal information are redacted before anything is written.
+
+use chrono::Utc;
+use mullvad_management_interface::Caller;
+use mullvad_types::audit_log::{AuditEntry, AuditSource, SettingsChange};
+use serde_json::Value;
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+};
+use talpid_types::ErrorExt;
+use tokio::{fs, io::AsyncWriteExt};
+
+const AUDIT_LOG_FILENAME: &str = "settings-audit.log";
+
+/// The log is rotated once it grows beyond this size.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Values of these keys are never written to the log, wherever they appear in the settings.
+const SECRET_KEYS: &[&str] = &["password", "private_key", "psk", "exit_psk"];
+
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to read settings audit log")]
+    Read(#[source] io::Error),
+
+    #[error("Failed to write to settings audit log")]
+    Write(#[source] io::Error),
+
+    #[error("Failed to rotate settings audit log")]
+    Rotate(#[source] talpid_core::logging::RotateLogError),
+}
+
+/// Returns the source of settings changes made by `caller`, or by the daemon if there is no
+/// caller.
+pub fn source_from_caller(caller: Option<Caller>) -> AuditSource {
+    match caller {
+        Some(Caller { method, uid, pid }) => AuditSource::Rpc { method, uid, pid },
+        None => AuditSource::Daemon,
+    }
+}
+
+/// Handle to the settings audit log. Does nothing if the daemon does not log to a file.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+}
+
+impl AuditLog {
+    pub fn new(log_dir: Option<&Path>) -> Self {
+        Self {
+            path: log_dir.map(|dir| dir.join(AUDIT_LOG_FILENAME)),
+        }
+    }
+
+    /// Record the changes between `old` and `new`, which must be serialized `Settings`. Failing
+    /// to write the entry is logged but otherwise ignored.
+    pub async fn record(&self, source: AuditSource, old: &Value, new: &Value) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let changes = diff(old, new);
+        if changes.is_empty() {
+            return;
+        }
+        let entry = AuditEntry {
+            timestamp: Utc::now(),
+            source,
+            changes,
+        };
+        if let Err(error) = Self::append(path, &entry).await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to record settings change")
+            );
+        }
+    }
+
+    async fn append(path: &Path, entry: &AuditEntry) -> Result<(), Error> {
+        match fs::metadata(path).await {
+            Ok(metadata) if metadata.len() >= MAX_LOG_SIZE => {
+                talpid_core::logging::rotate_log(path).map_err(Error::Rotate)?;
+            }
+            _ => (),
+        }
+
+        let mut line = serde_json::to_string(entry).expect("audit entry is always serializable");
+        line.push('\n');
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(Error::Write)?;
+        file.write_all(line.as_bytes()).await.map_err(Error::Write)
+    }
+
+    /// Returns all entries in the log, oldest first.
+    pub async fn read(&self) -> Result<Vec<AuditEntry>, Error> {
+        let Some(path) = &self.path else {
+            return Ok(vec![]);
+        };
+        let mut entries = vec![];
+        for path in [path.with_extension("old.log"), path.to_owned()] {
+            let contents = match fs::read_to_string(&path).await {
+                Ok(contents) => contents,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(Error::Read(error)),
+            };
+            entries.extend(contents.lines().filter_map(|line| {
+                serde_json::from_str(line)
+                    .inspect_err(|error| {
+                        log::warn!("Ignoring invalid settings audit log entry: {error}")
+                    })
+                    .ok()
+            }));
+        }
+        Ok(entries)
+    }
+}
+
+/// Returns every value that differs between `old` and `new`, with secrets redacted.
+fn diff(old: &Value, new: &Value) -> Vec<SettingsChange> {
+    let mut changes = vec![];
+    diff_inner("", old, new, &mut changes);
+    changes
+}
+
+fn diff_inner(path: &str, old: &Value, new: &Value, changes: &mut Vec<SettingsChange>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            let keys: BTreeSet<&String> = old_map.keys().chain(new_map.keys()).collect();
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.to_owned()
+                } else {
+                    format!("{path}.{key}")
+                };
+                diff_inner(
+                    &path,
+                    old_map.get(key).unwrap_or(&Value::Null),
+                    new_map.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ if old != new => {
+            let is_secret = SECRET_KEYS.contains(&path.rsplit('.').next().unwrap_or_default());
+            changes.push(SettingsChange {
+                path: path.to_owned(),
+                old: redact(old, is_secret),
+                new: redact(new, is_secret),
+            });
+        }
+        _ => (),
+    }
+}
+
+/// Serializes `value` with all secrets removed, using the same rules as problem reports for
+/// addresses and identifiers.
+fn redact(value: &Value, is_secret: bool) -> String {
+    if is_secret && !value.is_null() {
+        return Value::from(REDACTED).to_string();
+    }
+    let mut value = value.clone();
+    redact_secrets(&mut value);
+    mullvad_problem_report::redact(&value.to_string())
+}
+
+fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                if SECRET_KEYS.contains(&key.as_str()) && !value.is_null() {
+                    *value = Value::from(REDACTED);
+                } else {
+                    redact_secrets(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_secrets),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff() {
+        let old = json!({
+            "allow_lan": false,
+            "tunnel_options": {
+                "dns_options": { "state": "default", "custom_options": { "addresses": [] } },
+            },
+            "removed": 1,
+        });
+        let new = json!({
+            "allow_lan": true,
+            "tunnel_options": {
+                "dns_options": {
+                    "state": "custom",
+                    "custom_options": { "addresses": ["10.0.0.1"] },
+                },
+            },
+            "added": "value",
+        });
+
+        let changes: Vec<_> = diff(&old, &new)
+            .into_iter()
+            .map(|change| (change.path, change.old, change.new))
+            .collect();
+        let expected = [
+            ("added", "null", "\"value\""),
+            ("allow_lan", "false", "true"),
+            ("removed", "1", "null"),
+            (
+                "tunnel_options.dns_options.custom_options.addresses",
+                "[]",
+                "[\"[REDACTED]\"]",
+            ),
+            (
+                "tunnel_options.dns_options.state",
+                "\"default\"",
+                "\"custom\"",
+            ),
+        ]
+        .map(|(path, old, new)| (path.to_owned(), old.to_owned(), new.to_owned()));
+        assert_eq!(changes, expected);
+
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let old = json!({ "proxy": { "password": "hunter2", "port": 1080 } });
+        let new = json!({ "proxy": { "password": "hunter3", "port": 1080 } });
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "proxy.password");
+        assert_eq!(changes[0].old, "\"[REDACTED]\"");
+        assert_eq!(changes[0].new, "\"[REDACTED]\"");
+
+        // Secrets nested in a changed value are redacted too
+        let old = json!({ "access_methods": [] });
+        let new = json!({
+            "access_methods": [{ "name": "proxy", "password": "hunter2", "psk": null }],
+        });
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert!(!changes[0].new.contains("hunter2"));
+        assert!(changes[0].new.contains("\"password\":\"[REDACTED]\""));
+        assert!(changes[0].new.contains("\"psk\":null"));
+    }
+}
@@ -1,5 +1,7 @@
+use audit::AuditLog;
 use futures::TryFutureExt;
 use mullvad_types::{
+    audit_log::AuditSource,
     custom_list::Error as CustomListError,
     relay_constraints::{RelayConstraints, RelaySettings, WireguardConstraints},
     settings::{DnsState, Settings},
@@ -16,6 +18,7 @@ use tokio::{
     io::{self, AsyncWriteExt},
 };
 
+pub mod audit;
 pub mod patch;
 
 const SETTINGS_FILE: &str = "settings.json";
@@ -92,6 +95,7 @@ fn handle_custom_list_error(
 pub struct SettingsPersister {
     settings: Settings,
     path: PathBuf,
+    audit_log: AuditLog,
     #[allow(clippy::type_complexity)]
     on_change_listeners: Vec<Box<dyn Fn(&Settings) + Send + Sync>>,
 }
@@ -100,8 +104,8 @@ pub type MadeChanges = bool;
 
 impl SettingsPersister {
     /// Loads user settings from file. If it fails, it returns the defaults, and overwrites the old
-    /// settings.
-    pub async fn load(settings_dir: &Path) -> Self {
+    /// settings. Changes are recorded in `audit_log`.
+    pub async fn load(settings_dir: &Path, audit_log: AuditLog) -> Self {
         let path = settings_dir.join(SETTINGS_FILE);
         let LoadSettingsResult {
             settings,
@@ -111,6 +115,7 @@ impl SettingsPersister {
         let mut persister = SettingsPersister {
             settings,
             path,
+            audit_log,
             on_change_listeners: vec![],
         };
 
@@ -221,7 +226,8 @@ impl SettingsPersister {
         log::debug!("Writing settings to {}", path.display());
 
         let buffer = serde_json::to_string_pretty(settings).map_err(Error::SerializeError)?;
-        let mut file = mullvad_fs::AtomicFile::new(path)
+        // The settings contain the secrets of custom relays and access methods
+        let mut file = mullvad_fs::AtomicFile::new_private(path)
             .await
             .map_err(|e| Error::WriteError(path.display().to_string(), e))?;
         file.write_all(&buffer.into_bytes())
@@ -234,9 +240,9 @@ impl SettingsPersister {
         Ok(())
     }
 
-    /// Resets default settings
-    pub async fn reset(&mut self) -> Result<(), Error> {
-        self.settings = Self::default_settings();
+    /// Resets default settings. The change is attributed to `source` in the audit log.
+    pub async fn reset(&mut self, source: AuditSource) -> Result<(), Error> {
+        let old_settings = std::mem::replace(&mut self.settings, Self::default_settings());
         let path = self.path.clone();
         self.save()
             .or_else(|e| async move {
@@ -251,6 +257,7 @@ impl SettingsPersister {
             })
             .await?;
 
+        self.record_change(source, &old_settings).await;
         self.notify_listeners();
 
         Ok(())
@@ -275,7 +282,8 @@ impl SettingsPersister {
         settings
     }
 
-    /// Edit the settings in a closure and write the changes to disk.
+    /// Edit the settings in a closure and write the changes to disk. The changes are attributed to
+    /// `source` in the audit log.
     ///
     /// # On success
     ///
@@ -291,16 +299,18 @@ impl SettingsPersister {
     /// If no settings were changed, no I/O will be performed.
     pub async fn update(
         &mut self,
+        source: AuditSource,
         update_fn: impl FnOnce(&mut Settings),
     ) -> Result<MadeChanges, Error> {
-        self.try_update(|settings| -> Result<(), Error> {
+        self.try_update(source, |settings| -> Result<(), Error> {
             update_fn(settings);
             Ok(())
         })
         .await
     }
 
-    /// Edit the settings in a closure, and write the changes to disk.
+    /// Edit the settings in a closure, and write the changes to disk. The changes are attributed to
+    /// `source` in the audit log.
     ///
     /// # On success
     ///
@@ -329,7 +339,7 @@ impl SettingsPersister {
     /// }
     ///
     /// let settings = Settings::default_settings();
-    /// let err = settings.try_update(|settings| {
+    /// let err = settings.try_update(AuditSource::Daemon, |settings| {
     ///   // Perform some update on the settings
     ///   settings.allow_lan = !settings.allow_lan;
     ///   // Fail the update procedure due to some error
@@ -341,6 +351,7 @@ impl SettingsPersister {
     /// ```
     pub async fn try_update<E>(
         &mut self,
+        source: AuditSource,
         update_fn: impl FnOnce(&mut Settings) -> Result<(), E>,
     ) -> Result<MadeChanges, Error>
     where
@@ -357,8 +368,9 @@ impl SettingsPersister {
         }
 
         Self::save_inner(&self.path, &new_settings).await?;
-        self.settings = new_settings;
+        let old_settings = std::mem::replace(&mut self.settings, new_settings);
 
+        self.record_change(source, &old_settings).await;
         self.notify_listeners();
 
         Ok(true)
@@ -371,6 +383,23 @@ impl SettingsPersister {
         }
     }
 
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit_log
+    }
+
+    async fn record_change(&self, source: AuditSource, old_settings: &Settings) {
+        match (
+            serde_json::to_value(old_settings),
+            serde_json::to_value(&self.settings),
+        ) {
+            (Ok(old), Ok(new)) => self.audit_log.record(source, &old, &new).await,
+            (Err(error), _) | (_, Err(error)) => log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to serialize settings for audit log")
+            ),
+        }
+    }
+
     pub fn register_change_listener(
         &mut self,
         change_listener: impl Fn(&Settings) + Send + Sync + 'static,
@@ -613,6 +642,75 @@ mod test {
         let _ = SettingsPersister::load_from_bytes(settings).unwrap();
     }
 
+    /// Static PSKs of custom relays are persisted along with the relay, but not as part of its
+    /// peers, which never serialize their PSKs.
+    #[test]
+    fn test_custom_relay_psk() {
+        use mullvad_types::{
+            custom_tunnel::{ConnectionConfig, CustomTunnelEndpoint},
+            relay_constraints::RelaySettings,
+        };
+        use talpid_types::net::wireguard;
+
+        let peer = wireguard::PeerConfig {
+            public_key: wireguard::PrivateKey::new_from_random().public_key(),
+            allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
+            endpoint: "1.2.3.4:51820".parse().unwrap(),
+            psk: Some(wireguard::PresharedKey::from([1; 32])),
+            persistent_keepalive: None,
+            #[cfg(daita)]
+            constant_packet_size: false,
+        };
+        let mut settings = SettingsPersister::default_settings();
+        settings.relay_settings = RelaySettings::CustomTunnelEndpoint(CustomTunnelEndpoint::new(
+            "example.com".to_owned(),
+            ConnectionConfig::Wireguard(wireguard::ConnectionConfig {
+                tunnel: wireguard::TunnelConfig {
+                    private_key: wireguard::PrivateKey::new_from_random(),
+                    addresses: vec!["10.64.0.2".parse().unwrap()],
+                },
+                peer: peer.clone(),
+                exit_peer: None,
+                ipv4_gateway: "10.64.0.1".parse().unwrap(),
+                ipv6_gateway: None,
+                #[cfg(target_os = "linux")]
+                fwmark: None,
+            }),
+        ));
+
+        let serialized = serde_json::to_value(&settings).unwrap();
+        let stored = &serialized["relay_settings"]["custom_tunnel_endpoint"]["config"]["wireguard"];
+        assert_eq!(
+            stored["psk"],
+            wireguard::PresharedKey::from([1; 32]).to_base64()
+        );
+        assert!(stored.get("exit_psk").is_none());
+        assert!(stored["peer"].get("psk").is_none());
+
+        let deserialized: Settings = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized.relay_settings, settings.relay_settings);
+
+        // PSKs of other peers are never serialized
+        let serialized = serde_json::to_value(&peer).unwrap();
+        assert!(serialized.get("psk").is_none());
+    }
+
+    /// The settings file contains secrets, so it must only be accessible by its owner.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_settings_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SETTINGS_FILE);
+        SettingsPersister::save_inner(&path, &SettingsPersister::default_settings())
+            .await
+            .unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
     /// The [`SettingsPersister`] should always succeed when deserializing a
     /// [`Settings`] object from disk. However, there is a distinction between
     /// different error cases.
@@ -16,7 +16,7 @@
 //! [spec](../../../docs/settings-patch-format.md).
 
 use super::SettingsPersister;
-use mullvad_types::settings::Settings;
+use mullvad_types::{audit_log::AuditSource, settings::Settings};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -170,11 +170,12 @@ fn export_settings_inner(settings: &Settings) -> Result<serde_json::Value, Error
 pub async fn merge_validate_patch(
     settings: &mut SettingsPersister,
     json_patch: &str,
+    source: AuditSource,
 ) -> Result<(), Error> {
     let new_settings = merge_validate_patch_inner(settings, json_patch)?;
 
     settings
-        .update(move |settings| *settings = new_settings)
+        .update(source, move |settings| *settings = new_settings)
         .await
         .map_err(Error::Settings)?;
 
@@ -0,0 +1,61 @@
+//! Values shared by the tests of several modules.
+
+use mullvad_types::{features::FeatureIndicators, location::GeoIpLocation, states::TunnelState};
+use talpid_types::net::{Endpoint, TransportProtocol, TunnelEndpoint, TunnelType};
+
+/// Endpoint of a WireGuard relay, before the tunnel is up.
+pub fn relay_endpoint() -> TunnelEndpoint {
+    TunnelEndpoint {
+        endpoint: Endpoint::new([192, 0, 2, 1], 51820, TransportProtocol::Udp),
+        tunnel_type: TunnelType::Wireguard,
+        quantum_resistant: false,
+        proxy: None,
+        obfuscation: None,
+        entry_endpoint: None,
+        tunnel_interface: None,
+        tunnel_ips: vec![],
+        #[cfg(daita)]
+        daita: false,
+        #[cfg(daita)]
+        daita_parameters: None,
+        wireguard_backend: None,
+    }
+}
+
+/// Location of the relay at [`relay_endpoint`].
+pub fn relay_location() -> GeoIpLocation {
+    GeoIpLocation {
+        ipv4: None,
+        ipv6: None,
+        country: "Sweden".to_owned(),
+        city: Some("Gothenburg".to_owned()),
+        latitude: 57.7,
+        longitude: 11.97,
+        mullvad_exit_ip: true,
+        hostname: Some("se-got-wg-001".to_owned()),
+        bridge_hostname: None,
+        entry_hostname: None,
+        obfuscator_hostname: None,
+    }
+}
+
+/// State of a tunnel that is connecting to the relay at [`relay_endpoint`], or connected to it
+/// if `connected` is set.
+pub fn tunnel_state(connected: bool) -> TunnelState {
+    let endpoint = relay_endpoint();
+    let location = Some(relay_location());
+    let feature_indicators = FeatureIndicators::default();
+    if connected {
+        TunnelState::Connected {
+            endpoint,
+            location,
+            feature_indicators,
+        }
+    } else {
+        TunnelState::Connecting {
+            endpoint,
+            location,
+            feature_indicators,
+        }
+    }
+}
@@ -42,6 +42,8 @@ struct InnerParametersGenerator {
     relay_selector: RelaySelector,
     tunnel_options: TunnelOptions,
     account_manager: AccountManagerHandle,
+    #[cfg(target_os = "linux")]
+    fwmark: u32,
 
     last_generated_relays: Option<LastSelectedRelays>,
 }
@@ -52,12 +54,15 @@ impl ParametersGenerator {
         account_manager: AccountManagerHandle,
         relay_selector: RelaySelector,
         tunnel_options: TunnelOptions,
+        #[cfg(target_os = "linux")] fwmark: u32,
     ) -> Self {
         Self(Arc::new(Mutex::new(InnerParametersGenerator {
             tunnel_options,
             relay_selector,
 
             account_manager,
+            #[cfg(target_os = "linux")]
+            fwmark,
 
             last_generated_relays: None,
         })))
@@ -202,7 +207,12 @@ impl InnerParametersGenerator {
                 self.last_generated_relays = None;
                 custom_relay
                     // TODO: generate proxy settings for custom tunnels
-                    .to_tunnel_parameters(self.tunnel_options.clone(), None)
+                    .to_tunnel_parameters(
+                        self.tunnel_options.clone(),
+                        None,
+                        #[cfg(target_os = "linux")]
+                        self.fwmark,
+                    )
                     .map_err(|e| {
                         log::error!("Failed to resolve hostname for custom tunnel config: {}", e);
                         Error::ResolveCustomHostname
@@ -224,7 +234,7 @@ impl InnerParametersGenerator {
             generic_options: self.tunnel_options.generic.clone(),
             proxy: bridge_settings,
             #[cfg(target_os = "linux")]
-            fwmark: mullvad_types::TUNNEL_FWMARK,
+            fwmark: self.fwmark,
         }
         .into()
     }
@@ -250,7 +260,7 @@ impl InnerParametersGenerator {
                 ipv4_gateway: endpoint.ipv4_gateway,
                 ipv6_gateway: Some(endpoint.ipv6_gateway),
                 #[cfg(target_os = "linux")]
-                fwmark: Some(mullvad_types::TUNNEL_FWMARK),
+                fwmark: Some(self.fwmark),
             },
             options: self
                 .tunnel_options
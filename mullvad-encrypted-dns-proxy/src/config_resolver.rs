@@ -8,7 +8,7 @@ use std::{net::IpAddr, sync::Arc, time::Duration};
 use tokio::time::error::Elapsed;
 
 /// The port to connect to the DoH resolvers over.
-const RESOLVER_PORT: u16 = 443;
+pub const RESOLVER_PORT: u16 = 443;
 const DEFAULT_TIMEOUT: Duration = std::time::Duration::from_secs(10);
 
 pub struct Nameserver {
@@ -68,6 +68,28 @@ pub async fn resolve_configs(
     resolvers: &[Nameserver],
     domain: &str,
 ) -> Result<Vec<config::ProxyConfig>, Error> {
+    resolve_config_with_resolverconfig(
+        doh_resolver_config(resolvers),
+        doh_resolver_options(),
+        domain,
+        DEFAULT_TIMEOUT,
+    )
+    .await
+}
+
+/// Looks up the IP addresses of `hostname` towards the given DoH `resolvers`. Since the resolvers
+/// are reached by IP, this does not depend on the DNS servers configured on the host.
+pub async fn resolve_host(resolvers: &[Nameserver], hostname: &str) -> Result<Vec<IpAddr>, Error> {
+    let resolver =
+        TokioAsyncResolver::tokio(doh_resolver_config(resolvers), doh_resolver_options());
+    let lookup = tokio::time::timeout(DEFAULT_TIMEOUT, resolver.lookup_ip(hostname))
+        .await
+        .map_err(Error::Timeout)?
+        .map_err(Error::ResolutionError)?;
+    Ok(lookup.into_iter().collect())
+}
+
+fn doh_resolver_config(resolvers: &[Nameserver]) -> ResolverConfig {
     let mut nameservers = ResolverConfig::new();
     for resolver in resolvers.iter() {
         let ns_config_group = NameServerConfigGroup::from_ips_https(
@@ -83,10 +105,13 @@ pub async fn resolve_configs(
     }
 
     nameservers.set_tls_client_config(Arc::new(client_config_tls12()));
-    let mut resolver_config: ResolverOpts = Default::default();
+    nameservers
+}
 
-    resolver_config.timeout = Duration::from_secs(5);
-    resolve_config_with_resolverconfig(nameservers, resolver_config, domain, DEFAULT_TIMEOUT).await
+fn doh_resolver_options() -> ResolverOpts {
+    let mut options = ResolverOpts::default();
+    options.timeout = Duration::from_secs(5);
+    options
 }
 
 pub async fn resolve_config_with_resolverconfig(
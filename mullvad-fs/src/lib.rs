@@ -16,15 +16,37 @@ pub struct AtomicFile {
 
 impl AtomicFile {
     pub async fn new<P: Into<PathBuf>>(target_path: P) -> io::Result<Self> {
+        Self::with_options(target_path, Self::file_options()).await
+    }
+
+    /// Like [`AtomicFile::new`], but the file is only readable and writable by its owner on Unix.
+    /// Use this for files containing secrets.
+    pub async fn new_private<P: Into<PathBuf>>(target_path: P) -> io::Result<Self> {
+        let mut options = Self::file_options();
+        #[cfg(unix)]
+        options.mode(0o600);
+        Self::with_options(target_path, options).await
+    }
+
+    async fn with_options<P: Into<PathBuf>>(
+        target_path: P,
+        options: fs::OpenOptions,
+    ) -> io::Result<Self> {
         let target_path = target_path.into();
         let temp_path = target_path.with_file_name(uuid::Uuid::new_v4().to_string());
         Ok(Self {
-            file: Some(fs::File::create(&temp_path).await?),
+            file: Some(options.open(&temp_path).await?),
             temp_path,
             target_path,
         })
     }
 
+    fn file_options() -> fs::OpenOptions {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        options
+    }
+
     /// Flushes and moves the file to `self.target_path`, replacing it if it exists.
     pub async fn finalize(mut self) -> io::Result<()> {
         let result = async {
@@ -64,7 +64,7 @@ pub unsafe extern "C" fn new_shadowsocks_access_method_setting(
     let cipher = convert_c_string(c_cipher);
 
     let shadowsocks_configuration = Shadowsocks {
-        endpoint,
+        endpoint: endpoint.into(),
         password,
         cipher,
     };
@@ -103,6 +103,9 @@ pub unsafe extern "C" fn new_socks5_access_method_setting(
         }
     };
 
-    let socks5_configuration = Socks5Remote { endpoint, auth };
+    let socks5_configuration = Socks5Remote {
+        endpoint: endpoint.into(),
+        auth,
+    };
     Box::into_raw(Box::new(socks5_configuration)) as *mut c_void
 }
@@ -3,7 +3,7 @@ use std::{ffi::CStr, sync::Mutex, thread};
 use talpid_tunnel_config_client::{
     request_ephemeral_peer_with, EphemeralPeer, Error, RelayConfigService,
 };
-use talpid_types::net::wireguard::{PrivateKey, PublicKey};
+use talpid_types::net::wireguard::{DaitaLevel, PrivateKey, PublicKey};
 use tokio::{runtime::Handle as TokioHandle, task::JoinHandle};
 use tonic::transport::channel::Endpoint;
 use tower::util::service_fn;
@@ -138,7 +138,7 @@ impl EphemeralPeerExchange {
                 PublicKey::from(self.pub_key),
                 ephemeral_pub_key,
                 self.peer_parameters.enable_post_quantum,
-                self.peer_parameters.enable_daita,
+                self.peer_parameters.enable_daita.then_some(DaitaLevel::DEFAULT),
             ) =>  {
                 match ephemeral_peer {
                     Ok(EphemeralPeer { psk, daita }) => {
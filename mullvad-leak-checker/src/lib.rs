@@ -20,8 +20,9 @@ pub enum LeakInfo {
         interface: Interface,
     },
 
-    /// Queried a <https://am.i.mullvad.net>, and was not mullvad.
-    #[cfg(feature = "am-i-mullvad")]
+    /// Queried a <https://am.i.mullvad.net>, and was not mullvad. Only detected with the
+    /// `am-i-mullvad` feature, but always present so that matching on this does not depend on
+    /// which features are enabled.
     AmIMullvad { ip: IpAddr },
 }
 
@@ -42,6 +43,21 @@ impl From<String> for Interface {
     }
 }
 
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => f.write_str(name),
+
+            #[cfg(target_os = "windows")]
+            // SAFETY: u64 is valid for all bit patterns, so reading the union as a u64 is safe.
+            Self::Luid(luid) => write!(f, "LUID {}", unsafe { luid.Value }),
+
+            #[cfg(target_os = "macos")]
+            Self::Index(index) => write!(f, "index {index}"),
+        }
+    }
+}
+
 impl fmt::Debug for Interface {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
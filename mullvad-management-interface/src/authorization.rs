@@ -0,0 +1,286 @@
+//! Authorization of management interface clients based on their peer credentials.
+//!
+//! Read-only methods may be called by anyone who can connect to the socket. If an admin group
+//! is configured, all other methods may only be called by root and members of that group. The
+//! role of the caller is added to the extensions of each request, so that handlers can withhold
+//! secrets from non-admins.
+
+use crate::Error;
+use futures::future::{self, Either};
+use std::{
+    convert::Infallible,
+    fs, io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::net::{UnixListener, UnixStream};
+use tonic::{
+    body::BoxBody,
+    codegen::{http, Service},
+    server::NamedService,
+    transport::server::UdsConnectInfo,
+    Status,
+};
+
+/// Methods that may be called by any client, regardless of the admin group. These must neither
+/// change any state nor reveal the account number to non-admins. Passwords and keys in their
+/// responses must be redacted unless [`is_admin`] is true.
+const READ_ONLY_METHODS: &[&str] = &[
+    "GetTunnelState",
+    "GetTunnelStats",
+    "TunnelStatsListen",
+    "GetDataUsage",
+    "GetSessionHistory",
+    "EventsListen",
+    "GetCurrentVersion",
+    "GetVersionInfo",
+    "IsPerformingPostUpgrade",
+    "GetRelayLocations",
+    "GetSettings",
+    "GetWireguardKey",
+    "GetCurrentApiAccessMethod",
+    "GetCurrentApiEndpoint",
+    "GetSplitTunnelProcesses",
+    "GetExcludedProcesses",
+    "NeedFullDiskPermissions",
+    "GetFeatureIndicators",
+    "GetWireguardConfig",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Role {
+    /// May only call read-only methods
+    User,
+    /// May call any method
+    Admin,
+}
+
+/// Returns the role required to call the gRPC method at `path`.
+fn required_role(path: &str) -> Role {
+    let method = path.rsplit('/').next().unwrap_or_default();
+    if READ_ONLY_METHODS.contains(&method) {
+        Role::User
+    } else {
+        Role::Admin
+    }
+}
+
+/// Returns whether `request` was made by an admin. Requests that did not pass through
+/// [`AuthorizedService`] were not.
+pub(crate) fn is_admin<T>(request: &tonic::Request<T>) -> bool {
+    request.extensions().get::<Role>() == Some(&Role::Admin)
+}
+
+/// Group whose members may call any method.
+#[derive(Debug, Clone)]
+pub struct AdminGroup {
+    gid: u32,
+    member_uids: Vec<u32>,
+}
+
+impl AdminGroup {
+    /// Look up the group called `name` and its members. Changes to the group only take effect
+    /// when the daemon is restarted.
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        let group = nix::unistd::Group::from_name(name)
+            .map_err(Error::ObtainGidError)?
+            .ok_or(Error::NoGidError)?;
+        let member_uids = group
+            .mem
+            .iter()
+            .filter_map(|member| match nix::unistd::User::from_name(member) {
+                Ok(Some(user)) => Some(user.uid.as_raw()),
+                _ => {
+                    log::warn!("Ignoring unknown member of admin group: {member}");
+                    None
+                }
+            })
+            .collect();
+        Ok(Self {
+            gid: group.gid.as_raw(),
+            member_uids,
+        })
+    }
+
+    fn contains(&self, uid: u32, gid: u32) -> bool {
+        gid == self.gid || self.member_uids.contains(&uid)
+    }
+}
+
+/// Returns the role of a peer with the given user and group ID. Without an admin group, every
+/// peer is an admin.
+fn peer_role(peer: Option<(u32, u32)>, admin_group: Option<&AdminGroup>) -> Role {
+    let Some(admin_group) = admin_group else {
+        return Role::Admin;
+    };
+    match peer {
+        Some((0, _)) => Role::Admin,
+        Some((uid, gid)) if admin_group.contains(uid, gid) => Role::Admin,
+        _ => Role::User,
+    }
+}
+
+/// Wraps a gRPC service, rejecting calls that the peer is not allowed to make with
+/// `PERMISSION_DENIED`.
+#[derive(Clone)]
+pub struct AuthorizedService<S> {
+    inner: S,
+    admin_group: Option<Arc<AdminGroup>>,
+}
+
+impl<S> AuthorizedService<S> {
+    pub fn new(inner: S, admin_group: Option<AdminGroup>) -> Self {
+        Self {
+            inner,
+            admin_group: admin_group.map(Arc::new),
+        }
+    }
+}
+
+impl<S: NamedService> NamedService for AuthorizedService<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, B> Service<http::Request<B>> for AuthorizedService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>, Error = Infallible>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Either<S::Future, future::Ready<Result<Self::Response, Infallible>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let peer = request
+            .extensions()
+            .get::<UdsConnectInfo>()
+            .and_then(|info| info.peer_cred)
+            .map(|credentials| (credentials.uid(), credentials.gid()));
+        let role = peer_role(peer, self.admin_group.as_deref());
+
+        if role >= required_role(request.uri().path()) {
+            request.extensions_mut().insert(role);
+            return Either::Left(self.inner.call(request));
+        }
+
+        log::warn!(
+            "Denied call to {} from uid {:?}",
+            request.uri().path(),
+            peer.map(|(uid, _gid)| uid)
+        );
+        let status =
+            Status::permission_denied("Only root and members of the admin group may do this");
+        Either::Right(future::ready(Ok(status.into_http())))
+    }
+}
+
+/// Stream of connections to the management socket. The peer credentials of each connection
+/// are made available to [`AuthorizedService`].
+///
+/// Removes the socket file when dropped.
+pub struct Incoming {
+    path: PathBuf,
+    listener: UnixListener,
+}
+
+impl Incoming {
+    /// Create the socket at `path`, accessible to everyone.
+    pub fn bind(path: &Path) -> Result<Self, Error> {
+        let listener = UnixListener::bind(path).map_err(Error::StartServerError)?;
+        fs::set_permissions(path, PermissionsExt::from_mode(0o766))
+            .map_err(Error::PermissionsError)?;
+        Ok(Self {
+            path: path.to_owned(),
+            listener,
+        })
+    }
+}
+
+impl futures::Stream for Incoming {
+    type Item = io::Result<UnixStream>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.listener
+            .poll_accept(cx)
+            .map(|result| Some(result.map(|(stream, _addr)| stream)))
+    }
+}
+
+impl Drop for Incoming {
+    fn drop(&mut self) {
+        if fs::remove_file(&self.path).is_ok() {
+            log::trace!("Removed socket file at: {}", self.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SERVICE: &str = "/mullvad_daemon.management_interface.ManagementService";
+
+    #[test]
+    fn test_required_role() {
+        for method in [
+            "GetTunnelState",
+            "EventsListen",
+            "GetSettings",
+            "GetWireguardConfig",
+        ] {
+            assert_eq!(required_role(&format!("{SERVICE}/{method}")), Role::User);
+        }
+        for method in [
+            "FactoryReset",
+            "LoginAccount",
+            "SetDnsOptions",
+            "GetAccountHistory",
+            "GetDevice",
+            "GetWwwAuthToken",
+            "ExportJsonSettings",
+        ] {
+            assert_eq!(required_role(&format!("{SERVICE}/{method}")), Role::Admin);
+        }
+        // Unknown methods require an admin
+        assert_eq!(required_role(&format!("{SERVICE}/NewMethod")), Role::Admin);
+        assert_eq!(required_role(""), Role::Admin);
+    }
+
+    #[test]
+    fn test_is_admin() {
+        let mut request = tonic::Request::new(());
+        assert!(!is_admin(&request));
+        request.extensions_mut().insert(Role::User);
+        assert!(!is_admin(&request));
+        request.extensions_mut().insert(Role::Admin);
+        assert!(is_admin(&request));
+    }
+
+    #[test]
+    fn test_peer_role() {
+        let admin_group = AdminGroup {
+            gid: 1001,
+            member_uids: vec![1002],
+        };
+        assert_eq!(peer_role(Some((0, 0)), Some(&admin_group)), Role::Admin);
+        assert_eq!(
+            peer_role(Some((1000, 1001)), Some(&admin_group)),
+            Role::Admin
+        );
+        assert_eq!(
+            peer_role(Some((1002, 100)), Some(&admin_group)),
+            Role::Admin
+        );
+        assert_eq!(peer_role(Some((1000, 100)), Some(&admin_group)), Role::User);
+        // Peers without credentials are never admins
+        assert_eq!(peer_role(None, Some(&admin_group)), Role::User);
+        // Everyone is an admin if there is no admin group
+        assert_eq!(peer_role(Some((1000, 100)), None), Role::Admin);
+    }
+}
@@ -0,0 +1,91 @@
+//! Information about the client whose request is currently being handled.
+
+use std::task::{Context, Poll};
+use tokio::task::futures::TaskLocalFuture;
+use tonic::{
+    codegen::{http, Service},
+    server::NamedService,
+};
+
+/// The gRPC method and, where available, the process that called it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    /// Name of the gRPC method, e.g. `SetAllowLan`
+    pub method: String,
+    pub uid: Option<u32>,
+    pub pid: Option<i32>,
+}
+
+tokio::task_local! {
+    static CALLER: Caller;
+}
+
+/// Returns the caller of the gRPC method currently being handled, or `None` if this is not
+/// called from within a management interface handler.
+pub fn current_caller() -> Option<Caller> {
+    CALLER.try_with(Caller::clone).ok()
+}
+
+/// Wraps a gRPC service, making the caller of each request available through
+/// [`current_caller`] while it is handled.
+#[derive(Clone)]
+pub struct CallerService<S> {
+    inner: S,
+}
+
+impl<S> CallerService<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S: NamedService> NamedService for CallerService<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, B> Service<http::Request<B>> for CallerService<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TaskLocalFuture<Caller, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let caller = caller_from_request(&request);
+        CALLER.scope(caller, self.inner.call(request))
+    }
+}
+
+fn caller_from_request<B>(request: &http::Request<B>) -> Caller {
+    let method = request
+        .uri()
+        .path()
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_owned();
+
+    #[cfg(target_os = "linux")]
+    {
+        let credentials = request
+            .extensions()
+            .get::<tonic::transport::server::UdsConnectInfo>()
+            .and_then(|info| info.peer_cred);
+        Caller {
+            method,
+            uid: credentials.map(|credentials| credentials.uid()),
+            pid: credentials.and_then(|credentials| credentials.pid()),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    Caller {
+        method,
+        uid: None,
+        pid: None,
+    }
+}
@@ -8,6 +8,7 @@ use mullvad_types::wireguard::DaitaSettings;
 use mullvad_types::{
     access_method::AccessMethodSetting,
     device::{DeviceEvent, RemoveDeviceEvent},
+    leak::LeakInfo,
     relay_list::RelayList,
     settings::Settings,
     states::TunnelState,
@@ -18,17 +19,26 @@ use mullvad_types::{
 use mullvad_types::{
     access_method::{self, AccessMethod},
     account::{AccountData, AccountNumber, VoucherSubmission},
+    audit_log::AuditEntry,
     custom_list::{CustomList, Id},
+    data_usage::{DataUsageEntry, DataUsageQuota, QuotaExceeded},
     device::{Device, DeviceId, DeviceState},
     features::FeatureIndicators,
     relay_constraints::{
         BridgeSettings, BridgeState, ObfuscationSettings, RelayOverride, RelaySettings,
     },
+    session_history::Session,
     settings::DnsOptions,
-    wireguard::{PublicKey, QuantumResistantState, RotationInterval},
+    wireguard::{PublicKey, QuantumResistantState, RekeyInterval, RotationInterval},
 };
 #[cfg(not(target_os = "android"))]
-use std::{path::Path, str::FromStr};
+use std::{path::Path, str::FromStr, time::Duration};
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::{
+    stats::TunnelStats,
+    wireguard::{ConnectivityCheckOptions, WireguardBackend},
+    Endpoint,
+};
 #[cfg(target_os = "windows")]
 use talpid_types::split_tunnel::ExcludedProcess;
 #[cfg(not(target_os = "android"))]
@@ -51,6 +61,14 @@ pub enum DaemonEvent {
     Device(DeviceEvent),
     RemoveDevice(RemoveDeviceEvent),
     NewAccessMethod(AccessMethodSetting),
+    DataQuotaExceeded(QuotaExceeded),
+    /// The MTU of the active tunnel was changed after probing the path MTU.
+    MtuChanged(u16),
+    /// Traffic was found to leak outside the tunnel.
+    LeakDetected(LeakInfo),
+    /// The active tunnel stopped receiving traffic from the relay (`true`), or started
+    /// receiving traffic again (`false`).
+    ConnectivityDegraded(bool),
 }
 
 impl TryFrom<types::daemon_event::Event> for DaemonEvent {
@@ -81,6 +99,22 @@ impl TryFrom<types::daemon_event::Event> for DaemonEvent {
                     .map(DaemonEvent::NewAccessMethod)
                     .map_err(Error::InvalidResponse)
             }
+            types::daemon_event::Event::QuotaExceeded(event) => QuotaExceeded::try_from(event)
+                .map(DaemonEvent::DataQuotaExceeded)
+                .map_err(Error::InvalidResponse),
+            types::daemon_event::Event::MtuChanged(event) => u16::try_from(event.mtu)
+                .map(DaemonEvent::MtuChanged)
+                .map_err(|_| {
+                    Error::InvalidResponse(types::FromProtobufTypeError::InvalidArgument(
+                        "invalid MTU",
+                    ))
+                }),
+            types::daemon_event::Event::LeakDetected(event) => LeakInfo::try_from(event)
+                .map(DaemonEvent::LeakDetected)
+                .map_err(Error::InvalidResponse),
+            types::daemon_event::Event::ConnectivityChanged(event) => {
+                Ok(DaemonEvent::ConnectivityDegraded(event.degraded))
+            }
         }
     }
 }
@@ -133,6 +167,77 @@ impl MullvadProxyClient {
         TunnelState::try_from(state).map_err(Error::InvalidResponse)
     }
 
+    /// Returns `None` if there is no tunnel, or if its statistics are unavailable.
+    pub async fn get_tunnel_stats(&mut self) -> Result<Option<TunnelStats>> {
+        let stats = match self.0.get_tunnel_stats(()).await {
+            Ok(stats) => stats.into_inner(),
+            Err(status) if status.code() == Code::NotFound => return Ok(None),
+            Err(status) => return Err(Error::Rpc(status)),
+        };
+        TunnelStats::try_from(stats)
+            .map(Some)
+            .map_err(Error::InvalidResponse)
+    }
+
+    /// Returns the config of the current WireGuard tunnel in the wg-quick format, or `None` if
+    /// there is no WireGuard tunnel. The private key is redacted unless `include_secrets` is set.
+    pub async fn get_wireguard_config(&mut self, include_secrets: bool) -> Result<Option<String>> {
+        match self.0.get_wireguard_config(include_secrets).await {
+            Ok(config) => Ok(Some(config.into_inner())),
+            Err(status) if status.code() == Code::NotFound => Ok(None),
+            Err(status) => Err(Error::Rpc(status)),
+        }
+    }
+
+    pub async fn tunnel_stats_listen(
+        &mut self,
+        interval: Duration,
+    ) -> Result<impl Stream<Item = Result<TunnelStats>>> {
+        let interval = types::Duration::try_from(interval).map_err(|_| Error::DurationTooLarge)?;
+        let listener = self
+            .0
+            .tunnel_stats_listen(interval)
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+
+        Ok(listener.map(|stats| {
+            TunnelStats::try_from(stats.map_err(Error::Rpc)?).map_err(Error::InvalidResponse)
+        }))
+    }
+
+    pub async fn get_data_usage(&mut self) -> Result<Vec<DataUsageEntry>> {
+        self.0
+            .get_data_usage(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner()
+            .entries
+            .into_iter()
+            .map(|entry| DataUsageEntry::try_from(entry).map_err(Error::InvalidResponse))
+            .collect()
+    }
+
+    pub async fn get_session_history(&mut self) -> Result<Vec<Session>> {
+        self.0
+            .get_session_history(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner()
+            .sessions
+            .into_iter()
+            .map(|session| Session::try_from(session).map_err(Error::InvalidResponse))
+            .collect()
+    }
+
+    pub async fn set_data_usage_quota(&mut self, quota: DataUsageQuota) -> Result<()> {
+        self.0
+            .set_data_usage_quota(types::DataUsageQuota::from(quota))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn events_listen<'a>(
         &mut self,
     ) -> Result<impl Stream<Item = Result<DaemonEvent>> + 'a> {
@@ -244,6 +349,17 @@ impl MullvadProxyClient {
             })
     }
 
+    /// Return the endpoint which the daemon connects to in order to reach the API, as resolved
+    /// from the current access method.
+    pub async fn get_current_api_endpoint(&mut self) -> Result<Endpoint> {
+        self.0
+            .get_current_api_endpoint(())
+            .await
+            .map_err(Error::Rpc)
+            .map(tonic::Response::into_inner)
+            .and_then(|endpoint| Endpoint::try_from(endpoint).map_err(Error::InvalidResponse))
+    }
+
     pub async fn test_api_access_method(&mut self, id: access_method::Id) -> Result<bool> {
         let result = self
             .0
@@ -380,6 +496,54 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_quantum_resistant_rekey_interval(
+        &mut self,
+        interval: RekeyInterval,
+    ) -> Result<()> {
+        let duration = types::Duration::try_from(*interval.as_duration())
+            .map_err(|_| Error::DurationTooLarge)?;
+        self.0
+            .set_quantum_resistant_rekey_interval(duration)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn reset_quantum_resistant_rekey_interval(&mut self) -> Result<()> {
+        self.0
+            .reset_quantum_resistant_rekey_interval(())
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_connectivity_check_options(
+        &mut self,
+        options: ConnectivityCheckOptions,
+    ) -> Result<()> {
+        self.0
+            .set_connectivity_check_options(types::ConnectivityCheckOptions::from(options))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_wireguard_backend(&mut self, backend: WireguardBackend) -> Result<()> {
+        self.0
+            .set_wireguard_backend(types::WireguardBackend::from(backend))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_wireguard_make_before_break(&mut self, value: bool) -> Result<()> {
+        self.0
+            .set_wireguard_make_before_break(value)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     #[cfg(daita)]
     pub async fn set_enable_daita(&mut self, value: bool) -> Result<()> {
         self.0.set_enable_daita(value).await.map_err(Error::Rpc)?;
@@ -395,6 +559,18 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    #[cfg(daita)]
+    pub async fn set_daita_level(
+        &mut self,
+        level: talpid_types::net::wireguard::DaitaLevel,
+    ) -> Result<()> {
+        self.0
+            .set_daita_level(u32::from(level.get()))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     #[cfg(daita)]
     pub async fn set_daita_settings(&mut self, settings: DaitaSettings) -> Result<()> {
         let settings = types::DaitaSettings::from(settings);
@@ -779,6 +955,34 @@ impl MullvadProxyClient {
         self.0.enable_relay(relay).await.map_err(Error::Rpc)?;
         Ok(())
     }
+
+    pub async fn firewall_log_listen(
+        &mut self,
+    ) -> Result<impl Stream<Item = Result<talpid_types::net::firewall_log::BlockedPacket>>> {
+        let listener = self
+            .0
+            .firewall_log_listen(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+
+        Ok(listener.map(|packet| {
+            talpid_types::net::firewall_log::BlockedPacket::try_from(packet.map_err(Error::Rpc)?)
+                .map_err(Error::InvalidResponse)
+        }))
+    }
+
+    pub async fn get_settings_audit_log(&mut self) -> Result<Vec<AuditEntry>> {
+        self.0
+            .get_settings_audit_log(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner()
+            .entries
+            .into_iter()
+            .map(|entry| AuditEntry::try_from(entry).map_err(Error::InvalidResponse))
+            .collect()
+    }
 }
 
 #[cfg(not(target_os = "android"))]
@@ -1,22 +1,29 @@
+#[cfg(target_os = "linux")]
+mod authorization;
+mod caller;
 pub mod client;
 pub mod types;
 
 use parity_tokio_ipc::Endpoint as IpcEndpoint;
 #[cfg(unix)]
 use std::{env, fs, os::unix::fs::PermissionsExt};
+use std::{future::Future, io};
+#[cfg(not(target_os = "linux"))]
 use std::{
-    future::Future,
-    io,
     pin::Pin,
     task::{Context, Poll},
 };
+#[cfg(not(target_os = "linux"))]
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
-use tonic::transport::{server::Connected, Server};
+#[cfg(not(target_os = "linux"))]
+use tonic::transport::server::Connected;
+use tonic::transport::Server;
 #[cfg(not(target_os = "android"))]
 use tonic::transport::{Endpoint, Uri};
 #[cfg(not(target_os = "android"))]
 use tower::service_fn;
 
+pub use caller::{current_caller, Caller};
 pub use tonic::{async_trait, transport::Channel, Code, Request, Response, Status};
 
 pub type ManagementServiceClient =
@@ -28,6 +35,10 @@ use std::sync::LazyLock;
 #[cfg(unix)]
 static MULLVAD_MANAGEMENT_SOCKET_GROUP: LazyLock<Option<String>> =
     LazyLock::new(|| env::var("MULLVAD_MANAGEMENT_SOCKET_GROUP").ok());
+/// If set, only root and members of this group may call methods that are not read-only.
+#[cfg(target_os = "linux")]
+static MULLVAD_MANAGEMENT_ADMIN_GROUP: LazyLock<Option<String>> =
+    LazyLock::new(|| env::var("MULLVAD_MANAGEMENT_ADMIN_GROUP").ok());
 
 pub const CUSTOM_LIST_LIST_NOT_FOUND_DETAILS: &[u8] = b"custom_list_list_not_found";
 pub const CUSTOM_LIST_LIST_EXISTS_DETAILS: &[u8] = b"custom_list_list_exists";
@@ -137,22 +148,46 @@ pub use client::MullvadProxyClient;
 
 pub type ServerJoinHandle = tokio::task::JoinHandle<()>;
 
+/// Returns whether the client that made `request` may call any method, and see secrets such as
+/// private keys and proxy credentials. Clients are only restricted on Linux, if an admin group is
+/// configured.
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+pub fn is_admin<T>(request: &Request<T>) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        authorization::is_admin(request)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        true
+    }
+}
+
 pub fn spawn_rpc_server<T: ManagementService, F: Future<Output = ()> + Send + 'static>(
     service: T,
     abort_rx: F,
     rpc_socket_path: impl AsRef<std::path::Path>,
 ) -> std::result::Result<ServerJoinHandle, Error> {
-    use futures::stream::TryStreamExt;
-    use parity_tokio_ipc::SecurityAttributes;
-
-    let mut endpoint = IpcEndpoint::new(rpc_socket_path.as_ref().to_string_lossy().to_string());
-    endpoint.set_security_attributes(
-        SecurityAttributes::allow_everyone_create()
-            .map_err(Error::SecurityAttributes)?
-            .set_mode(0o766)
-            .map_err(Error::SecurityAttributes)?,
-    );
-    let incoming = endpoint.incoming().map_err(Error::StartServerError)?;
+    // Peer credentials are only available if the socket is created by us
+    #[cfg(target_os = "linux")]
+    let incoming = authorization::Incoming::bind(rpc_socket_path.as_ref())?;
+    #[cfg(not(target_os = "linux"))]
+    let incoming = {
+        use futures::stream::TryStreamExt;
+        use parity_tokio_ipc::SecurityAttributes;
+
+        let mut endpoint = IpcEndpoint::new(rpc_socket_path.as_ref().to_string_lossy().to_string());
+        endpoint.set_security_attributes(
+            SecurityAttributes::allow_everyone_create()
+                .map_err(Error::SecurityAttributes)?
+                .set_mode(0o766)
+                .map_err(Error::SecurityAttributes)?,
+        );
+        endpoint
+            .incoming()
+            .map_err(Error::StartServerError)?
+            .map_ok(StreamBox)
+    };
 
     #[cfg(unix)]
     if let Some(group_name) = &*MULLVAD_MANAGEMENT_SOCKET_GROUP {
@@ -165,10 +200,20 @@ pub fn spawn_rpc_server<T: ManagementService, F: Future<Output = ()> + Send + 's
             .map_err(Error::PermissionsError)?;
     }
 
+    let service = caller::CallerService::new(ManagementServiceServer::new(service));
+    #[cfg(target_os = "linux")]
+    let service = {
+        let admin_group = MULLVAD_MANAGEMENT_ADMIN_GROUP
+            .as_deref()
+            .map(authorization::AdminGroup::from_name)
+            .transpose()?;
+        authorization::AuthorizedService::new(service, admin_group)
+    };
+
     Ok(tokio::spawn(async move {
         if let Err(execution_error) = Server::builder()
-            .add_service(ManagementServiceServer::new(service))
-            .serve_with_incoming_shutdown(incoming.map_ok(StreamBox), abort_rx)
+            .add_service(service)
+            .serve_with_incoming_shutdown(incoming, abort_rx)
             .await
             .map_err(Error::GrpcTransportError)
         {
@@ -178,8 +223,10 @@ pub fn spawn_rpc_server<T: ManagementService, F: Future<Output = ()> + Send + 's
     }))
 }
 
+#[cfg(not(target_os = "linux"))]
 #[derive(Debug)]
 struct StreamBox<T: AsyncRead + AsyncWrite>(pub T);
+#[cfg(not(target_os = "linux"))]
 impl<T: AsyncRead + AsyncWrite> Connected for StreamBox<T> {
     type ConnectInfo = Option<()>;
 
@@ -187,6 +234,7 @@ impl<T: AsyncRead + AsyncWrite> Connected for StreamBox<T> {
         None
     }
 }
+#[cfg(not(target_os = "linux"))]
 impl<T: AsyncRead + AsyncWrite + Unpin> AsyncRead for StreamBox<T> {
     fn poll_read(
         mut self: Pin<&mut Self>,
@@ -196,6 +244,7 @@ impl<T: AsyncRead + AsyncWrite + Unpin> AsyncRead for StreamBox<T> {
         Pin::new(&mut self.0).poll_read(cx, buf)
     }
 }
+#[cfg(not(target_os = "linux"))]
 impl<T: AsyncRead + AsyncWrite + Unpin> AsyncWrite for StreamBox<T> {
     fn poll_write(
         mut self: Pin<&mut Self>,
@@ -0,0 +1,68 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use chrono::DateTime;
+use mullvad_types::audit_log::{AuditEntry, AuditSource, SettingsChange};
+
+impl From<AuditEntry> for proto::settings_audit_log::Entry {
+    fn from(entry: AuditEntry) -> Self {
+        use proto::settings_audit_log::{entry::Source, Daemon, Migration, Rpc};
+
+        let source = match entry.source {
+            AuditSource::Rpc { method, uid, pid } => Source::Rpc(Rpc { method, uid, pid }),
+            AuditSource::Daemon => Source::Daemon(Daemon {}),
+            AuditSource::Migration => Source::Migration(Migration {}),
+        };
+        proto::settings_audit_log::Entry {
+            timestamp: Some(prost_types::Timestamp {
+                seconds: entry.timestamp.timestamp(),
+                nanos: entry.timestamp.timestamp_subsec_nanos() as i32,
+            }),
+            source: Some(source),
+            changes: entry
+                .changes
+                .into_iter()
+                .map(|change| proto::settings_audit_log::Change {
+                    path: change.path,
+                    old: change.old,
+                    new: change.new,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::settings_audit_log::Entry> for AuditEntry {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(entry: proto::settings_audit_log::Entry) -> Result<Self, Self::Error> {
+        use proto::settings_audit_log::{entry::Source, Rpc};
+
+        let timestamp = entry
+            .timestamp
+            .ok_or(FromProtobufTypeError::InvalidArgument("missing timestamp"))?;
+        let timestamp = DateTime::from_timestamp(timestamp.seconds, timestamp.nanos as u32)
+            .ok_or(FromProtobufTypeError::InvalidArgument("invalid timestamp"))?;
+        let source = match entry
+            .source
+            .ok_or(FromProtobufTypeError::InvalidArgument("missing source"))?
+        {
+            Source::Rpc(Rpc { method, uid, pid }) => AuditSource::Rpc { method, uid, pid },
+            Source::Daemon(_) => AuditSource::Daemon,
+            Source::Migration(_) => AuditSource::Migration,
+        };
+
+        Ok(AuditEntry {
+            timestamp,
+            source,
+            changes: entry
+                .changes
+                .into_iter()
+                .map(|change| SettingsChange {
+                    path: change.path,
+                    old: change.old,
+                    new: change.new,
+                })
+                .collect(),
+        })
+    }
+}
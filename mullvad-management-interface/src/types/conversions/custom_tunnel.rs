@@ -1,5 +1,5 @@
 use crate::types::{
-    conversions::{bytes_to_privkey, bytes_to_pubkey},
+    conversions::{bytes_to_privkey, bytes_to_psk, bytes_to_pubkey},
     proto, FromProtobufTypeError,
 };
 use talpid_types::net::wireguard;
@@ -47,6 +47,12 @@ impl TryFrom<proto::ConnectionConfig> for mullvad_types::ConnectionConfig {
                 ))?;
 
                 let public_key = bytes_to_pubkey(&peer.public_key)?;
+                let psk = peer.psk.as_deref().map(bytes_to_psk).transpose()?;
+                let persistent_keepalive = peer
+                    .persistent_keepalive
+                    .map(u16::try_from)
+                    .transpose()
+                    .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid keepalive"))?;
 
                 let ipv4_gateway = config.ipv4_gateway.parse().map_err(|_err| {
                     FromProtobufTypeError::InvalidArgument("invalid IPv4 gateway")
@@ -90,7 +96,8 @@ impl TryFrom<proto::ConnectionConfig> for mullvad_types::ConnectionConfig {
                             public_key,
                             allowed_ips,
                             endpoint,
-                            psk: None,
+                            psk,
+                            persistent_keepalive,
                             #[cfg(daita)]
                             constant_packet_size: false,
                         },
@@ -142,6 +149,8 @@ impl From<mullvad_types::ConnectionConfig> for proto::ConnectionConfig {
                                 .map(|address| address.to_string())
                                 .collect(),
                             endpoint: config.peer.endpoint.to_string(),
+                            psk: config.peer.psk.as_ref().map(|psk| psk.as_bytes().to_vec()),
+                            persistent_keepalive: config.peer.persistent_keepalive.map(u32::from),
                         }),
                         ipv4_gateway: config.ipv4_gateway.to_string(),
                         ipv6_gateway: config
@@ -0,0 +1,98 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use mullvad_types::data_usage::{
+    DataUsage, DataUsageEntry, DataUsageQuota, QuotaExceeded, QuotaPeriod,
+};
+
+impl From<QuotaPeriod> for proto::data_usage_quota::Period {
+    fn from(period: QuotaPeriod) -> Self {
+        match period {
+            QuotaPeriod::Day => proto::data_usage_quota::Period::Day,
+            QuotaPeriod::Month => proto::data_usage_quota::Period::Month,
+        }
+    }
+}
+
+fn try_period_from_i32(period: i32) -> Result<QuotaPeriod, FromProtobufTypeError> {
+    match proto::data_usage_quota::Period::try_from(period) {
+        Ok(proto::data_usage_quota::Period::Day) => Ok(QuotaPeriod::Day),
+        Ok(proto::data_usage_quota::Period::Month) => Ok(QuotaPeriod::Month),
+        Err(_) => Err(FromProtobufTypeError::InvalidArgument(
+            "invalid quota period",
+        )),
+    }
+}
+
+impl From<DataUsageQuota> for proto::DataUsageQuota {
+    fn from(quota: DataUsageQuota) -> Self {
+        proto::DataUsageQuota {
+            period: i32::from(proto::data_usage_quota::Period::from(quota.period)),
+            soft_limit: quota.soft_limit,
+            hard_limit: quota.hard_limit,
+        }
+    }
+}
+
+impl TryFrom<proto::DataUsageQuota> for DataUsageQuota {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(quota: proto::DataUsageQuota) -> Result<Self, Self::Error> {
+        Ok(DataUsageQuota {
+            period: try_period_from_i32(quota.period)?,
+            soft_limit: quota.soft_limit,
+            hard_limit: quota.hard_limit,
+        })
+    }
+}
+
+impl From<DataUsageEntry> for proto::data_usage::Entry {
+    fn from(entry: DataUsageEntry) -> Self {
+        proto::data_usage::Entry {
+            date: entry.date.format("%Y-%m-%d").to_string(),
+            relay: entry.relay,
+            protocol: entry.protocol,
+            tx_bytes: entry.usage.tx_bytes,
+            rx_bytes: entry.usage.rx_bytes,
+        }
+    }
+}
+
+impl TryFrom<proto::data_usage::Entry> for DataUsageEntry {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(entry: proto::data_usage::Entry) -> Result<Self, Self::Error> {
+        Ok(DataUsageEntry {
+            date: super::arg_from_str(&entry.date, "invalid date")?,
+            relay: entry.relay,
+            protocol: entry.protocol,
+            usage: DataUsage {
+                tx_bytes: entry.tx_bytes,
+                rx_bytes: entry.rx_bytes,
+            },
+        })
+    }
+}
+
+impl From<QuotaExceeded> for proto::QuotaExceeded {
+    fn from(event: QuotaExceeded) -> Self {
+        proto::QuotaExceeded {
+            period: i32::from(proto::data_usage_quota::Period::from(event.period)),
+            limit: event.limit,
+            usage: event.usage,
+            hard: event.hard,
+        }
+    }
+}
+
+impl TryFrom<proto::QuotaExceeded> for QuotaExceeded {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(event: proto::QuotaExceeded) -> Result<Self, Self::Error> {
+        Ok(QuotaExceeded {
+            period: try_period_from_i32(event.period)?,
+            limit: event.limit,
+            usage: event.usage,
+            hard: event.hard,
+        })
+    }
+}
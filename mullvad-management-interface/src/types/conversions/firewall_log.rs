@@ -0,0 +1,67 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use std::time::SystemTime;
+use talpid_types::net::firewall_log::{BlockedPacket, FirewallChain};
+
+impl From<BlockedPacket> for proto::BlockedPacket {
+    fn from(packet: BlockedPacket) -> Self {
+        let chain = match packet.chain {
+            FirewallChain::Input => proto::blocked_packet::Chain::Input,
+            FirewallChain::Output => proto::blocked_packet::Chain::Output,
+            FirewallChain::Forward => proto::blocked_packet::Chain::Forward,
+        };
+        proto::BlockedPacket {
+            timestamp: Some(prost_types::Timestamp::from(packet.timestamp)),
+            chain: i32::from(chain),
+            protocol: u32::from(packet.protocol),
+            source: packet.source.to_string(),
+            source_port: packet.source_port.map(u32::from),
+            destination: packet.destination.to_string(),
+            destination_port: packet.destination_port.map(u32::from),
+            uid: packet.uid,
+            gid: packet.gid,
+            cgroup: packet.cgroup,
+        }
+    }
+}
+
+impl TryFrom<proto::BlockedPacket> for BlockedPacket {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(packet: proto::BlockedPacket) -> Result<Self, Self::Error> {
+        let timestamp = packet
+            .timestamp
+            .ok_or(FromProtobufTypeError::InvalidArgument("missing timestamp"))?;
+        let timestamp = SystemTime::try_from(timestamp)
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid timestamp"))?;
+        let chain = match proto::blocked_packet::Chain::try_from(packet.chain) {
+            Ok(proto::blocked_packet::Chain::Input) => FirewallChain::Input,
+            Ok(proto::blocked_packet::Chain::Output) => FirewallChain::Output,
+            Ok(proto::blocked_packet::Chain::Forward) => FirewallChain::Forward,
+            Err(_) => {
+                return Err(FromProtobufTypeError::InvalidArgument(
+                    "invalid firewall chain",
+                ))
+            }
+        };
+        let port = |port: Option<u32>| {
+            port.map(u16::try_from)
+                .transpose()
+                .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid port"))
+        };
+
+        Ok(BlockedPacket {
+            timestamp,
+            chain,
+            protocol: u8::try_from(packet.protocol)
+                .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid protocol"))?,
+            source: super::arg_from_str(&packet.source, "invalid source address")?,
+            source_port: port(packet.source_port)?,
+            destination: super::arg_from_str(&packet.destination, "invalid destination address")?,
+            destination_port: port(packet.destination_port)?,
+            uid: packet.uid,
+            gid: packet.gid,
+            cgroup: packet.cgroup,
+        })
+    }
+}
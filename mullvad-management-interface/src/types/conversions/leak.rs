@@ -0,0 +1,51 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use mullvad_types::leak::LeakInfo;
+
+impl From<LeakInfo> for proto::LeakDetected {
+    fn from(info: LeakInfo) -> Self {
+        use proto::leak_detected::{AmIMullvad, Leak, NodeReachableOnInterface};
+
+        let leak = match info {
+            LeakInfo::NodeReachableOnInterface {
+                interface,
+                reachable_nodes,
+            } => Leak::NodeReachableOnInterface(NodeReachableOnInterface {
+                interface,
+                reachable_nodes: reachable_nodes
+                    .into_iter()
+                    .map(|node| node.to_string())
+                    .collect(),
+            }),
+            LeakInfo::AmIMullvad { ip } => Leak::AmIMullvad(AmIMullvad { ip: ip.to_string() }),
+        };
+        proto::LeakDetected { leak: Some(leak) }
+    }
+}
+
+impl TryFrom<proto::LeakDetected> for LeakInfo {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(event: proto::LeakDetected) -> Result<Self, Self::Error> {
+        use proto::leak_detected::{AmIMullvad, Leak, NodeReachableOnInterface};
+
+        match event
+            .leak
+            .ok_or(FromProtobufTypeError::InvalidArgument("missing leak"))?
+        {
+            Leak::NodeReachableOnInterface(NodeReachableOnInterface {
+                interface,
+                reachable_nodes,
+            }) => Ok(LeakInfo::NodeReachableOnInterface {
+                interface,
+                reachable_nodes: reachable_nodes
+                    .iter()
+                    .map(|node| super::arg_from_str(node, "invalid reachable node"))
+                    .collect::<Result<_, _>>()?,
+            }),
+            Leak::AmIMullvad(AmIMullvad { ip }) => Ok(LeakInfo::AmIMullvad {
+                ip: super::arg_from_str(&ip, "invalid IP address")?,
+            }),
+        }
+    }
+}
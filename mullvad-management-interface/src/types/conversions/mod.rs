@@ -2,18 +2,24 @@ use std::str::FromStr;
 
 mod access_method;
 mod account;
+mod audit_log;
 mod custom_list;
 mod custom_tunnel;
+mod data_usage;
 mod device;
 mod features;
+mod firewall_log;
+mod leak;
 mod location;
 mod net;
 pub mod relay_constraints;
 mod relay_list;
+mod session_history;
 mod settings;
 #[cfg(target_os = "windows")]
 mod split_tunnel;
 mod states;
+mod stats;
 mod version;
 mod wireguard;
 
@@ -39,6 +45,14 @@ fn bytes_to_privkey(
     ))
 }
 
+fn bytes_to_psk(
+    bytes: &[u8],
+) -> Result<talpid_types::net::wireguard::PresharedKey, FromProtobufTypeError> {
+    Ok(talpid_types::net::wireguard::PresharedKey::from(
+        *bytes_to_wg_key(bytes, "invalid preshared key")?,
+    ))
+}
+
 fn bytes_to_wg_key<'a>(
     bytes: &'a [u8],
     error_msg: &'static str,
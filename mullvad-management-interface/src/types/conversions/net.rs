@@ -49,13 +49,27 @@ impl From<talpid_types::net::TunnelEndpoint> for proto::TunnelEndpoint {
                 address: entry.address.to_string(),
                 protocol: i32::from(proto::TransportProtocol::from(entry.protocol)),
             }),
-            tunnel_metadata: endpoint
-                .tunnel_interface
-                .map(|tunnel_interface| proto::TunnelMetadata { tunnel_interface }),
+            tunnel_metadata: endpoint.tunnel_interface.map(|tunnel_interface| {
+                proto::TunnelMetadata {
+                    tunnel_interface,
+                    tunnel_ips: endpoint
+                        .tunnel_ips
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect(),
+                }
+            }),
             #[cfg(daita)]
             daita: endpoint.daita,
             #[cfg(not(daita))]
             daita: false,
+            #[cfg(daita)]
+            daita_parameters: endpoint.daita_parameters.map(proto::DaitaParameters::from),
+            #[cfg(not(daita))]
+            daita_parameters: None,
+            wireguard_backend: endpoint
+                .wireguard_backend
+                .map(proto::WireguardBackend::from),
         }
     }
 }
@@ -147,11 +161,46 @@ impl TryFrom<proto::TunnelEndpoint> for talpid_types::net::TunnelEndpoint {
                     })
                 })
                 .transpose()?,
+            tunnel_ips: endpoint
+                .tunnel_metadata
+                .iter()
+                .flat_map(|tunnel_metadata| &tunnel_metadata.tunnel_ips)
+                .map(|ip| arg_from_str(ip, "invalid tunnel IP"))
+                .collect::<Result<_, _>>()?,
             tunnel_interface: endpoint
                 .tunnel_metadata
                 .map(|tunnel_metadata| tunnel_metadata.tunnel_interface),
             #[cfg(daita)]
             daita: endpoint.daita,
+            #[cfg(daita)]
+            daita_parameters: endpoint
+                .daita_parameters
+                .map(talpid_types::net::wireguard::DaitaParameters::try_from)
+                .transpose()?,
+            wireguard_backend: endpoint
+                .wireguard_backend
+                .map(talpid_types::net::wireguard::WireguardBackend::try_from)
+                .transpose()?,
+        })
+    }
+}
+
+impl From<talpid_types::net::Endpoint> for proto::Endpoint {
+    fn from(endpoint: talpid_types::net::Endpoint) -> Self {
+        proto::Endpoint {
+            address: endpoint.address.to_string(),
+            protocol: i32::from(proto::TransportProtocol::from(endpoint.protocol)),
+        }
+    }
+}
+
+impl TryFrom<proto::Endpoint> for talpid_types::net::Endpoint {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(endpoint: proto::Endpoint) -> Result<Self, Self::Error> {
+        Ok(talpid_types::net::Endpoint {
+            address: arg_from_str(&endpoint.address, "invalid endpoint address")?,
+            protocol: try_transport_protocol_from_i32(endpoint.protocol)?,
         })
     }
 }
@@ -213,11 +262,12 @@ pub fn try_transport_protocol_from_i32(
 }
 
 mod proxy {
-    use std::net::Ipv4Addr;
+    use std::net::{IpAddr, Ipv4Addr};
 
     use crate::types::{proto, FromProtobufTypeError};
     use talpid_types::net::proxy::{
-        CustomProxy, Shadowsocks, Socks5Local, Socks5Remote, SocksAuth,
+        CustomProxy, HttpAuth, HttpConnect, HttpProxyTls, ProxyAddress, ProxyHost, Shadowsocks,
+        Socks5Local, Socks5Remote, SocksAuth,
     };
 
     impl TryFrom<proto::CustomProxy> for CustomProxy {
@@ -234,6 +284,9 @@ mod proxy {
                 Some(proto::custom_proxy::ProxyMethod::Shadowsocks(shadowsocks)) => {
                     CustomProxy::Shadowsocks(Shadowsocks::try_from(shadowsocks)?)
                 }
+                Some(proto::custom_proxy::ProxyMethod::HttpConnect(http)) => {
+                    CustomProxy::HttpConnect(HttpConnect::try_from(http)?)
+                }
                 None => {
                     return Err(FromProtobufTypeError::InvalidArgument(
                         "CustomProxy missing proxy_method field",
@@ -265,21 +318,24 @@ mod proxy {
         type Error = FromProtobufTypeError;
 
         fn try_from(value: proto::Socks5Remote) -> Result<Self, Self::Error> {
-            let ip = value.ip.parse::<Ipv4Addr>().map_err(|_| {
-                FromProtobufTypeError::InvalidArgument(
-                    "Could not parse Socks5 (remote) message from protobuf",
-                )
-            })?;
-            let port = value.port as u16;
-            let socks = match value.auth {
+            let host = match value.hostname {
+                Some(hostname) => try_hostname_from_proto(hostname)?,
+                None => ProxyHost::Ip(IpAddr::from(value.ip.parse::<Ipv4Addr>().map_err(
+                    |_| {
+                        FromProtobufTypeError::InvalidArgument(
+                            "Could not parse Socks5 (remote) message from protobuf",
+                        )
+                    },
+                )?)),
+            };
+            let endpoint = ProxyAddress::new(host, value.port as u16);
+            Ok(match value.auth {
                 Some(credentials) => {
                     let auth = SocksAuth::try_from(credentials)?;
-                    Socks5Remote::new_with_authentication((ip, port), auth)
+                    Socks5Remote::new_with_authentication(endpoint, auth)
                 }
-                None => Socks5Remote::new((ip, port)),
-            };
-
-            Ok(socks)
+                None => Socks5Remote::new(endpoint),
+            })
         }
     }
 
@@ -287,17 +343,66 @@ mod proxy {
         type Error = FromProtobufTypeError;
 
         fn try_from(value: proto::Shadowsocks) -> Result<Self, Self::Error> {
-            let ip = value.ip.parse::<Ipv4Addr>().map_err(|_| {
-                FromProtobufTypeError::InvalidArgument(
-                    "Could not parse Socks5 (remote) message from protobuf",
-                )
-            })?;
+            let host = match value.hostname {
+                Some(hostname) => try_hostname_from_proto(hostname)?,
+                None => ProxyHost::Ip(IpAddr::from(value.ip.parse::<Ipv4Addr>().map_err(
+                    |_| {
+                        FromProtobufTypeError::InvalidArgument(
+                            "Could not parse Socks5 (remote) message from protobuf",
+                        )
+                    },
+                )?)),
+            };
 
-            Ok(Shadowsocks::new(
-                (ip, value.port as u16),
+            Shadowsocks::new(
+                ProxyAddress::new(host, value.port as u16),
                 value.cipher,
                 value.password,
-            ))
+            )
+            .map_err(|_| {
+                FromProtobufTypeError::InvalidArgument(
+                    "Failed to parse Shadowsocks message from protobuf. \
+                         Make sure the password is a valid key for the cipher.",
+                )
+            })
+        }
+    }
+
+    impl TryFrom<proto::HttpConnect> for HttpConnect {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::HttpConnect) -> Result<Self, Self::Error> {
+            let host = match value.hostname {
+                Some(hostname) => try_hostname_from_proto(hostname)?,
+                None => ProxyHost::Ip(value.ip.parse::<IpAddr>().map_err(|_| {
+                    FromProtobufTypeError::InvalidArgument(
+                        "Could not parse HTTP CONNECT proxy message from protobuf",
+                    )
+                })?),
+            };
+
+            Ok(HttpConnect {
+                endpoint: ProxyAddress::new(host, value.port as u16),
+                auth: value.auth.map(HttpAuth::try_from).transpose()?,
+                tls: value.tls.map(|tls| HttpProxyTls {
+                    server_name: tls.server_name,
+                }),
+            })
+        }
+    }
+
+    fn try_hostname_from_proto(hostname: String) -> Result<ProxyHost, FromProtobufTypeError> {
+        hostname.parse().map_err(|_| {
+            FromProtobufTypeError::InvalidArgument("Invalid hostname of proxy in protobuf")
+        })
+    }
+
+    /// Split the host of a proxy into the `ip` and `hostname` fields of its protobuf message. The
+    /// `ip` field is left empty for proxies given by hostname.
+    fn proxy_host_to_proto(host: ProxyHost) -> (String, Option<String>) {
+        match host {
+            ProxyHost::Ip(ip) => (ip.to_string(), None),
+            ProxyHost::Hostname(hostname) => (String::new(), Some(hostname)),
         }
     }
 
@@ -320,6 +425,11 @@ mod proxy {
                             config,
                         ))
                     }
+                    CustomProxy::HttpConnect(config) => {
+                        proto::custom_proxy::ProxyMethod::HttpConnect(proto::HttpConnect::from(
+                            config,
+                        ))
+                    }
                 }),
             }
         }
@@ -327,11 +437,13 @@ mod proxy {
 
     impl From<Shadowsocks> for proto::Shadowsocks {
         fn from(value: Shadowsocks) -> Self {
+            let (ip, hostname) = proxy_host_to_proto(value.endpoint.host);
             proto::Shadowsocks {
-                ip: value.endpoint.ip().to_string(),
-                port: value.endpoint.port() as u32,
+                ip,
+                port: u32::from(value.endpoint.port),
                 password: value.password,
                 cipher: value.cipher,
+                hostname,
             }
         }
     }
@@ -351,10 +463,27 @@ mod proxy {
 
     impl From<Socks5Remote> for proto::Socks5Remote {
         fn from(value: Socks5Remote) -> Self {
+            let (ip, hostname) = proxy_host_to_proto(value.endpoint.host);
             proto::Socks5Remote {
-                ip: value.endpoint.ip().to_string(),
-                port: value.endpoint.port() as u32,
+                ip,
+                port: u32::from(value.endpoint.port),
                 auth: value.auth.map(proto::SocksAuth::from),
+                hostname,
+            }
+        }
+    }
+
+    impl From<HttpConnect> for proto::HttpConnect {
+        fn from(value: HttpConnect) -> Self {
+            let (ip, hostname) = proxy_host_to_proto(value.endpoint.host);
+            proto::HttpConnect {
+                ip,
+                port: u32::from(value.endpoint.port),
+                auth: value.auth.map(proto::HttpAuth::from),
+                tls: value.tls.map(|tls| proto::HttpProxyTls {
+                    server_name: tls.server_name,
+                }),
+                hostname,
             }
         }
     }
@@ -380,4 +509,82 @@ mod proxy {
             })
         }
     }
+
+    impl From<HttpAuth> for proto::HttpAuth {
+        fn from(value: HttpAuth) -> Self {
+            proto::HttpAuth {
+                username: value.username().to_string(),
+                password: value.password().to_string(),
+            }
+        }
+    }
+
+    impl TryFrom<proto::HttpAuth> for HttpAuth {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::HttpAuth) -> Result<Self, Self::Error> {
+            HttpAuth::new(value.username, value.password).map_err(|_| {
+                FromProtobufTypeError::InvalidArgument(
+                    "Failed to parse HTTP CONNECT proxy with authentication. \
+                     Make sure the credentials are valid.",
+                )
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_custom_proxy_round_trip() {
+            let auth = SocksAuth::new("user".to_owned(), "hunter2".to_owned()).unwrap();
+            let http_auth = HttpAuth::new("user".to_owned(), "hunter2".to_owned()).unwrap();
+            let proxies = [
+                CustomProxy::from(Socks5Remote::new(([192, 0, 2, 1], 1080))),
+                CustomProxy::from(Socks5Remote::new_with_authentication(
+                    "proxy.example.com:1080".parse::<ProxyAddress>().unwrap(),
+                    auth,
+                )),
+                CustomProxy::from(
+                    Shadowsocks::new(
+                        "proxy.example.com:443".parse::<ProxyAddress>().unwrap(),
+                        "aes-256-gcm".to_owned(),
+                        "hunter2".to_owned(),
+                    )
+                    .unwrap(),
+                ),
+                CustomProxy::from(HttpConnect::new(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1], 8080))),
+                CustomProxy::from(HttpConnect {
+                    endpoint: "proxy.example.com:443".parse().unwrap(),
+                    auth: Some(http_auth),
+                    tls: Some(HttpProxyTls { server_name: None }),
+                }),
+            ];
+            for proxy in proxies {
+                let message = proto::CustomProxy::from(proxy.clone());
+                assert_eq!(CustomProxy::try_from(message).unwrap(), proxy);
+            }
+        }
+
+        #[test]
+        fn test_proxy_host_from_proto() {
+            let message = proto::Socks5Remote::from(Socks5Remote::new(
+                "proxy.example.com:1080".parse::<ProxyAddress>().unwrap(),
+            ));
+            assert_eq!(message.ip, "");
+            assert_eq!(message.hostname.as_deref(), Some("proxy.example.com"));
+
+            let invalid = proto::Socks5Remote {
+                hostname: Some("proxy..example.com".to_owned()),
+                ..message.clone()
+            };
+            assert!(Socks5Remote::try_from(invalid).is_err());
+            let missing = proto::Socks5Remote {
+                hostname: None,
+                ..message
+            };
+            assert!(Socks5Remote::try_from(missing).is_err());
+        }
+    }
 }
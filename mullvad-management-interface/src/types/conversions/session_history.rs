@@ -0,0 +1,121 @@
+use super::{net::try_tunnel_type_from_i32, FromProtobufTypeError};
+use crate::types::proto;
+use chrono::{DateTime, Utc};
+use mullvad_types::{
+    data_usage::DataUsage,
+    session_history::{Session, SessionEndReason},
+};
+use talpid_types::{
+    net::{ObfuscationType, TunnelType},
+    tunnel::ErrorState,
+};
+
+impl From<Session> for proto::session_history::Session {
+    fn from(session: Session) -> Self {
+        use proto::session_history::{session::EndReason, Disconnected, Interrupted};
+
+        let end_reason = session.end_reason.map(|reason| match reason {
+            SessionEndReason::Disconnected => EndReason::Disconnected(Disconnected {}),
+            SessionEndReason::Error(cause) => {
+                EndReason::Error(proto::ErrorState::from(ErrorState::new(cause, None)))
+            }
+            SessionEndReason::Interrupted => EndReason::Interrupted(Interrupted {}),
+        });
+        let obfuscation = session.obfuscation.map(|obfuscation| match obfuscation {
+            ObfuscationType::Udp2Tcp => {
+                i32::from(proto::obfuscation_endpoint::ObfuscationType::Udp2tcp)
+            }
+            ObfuscationType::Shadowsocks => {
+                i32::from(proto::obfuscation_endpoint::ObfuscationType::Shadowsocks)
+            }
+            ObfuscationType::Quic => i32::from(proto::obfuscation_endpoint::ObfuscationType::Quic),
+        });
+
+        proto::session_history::Session {
+            start: Some(to_timestamp(session.start)),
+            end: session.end.map(to_timestamp),
+            relay: session.relay,
+            country: session.country,
+            city: session.city,
+            tunnel_type: match session.tunnel_type {
+                TunnelType::Wireguard => i32::from(proto::TunnelType::Wireguard),
+                TunnelType::OpenVpn => i32::from(proto::TunnelType::Openvpn),
+            },
+            obfuscation,
+            connect_attempts: session.connect_attempts,
+            end_reason,
+            tx_bytes: session.usage.tx_bytes,
+            rx_bytes: session.usage.rx_bytes,
+        }
+    }
+}
+
+impl TryFrom<proto::session_history::Session> for Session {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(session: proto::session_history::Session) -> Result<Self, Self::Error> {
+        use proto::session_history::session::EndReason;
+
+        let end_reason = match session.end_reason {
+            Some(EndReason::Disconnected(_)) => Some(SessionEndReason::Disconnected),
+            Some(EndReason::Error(error_state)) => Some(SessionEndReason::Error(
+                ErrorState::try_from(error_state)?.cause().clone(),
+            )),
+            Some(EndReason::Interrupted(_)) => Some(SessionEndReason::Interrupted),
+            None => None,
+        };
+        let obfuscation = session
+            .obfuscation
+            .map(|obfuscation| {
+                match proto::obfuscation_endpoint::ObfuscationType::try_from(obfuscation) {
+                    Ok(proto::obfuscation_endpoint::ObfuscationType::Udp2tcp) => {
+                        Ok(ObfuscationType::Udp2Tcp)
+                    }
+                    Ok(proto::obfuscation_endpoint::ObfuscationType::Shadowsocks) => {
+                        Ok(ObfuscationType::Shadowsocks)
+                    }
+                    Ok(proto::obfuscation_endpoint::ObfuscationType::Quic) => {
+                        Ok(ObfuscationType::Quic)
+                    }
+                    Err(_) => Err(FromProtobufTypeError::InvalidArgument(
+                        "unknown obfuscation type",
+                    )),
+                }
+            })
+            .transpose()?;
+
+        Ok(Session {
+            start: try_from_timestamp(
+                session
+                    .start
+                    .ok_or(FromProtobufTypeError::InvalidArgument("missing start time"))?,
+            )?,
+            end: session.end.map(try_from_timestamp).transpose()?,
+            relay: session.relay,
+            country: session.country,
+            city: session.city,
+            tunnel_type: try_tunnel_type_from_i32(session.tunnel_type)?,
+            obfuscation,
+            connect_attempts: session.connect_attempts,
+            end_reason,
+            usage: DataUsage {
+                tx_bytes: session.tx_bytes,
+                rx_bytes: session.rx_bytes,
+            },
+        })
+    }
+}
+
+fn to_timestamp(time: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+fn try_from_timestamp(
+    timestamp: prost_types::Timestamp,
+) -> Result<DateTime<Utc>, FromProtobufTypeError> {
+    DateTime::from_timestamp(timestamp.seconds, timestamp.nanos as u32)
+        .ok_or(FromProtobufTypeError::InvalidArgument("invalid timestamp"))
+}
@@ -57,6 +57,7 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
                 .cloned()
                 .map(proto::RelayOverride::from)
                 .collect(),
+            data_usage_quota: Some(proto::DataUsageQuota::from(settings.data_usage_quota)),
         }
     }
 }
@@ -103,10 +104,19 @@ impl From<&mullvad_types::settings::TunnelOptions> for proto::TunnelOptions {
                         .expect("Failed to convert std::time::Duration to prost_types::Duration for tunnel_options.wireguard.rotation_interval")
                 }),
                 quantum_resistant: Some(proto::QuantumResistantState::from(options.wireguard.quantum_resistant)),
+                quantum_resistant_rekey_interval: options.wireguard.quantum_resistant_rekey_interval.map(|ivl| {
+                    prost_types::Duration::try_from(std::time::Duration::from(ivl))
+                        .expect("Failed to convert std::time::Duration to prost_types::Duration for tunnel_options.wireguard.quantum_resistant_rekey_interval")
+                }),
                 #[cfg(daita)]
                 daita: Some(proto::DaitaSettings::from(options.wireguard.daita.clone())),
                 #[cfg(not(daita))]
                 daita: None,
+                connectivity: Some(proto::ConnectivityCheckOptions::from(
+                    options.wireguard.connectivity,
+                )),
+                backend: Some(proto::WireguardBackend::from(options.wireguard.backend)),
+                make_before_break: options.wireguard.make_before_break,
             }),
             generic: Some(proto::tunnel_options::GenericOptions {
                 enable_ipv6: options.generic.enable_ipv6,
@@ -202,6 +212,12 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
             api_access_methods: mullvad_types::access_method::Settings::try_from(
                 api_access_methods_settings,
             )?,
+            // Settings from older daemons lack a quota, which is the same as having none
+            data_usage_quota: settings
+                .data_usage_quota
+                .map(mullvad_types::data_usage::DataUsageQuota::try_from)
+                .transpose()?
+                .unwrap_or_default(),
         })
     }
 }
@@ -281,6 +297,20 @@ impl TryFrom<proto::TunnelOptions> for mullvad_types::settings::TunnelOptions {
                         );
                         FromProtobufTypeError::InvalidArgument("invalid rotation interval")
                     })?,
+                quantum_resistant_rekey_interval: wireguard_options
+                    .quantum_resistant_rekey_interval
+                    .map(std::time::Duration::try_from)
+                    .transpose()
+                    .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid duration"))?
+                    .map(mullvad_types::wireguard::RekeyInterval::try_from)
+                    .transpose()
+                    .map_err(|error: mullvad_types::wireguard::RekeyIntervalError| {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Invalid re-key interval")
+                        );
+                        FromProtobufTypeError::InvalidArgument("invalid re-key interval")
+                    })?,
                 quantum_resistant: wireguard_options
                     .quantum_resistant
                     .map(mullvad_types::wireguard::QuantumResistantState::try_from)
@@ -290,10 +320,21 @@ impl TryFrom<proto::TunnelOptions> for mullvad_types::settings::TunnelOptions {
                 #[cfg(daita)]
                 daita: wireguard_options
                     .daita
-                    .map(mullvad_types::wireguard::DaitaSettings::from)
+                    .map(mullvad_types::wireguard::DaitaSettings::try_from)
                     .ok_or(FromProtobufTypeError::InvalidArgument(
                         "missing daita settings",
-                    ))?,
+                    ))??,
+                connectivity: wireguard_options
+                    .connectivity
+                    .map(net::wireguard::ConnectivityCheckOptions::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                backend: wireguard_options
+                    .backend
+                    .map(net::wireguard::WireguardBackend::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                make_before_break: wireguard_options.make_before_break,
             },
             generic: net::GenericTunnelOptions {
                 enable_ipv6: generic_options.enable_ipv6,
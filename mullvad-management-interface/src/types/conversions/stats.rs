@@ -0,0 +1,116 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use std::time::{Duration, SystemTime};
+use talpid_types::net::stats::{ObfuscatorStats, PeerStats, TunnelStats};
+
+impl From<TunnelStats> for proto::TunnelStats {
+    fn from(stats: TunnelStats) -> Self {
+        proto::TunnelStats {
+            timestamp: Some(prost_types::Timestamp::from(stats.timestamp)),
+            peers: stats
+                .peers
+                .into_iter()
+                .map(proto::tunnel_stats::Peer::from)
+                .collect(),
+            ping_rtt_upper_bound: stats.ping_rtt_upper_bound.and_then(|rtt| {
+                prost_types::Duration::try_from(rtt)
+                    .inspect_err(|_| log::error!("Ping RTT is out of range: {rtt:?}"))
+                    .ok()
+            }),
+            obfuscator: stats.obfuscator.map(proto::tunnel_stats::Obfuscator::from),
+        }
+    }
+}
+
+impl From<PeerStats> for proto::tunnel_stats::Peer {
+    fn from(peer: PeerStats) -> Self {
+        proto::tunnel_stats::Peer {
+            public_key: peer.public_key.as_bytes().to_vec(),
+            tx_bytes: peer.tx_bytes,
+            rx_bytes: peer.rx_bytes,
+            last_handshake: peer.last_handshake.map(prost_types::Timestamp::from),
+            tx_rate: peer.tx_rate,
+            rx_rate: peer.rx_rate,
+        }
+    }
+}
+
+impl From<ObfuscatorStats> for proto::tunnel_stats::Obfuscator {
+    fn from(stats: ObfuscatorStats) -> Self {
+        proto::tunnel_stats::Obfuscator {
+            rx_packets: stats.rx_packets,
+            tx_packets: stats.tx_packets,
+            rx_bytes: stats.rx_bytes,
+            tx_bytes: stats.tx_bytes,
+            fragmented_rx_packets: stats.fragmented_rx_packets,
+            fragmented_tx_packets: stats.fragmented_tx_packets,
+            fragmented_rx_bytes: stats.fragmented_rx_bytes,
+            fragmented_tx_bytes: stats.fragmented_tx_bytes,
+        }
+    }
+}
+
+impl TryFrom<proto::TunnelStats> for TunnelStats {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(stats: proto::TunnelStats) -> Result<Self, Self::Error> {
+        let timestamp = stats
+            .timestamp
+            .ok_or(FromProtobufTypeError::InvalidArgument("missing timestamp"))
+            .and_then(timestamp_from_proto)?;
+        let peers = stats
+            .peers
+            .into_iter()
+            .map(PeerStats::try_from)
+            .collect::<Result<_, _>>()?;
+        let ping_rtt_upper_bound = stats
+            .ping_rtt_upper_bound
+            .map(Duration::try_from)
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid ping RTT"))?;
+
+        Ok(TunnelStats {
+            timestamp,
+            peers,
+            ping_rtt_upper_bound,
+            obfuscator: stats.obfuscator.map(ObfuscatorStats::from),
+        })
+    }
+}
+
+impl TryFrom<proto::tunnel_stats::Peer> for PeerStats {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(peer: proto::tunnel_stats::Peer) -> Result<Self, Self::Error> {
+        Ok(PeerStats {
+            public_key: super::bytes_to_pubkey(&peer.public_key)?,
+            tx_bytes: peer.tx_bytes,
+            rx_bytes: peer.rx_bytes,
+            last_handshake: peer.last_handshake.map(timestamp_from_proto).transpose()?,
+            tx_rate: peer.tx_rate,
+            rx_rate: peer.rx_rate,
+        })
+    }
+}
+
+impl From<proto::tunnel_stats::Obfuscator> for ObfuscatorStats {
+    fn from(stats: proto::tunnel_stats::Obfuscator) -> Self {
+        ObfuscatorStats {
+            rx_packets: stats.rx_packets,
+            tx_packets: stats.tx_packets,
+            rx_bytes: stats.rx_bytes,
+            tx_bytes: stats.tx_bytes,
+            fragmented_rx_packets: stats.fragmented_rx_packets,
+            fragmented_tx_packets: stats.fragmented_tx_packets,
+            fragmented_rx_bytes: stats.fragmented_rx_bytes,
+            fragmented_tx_bytes: stats.fragmented_tx_bytes,
+        }
+    }
+}
+
+fn timestamp_from_proto(
+    timestamp: prost_types::Timestamp,
+) -> Result<SystemTime, FromProtobufTypeError> {
+    SystemTime::try_from(timestamp)
+        .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid timestamp"))
+}
@@ -73,22 +73,150 @@ impl TryFrom<proto::QuantumResistantState> for mullvad_types::wireguard::Quantum
     }
 }
 
+impl From<talpid_types::net::wireguard::WireguardBackend> for proto::WireguardBackend {
+    fn from(backend: talpid_types::net::wireguard::WireguardBackend) -> Self {
+        use proto::wireguard_backend::Backend;
+        use talpid_types::net::wireguard::WireguardBackend;
+
+        let backend = match backend {
+            WireguardBackend::Auto => Backend::Auto,
+            WireguardBackend::Kernel => Backend::Kernel,
+            WireguardBackend::WireguardGo => Backend::WireguardGo,
+            WireguardBackend::Boringtun => Backend::Boringtun,
+        };
+        proto::WireguardBackend {
+            backend: i32::from(backend),
+        }
+    }
+}
+
+impl TryFrom<proto::WireguardBackend> for talpid_types::net::wireguard::WireguardBackend {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(backend: proto::WireguardBackend) -> Result<Self, Self::Error> {
+        use proto::wireguard_backend::Backend;
+        use talpid_types::net::wireguard::WireguardBackend;
+
+        match Backend::try_from(backend.backend) {
+            Ok(Backend::Auto) => Ok(WireguardBackend::Auto),
+            Ok(Backend::Kernel) => Ok(WireguardBackend::Kernel),
+            Ok(Backend::WireguardGo) => Ok(WireguardBackend::WireguardGo),
+            Ok(Backend::Boringtun) => Ok(WireguardBackend::Boringtun),
+            Err(_) => Err(FromProtobufTypeError::InvalidArgument(
+                "invalid WireGuard backend",
+            )),
+        }
+    }
+}
+
 #[cfg(daita)]
 impl From<mullvad_types::wireguard::DaitaSettings> for proto::DaitaSettings {
     fn from(settings: mullvad_types::wireguard::DaitaSettings) -> Self {
         proto::DaitaSettings {
             enabled: settings.enabled,
             direct_only: !settings.use_multihop_if_necessary,
+            level: u32::from(settings.level.get()),
         }
     }
 }
 
 #[cfg(daita)]
-impl From<proto::DaitaSettings> for mullvad_types::wireguard::DaitaSettings {
-    fn from(settings: proto::DaitaSettings) -> Self {
-        mullvad_types::wireguard::DaitaSettings {
+impl TryFrom<proto::DaitaSettings> for mullvad_types::wireguard::DaitaSettings {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: proto::DaitaSettings) -> Result<Self, Self::Error> {
+        Ok(mullvad_types::wireguard::DaitaSettings {
             enabled: settings.enabled,
             use_multihop_if_necessary: !settings.direct_only,
+            level: try_daita_level_from_u32(settings.level)?,
+        })
+    }
+}
+
+impl From<talpid_types::net::wireguard::DaitaParameters> for proto::DaitaParameters {
+    fn from(parameters: talpid_types::net::wireguard::DaitaParameters) -> Self {
+        proto::DaitaParameters {
+            level: u32::from(parameters.level.get()),
+            max_padding_frac: parameters.max_padding_frac,
+            max_blocking_frac: parameters.max_blocking_frac,
+        }
+    }
+}
+
+impl TryFrom<proto::DaitaParameters> for talpid_types::net::wireguard::DaitaParameters {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(parameters: proto::DaitaParameters) -> Result<Self, Self::Error> {
+        let parameters = talpid_types::net::wireguard::DaitaParameters {
+            level: try_daita_level_from_u32(parameters.level)?,
+            max_padding_frac: parameters.max_padding_frac,
+            max_blocking_frac: parameters.max_blocking_frac,
+        };
+        if !parameters.is_valid() {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "invalid DAITA parameters",
+            ));
         }
+        Ok(parameters)
+    }
+}
+
+fn try_daita_level_from_u32(
+    level: u32,
+) -> Result<talpid_types::net::wireguard::DaitaLevel, FromProtobufTypeError> {
+    u8::try_from(level)
+        .ok()
+        .and_then(talpid_types::net::wireguard::DaitaLevel::new)
+        .ok_or(FromProtobufTypeError::InvalidArgument(
+            "invalid DAITA level",
+        ))
+}
+
+impl From<talpid_types::net::wireguard::ConnectivityCheckOptions>
+    for proto::ConnectivityCheckOptions
+{
+    fn from(options: talpid_types::net::wireguard::ConnectivityCheckOptions) -> Self {
+        let duration = |duration: std::time::Duration| {
+            Some(
+                prost_types::Duration::try_from(duration)
+                    .expect("Failed to convert std::time::Duration to prost_types::Duration"),
+            )
+        };
+        proto::ConnectivityCheckOptions {
+            rx_timeout: duration(options.rx_timeout),
+            traffic_timeout: duration(options.traffic_timeout),
+            ping_timeout: duration(options.ping_timeout),
+            ping_interval: duration(options.ping_interval),
+            establish_timeout: duration(options.establish_timeout),
+            ping_target: options.ping_target.map(|addr| addr.to_string()),
+        }
+    }
+}
+
+impl TryFrom<proto::ConnectivityCheckOptions>
+    for talpid_types::net::wireguard::ConnectivityCheckOptions
+{
+    type Error = FromProtobufTypeError;
+
+    fn try_from(options: proto::ConnectivityCheckOptions) -> Result<Self, Self::Error> {
+        let duration = |duration: Option<prost_types::Duration>, missing_msg: &'static str| {
+            duration
+                .ok_or(FromProtobufTypeError::InvalidArgument(missing_msg))
+                .and_then(|duration| {
+                    std::time::Duration::try_from(duration)
+                        .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid duration"))
+                })
+        };
+        Ok(talpid_types::net::wireguard::ConnectivityCheckOptions {
+            rx_timeout: duration(options.rx_timeout, "missing rx timeout")?,
+            traffic_timeout: duration(options.traffic_timeout, "missing traffic timeout")?,
+            ping_timeout: duration(options.ping_timeout, "missing ping timeout")?,
+            ping_interval: duration(options.ping_interval, "missing ping interval")?,
+            establish_timeout: duration(options.establish_timeout, "missing establish timeout")?,
+            ping_target: options
+                .ping_target
+                .map(|addr| super::arg_from_str(&addr, "invalid ping target"))
+                .transpose()?,
+        })
     }
 }
@@ -149,6 +149,10 @@ pub struct ClientConfig {
     /// Set the authorization header to use in the CONNECT-UDP request.
     #[builder(default)]
     pub auth_header: Option<String>,
+
+    /// Traffic counters to update. Pass a shared instance to read them while the client runs.
+    #[builder(default)]
+    pub stats: Arc<Stats>,
 }
 
 impl Client {
@@ -204,7 +208,7 @@ impl Client {
             request_stream,
             _send_stream: send_stream,
             max_udp_payload_size,
-            stats: Arc::default(),
+            stats: config.stats,
         })
     }
 
@@ -4,7 +4,7 @@ use std::net::SocketAddr;
 pub mod client;
 mod fragment;
 pub mod server;
-mod stats;
+pub mod stats;
 
 pub const MASQUE_WELL_KNOWN_PATH: &str = "/.well-known/masque/udp/";
 
@@ -1,5 +1,6 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// Traffic counters of a proxy connection.
 #[derive(Debug, Default)]
 pub struct Stats {
     rx_packets: AtomicUsize,
@@ -15,6 +16,22 @@ pub struct Stats {
     fragmented_rx_packets: AtomicUsize,
 }
 
+/// A point-in-time copy of [`Stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub rx_packets: usize,
+    pub tx_packets: usize,
+
+    pub rx_bytes: usize,
+    pub tx_bytes: usize,
+
+    pub fragmented_tx_bytes: usize,
+    pub fragmented_rx_bytes: usize,
+
+    pub fragmented_tx_packets: usize,
+    pub fragmented_rx_packets: usize,
+}
+
 const ORD: Ordering = Ordering::Relaxed;
 
 impl Drop for Stats {
@@ -43,4 +60,17 @@ impl Stats {
             self.fragmented_rx_bytes.fetch_add(packet_len, ORD);
         }
     }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            rx_packets: self.rx_packets.load(ORD),
+            tx_packets: self.tx_packets.load(ORD),
+            rx_bytes: self.rx_bytes.load(ORD),
+            tx_bytes: self.tx_bytes.load(ORD),
+            fragmented_tx_bytes: self.fragmented_tx_bytes.load(ORD),
+            fragmented_rx_bytes: self.fragmented_rx_bytes.load(ORD),
+            fragmented_tx_packets: self.fragmented_tx_packets.load(ORD),
+            fragmented_rx_packets: self.fragmented_rx_packets.load(ORD),
+        }
+    }
 }
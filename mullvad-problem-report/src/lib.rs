@@ -402,11 +402,7 @@ impl ProblemReport {
     }
 
     fn redact(&self, input: &str) -> String {
-        let out1 = Self::redact_account_number(input);
-        let out2 = Self::redact_home_dir(&out1);
-        let out3 = Self::redact_network_info(&out2);
-        let out4 = Self::redact_guids(&out3);
-        self.redact_custom_strings(&out4).to_string()
+        self.redact_custom_strings(&redact(input)).to_string()
     }
 
     fn redact_account_number(input: &str) -> Cow<'_, str> {
@@ -493,6 +489,14 @@ impl ProblemReport {
     }
 }
 
+/// Redacts account numbers, the home directory, IP and MAC addresses, and GUIDs from `input`.
+pub fn redact(input: &str) -> String {
+    let out1 = ProblemReport::redact_account_number(input);
+    let out2 = ProblemReport::redact_home_dir(&out1);
+    let out3 = ProblemReport::redact_network_info(&out2);
+    ProblemReport::redact_guids(&out3).into_owned()
+}
+
 fn redact_home_dir_inner(input: &str, home_dir: Option<PathBuf>) -> Cow<'_, str> {
     match home_dir {
         Some(home) => {
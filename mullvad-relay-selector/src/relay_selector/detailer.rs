@@ -82,6 +82,7 @@ fn wireguard_singlehop_endpoint(
         allowed_ips: all_of_the_internet(),
         // This will be filled in later, not the relay selector's problem
         psk: None,
+        persistent_keepalive: None,
         // This will be filled in later
         #[cfg(daita)]
         constant_packet_size: false,
@@ -123,6 +124,7 @@ fn wireguard_multihop_endpoint(
         allowed_ips: all_of_the_internet(),
         // This will be filled in later, not the relay selector's problem
         psk: None,
+        persistent_keepalive: None,
         // This will be filled in later
         #[cfg(daita)]
         constant_packet_size: false,
@@ -141,6 +143,7 @@ fn wireguard_multihop_endpoint(
         allowed_ips: vec![IpNetwork::from(exit.endpoint.ip())],
         // This will be filled in later
         psk: None,
+        persistent_keepalive: None,
         // This will be filled in later
         #[cfg(daita)]
         constant_packet_size: false,
@@ -136,9 +136,15 @@ async fn reset_firewall() -> Result<(), Error> {
         return Err(Error::DaemonIsRunning);
     }
 
+    // The identifiers only affect which rules are added, so the defaults suffice for resetting.
     Firewall::new(
         #[cfg(target_os = "linux")]
-        mullvad_types::TUNNEL_FWMARK,
+        talpid_core::tunnel_state_machine::LinuxNetworkingIdentifiers {
+            fwmark: mullvad_types::TUNNEL_FWMARK,
+            table_id: mullvad_types::TUNNEL_TABLE_ID,
+            split_tunnel_mark: talpid_core::split_tunnel::DEFAULT_MARK,
+            net_cls_classid: talpid_core::split_tunnel::DEFAULT_NET_CLS_CLASSID,
+        },
     )
     .map_err(Error::FirewallError)?
     .reset_policy()
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remote};
+use talpid_types::net::proxy::{CustomProxy, HttpConnect, Shadowsocks, Socks5Local, Socks5Remote};
 
 /// Settings for API access methods.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -86,6 +86,13 @@ impl Settings {
         self.ensure_consistent_state();
     }
 
+    /// Replace the passwords of all custom access methods with placeholders.
+    pub fn redact_secrets(&mut self) {
+        self.custom
+            .iter_mut()
+            .for_each(AccessMethodSetting::redact_secrets);
+    }
+
     /// Check that `self` contains atleast one enabled access methods. If not,
     /// the `Direct` access method is re-enabled.
     fn ensure_consistent_state(&mut self) {
@@ -255,6 +262,13 @@ impl AccessMethodSetting {
         !self.enabled
     }
 
+    /// Replace the password of a custom access method with a placeholder.
+    pub fn redact_secrets(&mut self) {
+        if let AccessMethod::Custom(proxy) = &mut self.access_method {
+            proxy.redact_secrets();
+        }
+    }
+
     pub fn as_custom(&self) -> Option<&CustomProxy> {
         self.access_method.as_custom()
     }
@@ -344,3 +358,9 @@ impl From<Shadowsocks> for AccessMethod {
         CustomProxy::Shadowsocks(value).into()
     }
 }
+
+impl From<HttpConnect> for AccessMethod {
+    fn from(value: HttpConnect) -> Self {
+        CustomProxy::HttpConnect(value).into()
+    }
+}
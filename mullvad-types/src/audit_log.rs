@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A single change to the settings, as recorded in the settings audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub source: AuditSource,
+    /// The settings that changed, with secrets redacted.
+    pub changes: Vec<SettingsChange>,
+}
+
+/// What caused the settings to change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSource {
+    /// A call to the management interface.
+    Rpc {
+        /// Name of the gRPC method, e.g. `SetAllowLan`.
+        method: String,
+        /// User ID of the calling process, if known.
+        uid: Option<u32>,
+        /// Process ID of the calling process, if known.
+        pid: Option<i32>,
+    },
+    /// The daemon itself, for example when rotating the WireGuard key.
+    Daemon,
+    /// Settings from an older version of the app being migrated.
+    Migration,
+}
+
+impl std::fmt::Display for AuditSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditSource::Rpc { method, uid, pid } => {
+                write!(f, "{method}")?;
+                if let Some(uid) = uid {
+                    write!(f, " (uid {uid}")?;
+                    if let Some(pid) = pid {
+                        write!(f, ", pid {pid}")?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            AuditSource::Daemon => f.write_str("daemon"),
+            AuditSource::Migration => f.write_str("migration"),
+        }
+    }
+}
+
+/// A changed value in the settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettingsChange {
+    /// Dot-separated path to the value, e.g. `tunnel_options.dns_options.state`.
+    pub path: String,
+    /// JSON representation of the old value, or `null` if it was added.
+    pub old: String,
+    /// JSON representation of the new value, or `null` if it was removed.
+    pub new: String,
+}
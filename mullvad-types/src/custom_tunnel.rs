@@ -37,6 +37,7 @@ impl CustomTunnelEndpoint {
         &self,
         tunnel_options: TunnelOptions,
         proxy: Option<CustomProxy>,
+        #[cfg(target_os = "linux")] fwmark: u32,
     ) -> Result<TunnelParameters, Error> {
         let ip = resolve_to_ip(&self.host)?;
         let mut config = self.config.clone();
@@ -49,10 +50,15 @@ impl CustomTunnelEndpoint {
                 generic_options: tunnel_options.generic,
                 proxy,
                 #[cfg(target_os = "linux")]
-                fwmark: crate::TUNNEL_FWMARK,
+                fwmark,
             }
             .into(),
             ConnectionConfig::Wireguard(connection) => {
+                #[cfg(target_os = "linux")]
+                let connection = wireguard::ConnectionConfig {
+                    fwmark: Some(fwmark),
+                    ..connection
+                };
                 let mut options = tunnel_options.wireguard.into_talpid_tunnel_options();
                 if options.quantum_resistant {
                     options.quantum_resistant = false;
@@ -116,10 +122,67 @@ fn resolve_to_ip(host: &str) -> Result<IpAddr, Error> {
 pub enum ConnectionConfig {
     #[serde(rename = "openvpn")]
     OpenVpn(openvpn::ConnectionConfig),
-    #[serde(rename = "wireguard")]
+    #[serde(rename = "wireguard", with = "wireguard_connection")]
     Wireguard(wireguard::ConnectionConfig),
 }
 
+/// (De)serializes the config of a custom WireGuard relay along with the static PSKs of its peers,
+/// which are left out when serializing [`wireguard::PeerConfig`].
+mod wireguard_connection {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use talpid_types::net::wireguard::{ConnectionConfig, PresharedKey};
+
+    #[derive(Serialize)]
+    struct StoredConfigRef<'a> {
+        #[serde(flatten)]
+        connection: &'a ConnectionConfig,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        psk: Option<&'a PresharedKey>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exit_psk: Option<&'a PresharedKey>,
+    }
+
+    #[derive(Deserialize)]
+    struct StoredConfig {
+        #[serde(flatten)]
+        connection: ConnectionConfig,
+        #[serde(default)]
+        psk: Option<PresharedKey>,
+        #[serde(default)]
+        exit_psk: Option<PresharedKey>,
+    }
+
+    pub fn serialize<S: Serializer>(
+        connection: &ConnectionConfig,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        StoredConfigRef {
+            connection,
+            psk: connection.peer.psk.as_ref(),
+            exit_psk: connection
+                .exit_peer
+                .as_ref()
+                .and_then(|peer| peer.psk.as_ref()),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ConnectionConfig, D::Error> {
+        let StoredConfig {
+            mut connection,
+            psk,
+            exit_psk,
+        } = StoredConfig::deserialize(deserializer)?;
+        connection.peer.psk = psk;
+        if let Some(exit_peer) = &mut connection.exit_peer {
+            exit_peer.psk = exit_psk;
+        }
+        Ok(connection)
+    }
+}
+
 impl ConnectionConfig {
     fn set_ip(&mut self, ip: IpAddr) {
         match self {
@@ -131,4 +194,20 @@ impl ConnectionConfig {
             }
         }
     }
+
+    /// Replace the password, private key and preshared keys with placeholders. The placeholder
+    /// keys are all zeros.
+    pub fn redact_secrets(&mut self) {
+        match self {
+            ConnectionConfig::OpenVpn(config) => config.password.clear(),
+            ConnectionConfig::Wireguard(config) => {
+                config.tunnel.private_key = wireguard::PrivateKey::from([0u8; 32]);
+                for peer in std::iter::once(&mut config.peer).chain(&mut config.exit_peer) {
+                    if peer.psk.is_some() {
+                        peer.psk = Some(wireguard::PresharedKey::from([0u8; 32]));
+                    }
+                }
+            }
+        }
+    }
 }
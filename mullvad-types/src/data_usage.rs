@@ -0,0 +1,136 @@
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Period over which data usage is counted against a quota.
+#[derive(Serialize, Deserialize, Default, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum QuotaPeriod {
+    Day,
+    #[default]
+    Month,
+}
+
+impl QuotaPeriod {
+    /// Returns the first day of the period that `date` is in.
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            QuotaPeriod::Day => date,
+            QuotaPeriod::Month => date.with_day(1).expect("Every month has a first day"),
+        }
+    }
+}
+
+impl fmt::Display for QuotaPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaPeriod::Day => f.write_str("day"),
+            QuotaPeriod::Month => f.write_str("month"),
+        }
+    }
+}
+
+/// Limits on how much traffic may pass through the tunnel during a period. Both limits are in
+/// bytes, and count sent and received traffic combined.
+#[derive(Serialize, Deserialize, Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct DataUsageQuota {
+    pub period: QuotaPeriod,
+    /// Notify listeners once usage exceeds this limit.
+    pub soft_limit: Option<u64>,
+    /// Disconnect once usage exceeds this limit.
+    pub hard_limit: Option<u64>,
+}
+
+/// Bytes sent and received through the tunnel.
+#[derive(Serialize, Deserialize, Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DataUsage {
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+}
+
+impl DataUsage {
+    pub fn total(&self) -> u64 {
+        self.tx_bytes.saturating_add(self.rx_bytes)
+    }
+}
+
+impl std::ops::AddAssign for DataUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.tx_bytes = self.tx_bytes.saturating_add(other.tx_bytes);
+        self.rx_bytes = self.rx_bytes.saturating_add(other.rx_bytes);
+    }
+}
+
+/// Data usage during a single day, through a single relay, using a single protocol.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DataUsageEntry {
+    /// Local date on which the traffic was observed.
+    pub date: NaiveDate,
+    /// Hostname of the relay that the traffic went through.
+    pub relay: String,
+    /// Tunnel protocol, e.g. "WireGuard/UDP".
+    pub protocol: String,
+    #[serde(flatten)]
+    pub usage: DataUsage,
+}
+
+/// Returns the total usage during the period that `date` is in.
+pub fn usage_in_period(
+    entries: &[DataUsageEntry],
+    period: QuotaPeriod,
+    date: NaiveDate,
+) -> DataUsage {
+    let start = period.start(date);
+    let mut usage = DataUsage::default();
+    for entry in entries {
+        if period.start(entry.date) == start {
+            usage += entry.usage;
+        }
+    }
+    usage
+}
+
+/// Emitted when the data usage during a quota period exceeds one of the quota limits.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub period: QuotaPeriod,
+    /// The limit that was exceeded, in bytes.
+    pub limit: u64,
+    /// Usage during the current period, in bytes.
+    pub usage: u64,
+    /// Whether the hard limit was exceeded, in which case the tunnel is disconnected.
+    pub hard: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(date: &str, tx_bytes: u64, rx_bytes: u64) -> DataUsageEntry {
+        DataUsageEntry {
+            date: date.parse().unwrap(),
+            relay: "se-got-wg-001".to_owned(),
+            protocol: "WireGuard/UDP".to_owned(),
+            usage: DataUsage { tx_bytes, rx_bytes },
+        }
+    }
+
+    #[test]
+    fn test_usage_in_period() {
+        let entries = [
+            entry("2024-01-31", 1, 2),
+            entry("2024-02-01", 10, 20),
+            entry("2024-02-01", 100, 200),
+            entry("2024-02-29", 1000, 2000),
+        ];
+        let date = "2024-02-01".parse().unwrap();
+
+        let daily = usage_in_period(&entries, QuotaPeriod::Day, date);
+        assert_eq!(daily.total(), 330);
+
+        let monthly = usage_in_period(&entries, QuotaPeriod::Month, date);
+        assert_eq!(monthly.total(), 3330);
+    }
+}
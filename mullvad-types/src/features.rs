@@ -251,7 +251,10 @@ mod tests {
             obfuscation: Default::default(),
             entry_endpoint: Default::default(),
             tunnel_interface: Default::default(),
+            tunnel_ips: Default::default(),
             daita: Default::default(),
+            daita_parameters: Default::default(),
+            wireguard_backend: Default::default(),
         };
 
         let mut expected_indicators: FeatureIndicators = [].into_iter().collect();
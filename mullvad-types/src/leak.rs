@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, net::IpAddr};
+
+/// Details about a network leak detected while connected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeakInfo {
+    /// Managed to reach other network nodes on a physical interface, bypassing the tunnel and
+    /// the firewall rules.
+    NodeReachableOnInterface {
+        interface: String,
+        reachable_nodes: Vec<IpAddr>,
+    },
+    /// Queried am.i.mullvad.net, which reported that the traffic did not come from a relay.
+    AmIMullvad { ip: IpAddr },
+}
+
+impl fmt::Display for LeakInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeakInfo::NodeReachableOnInterface {
+                interface,
+                reachable_nodes,
+            } => {
+                write!(f, "reached ")?;
+                for (i, node) in reachable_nodes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{node}")?;
+                }
+                write!(f, " outside the tunnel on interface {interface}")
+            }
+            LeakInfo::AmIMullvad { ip } => {
+                write!(f, "traffic left through {ip}, which is not a Mullvad relay")
+            }
+        }
+    }
+}
@@ -1,25 +1,33 @@
 pub mod access_method;
 pub mod account;
+pub mod audit_log;
 pub mod auth_failed;
 pub mod constraints;
 pub mod custom_list;
+pub mod data_usage;
 pub mod device;
 pub mod endpoint;
 pub mod features;
+pub mod leak;
 pub mod location;
 pub mod relay_constraints;
 pub mod relay_list;
+pub mod session_history;
 pub mod settings;
 pub mod states;
 pub mod version;
+pub mod wg_quick;
 pub mod wireguard;
 
 mod custom_tunnel;
 pub use crate::custom_tunnel::*;
 
 // b"mole" is [ 0x6d, 0x6f 0x6c, 0x65 ]
+/// Default routing table ID used for the tunnel. Can be overridden when starting the daemon.
 #[cfg(target_os = "linux")]
 pub const TUNNEL_TABLE_ID: u32 = 0x6d6f6c65;
+/// Default firewall mark used for traffic that bypasses the tunnel. Can be overridden when
+/// starting the daemon.
 #[cfg(target_os = "linux")]
 pub const TUNNEL_FWMARK: u32 = 0x6d6f6c65;
 
@@ -253,7 +253,7 @@ pub struct ShadowsocksEndpointData {
 impl ShadowsocksEndpointData {
     pub fn to_proxy_settings(&self, addr: IpAddr) -> Shadowsocks {
         Shadowsocks {
-            endpoint: SocketAddr::new(addr, self.port),
+            endpoint: SocketAddr::new(addr, self.port).into(),
             password: self.password.clone(),
             cipher: self.cipher.clone(),
         }
@@ -0,0 +1,50 @@
+use crate::data_usage::DataUsage;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use talpid_types::{
+    net::{ObfuscationType, TunnelType},
+    tunnel::ErrorStateCause,
+};
+
+/// A tunnel session, lasting from the first connection attempt until the tunnel is disconnected
+/// or enters the error state. Reconnecting does not start a new session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub start: DateTime<Utc>,
+    /// When the session ended, or `None` if it is ongoing or was interrupted.
+    pub end: Option<DateTime<Utc>>,
+    /// Hostname of the relay used by the most recent connection attempt.
+    pub relay: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub tunnel_type: TunnelType,
+    pub obfuscation: Option<ObfuscationType>,
+    /// Number of times the tunnel was connecting during the session.
+    pub connect_attempts: u32,
+    /// Why the session ended, or `None` if it is ongoing.
+    pub end_reason: Option<SessionEndReason>,
+    /// Traffic that passed through the tunnel during the session.
+    pub usage: DataUsage,
+}
+
+/// Why a [`Session`] ended.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEndReason {
+    /// The tunnel was disconnected.
+    Disconnected,
+    /// The tunnel entered the error state.
+    Error(ErrorStateCause),
+    /// The daemon stopped without the session ending, e.g. because it crashed.
+    Interrupted,
+}
+
+impl std::fmt::Display for SessionEndReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionEndReason::Disconnected => f.write_str("disconnected"),
+            SessionEndReason::Error(cause) => write!(f, "error: {cause}"),
+            SessionEndReason::Interrupted => f.write_str("interrupted"),
+        }
+    }
+}
@@ -2,6 +2,7 @@ use crate::{
     access_method,
     constraints::Constraint,
     custom_list::CustomListsSettings,
+    data_usage::DataUsageQuota,
     relay_constraints::{
         BridgeSettings, BridgeState, GeographicLocationConstraint, LocationConstraint,
         ObfuscationSettings, RelayConstraints, RelayOverride, RelaySettings,
@@ -96,6 +97,8 @@ pub struct Settings {
     pub relay_overrides: Vec<RelayOverride>,
     /// Whether to notify users of beta updates.
     pub show_beta_releases: bool,
+    /// Limits on how much traffic may pass through the tunnel.
+    pub data_usage_quota: DataUsageQuota,
     /// Split tunneling settings
     #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     pub split_tunnel: SplitTunnelSettings,
@@ -209,6 +212,7 @@ impl Default for Settings {
             tunnel_options: TunnelOptions::default(),
             relay_overrides: vec![],
             show_beta_releases: false,
+            data_usage_quota: DataUsageQuota::default(),
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             split_tunnel: SplitTunnelSettings::default(),
             settings_version: CURRENT_SETTINGS_VERSION,
@@ -243,6 +247,18 @@ impl Settings {
         }
     }
 
+    /// Replace all passwords and keys with placeholders, so that the settings can be shown to
+    /// clients that may not see them.
+    pub fn redact_secrets(&mut self) {
+        if let RelaySettings::CustomTunnelEndpoint(endpoint) = &mut self.relay_settings {
+            endpoint.config.redact_secrets();
+        }
+        if let Some(proxy) = &mut self.bridge_settings.custom {
+            proxy.redact_secrets();
+        }
+        self.api_access_methods.redact_secrets();
+    }
+
     pub fn set_relay_override(&mut self, relay_override: RelayOverride) {
         let existing_override = self
             .relay_overrides
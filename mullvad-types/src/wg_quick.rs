@@ -0,0 +1,601 @@
+//! Parsing and serialization of WireGuard configs in the INI format used by `wg-quick`.
+//!
+//! Only keys that can be represented by a [`wireguard::ConnectionConfig`] are used. Other keys,
+//! such as `DNS` or `PostUp`, are ignored and reported back to the caller. Settings that have no
+//! counterpart in `wg-quick` are stored in comments, which keeps the file usable with `wg-quick`.
+//! The tunnel gateways are stored in a `# Gateway = ...` comment in the `[Interface]` section. A
+//! multihop config has a second `[Peer]` section for the exit peer, which is marked by a
+//! `# Multihop = exit` comment. Configs with several unmarked peers are rejected.
+
+use crate::{ConnectionConfig, CustomTunnelEndpoint};
+use ipnetwork::IpNetwork;
+use std::{
+    fmt::Write,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+use talpid_types::net::{all_of_the_internet, wireguard};
+
+/// Placeholder written instead of secrets that were not requested.
+const REDACTED: &str = "<redacted>";
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Line {0}: Expected a section header or 'Key = Value'")]
+    Syntax(usize),
+
+    #[error("Line {0}: Unknown section '{1}'")]
+    UnknownSection(usize, String),
+
+    #[error("Line {0}: Key outside of a section")]
+    KeyOutsideSection(usize),
+
+    #[error("Line {0}: Invalid value for '{1}'")]
+    InvalidValue(usize, String),
+
+    #[error("Only a single [{0}] section is supported")]
+    DuplicateSection(&'static str),
+
+    #[error("At most two [Peer] sections are supported")]
+    TooManyPeers,
+
+    #[error("A second [Peer] section must be a multihop exit peer, marked by '# Multihop = exit'")]
+    UnmarkedExitPeer,
+
+    #[error("The multihop exit peer must follow the entry peer")]
+    MisplacedExitPeer,
+
+    #[error("The endpoint of the exit peer must be an IP address")]
+    ExitPeerHostname,
+
+    #[error("Missing [{0}] section")]
+    MissingSection(&'static str),
+
+    #[error("Missing '{0}' in [{1}] section")]
+    MissingKey(&'static str, &'static str),
+
+    #[error("An IPv4 gateway must be specified")]
+    MissingGateway,
+}
+
+/// Custom WireGuard relay parsed from a `wg-quick` config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedConfig {
+    pub endpoint: CustomTunnelEndpoint,
+    /// Keys that were present in the config but are not supported, in the order they appeared.
+    pub ignored_keys: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Interface,
+    Peer,
+}
+
+#[derive(Default)]
+struct Interface {
+    private_key: Option<wireguard::PrivateKey>,
+    addresses: Vec<IpAddr>,
+    ipv4_gateway: Option<Ipv4Addr>,
+    ipv6_gateway: Option<Ipv6Addr>,
+}
+
+#[derive(Default)]
+struct Peer {
+    public_key: Option<wireguard::PublicKey>,
+    host: Option<String>,
+    port: u16,
+    allowed_ips: Vec<IpNetwork>,
+    psk: Option<wireguard::PresharedKey>,
+    persistent_keepalive: Option<u16>,
+    /// Whether the peer is marked as the exit peer of a multihop config.
+    exit: bool,
+}
+
+/// Parse a `wg-quick` config with one peer, or two peers for multihop, into a custom WireGuard
+/// relay. The gateways override any gateways found in the config.
+pub fn parse(
+    input: &str,
+    ipv4_gateway: Option<Ipv4Addr>,
+    ipv6_gateway: Option<Ipv6Addr>,
+) -> Result<ParsedConfig, Error> {
+    let mut interface = None;
+    let mut peers: Vec<Peer> = vec![];
+    let mut section = None;
+    let mut ignored_keys = vec![];
+
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+
+        if let Some(comment) = line.strip_prefix('#') {
+            let Some((key, value)) = split_key_value(comment) else {
+                continue;
+            };
+            let invalid = || Error::InvalidValue(line_number, key.to_owned());
+            match section {
+                Some(Section::Interface) if key.eq_ignore_ascii_case("gateway") => {
+                    let interface: &mut Interface = interface.as_mut().unwrap();
+                    parse_gateways(value, interface).ok_or_else(invalid)?;
+                }
+                Some(Section::Peer) if key.eq_ignore_ascii_case("multihop") => {
+                    if !value.eq_ignore_ascii_case("exit") {
+                        return Err(invalid());
+                    }
+                    peers.last_mut().unwrap().exit = true;
+                }
+                _ => (),
+            }
+            continue;
+        }
+        let line = line
+            .split_once('#')
+            .map_or(line, |(line, _comment)| line.trim());
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            section = match name.trim().to_ascii_lowercase().as_str() {
+                "interface" if interface.is_some() => {
+                    return Err(Error::DuplicateSection("Interface"))
+                }
+                "interface" => {
+                    interface = Some(Interface::default());
+                    Some(Section::Interface)
+                }
+                "peer" if peers.len() == 2 => return Err(Error::TooManyPeers),
+                "peer" => {
+                    peers.push(Peer::default());
+                    Some(Section::Peer)
+                }
+                _ => return Err(Error::UnknownSection(line_number, name.to_owned())),
+            };
+            continue;
+        }
+
+        let Some((key, value)) = split_key_value(line) else {
+            return Err(Error::Syntax(line_number));
+        };
+        let invalid = || Error::InvalidValue(line_number, key.to_owned());
+
+        match section {
+            None => return Err(Error::KeyOutsideSection(line_number)),
+            Some(Section::Interface) => {
+                let interface = interface.as_mut().unwrap();
+                match key.to_ascii_lowercase().as_str() {
+                    "privatekey" => {
+                        let key =
+                            wireguard::PrivateKey::from_base64(value).map_err(|_| invalid())?;
+                        interface.private_key = Some(key);
+                    }
+                    "address" => {
+                        for address in split_list(value) {
+                            let address = parse_address(address).ok_or_else(invalid)?;
+                            interface.addresses.push(address);
+                        }
+                    }
+                    _ => ignored_keys.push(key.to_owned()),
+                }
+            }
+            Some(Section::Peer) => {
+                let peer = peers.last_mut().unwrap();
+                match key.to_ascii_lowercase().as_str() {
+                    "publickey" => {
+                        let key =
+                            wireguard::PublicKey::from_base64(value).map_err(|_| invalid())?;
+                        peer.public_key = Some(key);
+                    }
+                    "endpoint" => {
+                        let (host, port) = parse_endpoint(value).ok_or_else(invalid)?;
+                        peer.host = Some(host);
+                        peer.port = port;
+                    }
+                    "presharedkey" => {
+                        let key =
+                            wireguard::PresharedKey::from_base64(value).map_err(|_| invalid())?;
+                        peer.psk = Some(key);
+                    }
+                    "persistentkeepalive" => {
+                        peer.persistent_keepalive = match value {
+                            "off" => None,
+                            _ => Some(value.parse().map_err(|_| invalid())?),
+                        };
+                    }
+                    "allowedips" => {
+                        for network in split_list(value) {
+                            peer.allowed_ips
+                                .push(network.parse().map_err(|_| invalid())?);
+                        }
+                    }
+                    _ => ignored_keys.push(key.to_owned()),
+                }
+            }
+        }
+    }
+
+    let interface = interface.ok_or(Error::MissingSection("Interface"))?;
+    // Other peers may be meant for something else entirely, so only a marked exit peer is used
+    match peers.as_slice() {
+        [Peer { exit: true, .. }, ..] => return Err(Error::MisplacedExitPeer),
+        [_, Peer { exit: false, .. }] => return Err(Error::UnmarkedExitPeer),
+        _ => (),
+    }
+    let mut peers = peers.into_iter();
+    let peer = peers.next().ok_or(Error::MissingSection("Peer"))?;
+    let exit_peer = peers.next();
+
+    let private_key = interface
+        .private_key
+        .ok_or(Error::MissingKey("PrivateKey", "Interface"))?;
+    if interface.addresses.is_empty() {
+        return Err(Error::MissingKey("Address", "Interface"));
+    }
+    let host = peer
+        .host
+        .clone()
+        .ok_or(Error::MissingKey("Endpoint", "Peer"))?;
+    // The host is resolved when connecting
+    let peer = peer_config(peer, IpAddr::V4(Ipv4Addr::UNSPECIFIED))?;
+    let exit_peer = match exit_peer {
+        Some(exit_peer) => {
+            let host = exit_peer
+                .host
+                .as_deref()
+                .ok_or(Error::MissingKey("Endpoint", "Peer"))?;
+            let ip = host.parse().map_err(|_| Error::ExitPeerHostname)?;
+            Some(peer_config(exit_peer, ip)?)
+        }
+        None => None,
+    };
+    let ipv4_gateway = ipv4_gateway
+        .or(interface.ipv4_gateway)
+        .ok_or(Error::MissingGateway)?;
+    let ipv6_gateway = ipv6_gateway.or(interface.ipv6_gateway);
+
+    let config = wireguard::ConnectionConfig {
+        tunnel: wireguard::TunnelConfig {
+            private_key,
+            addresses: interface.addresses,
+        },
+        peer,
+        exit_peer,
+        ipv4_gateway,
+        ipv6_gateway,
+        #[cfg(target_os = "linux")]
+        fwmark: None,
+    };
+
+    Ok(ParsedConfig {
+        endpoint: CustomTunnelEndpoint::new(host, ConnectionConfig::Wireguard(config)),
+        ignored_keys,
+    })
+}
+
+/// Convert a parsed `[Peer]` section into a peer that is reached at `ip`.
+fn peer_config(peer: Peer, ip: IpAddr) -> Result<wireguard::PeerConfig, Error> {
+    let public_key = peer
+        .public_key
+        .ok_or(Error::MissingKey("PublicKey", "Peer"))?;
+    let allowed_ips = if peer.allowed_ips.is_empty() {
+        all_of_the_internet()
+    } else {
+        peer.allowed_ips
+    };
+    Ok(wireguard::PeerConfig {
+        public_key,
+        allowed_ips,
+        endpoint: SocketAddr::new(ip, peer.port),
+        psk: peer.psk,
+        persistent_keepalive: peer.persistent_keepalive,
+        #[cfg(daita)]
+        constant_packet_size: false,
+    })
+}
+
+/// Serialize a WireGuard config in the `wg-quick` format, connecting to the peer at `host`.
+/// The private key and preshared keys are only included if `include_secrets` is set. All peers
+/// are included, so multihop configs result in a second `[Peer]` section for the exit peer,
+/// marked by a `# Multihop = exit` comment.
+pub fn serialize(
+    host: &str,
+    config: &wireguard::ConnectionConfig,
+    include_secrets: bool,
+) -> String {
+    let mut output = String::new();
+
+    let private_key = if include_secrets {
+        config.tunnel.private_key.to_base64()
+    } else {
+        REDACTED.to_owned()
+    };
+    let addresses = config.tunnel.addresses.iter().map(|address| match address {
+        IpAddr::V4(address) => format!("{address}/32"),
+        IpAddr::V6(address) => format!("{address}/128"),
+    });
+    let gateways = std::iter::once(IpAddr::from(config.ipv4_gateway))
+        .chain(config.ipv6_gateway.map(IpAddr::from))
+        .map(|gateway| gateway.to_string());
+
+    writeln!(output, "[Interface]").unwrap();
+    writeln!(output, "PrivateKey = {private_key}").unwrap();
+    writeln!(output, "Address = {}", join(addresses)).unwrap();
+    writeln!(output, "# Gateway = {}", join(gateways)).unwrap();
+
+    let mut write_peer = |peer: &wireguard::PeerConfig, host: &str, exit: bool| {
+        let allowed_ips = peer.allowed_ips.iter().map(|network| network.to_string());
+        let host = match host.parse::<Ipv6Addr>() {
+            Ok(_) => format!("[{host}]"),
+            Err(_) => host.to_owned(),
+        };
+        writeln!(output).unwrap();
+        writeln!(output, "[Peer]").unwrap();
+        if exit {
+            writeln!(output, "# Multihop = exit").unwrap();
+        }
+        writeln!(output, "PublicKey = {}", peer.public_key).unwrap();
+        if let Some(psk) = &peer.psk {
+            let psk = if include_secrets {
+                psk.to_base64()
+            } else {
+                REDACTED.to_owned()
+            };
+            writeln!(output, "PresharedKey = {psk}").unwrap();
+        }
+        writeln!(output, "AllowedIPs = {}", join(allowed_ips)).unwrap();
+        writeln!(output, "Endpoint = {host}:{}", peer.endpoint.port()).unwrap();
+        if let Some(interval) = peer.persistent_keepalive {
+            writeln!(output, "PersistentKeepalive = {interval}").unwrap();
+        }
+    };
+    write_peer(&config.peer, host, false);
+    if let Some(exit_peer) = &config.exit_peer {
+        write_peer(exit_peer, &exit_peer.endpoint.ip().to_string(), true);
+    }
+
+    output
+}
+
+/// Split `Key = Value` into a key and a value.
+fn split_key_value(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once('=')?;
+    let key = key.trim();
+    let value = value.trim();
+    if key.is_empty() || value.is_empty() {
+        return None;
+    }
+    Some((key, value))
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+fn join(items: impl Iterator<Item = String>) -> String {
+    items.collect::<Vec<_>>().join(", ")
+}
+
+/// Parse a tunnel address, which `wg-quick` accepts with or without a prefix length.
+fn parse_address(address: &str) -> Option<IpAddr> {
+    match address.split_once('/') {
+        Some(_) => address
+            .parse::<IpNetwork>()
+            .ok()
+            .map(|network| network.ip()),
+        None => address.parse().ok(),
+    }
+}
+
+/// Parse `host:port`, where an IPv6 host must be enclosed in brackets.
+fn parse_endpoint(endpoint: &str) -> Option<(String, u16)> {
+    let (host, port) = endpoint.rsplit_once(':')?;
+    let host = match host.strip_prefix('[') {
+        Some(host) => host
+            .strip_suffix(']')?
+            .parse::<Ipv6Addr>()
+            .ok()?
+            .to_string(),
+        None if host.contains(':') => return None,
+        None => host.to_owned(),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host, port.parse().ok()?))
+}
+
+fn parse_gateways(value: &str, interface: &mut Interface) -> Option<()> {
+    for gateway in split_list(value) {
+        match gateway.parse().ok()? {
+            IpAddr::V4(gateway) => interface.ipv4_gateway = Some(gateway),
+            IpAddr::V6(gateway) => interface.ipv6_gateway = Some(gateway),
+        }
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PRIVATE_KEY: &str = "mPue6Xt0pdz4NRAhfQSp/SLKo7kV7DW+2zvBq0N9iUI=";
+    const PUBLIC_KEY: &str = "7svBwGBefP7KVmH/yes+pZCfO6uSOYeGieYYa1+kZ0E=";
+    const PRESHARED_KEY: &str = "FuSj0/+LUiM/0hWUlGxN+oTUd/jvB6XIJ6pOyB4Z4hQ=";
+
+    const CONFIG: &str = r#"
+[Interface]
+# Gateway = 10.64.0.1, fc00:bbbb:bbbb:bb01::1
+PrivateKey = mPue6Xt0pdz4NRAhfQSp/SLKo7kV7DW+2zvBq0N9iUI=
+Address = 10.64.0.2/32, fc00:bbbb:bbbb:bb01::2/128
+DNS = 10.64.0.1
+
+[Peer]
+PublicKey = 7svBwGBefP7KVmH/yes+pZCfO6uSOYeGieYYa1+kZ0E=
+PresharedKey = FuSj0/+LUiM/0hWUlGxN+oTUd/jvB6XIJ6pOyB4Z4hQ=
+AllowedIPs = 0.0.0.0/0, ::/0 # Route everything
+Endpoint = relay.example.com:51820
+PersistentKeepalive = 25
+"#;
+
+    const EXIT_PEER: &str = r#"
+[Peer]
+# Multihop = exit
+PublicKey = 7svBwGBefP7KVmH/yes+pZCfO6uSOYeGieYYa1+kZ0E=
+AllowedIPs = 0.0.0.0/0, ::/0
+Endpoint = 1.2.3.4:51820
+"#;
+
+    #[test]
+    fn test_parse() {
+        let parsed = parse(CONFIG, None, None).unwrap();
+        assert_eq!(parsed.ignored_keys, ["DNS"]);
+        assert_eq!(parsed.endpoint.host, "relay.example.com");
+
+        let ConnectionConfig::Wireguard(config) = parsed.endpoint.config else {
+            panic!("expected a WireGuard config");
+        };
+        assert_eq!(config.tunnel.private_key.to_base64(), PRIVATE_KEY);
+        assert_eq!(
+            config.tunnel.addresses,
+            [
+                "10.64.0.2".parse::<IpAddr>().unwrap(),
+                "fc00:bbbb:bbbb:bb01::2".parse().unwrap()
+            ]
+        );
+        assert_eq!(config.peer.public_key.to_base64(), PUBLIC_KEY);
+        assert_eq!(
+            config
+                .peer
+                .psk
+                .as_ref()
+                .map(|psk| psk.to_base64())
+                .as_deref(),
+            Some(PRESHARED_KEY)
+        );
+        assert_eq!(config.peer.persistent_keepalive, Some(25));
+        assert_eq!(config.peer.allowed_ips, all_of_the_internet());
+        assert_eq!(config.peer.endpoint.port(), 51820);
+        assert_eq!(config.ipv4_gateway, Ipv4Addr::new(10, 64, 0, 1));
+        assert_eq!(config.ipv6_gateway, "fc00:bbbb:bbbb:bb01::1".parse().ok());
+    }
+
+    #[test]
+    fn test_gateway_override() {
+        let gateway = Ipv4Addr::new(10, 0, 0, 1);
+        let parsed = parse(CONFIG, Some(gateway), None).unwrap();
+        let ConnectionConfig::Wireguard(config) = parsed.endpoint.config else {
+            panic!("expected a WireGuard config");
+        };
+        assert_eq!(config.ipv4_gateway, gateway);
+
+        let config = CONFIG.replace("# Gateway", "# Comment");
+        assert_eq!(parse(&config, None, None), Err(Error::MissingGateway));
+        assert!(parse(&config, Some(gateway), None).is_ok());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let config = CONFIG.replace("PrivateKey", "# PrivateKey");
+        assert_eq!(
+            parse(&config, None, None),
+            Err(Error::MissingKey("PrivateKey", "Interface"))
+        );
+
+        let config = format!("{CONFIG}\n[Interface]\n");
+        assert_eq!(
+            parse(&config, None, None),
+            Err(Error::DuplicateSection("Interface"))
+        );
+
+        let config = format!("{CONFIG}{EXIT_PEER}\n[Peer]\n");
+        assert_eq!(parse(&config, None, None), Err(Error::TooManyPeers));
+
+        let config = format!("{CONFIG}{}", EXIT_PEER.replace("# Multihop = exit", ""));
+        assert_eq!(parse(&config, None, None), Err(Error::UnmarkedExitPeer));
+
+        let config = CONFIG.replace("[Peer]", "[Peer]\n# Multihop = exit");
+        assert_eq!(parse(&config, None, None), Err(Error::MisplacedExitPeer));
+
+        let config = format!("{CONFIG}{}", EXIT_PEER.replace("= exit", "= entry"));
+        assert_eq!(
+            parse(&config, None, None),
+            Err(Error::InvalidValue(16, "Multihop".to_owned()))
+        );
+
+        let config = format!(
+            "{CONFIG}{}",
+            EXIT_PEER.replace("1.2.3.4", "exit.example.com")
+        );
+        assert_eq!(parse(&config, None, None), Err(Error::ExitPeerHostname));
+
+        let config = CONFIG.replace("relay.example.com:51820", "fc00::1:51820");
+        assert_eq!(
+            parse(&config, None, None),
+            Err(Error::InvalidValue(12, "Endpoint".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(
+            parse_endpoint("[fc00::1]:51820"),
+            Some(("fc00::1".to_owned(), 51820))
+        );
+        assert_eq!(parse_endpoint("1.2.3.4:1"), Some(("1.2.3.4".to_owned(), 1)));
+        assert_eq!(parse_endpoint("1.2.3.4"), None);
+        assert_eq!(parse_endpoint(":51820"), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let parsed = parse(CONFIG, None, None).unwrap();
+        let ConnectionConfig::Wireguard(config) = &parsed.endpoint.config else {
+            panic!("expected a WireGuard config");
+        };
+
+        let serialized = serialize(&parsed.endpoint.host, config, true);
+        let reparsed = parse(&serialized, None, None).unwrap();
+        assert_eq!(reparsed.endpoint, parsed.endpoint);
+        assert!(reparsed.ignored_keys.is_empty());
+    }
+
+    /// The exit peer of a multihop config is written as a second, marked `[Peer]` section, and
+    /// read back as the exit peer.
+    #[test]
+    fn test_multihop_round_trip() {
+        let mut parsed = parse(CONFIG, None, None).unwrap();
+        let ConnectionConfig::Wireguard(config) = &mut parsed.endpoint.config else {
+            panic!("expected a WireGuard config");
+        };
+        let exit_peer = wireguard::PeerConfig {
+            endpoint: "1.2.3.4:51820".parse().unwrap(),
+            psk: None,
+            persistent_keepalive: None,
+            ..config.peer.clone()
+        };
+        config.peer.allowed_ips = vec![IpNetwork::from(exit_peer.endpoint.ip())];
+        config.exit_peer = Some(exit_peer);
+
+        let serialized = serialize(&parsed.endpoint.host, config, true);
+        assert_eq!(serialized.matches("[Peer]").count(), 2);
+        assert_eq!(serialized.matches("# Multihop = exit").count(), 1);
+        let reparsed = parse(&serialized, None, None).unwrap();
+        assert_eq!(reparsed.endpoint, parsed.endpoint);
+    }
+
+    #[test]
+    fn test_serialize_redacts_secrets() {
+        let parsed = parse(CONFIG, None, None).unwrap();
+        let ConnectionConfig::Wireguard(config) = &parsed.endpoint.config else {
+            panic!("expected a WireGuard config");
+        };
+
+        let serialized = serialize(&parsed.endpoint.host, config, false);
+        assert!(!serialized.contains(PRIVATE_KEY));
+        assert!(!serialized.contains(PRESHARED_KEY));
+        assert!(serialized.contains(&format!("PresharedKey = {REDACTED}")));
+        assert!(serialized.contains(&format!("PrivateKey = {REDACTED}")));
+    }
+}
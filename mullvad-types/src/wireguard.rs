@@ -10,6 +10,9 @@ pub const MIN_ROTATION_INTERVAL: Duration = Duration::from_secs(1 * 24 * 60 * 60
 pub const MAX_ROTATION_INTERVAL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
 pub const DEFAULT_ROTATION_INTERVAL: Duration = MAX_ROTATION_INTERVAL;
 
+pub const MIN_REKEY_INTERVAL: Duration = Duration::from_secs(1 * 60 * 60);
+pub const MAX_REKEY_INTERVAL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
 /// Whether to enable or disable quantum resistant tunnels when the setting is set to
 /// `QuantumResistantState::Auto`. It is currently enabled by default on desktop,
 /// but disabled on Android.
@@ -89,6 +92,10 @@ pub struct DaitaSettings {
     /// Whether to use multihop if the selected relay is not DAITA-compatible. Note that this is
     /// the inverse of of "Direct only" in the GUI.
     pub use_multihop_if_necessary: bool,
+
+    /// DAITA level to request from the relay.
+    #[serde(default)]
+    pub level: wireguard::DaitaLevel,
 }
 
 #[cfg(daita)]
@@ -105,6 +112,7 @@ impl Default for DaitaSettings {
         Self {
             enabled: false,
             use_multihop_if_necessary: Self::default_use_multihop_if_necessary(),
+            level: wireguard::DaitaLevel::default(),
         }
     }
 }
@@ -239,6 +247,112 @@ impl Default for RotationInterval {
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum RekeyIntervalError {
+    TooSmall,
+    TooLarge,
+}
+
+impl fmt::Display for RekeyIntervalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use RekeyIntervalError::*;
+
+        match *self {
+            TooSmall => write!(
+                f,
+                "Re-key interval must be at least {} hours",
+                MIN_REKEY_INTERVAL.as_secs() / 60 / 60
+            ),
+            TooLarge => write!(
+                f,
+                "Re-key interval must be at most {} hours",
+                MAX_REKEY_INTERVAL.as_secs() / 60 / 60
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RekeyIntervalError {}
+
+/// How often to negotiate a new ephemeral peer and PSK for a quantum-resistant tunnel, without
+/// reconnecting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RekeyInterval(Duration);
+
+impl RekeyInterval {
+    pub fn new(interval: Duration) -> Result<RekeyInterval, RekeyIntervalError> {
+        if interval < MIN_REKEY_INTERVAL {
+            Err(RekeyIntervalError::TooSmall)
+        } else if interval > MAX_REKEY_INTERVAL {
+            Err(RekeyIntervalError::TooLarge)
+        } else {
+            Ok(RekeyInterval(interval))
+        }
+    }
+
+    pub fn as_duration(&self) -> &Duration {
+        &self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for RekeyInterval {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let ivl = <Duration>::deserialize(deserializer)?;
+        RekeyInterval::new(ivl).map_err(|_error| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Other("Duration"),
+                &"interval within allowed range",
+            )
+        })
+    }
+}
+
+impl TryFrom<Duration> for RekeyInterval {
+    type Error = RekeyIntervalError;
+
+    fn try_from(duration: Duration) -> Result<RekeyInterval, RekeyIntervalError> {
+        RekeyInterval::new(duration)
+    }
+}
+
+impl fmt::Display for RekeyInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} hours", self.as_duration().as_secs() / 60 / 60)
+    }
+}
+
+#[cfg(feature = "clap")]
+impl clap::builder::ValueParserFactory for RekeyInterval {
+    type Parser = clap::builder::RangedU64ValueParser<RekeyInterval>;
+
+    fn value_parser() -> Self::Parser {
+        clap::builder::RangedU64ValueParser::new().range(
+            (MIN_REKEY_INTERVAL.as_secs() / 60 / 60)..=(MAX_REKEY_INTERVAL.as_secs() / 60 / 60),
+        )
+    }
+}
+
+impl TryFrom<u64> for RekeyInterval {
+    type Error = RekeyIntervalError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        // Convert a u64, specified in hours, to a `RekeyInterval`
+        let val = value
+            .checked_mul(60 * 60)
+            .ok_or(RekeyIntervalError::TooLarge)?;
+        RekeyInterval::new(Duration::from_secs(val))
+    }
+}
+
+impl From<RekeyInterval> for Duration {
+    fn from(interval: RekeyInterval) -> Duration {
+        *interval.as_duration()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct TunnelOptions {
@@ -251,6 +365,14 @@ pub struct TunnelOptions {
     pub daita: DaitaSettings,
     /// Interval used for automatic key rotation
     pub rotation_interval: Option<RotationInterval>,
+    /// Interval at which quantum-resistant tunnels negotiate a new PSK. Disabled if unset.
+    pub quantum_resistant_rekey_interval: Option<RekeyInterval>,
+    /// Parameters of the connectivity monitor
+    pub connectivity: wireguard::ConnectivityCheckOptions,
+    /// WireGuard implementation to try first
+    pub backend: wireguard::WireguardBackend,
+    /// Connect to a new relay before disconnecting from the current one, when possible
+    pub make_before_break: bool,
 }
 
 #[allow(clippy::derivable_impls)]
@@ -262,6 +384,10 @@ impl Default for TunnelOptions {
             #[cfg(daita)]
             daita: DaitaSettings::default(),
             rotation_interval: None,
+            quantum_resistant_rekey_interval: None,
+            connectivity: wireguard::ConnectivityCheckOptions::default(),
+            backend: wireguard::WireguardBackend::default(),
+            make_before_break: false,
         }
     }
 }
@@ -273,6 +399,12 @@ impl TunnelOptions {
             quantum_resistant: self.quantum_resistant.enabled(),
             #[cfg(daita)]
             daita: self.daita.enabled,
+            #[cfg(daita)]
+            daita_level: self.daita.level,
+            rekey_interval: self.quantum_resistant_rekey_interval.map(Duration::from),
+            connectivity: self.connectivity,
+            backend: self.backend,
+            make_before_break: self.make_before_break,
         }
     }
 }
@@ -1,9 +1,9 @@
-use super::{FirewallArguments, FirewallPolicy};
-use crate::{split_tunnel, tunnel};
+use super::{nflog, FirewallArguments, FirewallPolicy};
+use crate::{tunnel, tunnel_state_machine::LinuxNetworkingIdentifiers};
 use ipnetwork::IpNetwork;
 use nftnl::{
     expr::{self, IcmpCode, Payload, RejectionType, Verdict},
-    nft_expr, table, Batch, Chain, FinalizedBatch, ProtoFamily, Rule, Table,
+    nft_expr, nftnl_sys, table, Batch, Chain, FinalizedBatch, ProtoFamily, Rule, Table,
 };
 use std::{
     env,
@@ -97,27 +97,50 @@ enum End {
 
 /// The Linux implementation for the firewall and DNS.
 pub struct Firewall {
-    fwmark: u32,
+    linux_ids: LinuxNetworkingIdentifiers,
+    /// Whether blocked packets should be logged to the nflog group.
+    log_blocked: bool,
+    /// The currently applied policy, reapplied when `log_blocked` changes.
+    policy: Option<FirewallPolicy>,
 }
 
 impl Firewall {
     pub fn from_args(args: FirewallArguments) -> Result<Self> {
-        Firewall::new(args.fwmark)
+        Firewall::new(args.linux_ids)
     }
 
-    pub fn new(fwmark: u32) -> Result<Self> {
-        Ok(Firewall { fwmark })
+    pub fn new(linux_ids: LinuxNetworkingIdentifiers) -> Result<Self> {
+        Ok(Firewall {
+            linux_ids,
+            log_blocked: false,
+            policy: None,
+        })
     }
 
     pub fn apply_policy(&mut self, policy: FirewallPolicy) -> Result<()> {
         let table = Table::new(&TABLE_NAME, ProtoFamily::Inet);
-        let batch = PolicyBatch::new(&table).finalize(&policy, self.fwmark)?;
+        let batch = PolicyBatch::new(&table, self.log_blocked).finalize(&policy, self.linux_ids)?;
         Self::send_and_process(&batch)?;
         Self::apply_kernel_config(&policy);
-        self.verify_tables(&[TABLE_NAME])
+        self.verify_tables(&[TABLE_NAME])?;
+        self.policy = Some(policy);
+        Ok(())
+    }
+
+    pub fn set_log_blocked(&mut self, enabled: bool) -> Result<()> {
+        if self.log_blocked == enabled {
+            return Ok(());
+        }
+        self.log_blocked = enabled;
+        match self.policy.clone() {
+            Some(policy) => self.apply_policy(policy),
+            None => Ok(()),
+        }
     }
 
     pub fn reset_policy(&mut self) -> Result<()> {
+        self.policy = None;
+
         let table = Table::new(&TABLE_NAME, ProtoFamily::Inet);
         let mut batch = Batch::new();
 
@@ -235,6 +258,7 @@ impl Firewall {
 
 struct PolicyBatch<'a> {
     batch: Batch,
+    log_blocked: bool,
     in_chain: Chain<'a>,
     out_chain: Chain<'a>,
     forward_chain: Chain<'a>,
@@ -246,7 +270,7 @@ struct PolicyBatch<'a> {
 impl<'a> PolicyBatch<'a> {
     /// Bootstrap a new nftnl message batch object and add the initial messages creating the
     /// table and chains.
-    pub fn new(table: &'a Table) -> Self {
+    pub fn new(table: &'a Table, log_blocked: bool) -> Self {
         let mut batch = Batch::new();
 
         batch_deprecated_tables(&mut batch);
@@ -290,6 +314,7 @@ impl<'a> PolicyBatch<'a> {
 
         PolicyBatch {
             batch,
+            log_blocked,
             in_chain,
             out_chain,
             forward_chain,
@@ -301,17 +326,32 @@ impl<'a> PolicyBatch<'a> {
 
     /// Finalize the nftnl message batch by adding every firewall rule needed to satisfy the given
     /// policy.
-    pub fn finalize(mut self, policy: &FirewallPolicy, fwmark: u32) -> Result<FinalizedBatch> {
+    pub fn finalize(
+        mut self,
+        policy: &FirewallPolicy,
+        linux_ids: LinuxNetworkingIdentifiers,
+    ) -> Result<FinalizedBatch> {
         self.add_loopback_rules()?;
-        self.add_split_tunneling_rules(policy, fwmark)?;
+        self.add_split_tunneling_rules(policy, linux_ids)?;
         self.add_dhcp_client_rules();
         self.add_ndp_rules();
-        self.add_policy_specific_rules(policy, fwmark)?;
+        self.add_policy_specific_rules(policy, linux_ids)?;
 
         Ok(self.batch.finalize())
     }
 
-    fn add_split_tunneling_rules(&mut self, policy: &FirewallPolicy, fwmark: u32) -> Result<()> {
+    fn add_split_tunneling_rules(
+        &mut self,
+        policy: &FirewallPolicy,
+        linux_ids: LinuxNetworkingIdentifiers,
+    ) -> Result<()> {
+        let LinuxNetworkingIdentifiers {
+            fwmark,
+            split_tunnel_mark,
+            net_cls_classid,
+            ..
+        } = linux_ids;
+
         // Send select DNS requests in the tunnel
         if let FirewallPolicy::Connected {
             tunnel, dns_config, ..
@@ -337,16 +377,16 @@ impl<'a> PolicyBatch<'a> {
 
         // Split tunneled processes have their PIDs added to a net_cls cgroup.
         // This causes all packets sent by that process to be marked with the
-        // cgroups classid (`NET_CLS_CLASSID`). This rule checks incoming packets for that classid.
+        // cgroups classid (`net_cls_classid`). This rule checks incoming packets for that classid.
         // If the packet has the classid set then the packet will have two new marks applied to it.
-        // The `split_tunnel::MARK` as a connection tracking mark and the `fwmark` as packet
+        // The `split_tunnel_mark` as a connection tracking mark and the `fwmark` as packet
         // metadata.
         let mut rule = Rule::new(&self.mangle_chain);
         rule.add_expr(&nft_expr!(meta cgroup));
-        rule.add_expr(&nft_expr!(cmp == split_tunnel::NET_CLS_CLASSID));
-        // Loads `split_tunnel::MARK` into first nftnl register
-        rule.add_expr(&nft_expr!(immediate data split_tunnel::MARK));
-        // Sets `split_tunnel::MARK` as connection tracker mark
+        rule.add_expr(&nft_expr!(cmp == net_cls_classid));
+        // Loads `split_tunnel_mark` into first nftnl register
+        rule.add_expr(&nft_expr!(immediate data split_tunnel_mark));
+        // Sets `split_tunnel_mark` as connection tracker mark
         rule.add_expr(&nft_expr!(ct mark set));
         // Loads `fwmark` into first nftnl register
         rule.add_expr(&nft_expr!(immediate data fwmark));
@@ -357,7 +397,7 @@ impl<'a> PolicyBatch<'a> {
         for chain in &[&self.in_chain, &self.out_chain, &self.forward_chain] {
             let mut rule = Rule::new(chain);
             rule.add_expr(&nft_expr!(ct mark));
-            rule.add_expr(&nft_expr!(cmp == split_tunnel::MARK));
+            rule.add_expr(&nft_expr!(cmp == split_tunnel_mark));
             add_verdict(&mut rule, &Verdict::Accept);
             self.batch.add(&rule, nftnl::MsgType::Add);
         }
@@ -367,7 +407,7 @@ impl<'a> PolicyBatch<'a> {
             let mut block_tunnel_rule = Rule::new(&self.nat_chain);
             check_iface(&mut block_tunnel_rule, Direction::Out, &tunnel.interface)?;
             block_tunnel_rule.add_expr(&nft_expr!(ct mark));
-            block_tunnel_rule.add_expr(&nft_expr!(cmp == split_tunnel::MARK));
+            block_tunnel_rule.add_expr(&nft_expr!(cmp == split_tunnel_mark));
             add_verdict(&mut block_tunnel_rule, &Verdict::Drop);
             self.batch.add(&block_tunnel_rule, nftnl::MsgType::Add);
         }
@@ -382,7 +422,7 @@ impl<'a> PolicyBatch<'a> {
         rule.add_expr(&nft_expr!(cmp != iface_index));
 
         rule.add_expr(&nft_expr!(ct mark));
-        rule.add_expr(&nft_expr!(cmp == split_tunnel::MARK));
+        rule.add_expr(&nft_expr!(cmp == split_tunnel_mark));
 
         rule.add_expr(&nft_expr!(masquerade));
         if *ADD_COUNTERS {
@@ -396,7 +436,7 @@ impl<'a> PolicyBatch<'a> {
             let mut prerouting_rule = Rule::new(&self.prerouting_chain);
             check_not_iface(&mut prerouting_rule, Direction::In, &tunnel.interface)?;
             prerouting_rule.add_expr(&nft_expr!(ct mark));
-            prerouting_rule.add_expr(&nft_expr!(cmp == split_tunnel::MARK));
+            prerouting_rule.add_expr(&nft_expr!(cmp == split_tunnel_mark));
             prerouting_rule.add_expr(&nft_expr!(immediate data fwmark));
             prerouting_rule.add_expr(&nft_expr!(meta mark set));
             if *ADD_COUNTERS {
@@ -535,7 +575,11 @@ impl<'a> PolicyBatch<'a> {
         }
     }
 
-    fn add_policy_specific_rules(&mut self, policy: &FirewallPolicy, fwmark: u32) -> Result<()> {
+    fn add_policy_specific_rules(
+        &mut self,
+        policy: &FirewallPolicy,
+        linux_ids: LinuxNetworkingIdentifiers,
+    ) -> Result<()> {
         let allow_lan = match policy {
             FirewallPolicy::Connecting {
                 peer_endpoint,
@@ -544,7 +588,7 @@ impl<'a> PolicyBatch<'a> {
                 allowed_endpoint,
                 allowed_tunnel_traffic,
             } => {
-                self.add_allow_tunnel_endpoint_rules(peer_endpoint, fwmark);
+                self.add_allow_tunnel_endpoint_rules(peer_endpoint, linux_ids);
                 self.add_allow_endpoint_rules(allowed_endpoint);
 
                 // Important to block DNS after allow relay rule (so the relay can operate
@@ -573,11 +617,15 @@ impl<'a> PolicyBatch<'a> {
             }
             FirewallPolicy::Connected {
                 peer_endpoint,
+                next_peer_endpoint,
                 tunnel,
                 allow_lan,
                 dns_config,
             } => {
-                self.add_allow_tunnel_endpoint_rules(peer_endpoint, fwmark);
+                self.add_allow_tunnel_endpoint_rules(peer_endpoint, linux_ids);
+                if let Some(next_peer_endpoint) = next_peer_endpoint {
+                    self.add_allow_tunnel_endpoint_rules(next_peer_endpoint, linux_ids);
+                }
 
                 for server in dns_config.tunnel_config() {
                     self.add_allow_tunnel_dns_rule(
@@ -631,8 +679,19 @@ impl<'a> PolicyBatch<'a> {
             self.add_allow_lan_rules();
         }
 
+        // Log any remaining incoming traffic before the chain policy drops it
+        if self.log_blocked {
+            add_log_rule(&mut self.batch, &self.in_chain, nflog::INPUT_PREFIX, |_| ());
+        }
+
         // Reject any remaining outgoing traffic
-        for chain in &[&self.out_chain, &self.forward_chain] {
+        for (chain, log_prefix) in [
+            (&self.out_chain, nflog::OUTPUT_PREFIX),
+            (&self.forward_chain, nflog::FORWARD_PREFIX),
+        ] {
+            if self.log_blocked {
+                add_log_rule(&mut self.batch, chain, log_prefix, |_| ());
+            }
             let mut reject_rule = Rule::new(chain);
             add_verdict(
                 &mut reject_rule,
@@ -644,7 +703,16 @@ impl<'a> PolicyBatch<'a> {
         Ok(())
     }
 
-    fn add_allow_tunnel_endpoint_rules(&mut self, endpoint: &AllowedEndpoint, fwmark: u32) {
+    fn add_allow_tunnel_endpoint_rules(
+        &mut self,
+        endpoint: &AllowedEndpoint,
+        linux_ids: LinuxNetworkingIdentifiers,
+    ) {
+        let LinuxNetworkingIdentifiers {
+            fwmark,
+            split_tunnel_mark,
+            ..
+        } = linux_ids;
         let mut prerouting_rule = Rule::new(&self.prerouting_chain);
         // Mark incoming traffic from endpoint with fwmark
         check_endpoint(&mut prerouting_rule, End::Src, &endpoint.endpoint);
@@ -686,7 +754,7 @@ impl<'a> PolicyBatch<'a> {
         if endpoint.clients.allow_all() {
             let mut rule = Rule::new(&self.mangle_chain);
             check_endpoint(&mut rule, End::Dst, &endpoint.endpoint);
-            rule.add_expr(&nft_expr!(immediate data split_tunnel::MARK));
+            rule.add_expr(&nft_expr!(immediate data split_tunnel_mark));
             rule.add_expr(&nft_expr!(ct mark set));
             rule.add_expr(&nft_expr!(immediate data fwmark));
             rule.add_expr(&nft_expr!(meta mark set));
@@ -780,7 +848,18 @@ impl<'a> PolicyBatch<'a> {
 
     /// Blocks all outgoing DNS (port 53) on both TCP and UDP
     fn add_drop_dns_rule(&mut self) {
-        for chain in &[&self.out_chain, &self.forward_chain] {
+        for (chain, log_prefix) in [
+            (&self.out_chain, nflog::OUTPUT_PREFIX),
+            (&self.forward_chain, nflog::FORWARD_PREFIX),
+        ] {
+            if self.log_blocked {
+                for protocol in [TransportProtocol::Udp, TransportProtocol::Tcp] {
+                    add_log_rule(&mut self.batch, chain, log_prefix, |rule| {
+                        check_port(rule, protocol, End::Dst, 53)
+                    });
+                }
+            }
+
             let mut block_udp_rule = Rule::new(chain);
             check_port(&mut block_udp_rule, TransportProtocol::Udp, End::Dst, 53);
             add_verdict(
@@ -1064,6 +1143,104 @@ fn l4proto(protocol: TransportProtocol) -> u8 {
     }
 }
 
+/// Adds a rule that sends packets matching `add_matches` to the nflog group read by
+/// [`nflog::BlockedPacketListener`], subject to a rate limit. The rule has no verdict, so it must
+/// be added right before the rule that blocks the same packets.
+///
+/// In the output chain, the net_cls class ID of the packets is also copied into their mark, since
+/// nflog cannot report the cgroup of a packet. The mark is cleared first, since the class ID is
+/// only available for packets sent from a local socket. The packets are blocked right after, so
+/// their mark is not used for anything else.
+fn add_log_rule(
+    batch: &mut Batch,
+    chain: &Chain<'_>,
+    prefix: &'static CStr,
+    add_matches: impl Fn(&mut Rule<'_>),
+) {
+    if prefix == nflog::OUTPUT_PREFIX {
+        let mut clear_mark_rule = Rule::new(chain);
+        add_matches(&mut clear_mark_rule);
+        clear_mark_rule.add_expr(&nft_expr!(immediate data 0u32));
+        clear_mark_rule.add_expr(&nft_expr!(meta mark set));
+        batch.add(&clear_mark_rule, nftnl::MsgType::Add);
+
+        let mut cgroup_rule = Rule::new(chain);
+        add_matches(&mut cgroup_rule);
+        cgroup_rule.add_expr(&nft_expr!(meta cgroup));
+        cgroup_rule.add_expr(&nft_expr!(meta mark set));
+        batch.add(&cgroup_rule, nftnl::MsgType::Add);
+    }
+
+    let mut rule = Rule::new(chain);
+    add_matches(&mut rule);
+    rule.add_expr(&Limit {
+        rate_per_second: nflog::LOG_RATE_PER_SECOND,
+        burst: nflog::LOG_BURST,
+    });
+    if *ADD_COUNTERS {
+        rule.add_expr(&nft_expr!(counter));
+    }
+    rule.add_expr(&Log {
+        group: nflog::LOG_GROUP,
+        prefix,
+    });
+    batch.add(&rule, nftnl::MsgType::Add);
+}
+
+/// A `limit rate <rate>/second burst <burst> packets` expression.
+struct Limit {
+    rate_per_second: u64,
+    burst: u32,
+}
+
+impl expr::Expression for Limit {
+    fn to_expr(&self, _rule: &Rule<'_>) -> *mut nftnl_sys::nftnl_expr {
+        const SECOND: u64 = 1;
+
+        // SAFETY: The expression is allocated by libnftnl and only set with attributes that are
+        // valid for `limit` expressions. Ownership is passed on to the rule.
+        unsafe {
+            let expr = nftnl_sys::nftnl_expr_alloc(c"limit".as_ptr());
+            nftnl_sys::nftnl_expr_set_u64(
+                expr,
+                nftnl_sys::NFTNL_EXPR_LIMIT_RATE as u16,
+                self.rate_per_second,
+            );
+            nftnl_sys::nftnl_expr_set_u64(expr, nftnl_sys::NFTNL_EXPR_LIMIT_UNIT as u16, SECOND);
+            nftnl_sys::nftnl_expr_set_u32(
+                expr,
+                nftnl_sys::NFTNL_EXPR_LIMIT_BURST as u16,
+                self.burst,
+            );
+            expr
+        }
+    }
+}
+
+/// A `log group <group> prefix <prefix>` expression.
+struct Log {
+    group: u16,
+    prefix: &'static CStr,
+}
+
+impl expr::Expression for Log {
+    fn to_expr(&self, _rule: &Rule<'_>) -> *mut nftnl_sys::nftnl_expr {
+        // SAFETY: The expression is allocated by libnftnl and only set with attributes that are
+        // valid for `log` expressions. libnftnl copies the prefix string. Ownership is passed on
+        // to the rule.
+        unsafe {
+            let expr = nftnl_sys::nftnl_expr_alloc(c"log".as_ptr());
+            nftnl_sys::nftnl_expr_set_u16(expr, nftnl_sys::NFTNL_EXPR_LOG_GROUP as u16, self.group);
+            nftnl_sys::nftnl_expr_set_str(
+                expr,
+                nftnl_sys::NFTNL_EXPR_LOG_PREFIX as u16,
+                self.prefix.as_ptr(),
+            );
+            expr
+        }
+    }
+}
+
 fn add_verdict(rule: &mut Rule<'_>, verdict: &expr::Verdict) {
     if *ADD_COUNTERS {
         rule.add_expr(&nft_expr!(counter));
@@ -315,11 +315,13 @@ impl Firewall {
         }
 
         // no nat to [vpn ip]
-        let no_nat_to_vpn_server = pfctl::NatRuleBuilder::default()
-            .action(pfctl::NatRuleAction::NoNat)
-            .to(peer_endpoint.endpoint.address)
-            .build()?;
-        rules.push(no_nat_to_vpn_server);
+        for peer_endpoint in std::iter::once(peer_endpoint).chain(policy.next_peer_endpoint()) {
+            let no_nat_to_vpn_server = pfctl::NatRuleBuilder::default()
+                .action(pfctl::NatRuleAction::NoNat)
+                .to(peer_endpoint.endpoint.address)
+                .build()?;
+            rules.push(no_nat_to_vpn_server);
+        }
 
         // no nat on [tun interface]
         let no_nat_on_tun = pfctl::NatRuleBuilder::default()
@@ -398,6 +400,7 @@ impl Firewall {
             }
             FirewallPolicy::Connected {
                 peer_endpoint,
+                next_peer_endpoint,
                 tunnel,
                 allow_lan,
                 dns_config,
@@ -417,6 +420,9 @@ impl Firewall {
                 }
 
                 rules.push(self.get_allow_relay_rule(peer_endpoint)?);
+                if let Some(next_peer_endpoint) = next_peer_endpoint {
+                    rules.push(self.get_allow_relay_rule(next_peer_endpoint)?);
+                }
 
                 // Important to block DNS *before* we allow the tunnel and allow LAN. So DNS
                 // can't leak to the wrong IPs in the tunnel or on the LAN.
@@ -1,5 +1,7 @@
 #[cfg(not(target_os = "android"))]
 use crate::dns::ResolvedDnsConfig;
+#[cfg(target_os = "linux")]
+use crate::tunnel_state_machine::LinuxNetworkingIdentifiers;
 use ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
 use std::{
     fmt,
@@ -16,6 +18,9 @@ mod imp;
 #[path = "linux.rs"]
 mod imp;
 
+#[cfg(target_os = "linux")]
+pub mod nflog;
+
 #[cfg(windows)]
 #[path = "windows/mod.rs"]
 mod imp;
@@ -100,6 +105,9 @@ pub enum FirewallPolicy {
     Connected {
         /// The peer endpoint that should be allowed.
         peer_endpoint: AllowedEndpoint,
+        /// Relay that should also be allowed while the tunnel is being moved to it.
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        next_peer_endpoint: Option<AllowedEndpoint>,
         /// Metadata about the tunnel and tunnel interface.
         tunnel: crate::tunnel::TunnelMetadata,
         /// Flag setting if communication with LAN networks should be possible.
@@ -131,6 +139,17 @@ impl FirewallPolicy {
         }
     }
 
+    /// Return the relay that the tunnel is being moved to, if any
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn next_peer_endpoint(&self) -> Option<&AllowedEndpoint> {
+        match self {
+            FirewallPolicy::Connected {
+                next_peer_endpoint, ..
+            } => next_peer_endpoint.as_ref(),
+            _ => None,
+        }
+    }
+
     /// Return the allowed endpoint, if available
     pub fn allowed_endpoint(&self) -> Option<&AllowedEndpoint> {
         match self {
@@ -237,21 +256,28 @@ impl fmt::Display for FirewallPolicy {
                 tunnel,
                 allow_lan,
                 ..
-            } => write!(
-                f,
-                "Connected to {} over \"{}\" (ip: {}, v4 gw: {}, v6 gw: {:?}), {} LAN",
-                peer_endpoint,
-                tunnel.interface,
-                tunnel
-                    .ips
-                    .iter()
-                    .map(|ip| ip.to_string())
-                    .collect::<Vec<_>>()
-                    .join(","),
-                tunnel.ipv4_gateway,
-                tunnel.ipv6_gateway,
-                if *allow_lan { "Allowing" } else { "Blocking" }
-            ),
+            } => {
+                write!(
+                    f,
+                    "Connected to {} over \"{}\" (ip: {}, v4 gw: {}, v6 gw: {:?}), {} LAN",
+                    peer_endpoint,
+                    tunnel.interface,
+                    tunnel
+                        .ips
+                        .iter()
+                        .map(|ip| ip.to_string())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    tunnel.ipv4_gateway,
+                    tunnel.ipv6_gateway,
+                    if *allow_lan { "Allowing" } else { "Blocking" }
+                )?;
+                #[cfg(any(target_os = "linux", target_os = "macos"))]
+                if let Some(next_peer_endpoint) = self.next_peer_endpoint() {
+                    write!(f, ". Switching to {next_peer_endpoint}")?;
+                }
+                Ok(())
+            }
             FirewallPolicy::Blocked {
                 allow_lan,
                 allowed_endpoint,
@@ -281,10 +307,10 @@ pub struct FirewallArguments {
     pub initial_state: InitialFirewallState,
     /// This argument is required for the blocked state to configure the firewall correctly.
     pub allow_lan: bool,
-    /// Specifies the firewall mark used to identify traffic that is allowed to be excluded from
+    /// Specifies the firewall marks used to identify traffic that is allowed to be excluded from
     /// the tunnel and _leaked_ during blocked states.
     #[cfg(target_os = "linux")]
-    pub fwmark: u32,
+    pub linux_ids: LinuxNetworkingIdentifiers,
 }
 
 /// State to enter during firewall init.
@@ -304,11 +330,13 @@ impl Firewall {
     }
 
     /// Createsa new firewall instance.
-    pub fn new(#[cfg(target_os = "linux")] fwmark: u32) -> Result<Self, Error> {
+    pub fn new(
+        #[cfg(target_os = "linux")] linux_ids: LinuxNetworkingIdentifiers,
+    ) -> Result<Self, Error> {
         Ok(Firewall {
             inner: imp::Firewall::new(
                 #[cfg(target_os = "linux")]
-                fwmark,
+                linux_ids,
             )?,
         })
     }
@@ -326,4 +354,15 @@ impl Firewall {
         log::info!("Resetting firewall policy");
         self.inner.reset_policy()
     }
+
+    /// Enable or disable logging of blocked packets to the nflog group read by
+    /// [`nflog::BlockedPacketListener`]. The current policy is reapplied if the setting changes.
+    #[cfg(target_os = "linux")]
+    pub fn set_log_blocked(&mut self, enabled: bool) -> Result<(), Error> {
+        log::info!(
+            "{} logging of blocked packets",
+            if enabled { "Enabling" } else { "Disabling" }
+        );
+        self.inner.set_log_blocked(enabled)
+    }
 }
@@ -0,0 +1,500 @@
+//! Reads packets logged by the firewall's `log` rules via nfnetlink_log.
+//!
+//! nfnetlink_log includes the owner of the sending socket, but has no attribute for its cgroup.
+//! The firewall therefore copies the net_cls class ID of locally generated packets into the packet
+//! mark before logging them, and the mark of packets blocked in the output chain is read as the
+//! class ID.
+
+use nix::sys::time::{TimeVal, TimeValLike};
+use std::{
+    ffi::CStr,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    os::unix::io::AsRawFd,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc as sync_mpsc, Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use talpid_types::net::firewall_log::{BlockedPacket, FirewallChain};
+
+/// nflog group that blocked packets are logged to.
+pub(super) const LOG_GROUP: u16 = 0x6d76;
+/// Prefixes attached to logged packets, used to tell which chain blocked them.
+pub(super) const INPUT_PREFIX: &CStr = c"mullvad-block-in ";
+pub(super) const OUTPUT_PREFIX: &CStr = c"mullvad-block-out ";
+pub(super) const FORWARD_PREFIX: &CStr = c"mullvad-block-fwd ";
+/// Maximum number of packets per second that are logged.
+pub(super) const LOG_RATE_PER_SECOND: u64 = 10;
+/// Number of packets that may be logged in a burst before the rate limit applies.
+pub(super) const LOG_BURST: u32 = 20;
+
+/// Number of bytes of each packet to copy. Enough to cover IPv6 and transport headers.
+const COPY_RANGE: u32 = 128;
+const RECV_TIMEOUT: Duration = Duration::from_millis(500);
+const RECV_BUFFER_SIZE: usize = 64 * 1024;
+
+const NLMSG_HDR_LEN: usize = 16;
+const NFGENMSG_LEN: usize = 4;
+const NLA_HDR_LEN: usize = 4;
+const NLA_TYPE_MASK: u16 = 0x3fff;
+
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_ACK: u16 = 0x4;
+
+const NFNL_SUBSYS_ULOG: u16 = 4;
+const NFULNL_MSG_PACKET: u16 = 0;
+const NFULNL_MSG_CONFIG: u16 = 1;
+
+const NFULA_CFG_CMD: u16 = 1;
+const NFULA_CFG_MODE: u16 = 2;
+const NFULNL_CFG_CMD_BIND: u8 = 1;
+const NFULNL_COPY_PACKET: u8 = 2;
+
+const NFULA_MARK: u16 = 2;
+const NFULA_TIMESTAMP: u16 = 3;
+const NFULA_PAYLOAD: u16 = 9;
+const NFULA_PREFIX: u16 = 10;
+const NFULA_UID: u16 = 11;
+const NFULA_GID: u16 = 14;
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+/// Errors that can occur while listening for blocked packets.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Unable to open netlink socket to netfilter.
+    #[error("Unable to open netlink socket to netfilter")]
+    OpenSocket(#[source] io::Error),
+
+    /// Failed to set the receive timeout of the netlink socket.
+    #[error("Failed to set socket receive timeout")]
+    SetTimeout(#[source] nix::Error),
+
+    /// Failed to bind to the nflog group.
+    #[error("Failed to bind to nflog group {LOG_GROUP}")]
+    Bind(#[source] io::Error),
+
+    /// Failed to spawn the listener thread.
+    #[error("Failed to spawn nflog listener thread")]
+    SpawnThread(#[source] io::Error),
+}
+
+/// Receives packets logged by the firewall and passes them to a callback on a background thread.
+/// Listening stops when this value is dropped.
+pub struct BlockedPacketListener {
+    stop: Arc<AtomicBool>,
+}
+
+impl BlockedPacketListener {
+    /// Bind to the firewall's nflog group and start listening for blocked packets.
+    pub fn start(mut on_packet: impl FnMut(BlockedPacket) + Send + 'static) -> Result<Self, Error> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let (init_tx, init_rx) = sync_mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("nflog-listener".to_owned())
+            .spawn(move || {
+                let socket = match open_socket() {
+                    Ok(socket) => {
+                        let _ = init_tx.send(Ok(()));
+                        socket
+                    }
+                    Err(error) => {
+                        let _ = init_tx.send(Err(error));
+                        return;
+                    }
+                };
+                let mut buffer = vec![0u8; RECV_BUFFER_SIZE];
+                while !thread_stop.load(Ordering::Relaxed) {
+                    match socket.recv(&mut buffer) {
+                        Ok(len) => parse_messages(&buffer[..len]).for_each(&mut on_packet),
+                        Err(error)
+                            if matches!(
+                                error.kind(),
+                                io::ErrorKind::WouldBlock
+                                    | io::ErrorKind::TimedOut
+                                    | io::ErrorKind::Interrupted
+                            ) => {}
+                        Err(error) if error.raw_os_error() == Some(libc::ENOBUFS) => {
+                            log::debug!("Dropped logged packets due to full socket buffer");
+                        }
+                        Err(error) => {
+                            log::error!("Failed to read from nflog socket: {error}");
+                            break;
+                        }
+                    }
+                }
+                log::debug!("Stopped listening for blocked packets");
+            })
+            .map_err(Error::SpawnThread)?;
+
+        init_rx
+            .recv()
+            .unwrap_or_else(|_| Err(Error::SpawnThread(io::ErrorKind::BrokenPipe.into())))?;
+
+        Ok(Self { stop })
+    }
+}
+
+impl Drop for BlockedPacketListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn open_socket() -> Result<mnl::Socket, Error> {
+    let socket = mnl::Socket::new(mnl::Bus::Netfilter).map_err(Error::OpenSocket)?;
+
+    let timeout = TimeVal::milliseconds(RECV_TIMEOUT.as_millis() as i64);
+    nix::sys::socket::setsockopt(
+        socket.as_raw_fd(),
+        nix::sys::socket::sockopt::ReceiveTimeout,
+        &timeout,
+    )
+    .map_err(Error::SetTimeout)?;
+
+    let portid = socket.portid();
+    let seq = 0;
+
+    let bind = config_message(seq, NFULA_CFG_CMD, &[NFULNL_CFG_CMD_BIND]);
+    send_config(&socket, &bind, seq, portid)?;
+
+    let mut mode = COPY_RANGE.to_be_bytes().to_vec();
+    mode.extend_from_slice(&[NFULNL_COPY_PACKET, 0]);
+    let mode = config_message(seq, NFULA_CFG_MODE, &mode);
+    send_config(&socket, &mode, seq, portid)?;
+
+    Ok(socket)
+}
+
+/// Send a config message and wait for the kernel to acknowledge it.
+fn send_config(socket: &mnl::Socket, message: &[u8], seq: u32, portid: u32) -> Result<(), Error> {
+    socket.send(message).map_err(Error::Bind)?;
+
+    let mut buffer = vec![0; RECV_BUFFER_SIZE];
+    loop {
+        let len = socket.recv(&mut buffer).map_err(Error::Bind)?;
+        if len == 0 {
+            return Ok(());
+        }
+        match mnl::cb_run(&buffer[..len], seq, portid).map_err(Error::Bind)? {
+            mnl::CbResult::Stop => return Ok(()),
+            mnl::CbResult::Ok => (),
+        }
+    }
+}
+
+/// Build an nfnetlink_log config message for [LOG_GROUP] containing a single attribute.
+fn config_message(seq: u32, attr_type: u16, attr_payload: &[u8]) -> Vec<u8> {
+    let attr_len = NLA_HDR_LEN + attr_payload.len();
+    let total_len = NLMSG_HDR_LEN + NFGENMSG_LEN + align(attr_len);
+
+    let mut message = Vec::with_capacity(total_len);
+    message.extend_from_slice(&(total_len as u32).to_ne_bytes());
+    message.extend_from_slice(&((NFNL_SUBSYS_ULOG << 8) | NFULNL_MSG_CONFIG).to_ne_bytes());
+    message.extend_from_slice(&(NLM_F_REQUEST | NLM_F_ACK).to_ne_bytes());
+    message.extend_from_slice(&seq.to_ne_bytes());
+    message.extend_from_slice(&0u32.to_ne_bytes());
+
+    // struct nfgenmsg
+    message.push(libc::AF_UNSPEC as u8);
+    message.push(0);
+    message.extend_from_slice(&LOG_GROUP.to_be_bytes());
+
+    message.extend_from_slice(&(attr_len as u16).to_ne_bytes());
+    message.extend_from_slice(&attr_type.to_ne_bytes());
+    message.extend_from_slice(attr_payload);
+    message.resize(total_len, 0);
+
+    message
+}
+
+/// Parse all nfnetlink_log packet messages in a buffer received from the kernel.
+fn parse_messages(buffer: &[u8]) -> impl Iterator<Item = BlockedPacket> + '_ {
+    let mut offset = 0;
+    std::iter::from_fn(move || loop {
+        let header = buffer.get(offset..offset + NLMSG_HDR_LEN)?;
+        let len = u32::from_ne_bytes(header[0..4].try_into().unwrap()) as usize;
+        let msg_type = u16::from_ne_bytes(header[4..6].try_into().unwrap());
+        if len < NLMSG_HDR_LEN {
+            return None;
+        }
+        let message = buffer.get(offset + NLMSG_HDR_LEN..offset + len)?;
+        offset += align(len);
+
+        if msg_type != (NFNL_SUBSYS_ULOG << 8) | NFULNL_MSG_PACKET {
+            continue;
+        }
+        if let Some(packet) = parse_packet_message(message) {
+            return Some(packet);
+        }
+    })
+}
+
+/// Parse the attributes of a single `NFULNL_MSG_PACKET` message, excluding the netlink header.
+fn parse_packet_message(message: &[u8]) -> Option<BlockedPacket> {
+    let mut attributes = message.get(NFGENMSG_LEN..)?;
+
+    let mut chain = None;
+    let mut payload = None;
+    let mut timestamp = None;
+    let mut uid = None;
+    let mut gid = None;
+    let mut mark = None;
+
+    while attributes.len() >= NLA_HDR_LEN {
+        let len = u16::from_ne_bytes(attributes[0..2].try_into().unwrap()) as usize;
+        let attr_type = u16::from_ne_bytes(attributes[2..4].try_into().unwrap()) & NLA_TYPE_MASK;
+        if len < NLA_HDR_LEN {
+            break;
+        }
+        let value = attributes.get(NLA_HDR_LEN..len)?;
+
+        match attr_type {
+            NFULA_PREFIX => {
+                let prefix = CStr::from_bytes_until_nul(value).ok()?;
+                chain = chain_from_prefix(prefix);
+            }
+            NFULA_PAYLOAD => payload = Some(value),
+            NFULA_UID => uid = read_be_u32(value),
+            NFULA_GID => gid = read_be_u32(value),
+            NFULA_MARK => mark = read_be_u32(value),
+            NFULA_TIMESTAMP if value.len() >= 16 => {
+                let secs = u64::from_be_bytes(value[0..8].try_into().unwrap());
+                let usecs = u64::from_be_bytes(value[8..16].try_into().unwrap());
+                timestamp =
+                    Some(UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_micros(usecs));
+            }
+            _ => (),
+        }
+
+        attributes = attributes.get(align(len)..).unwrap_or_default();
+    }
+
+    // Ignore packets logged by anyone else to the same group
+    let chain = chain?;
+    let headers = parse_ip_headers(payload?)?;
+
+    Some(BlockedPacket {
+        timestamp: timestamp.unwrap_or_else(SystemTime::now),
+        chain,
+        protocol: headers.protocol,
+        source: headers.source,
+        source_port: headers.ports.map(|(source, _)| source),
+        destination: headers.destination,
+        destination_port: headers.ports.map(|(_, destination)| destination),
+        uid,
+        gid,
+        // Only packets logged in the output chain have their class ID in the mark. A zero mark
+        // means that the process is not in any net_cls cgroup.
+        cgroup: mark.filter(|&mark| mark != 0 && chain == FirewallChain::Output),
+    })
+}
+
+fn chain_from_prefix(prefix: &CStr) -> Option<FirewallChain> {
+    if prefix == INPUT_PREFIX {
+        Some(FirewallChain::Input)
+    } else if prefix == OUTPUT_PREFIX {
+        Some(FirewallChain::Output)
+    } else if prefix == FORWARD_PREFIX {
+        Some(FirewallChain::Forward)
+    } else {
+        None
+    }
+}
+
+struct IpHeaders {
+    protocol: u8,
+    source: IpAddr,
+    destination: IpAddr,
+    ports: Option<(u16, u16)>,
+}
+
+fn parse_ip_headers(packet: &[u8]) -> Option<IpHeaders> {
+    let (protocol, source, destination, transport) = match packet.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(packet[0] & 0x0f) * 4;
+            let header = packet.get(..20)?;
+            let fragment_offset = u16::from_be_bytes([header[6], header[7]]) & 0x1fff;
+            let source = Ipv4Addr::from(<[u8; 4]>::try_from(&header[12..16]).unwrap());
+            let destination = Ipv4Addr::from(<[u8; 4]>::try_from(&header[16..20]).unwrap());
+            // Only the first fragment contains the transport header
+            let transport = if fragment_offset == 0 {
+                packet.get(header_len..)
+            } else {
+                None
+            };
+            (
+                header[9],
+                IpAddr::from(source),
+                IpAddr::from(destination),
+                transport,
+            )
+        }
+        6 => {
+            let header = packet.get(..40)?;
+            let source = Ipv6Addr::from(<[u8; 16]>::try_from(&header[8..24]).unwrap());
+            let destination = Ipv6Addr::from(<[u8; 16]>::try_from(&header[24..40]).unwrap());
+            (
+                header[6],
+                IpAddr::from(source),
+                IpAddr::from(destination),
+                packet.get(40..),
+            )
+        }
+        _ => return None,
+    };
+
+    let ports = match protocol {
+        IPPROTO_TCP | IPPROTO_UDP => transport.and_then(|transport| {
+            let ports = transport.get(..4)?;
+            Some((
+                u16::from_be_bytes([ports[0], ports[1]]),
+                u16::from_be_bytes([ports[2], ports[3]]),
+            ))
+        }),
+        _ => None,
+    };
+
+    Some(IpHeaders {
+        protocol,
+        source,
+        destination,
+        ports,
+    })
+}
+
+fn read_be_u32(value: &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(value.get(..4)?.try_into().unwrap()))
+}
+
+const fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn attribute(attr_type: u16, value: &[u8]) -> Vec<u8> {
+        let mut attr = Vec::new();
+        attr.extend_from_slice(&((NLA_HDR_LEN + value.len()) as u16).to_ne_bytes());
+        attr.extend_from_slice(&attr_type.to_ne_bytes());
+        attr.extend_from_slice(value);
+        attr.resize(align(attr.len()), 0);
+        attr
+    }
+
+    fn packet_message(attributes: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = attributes.concat();
+        let len = NLMSG_HDR_LEN + NFGENMSG_LEN + body.len();
+        let mut message = Vec::new();
+        message.extend_from_slice(&(len as u32).to_ne_bytes());
+        message.extend_from_slice(&((NFNL_SUBSYS_ULOG << 8) | NFULNL_MSG_PACKET).to_ne_bytes());
+        message.extend_from_slice(&[0; 10]);
+        message.extend_from_slice(&[libc::AF_INET as u8, 0]);
+        message.extend_from_slice(&LOG_GROUP.to_be_bytes());
+        message.extend_from_slice(&body);
+        message
+    }
+
+    fn udp_ipv4_packet() -> Vec<u8> {
+        let mut packet = vec![0x45, 0, 0, 28, 0, 0, 0x40, 0, 64, IPPROTO_UDP, 0, 0];
+        packet.extend_from_slice(&[10, 0, 0, 2]);
+        packet.extend_from_slice(&[1, 1, 1, 1]);
+        packet.extend_from_slice(&5353u16.to_be_bytes());
+        packet.extend_from_slice(&53u16.to_be_bytes());
+        packet.extend_from_slice(&[0, 8, 0, 0]);
+        packet
+    }
+
+    #[test]
+    fn test_parse_udp_packet() {
+        let message = packet_message(&[
+            attribute(NFULA_PREFIX, OUTPUT_PREFIX.to_bytes_with_nul()),
+            attribute(NFULA_UID, &1000u32.to_be_bytes()),
+            attribute(NFULA_GID, &100u32.to_be_bytes()),
+            attribute(NFULA_MARK, &0x4d9f41u32.to_be_bytes()),
+            attribute(NFULA_PAYLOAD, &udp_ipv4_packet()),
+        ]);
+
+        let packets: Vec<_> = parse_messages(&message).collect();
+        assert_eq!(packets.len(), 1);
+        let packet = &packets[0];
+        assert_eq!(packet.chain, FirewallChain::Output);
+        assert_eq!(packet.protocol, IPPROTO_UDP);
+        assert_eq!(packet.source, IpAddr::from([10, 0, 0, 2]));
+        assert_eq!(packet.source_port, Some(5353));
+        assert_eq!(packet.destination, IpAddr::from([1, 1, 1, 1]));
+        assert_eq!(packet.destination_port, Some(53));
+        assert_eq!(packet.uid, Some(1000));
+        assert_eq!(packet.gid, Some(100));
+        assert_eq!(packet.cgroup, Some(0x4d9f41));
+    }
+
+    #[test]
+    fn test_parse_packet_without_cgroup() {
+        let message = packet_message(&[
+            attribute(NFULA_PREFIX, OUTPUT_PREFIX.to_bytes_with_nul()),
+            attribute(NFULA_MARK, &0u32.to_be_bytes()),
+            attribute(NFULA_PAYLOAD, &udp_ipv4_packet()),
+        ]);
+
+        let packet = parse_messages(&message).next().unwrap();
+        assert_eq!(packet.chain, FirewallChain::Output);
+        assert_eq!(packet.cgroup, None);
+    }
+
+    #[test]
+    fn test_parse_ipv6_packet() {
+        let mut payload = vec![0x60, 0, 0, 0, 0, 20, IPPROTO_TCP, 64];
+        payload.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        payload.extend_from_slice(&Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).octets());
+        payload.extend_from_slice(&443u16.to_be_bytes());
+        payload.extend_from_slice(&50000u16.to_be_bytes());
+
+        let message = packet_message(&[
+            attribute(NFULA_PREFIX, INPUT_PREFIX.to_bytes_with_nul()),
+            attribute(NFULA_MARK, &0x6d6f6c65u32.to_be_bytes()),
+            attribute(NFULA_PAYLOAD, &payload),
+        ]);
+
+        let packet = parse_messages(&message).next().unwrap();
+        assert_eq!(packet.chain, FirewallChain::Input);
+        assert_eq!(packet.protocol, IPPROTO_TCP);
+        assert_eq!(packet.source, IpAddr::from(Ipv6Addr::LOCALHOST));
+        assert_eq!(packet.source_port, Some(443));
+        assert_eq!(packet.destination_port, Some(50000));
+        assert_eq!(packet.uid, None);
+        // The mark of incoming packets is not a class ID
+        assert_eq!(packet.cgroup, None);
+    }
+
+    #[test]
+    fn test_ignore_foreign_prefix() {
+        let message = packet_message(&[
+            attribute(NFULA_PREFIX, c"other ".to_bytes_with_nul()),
+            attribute(NFULA_PAYLOAD, &udp_ipv4_packet()),
+        ]);
+        assert_eq!(parse_messages(&message).count(), 0);
+    }
+
+    #[test]
+    fn test_parse_multiple_messages() {
+        let mut buffer = packet_message(&[
+            attribute(NFULA_PREFIX, OUTPUT_PREFIX.to_bytes_with_nul()),
+            attribute(NFULA_PAYLOAD, &udp_ipv4_packet()),
+        ]);
+        buffer.extend(packet_message(&[
+            attribute(NFULA_PREFIX, FORWARD_PREFIX.to_bytes_with_nul()),
+            attribute(NFULA_PAYLOAD, &udp_ipv4_packet()),
+        ]));
+
+        let chains: Vec<_> = parse_messages(&buffer).map(|packet| packet.chain).collect();
+        assert_eq!(chains, [FirewallChain::Output, FirewallChain::Forward]);
+    }
+}
@@ -8,12 +8,12 @@ use talpid_types::cgroup::{find_net_cls_mount, SPLIT_TUNNEL_CGROUP_NAME};
 const DEFAULT_NET_CLS_DIR: &str = "/sys/fs/cgroup/net_cls";
 const NET_CLS_DIR_OVERRIDE_ENV_VAR: &str = "TALPID_NET_CLS_MOUNT_DIR";
 
-/// Identifies packets coming from the cgroup.
+/// Default class ID used to identify packets coming from the cgroup.
 /// This should be an arbitrary but unique integer.
-pub const NET_CLS_CLASSID: u32 = 0x4d9f41;
-/// Value used to mark packets and associated connections.
+pub const DEFAULT_NET_CLS_CLASSID: u32 = 0x4d9f41;
+/// Default value used to mark packets and associated connections.
 /// This should be an arbitrary but unique integer.
-pub const MARK: i32 = 0xf41;
+pub const DEFAULT_MARK: u32 = 0xf41;
 
 /// Errors related to split tunneling.
 #[derive(thiserror::Error, Debug)]
@@ -50,16 +50,18 @@ pub enum Error {
 /// Manages PIDs in the Linux Cgroup excluded from the VPN tunnel.
 pub struct PidManager {
     net_cls_path: PathBuf,
+    net_cls_classid: u32,
 }
 
 impl PidManager {
     /// Creates a new PID Cgroup manager.
     ///
     /// Finds the corresponding Cgroup to use. Will mount a `net_cls` filesystem
-    /// if none exists.
-    pub fn new() -> Result<PidManager, Error> {
+    /// if none exists. Packets sent by processes in the cgroup are tagged with `net_cls_classid`.
+    pub fn new(net_cls_classid: u32) -> Result<PidManager, Error> {
         let manager = PidManager {
             net_cls_path: Self::create_cgroup()?,
+            net_cls_classid,
         };
         manager.setup_exclusion_group()?;
         Ok(manager)
@@ -99,7 +101,7 @@ impl PidManager {
         }
 
         let classid_path = exclusions_dir.join("net_cls.classid");
-        fs::write(classid_path, NET_CLS_CLASSID.to_string().as_bytes())
+        fs::write(classid_path, self.net_cls_classid.to_string().as_bytes())
             .map_err(Error::SetCGroupClassId)
     }
 
@@ -13,6 +13,9 @@ use talpid_types::{
     net::{wireguard as wireguard_types, TunnelParameters},
     tunnel::ErrorStateCause,
 };
+pub use talpid_wireguard::TunnelStatsHandle;
+#[cfg(not(target_os = "android"))]
+pub use talpid_wireguard::{RelaySwitchError, RelaySwitchHandle};
 
 #[cfg(not(target_os = "android"))]
 use talpid_tunnel::EventHook;
@@ -168,7 +171,9 @@ impl TunnelMonitor {
             TunnelParameters::OpenVpn(params) => match &params.proxy {
                 Some(CustomProxy::Shadowsocks(_)) => Some(std::env::current_exe().unwrap()),
                 Some(CustomProxy::Socks5Local(_)) => None,
-                Some(CustomProxy::Socks5Remote(_)) | None => Some(resource_dir.join("openvpn.exe")),
+                Some(CustomProxy::Socks5Remote(_)) | Some(CustomProxy::HttpConnect(_)) | None => {
+                    Some(resource_dir.join("openvpn.exe"))
+                }
             },
             _ => Some(std::env::current_exe().unwrap()),
         }
@@ -265,6 +270,26 @@ impl TunnelMonitor {
         }
     }
 
+    /// Returns a handle for reading the traffic statistics of the tunnel. Only WireGuard tunnels
+    /// support this.
+    pub fn stats_handle(&self) -> Option<TunnelStatsHandle> {
+        match &self.monitor {
+            #[cfg(not(target_os = "android"))]
+            InternalTunnelMonitor::OpenVpn(_) => None,
+            InternalTunnelMonitor::Wireguard(monitor) => Some(monitor.stats_handle()),
+        }
+    }
+
+    /// Returns a handle for moving the tunnel to another relay without taking it down. Only
+    /// WireGuard tunnels support this.
+    #[cfg(not(target_os = "android"))]
+    pub fn relay_switch_handle(&self) -> Option<RelaySwitchHandle> {
+        match &self.monitor {
+            InternalTunnelMonitor::OpenVpn(_) => None,
+            InternalTunnelMonitor::Wireguard(monitor) => Some(monitor.relay_switch_handle()),
+        }
+    }
+
     /// Consumes the monitor and blocks until the tunnel exits or there is an error.
     pub fn wait(self) -> Result<()> {
         self.monitor.wait()
@@ -2,7 +2,7 @@ use futures::channel::{mpsc, oneshot};
 use futures::stream::Fuse;
 use futures::StreamExt;
 
-use talpid_types::net::{AllowedClients, AllowedEndpoint, TunnelParameters};
+use talpid_types::net::{AllowedClients, AllowedEndpoint, TunnelEndpoint, TunnelParameters};
 use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
 use talpid_types::{BoxedError, ErrorExt};
 
@@ -10,11 +10,15 @@ use crate::dns::ResolvedDnsConfig;
 use crate::firewall::FirewallPolicy;
 #[cfg(target_os = "macos")]
 use crate::resolver::LOCAL_DNS_RESOLVER;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use crate::tunnel::RelaySwitchError;
 #[cfg(windows)]
 use crate::tunnel::TunnelMonitor;
 use crate::tunnel::{TunnelEvent, TunnelMetadata};
 
-use super::connecting_state::TunnelCloseEvent;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use super::connecting_state::RelaySwitchSlot;
+use super::connecting_state::{send_tunnel_stats, TunnelCloseEvent, TunnelStatsSlot};
 use super::{
     AfterDisconnect, ConnectingState, DisconnectingState, ErrorState, EventConsequence,
     EventResult, SharedTunnelStateValues, TunnelCommand, TunnelCommandReceiver, TunnelState,
@@ -24,6 +28,17 @@ use super::{
 pub(crate) type TunnelEventsReceiver =
     Fuse<mpsc::UnboundedReceiver<(TunnelEvent, oneshot::Sender<()>)>>;
 
+/// Outcome of trying to move the tunnel to the next relay without reconnecting.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+enum SwitchOutcome {
+    /// The tunnel is being moved to the new relay. The result is reported using
+    /// [`TunnelCommand::RelaySwitchFinished`].
+    Started,
+    /// A new tunnel must be started, using the given parameters if they have already been
+    /// generated.
+    Reconnect(Option<TunnelParameters>),
+}
+
 /// The tunnel is up and working.
 pub struct ConnectedState {
     metadata: TunnelMetadata,
@@ -31,10 +46,17 @@ pub struct ConnectedState {
     tunnel_parameters: TunnelParameters,
     tunnel_close_event: TunnelCloseEvent,
     tunnel_close_tx: oneshot::Sender<()>,
+    tunnel_stats: TunnelStatsSlot,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    relay_switch: RelaySwitchSlot,
+    /// Parameters of the relay that the tunnel is being moved to, if any.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    next_tunnel_parameters: Option<TunnelParameters>,
 }
 
 impl ConnectedState {
     #[cfg_attr(target_os = "android", allow(unused_variables))]
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn enter(
         shared_values: &mut SharedTunnelStateValues,
         metadata: TunnelMetadata,
@@ -42,6 +64,8 @@ impl ConnectedState {
         tunnel_parameters: TunnelParameters,
         tunnel_close_event: TunnelCloseEvent,
         tunnel_close_tx: oneshot::Sender<()>,
+        tunnel_stats: TunnelStatsSlot,
+        #[cfg(any(target_os = "linux", target_os = "macos"))] relay_switch: RelaySwitchSlot,
     ) -> (Box<dyn TunnelState>, TunnelStateTransition) {
         let connected_state = ConnectedState {
             metadata,
@@ -49,13 +73,14 @@ impl ConnectedState {
             tunnel_parameters,
             tunnel_close_event,
             tunnel_close_tx,
+            tunnel_stats,
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            relay_switch,
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            next_tunnel_parameters: None,
         };
 
-        let tunnel_interface = Some(connected_state.metadata.interface.clone());
-        let tunnel_endpoint = talpid_types::net::TunnelEndpoint {
-            tunnel_interface,
-            ..connected_state.tunnel_parameters.get_tunnel_endpoint()
-        };
+        let tunnel_endpoint = connected_state.tunnel_endpoint();
 
         if let Err(error) = connected_state.set_firewall_policy(shared_values) {
             DisconnectingState::enter(
@@ -78,11 +103,29 @@ impl ConnectedState {
         }
     }
 
+    fn tunnel_endpoint(&self) -> TunnelEndpoint {
+        TunnelEndpoint {
+            tunnel_interface: Some(self.metadata.interface.clone()),
+            tunnel_ips: self.metadata.ips.clone(),
+            #[cfg(daita)]
+            daita_parameters: self.metadata.daita,
+            wireguard_backend: self.metadata.wireguard_backend,
+            ..self.tunnel_parameters.get_tunnel_endpoint()
+        }
+    }
+
     fn set_firewall_policy(
         &self,
         shared_values: &mut SharedTunnelStateValues,
     ) -> Result<(), FirewallPolicyError> {
         let policy = self.get_firewall_policy(shared_values);
+        Self::apply_firewall_policy(shared_values, policy)
+    }
+
+    fn apply_firewall_policy(
+        shared_values: &mut SharedTunnelStateValues,
+        policy: FirewallPolicy,
+    ) -> Result<(), FirewallPolicyError> {
         shared_values
             .firewall
             .apply_policy(policy)
@@ -104,18 +147,46 @@ impl ConnectedState {
     }
 
     fn get_firewall_policy(&self, shared_values: &SharedTunnelStateValues) -> FirewallPolicy {
-        let endpoint = self.tunnel_parameters.get_next_hop_endpoint();
+        let peer_endpoint = Self::peer_endpoint(&self.tunnel_parameters, shared_values);
+
+        #[cfg(target_os = "macos")]
+        let redirect_interface = shared_values
+            .runtime
+            .block_on(shared_values.split_tunnel.interface());
+
+        FirewallPolicy::Connected {
+            peer_endpoint,
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            next_peer_endpoint: self
+                .next_tunnel_parameters
+                .as_ref()
+                .map(|parameters| Self::peer_endpoint(parameters, shared_values)),
+            tunnel: self.metadata.clone(),
+            allow_lan: shared_values.allow_lan,
+            #[cfg(not(target_os = "android"))]
+            dns_config: Self::resolve_dns(&self.metadata, shared_values),
+            #[cfg(target_os = "macos")]
+            redirect_interface,
+        }
+    }
+
+    /// Returns the relay endpoint that must be reachable outside the tunnel.
+    #[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
+    fn peer_endpoint(
+        tunnel_parameters: &TunnelParameters,
+        shared_values: &SharedTunnelStateValues,
+    ) -> AllowedEndpoint {
+        let endpoint = tunnel_parameters.get_next_hop_endpoint();
 
         #[cfg(target_os = "windows")]
         let clients = AllowedClients::from(
-            TunnelMonitor::get_relay_client(&shared_values.resource_dir, &self.tunnel_parameters)
+            TunnelMonitor::get_relay_client(&shared_values.resource_dir, tunnel_parameters)
                 .into_iter()
                 .collect::<Vec<_>>(),
         );
 
         #[cfg(not(target_os = "windows"))]
-        let clients = if self
-            .tunnel_parameters
+        let clients = if tunnel_parameters
             .get_openvpn_local_proxy_settings()
             .is_some()
         {
@@ -124,22 +195,7 @@ impl ConnectedState {
             AllowedClients::Root
         };
 
-        let peer_endpoint = AllowedEndpoint { endpoint, clients };
-
-        #[cfg(target_os = "macos")]
-        let redirect_interface = shared_values
-            .runtime
-            .block_on(shared_values.split_tunnel.interface());
-
-        FirewallPolicy::Connected {
-            peer_endpoint,
-            tunnel: self.metadata.clone(),
-            allow_lan: shared_values.allow_lan,
-            #[cfg(not(target_os = "android"))]
-            dns_config: Self::resolve_dns(&self.metadata, shared_values),
-            #[cfg(target_os = "macos")]
-            redirect_interface,
-        }
+        AllowedEndpoint { endpoint, clients }
     }
 
     fn resolve_dns(
@@ -223,6 +279,197 @@ impl ConnectedState {
         }
     }
 
+    /// Returns whether a WireGuard relay may still be reached outside the tunnel, in which case the
+    /// tunnel is kept up while the host appears to be offline. The offline monitor reports the
+    /// host as briefly offline whenever the default route changes, but WireGuard can roam to the
+    /// new route. The connectivity monitor of the tunnel closes it if the relay is unreachable.
+    #[cfg(target_os = "macos")]
+    fn relay_is_routable(&self, shared_values: &SharedTunnelStateValues) -> bool {
+        if !matches!(self.tunnel_parameters, TunnelParameters::Wireguard(_)) {
+            return false;
+        }
+        let relay = self.tunnel_parameters.get_next_hop_endpoint().address;
+        match shared_values
+            .runtime
+            .block_on(shared_values.route_manager.get_default_routes())
+        {
+            Ok((v4_route, v6_route)) => {
+                let routable = if relay.is_ipv4() {
+                    v4_route.is_some()
+                } else {
+                    v6_route.is_some()
+                };
+                if routable {
+                    log::debug!("Keeping tunnel up since the relay is still routable");
+                }
+                routable
+            }
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to get default routes")
+                );
+                false
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn relay_is_routable(&self, _shared_values: &SharedTunnelStateValues) -> bool {
+        false
+    }
+
+    /// Connects to the relay selected by the tunnel parameters generator. The tunnel is moved to
+    /// the new relay without taking it down if possible, and torn down otherwise.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn reconnect(
+        mut self: Box<Self>,
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> EventConsequence {
+        match self.start_relay_switch(shared_values) {
+            Ok(SwitchOutcome::Started) => EventConsequence::SameState(self),
+            Ok(SwitchOutcome::Reconnect(Some(parameters))) => self.disconnect(
+                shared_values,
+                AfterDisconnect::ReconnectWith(Box::new(parameters)),
+            ),
+            Ok(SwitchOutcome::Reconnect(None)) => {
+                self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
+            }
+            Err(error) => self.disconnect(
+                shared_values,
+                AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+            ),
+        }
+    }
+
+    #[cfg(any(target_os = "android", target_os = "windows"))]
+    fn reconnect(self: Box<Self>, shared_values: &mut SharedTunnelStateValues) -> EventConsequence {
+        self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
+    }
+
+    /// Starts moving the tunnel to the next relay if make-before-break is enabled, and the relay is
+    /// the only thing that would change. The firewall allows both relays until the new relay has
+    /// responded. Traffic keeps using the current relay meanwhile, or is paused while an ephemeral
+    /// peer is negotiated with the new relay if the tunnel uses one. The switch runs in the
+    /// background, so that the state machine keeps handling commands.
+    ///
+    /// Not supported on Windows, where the firewall only allows a single relay while connected, nor
+    /// for multihop or obfuscated tunnels.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn start_relay_switch(
+        &mut self,
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> Result<SwitchOutcome, FirewallPolicyError> {
+        let TunnelParameters::Wireguard(current_parameters) = &self.tunnel_parameters else {
+            return Ok(SwitchOutcome::Reconnect(None));
+        };
+        if !current_parameters.options.make_before_break {
+            return Ok(SwitchOutcome::Reconnect(None));
+        }
+        // Reconnect as usual rather than changing destination in the middle of a switch
+        if self.next_tunnel_parameters.is_some() {
+            return Ok(SwitchOutcome::Reconnect(None));
+        }
+        let Some(relay_switch) = self.relay_switch.lock().unwrap().clone() else {
+            return Ok(SwitchOutcome::Reconnect(None));
+        };
+        let Some(ip_availability) = shared_values.connectivity.availability() else {
+            return Ok(SwitchOutcome::Reconnect(None));
+        };
+        let new_parameters = match shared_values.runtime.block_on(
+            shared_values
+                .tunnel_parameters_generator
+                .generate(0, ip_availability),
+        ) {
+            Ok(parameters) => parameters,
+            // Any error is reported once a new tunnel is started
+            Err(_) => return Ok(SwitchOutcome::Reconnect(None)),
+        };
+        let TunnelParameters::Wireguard(new_wireguard_parameters) = &new_parameters else {
+            return Ok(SwitchOutcome::Reconnect(Some(new_parameters)));
+        };
+        if !new_wireguard_parameters.options.make_before_break
+            || new_wireguard_parameters.connection.peer == current_parameters.connection.peer
+            || !current_parameters.can_switch_peer_to(new_wireguard_parameters)
+        {
+            return Ok(SwitchOutcome::Reconnect(Some(new_parameters)));
+        }
+
+        log::info!("Switching relay before disconnecting from the current one");
+        let new_peer = new_wireguard_parameters.connection.peer.clone();
+        let device_key = new_wireguard_parameters
+            .connection
+            .tunnel
+            .private_key
+            .clone();
+
+        self.next_tunnel_parameters = Some(new_parameters.clone());
+        self.set_firewall_policy(shared_values)?;
+
+        let command_tx = shared_values.command_tx.clone();
+        shared_values.runtime.spawn(async move {
+            let result = relay_switch.switch(new_peer, device_key).await;
+            if let Some(tx) = command_tx.upgrade() {
+                let _ = tx.unbounded_send(TunnelCommand::RelaySwitchFinished(
+                    Box::new(new_parameters),
+                    result,
+                ));
+            }
+        });
+
+        Ok(SwitchOutcome::Started)
+    }
+
+    /// Handles the result of moving the tunnel to the relay in `parameters`. The tunnel is
+    /// reconnected to that relay if it could not be moved.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn finish_relay_switch(
+        mut self: Box<Self>,
+        parameters: TunnelParameters,
+        result: Result<(), RelaySwitchError>,
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> EventConsequence {
+        if self.next_tunnel_parameters.as_ref() != Some(&parameters) {
+            log::debug!("Ignoring result of an abandoned relay switch");
+            return EventConsequence::SameState(self);
+        }
+        self.next_tunnel_parameters = None;
+
+        let reconnect_parameters = match result {
+            Ok(()) => {
+                log::info!("Switched relay without reconnecting");
+                self.tunnel_parameters = parameters;
+                None
+            }
+            Err(error) => {
+                log::warn!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to switch relay. Reconnecting")
+                );
+                Some(parameters)
+            }
+        };
+
+        // Stop allowing the relay that is no longer used
+        if let Err(error) = self.set_firewall_policy(shared_values) {
+            return self.disconnect(
+                shared_values,
+                AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+            );
+        }
+
+        match reconnect_parameters {
+            None => {
+                let transition = TunnelStateTransition::Connected(self.tunnel_endpoint());
+                EventConsequence::NewState((self, transition))
+            }
+            Some(parameters) => self.disconnect(
+                shared_values,
+                AfterDisconnect::ReconnectWith(Box::new(parameters)),
+            ),
+        }
+    }
+
     fn disconnect(
         self,
         shared_values: &mut SharedTunnelStateValues,
@@ -322,7 +569,7 @@ impl ConnectedState {
             }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
-                if connectivity.is_offline() {
+                if connectivity.is_offline() && !self.relay_is_routable(shared_values) {
                     self.disconnect(
                         shared_values,
                         AfterDisconnect::Block(ErrorStateCause::IsOffline),
@@ -331,15 +578,51 @@ impl ConnectedState {
                     SameState(self)
                 }
             }
-            Some(TunnelCommand::Connect) => {
-                self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
+            #[cfg(target_os = "macos")]
+            Some(TunnelCommand::DefaultRouteChanged(event)) => {
+                log::debug!("Non-tunnel default route changed while connected: {event:?}");
+                // Routes to the relay are updated by the route manager, but make sure that they
+                // exist. The firewall policy depends on the default interface if split tunneling
+                // is enabled.
+                if let Err(error) = shared_values.route_manager.refresh_routes() {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to refresh routes")
+                    );
+                }
+                match self.set_firewall_policy(shared_values) {
+                    Ok(()) => SameState(self),
+                    Err(error) => self.disconnect(
+                        shared_values,
+                        AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                    ),
+                }
+            }
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            Some(TunnelCommand::RelaySwitchFinished(parameters, result)) => {
+                self.finish_relay_switch(*parameters, result, shared_values)
             }
+            Some(TunnelCommand::Connect) => self.reconnect(shared_values),
             Some(TunnelCommand::Disconnect) | None => {
                 self.disconnect(shared_values, AfterDisconnect::Nothing)
             }
             Some(TunnelCommand::Block(reason)) => {
                 self.disconnect(shared_values, AfterDisconnect::Block(reason))
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::LogBlockedPackets(enabled, complete_tx)) => {
+                shared_values.set_log_blocked_packets(enabled);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            Some(TunnelCommand::GetTunnelStats(stats_tx)) => {
+                send_tunnel_stats(&shared_values.runtime, &self.tunnel_stats, stats_tx);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetTunnelParameters(parameters_tx)) => {
+                let _ = parameters_tx.send(Some(self.tunnel_parameters.clone()));
+                SameState(self)
+            }
             #[cfg(target_os = "android")]
             Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
                 shared_values.bypass_socket(fd, done_tx);
@@ -399,6 +682,10 @@ impl ConnectedState {
             Some((TunnelEvent::Down, _)) | None => {
                 self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
             }
+            Some((TunnelEvent::Notification(notification), _)) => {
+                shared_values.notify_tunnel_listener(notification);
+                SameState(self)
+            }
             Some(_) => SameState(self),
         }
     }
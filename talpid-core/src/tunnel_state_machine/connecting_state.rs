@@ -9,6 +9,7 @@ use futures::{FutureExt, StreamExt};
 use talpid_routing::RouteManagerHandle;
 use talpid_tunnel::tun_provider::TunProvider;
 use talpid_tunnel::{EventHook, TunnelArgs, TunnelEvent, TunnelMetadata};
+use talpid_types::net::stats::TunnelStats;
 use talpid_types::net::{AllowedClients, AllowedEndpoint, AllowedTunnelTraffic, TunnelParameters};
 use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
 use talpid_types::ErrorExt;
@@ -25,10 +26,21 @@ use crate::dns::DnsConfig;
 use crate::firewall::FirewallPolicy;
 #[cfg(target_os = "macos")]
 use crate::resolver::LOCAL_DNS_RESOLVER;
-use crate::tunnel::{self, TunnelMonitor};
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use crate::tunnel::RelaySwitchHandle;
+use crate::tunnel::{self, TunnelMonitor, TunnelStatsHandle};
 
 pub(crate) type TunnelCloseEvent = Fuse<oneshot::Receiver<Option<ErrorStateCause>>>;
 
+/// Stats handle of the tunnel monitor. It is set by the tunnel monitor thread once the tunnel
+/// has been started.
+pub(crate) type TunnelStatsSlot = Arc<Mutex<Option<TunnelStatsHandle>>>;
+
+/// Handle for moving the tunnel to another relay. It is set by the tunnel monitor thread once the
+/// tunnel has been started.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) type RelaySwitchSlot = Arc<Mutex<Option<RelaySwitchHandle>>>;
+
 #[cfg(target_os = "android")]
 const MAX_ATTEMPTS_WITH_SAME_TUN: u32 = 5;
 const MIN_TUNNEL_ALIVE_TIME: Duration = Duration::from_millis(1000);
@@ -45,6 +57,9 @@ pub struct ConnectingState {
     allowed_tunnel_traffic: AllowedTunnelTraffic,
     tunnel_close_event: TunnelCloseEvent,
     tunnel_close_tx: oneshot::Sender<()>,
+    tunnel_stats: TunnelStatsSlot,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    relay_switch: RelaySwitchSlot,
     retry_attempt: u32,
 }
 
@@ -52,6 +67,16 @@ impl ConnectingState {
     pub(super) fn enter(
         shared_values: &mut SharedTunnelStateValues,
         retry_attempt: u32,
+    ) -> (Box<dyn TunnelState>, TunnelStateTransition) {
+        Self::enter_with_parameters(shared_values, retry_attempt, None)
+    }
+
+    /// Like [`ConnectingState::enter`], but starts the tunnel using `tunnel_parameters` instead of
+    /// generating new parameters, if given.
+    pub(super) fn enter_with_parameters(
+        shared_values: &mut SharedTunnelStateValues,
+        retry_attempt: u32,
+        tunnel_parameters: Option<TunnelParameters>,
     ) -> (Box<dyn TunnelState>, TunnelStateTransition) {
         #[cfg(target_os = "macos")]
         if *LOCAL_DNS_RESOLVER {
@@ -87,11 +112,15 @@ impl ConnectingState {
             }
         };
 
-        match shared_values.runtime.block_on(
-            shared_values
-                .tunnel_parameters_generator
-                .generate(retry_attempt, ip_availability),
-        ) {
+        let tunnel_parameters = match tunnel_parameters {
+            Some(tunnel_parameters) => Ok(tunnel_parameters),
+            None => shared_values.runtime.block_on(
+                shared_values
+                    .tunnel_parameters_generator
+                    .generate(retry_attempt, ip_availability),
+            ),
+        };
+        match tunnel_parameters {
             Err(err) => {
                 ErrorState::enter(shared_values, ErrorStateCause::TunnelParameterError(err))
             }
@@ -237,6 +266,12 @@ impl ConnectingState {
         let (tunnel_close_event_tx, tunnel_close_event_rx) = oneshot::channel();
 
         let tunnel_parameters = parameters.clone();
+        let tunnel_stats = TunnelStatsSlot::default();
+        let moved_tunnel_stats = tunnel_stats.clone();
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        let relay_switch = RelaySwitchSlot::default();
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        let moved_relay_switch = relay_switch.clone();
 
         tokio::task::spawn_blocking(move || {
             let start = Instant::now();
@@ -253,6 +288,11 @@ impl ConnectingState {
 
             let block_reason = match TunnelMonitor::start(&tunnel_parameters, &log_dir, args) {
                 Ok(monitor) => {
+                    *moved_tunnel_stats.lock().unwrap() = monitor.stats_handle();
+                    #[cfg(any(target_os = "linux", target_os = "macos"))]
+                    {
+                        *moved_relay_switch.lock().unwrap() = monitor.relay_switch_handle();
+                    }
                     let reason = Self::wait_for_tunnel_monitor(monitor, retry_attempt);
                     log::debug!("Tunnel monitor exited with block reason: {:?}", reason);
                     reason
@@ -292,6 +332,9 @@ impl ConnectingState {
             allowed_tunnel_traffic: INITIAL_ALLOWED_TUNNEL_TRAFFIC,
             tunnel_close_event: tunnel_close_event_rx.fuse(),
             tunnel_close_tx,
+            tunnel_stats,
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            relay_switch,
             retry_attempt,
         }
     }
@@ -464,6 +507,24 @@ impl ConnectingState {
             Some(TunnelCommand::Block(reason)) => {
                 self.disconnect(shared_values, AfterDisconnect::Block(reason))
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::LogBlockedPackets(enabled, complete_tx)) => {
+                shared_values.set_log_blocked_packets(enabled);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "macos")]
+            Some(TunnelCommand::DefaultRouteChanged(_)) => SameState(self),
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            Some(TunnelCommand::RelaySwitchFinished(..)) => SameState(self),
+            Some(TunnelCommand::GetTunnelStats(stats_tx)) => {
+                send_tunnel_stats(&shared_values.runtime, &self.tunnel_stats, stats_tx);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetTunnelParameters(parameters_tx)) => {
+                let _ = parameters_tx.send(Some(self.tunnel_parameters.clone()));
+                SameState(self)
+            }
             #[cfg(target_os = "android")]
             Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
                 shared_values.bypass_socket(fd, done_tx);
@@ -575,6 +636,9 @@ impl ConnectingState {
                 self.tunnel_parameters,
                 self.tunnel_close_event,
                 self.tunnel_close_tx,
+                self.tunnel_stats,
+                #[cfg(any(target_os = "linux", target_os = "macos"))]
+                self.relay_switch,
             )),
             Some((TunnelEvent::Down, _)) => {
                 // It is important to reset this before the tunnel device is down,
@@ -585,6 +649,10 @@ impl ConnectingState {
 
                 SameState(self)
             }
+            Some((TunnelEvent::Notification(notification), _)) => {
+                shared_values.notify_tunnel_listener(notification);
+                SameState(self)
+            }
             None => {
                 // The channel was closed
                 log::debug!("The tunnel disconnected unexpectedly");
@@ -627,6 +695,21 @@ fn should_retry(error: &tunnel::Error, retry_attempt: u32) -> bool {
     error.is_recoverable()
 }
 
+/// Sample the stats of the tunnel in `tunnel_stats`, if any, and send them to `stats_tx`.
+pub(crate) fn send_tunnel_stats(
+    runtime: &tokio::runtime::Handle,
+    tunnel_stats: &TunnelStatsSlot,
+    stats_tx: oneshot::Sender<Option<TunnelStats>>,
+) {
+    let Some(handle) = tunnel_stats.lock().unwrap().clone() else {
+        let _ = stats_tx.send(None);
+        return;
+    };
+    runtime.spawn(async move {
+        let _ = stats_tx.send(handle.get().await);
+    });
+}
+
 impl TunnelState for ConnectingState {
     fn handle_event(
         mut self: Box<Self>,
@@ -4,6 +4,8 @@ use super::{
     TunnelState, TunnelStateTransition,
 };
 use futures::{channel::oneshot, future::FusedFuture, StreamExt};
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use talpid_types::net::TunnelParameters;
 use talpid_types::tunnel::{ActionAfterDisconnect, ErrorStateCause};
 
 /// This state is active from when we manually trigger a tunnel kill until the tunnel wait
@@ -62,6 +64,15 @@ impl DisconnectingState {
                     AfterDisconnect::Reconnect(_) if connectivity.is_offline() => {
                         self.after_disconnect = AfterDisconnect::Block(ErrorStateCause::IsOffline)
                     }
+                    #[cfg(any(target_os = "linux", target_os = "macos"))]
+                    AfterDisconnect::ReconnectWith(_) => {
+                        // The parameters may no longer match the available IP versions
+                        self.after_disconnect = if connectivity.is_offline() {
+                            AfterDisconnect::Block(ErrorStateCause::IsOffline)
+                        } else {
+                            AfterDisconnect::Reconnect(0)
+                        };
+                    }
                     AfterDisconnect::Block(ErrorStateCause::IsOffline)
                         if !connectivity.is_offline() =>
                     {
@@ -87,8 +98,23 @@ impl DisconnectingState {
                     _ => AfterDisconnect::Block(reason),
                 }
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::LogBlockedPackets(enabled, complete_tx)) => {
+                shared_values.set_log_blocked_packets(enabled);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "macos")]
+            Some(TunnelCommand::DefaultRouteChanged(_)) => (),
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            Some(TunnelCommand::RelaySwitchFinished(..)) => (),
+            Some(TunnelCommand::GetTunnelStats(stats_tx)) => {
+                let _ = stats_tx.send(None);
+            }
+            Some(TunnelCommand::GetTunnelParameters(parameters_tx)) => {
+                let _ = parameters_tx.send(None);
+            }
             None => {
-                if let AfterDisconnect::Reconnect(_) = self.after_disconnect {
+                if let ActionAfterDisconnect::Reconnect = self.after_disconnect.action() {
                     self.after_disconnect = AfterDisconnect::Nothing;
                 }
             }
@@ -129,6 +155,10 @@ impl DisconnectingState {
             AfterDisconnect::Reconnect(retry_attempt) => {
                 ConnectingState::enter(shared_values, retry_attempt)
             }
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            AfterDisconnect::ReconnectWith(tunnel_parameters) => {
+                ConnectingState::enter_with_parameters(shared_values, 0, Some(*tunnel_parameters))
+            }
         }
     }
 }
@@ -175,6 +205,9 @@ pub enum AfterDisconnect {
     Nothing,
     Block(ErrorStateCause),
     Reconnect(u32),
+    /// Reconnect using parameters that have already been generated.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    ReconnectWith(Box<TunnelParameters>),
 }
 
 impl AfterDisconnect {
@@ -184,6 +217,8 @@ impl AfterDisconnect {
             AfterDisconnect::Nothing => ActionAfterDisconnect::Nothing,
             AfterDisconnect::Block(..) => ActionAfterDisconnect::Block,
             AfterDisconnect::Reconnect(..) => ActionAfterDisconnect::Reconnect,
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            AfterDisconnect::ReconnectWith(..) => ActionAfterDisconnect::Reconnect,
         }
     }
 }
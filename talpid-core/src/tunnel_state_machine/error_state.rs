@@ -227,6 +227,24 @@ impl TunnelState for ErrorState {
             Some(TunnelCommand::Block(reason)) => {
                 NewState(ErrorState::enter(shared_values, reason))
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::LogBlockedPackets(enabled, complete_tx)) => {
+                shared_values.set_log_blocked_packets(enabled);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "macos")]
+            Some(TunnelCommand::DefaultRouteChanged(_)) => SameState(self),
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            Some(TunnelCommand::RelaySwitchFinished(..)) => SameState(self),
+            Some(TunnelCommand::GetTunnelStats(stats_tx)) => {
+                let _ = stats_tx.send(None);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetTunnelParameters(parameters_tx)) => {
+                let _ = parameters_tx.send(None);
+                SameState(self)
+            }
             #[cfg(target_os = "android")]
             Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
                 shared_values.bypass_socket(fd, done_tx);
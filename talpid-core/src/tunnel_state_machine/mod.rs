@@ -21,11 +21,13 @@ use crate::{
 };
 #[cfg(any(target_os = "windows", target_os = "macos"))]
 use std::ffi::OsString;
+#[cfg(target_os = "macos")]
+use talpid_routing::DefaultRouteEvent;
 use talpid_routing::RouteManagerHandle;
 #[cfg(target_os = "macos")]
 use talpid_tunnel::TunnelMetadata;
 use talpid_tunnel::{tun_provider::TunProvider, TunnelEvent};
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
 use talpid_types::ErrorExt;
 
 use futures::{
@@ -45,8 +47,10 @@ use std::{
 #[cfg(target_os = "android")]
 use talpid_types::{android::AndroidContext, ErrorExt};
 use talpid_types::{
-    net::{AllowedEndpoint, Connectivity, IpAvailability, TunnelParameters},
-    tunnel::{ErrorStateCause, ParameterGenerationError, TunnelStateTransition},
+    net::{stats::TunnelStats, AllowedEndpoint, Connectivity, IpAvailability, TunnelParameters},
+    tunnel::{
+        ErrorStateCause, ParameterGenerationError, TunnelNotification, TunnelStateTransition,
+    },
 };
 
 #[cfg(target_os = "android")]
@@ -113,12 +117,17 @@ pub struct InitialTunnelState {
 /// Identifiers for various network resources that should be unique to a given instance of a tunnel
 /// state machine.
 #[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LinuxNetworkingIdentifiers {
     /// Firewall mark is used to mark traffic which should be able to bypass the tunnel
     pub fwmark: u32,
     /// The table ID will be used for the routing table that will route all traffic through the
     /// tunnel interface.
     pub table_id: u32,
+    /// Connection tracking mark applied to connections of processes excluded from the tunnel.
+    pub split_tunnel_mark: u32,
+    /// Class ID of the `net_cls` cgroup that contains processes excluded from the tunnel.
+    pub net_cls_classid: u32,
 }
 
 /// Spawn the tunnel state machine thread, returning a channel for sending tunnel commands.
@@ -129,6 +138,7 @@ pub async fn spawn(
     log_dir: Option<PathBuf>,
     resource_dir: PathBuf,
     state_change_listener: impl Sender<TunnelStateTransition> + Send + 'static,
+    tunnel_notification_listener: impl Sender<TunnelNotification> + Send + 'static,
     offline_state_listener: mpsc::UnboundedSender<Connectivity>,
     route_manager: RouteManagerHandle,
     #[cfg(target_os = "windows")] volume_update_rx: mpsc::UnboundedReceiver<()>,
@@ -153,6 +163,7 @@ pub async fn spawn(
         settings: initial_settings,
         command_tx: weak_command_tx,
         offline_state_tx: offline_state_listener,
+        tunnel_notification_tx: Box::new(tunnel_notification_listener),
         tunnel_parameters_generator,
         tun_provider,
         log_dir,
@@ -203,12 +214,32 @@ pub enum TunnelCommand {
     BlockWhenDisconnected(bool, oneshot::Sender<()>),
     /// Notify the state machine of the connectivity of the device.
     Connectivity(Connectivity),
+    /// Notify the state machine that a non-tunnel default route was added, changed or removed.
+    #[cfg(target_os = "macos")]
+    DefaultRouteChanged(DefaultRouteEvent),
+    /// Notify the state machine that moving the tunnel to the relay in the given parameters has
+    /// finished, successfully or not.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    RelaySwitchFinished(
+        Box<TunnelParameters>,
+        Result<(), crate::tunnel::RelaySwitchError>,
+    ),
     /// Open tunnel connection.
     Connect,
     /// Close tunnel connection.
     Disconnect,
     /// Block all network access unless tunnel is disconnecting or disconnected
     Block(ErrorStateCause),
+    /// Enable or disable logging of packets blocked by the firewall. `()` is sent to the channel
+    /// after attempting to update the firewall, regardless of whether it succeeded.
+    #[cfg(target_os = "linux")]
+    LogBlockedPackets(bool, oneshot::Sender<()>),
+    /// Sample the traffic statistics of the current tunnel. `None` is sent if there is no tunnel,
+    /// or if its statistics cannot be read.
+    GetTunnelStats(oneshot::Sender<Option<TunnelStats>>),
+    /// Get the parameters that the current tunnel was started with, or that it has been moved to
+    /// since. `None` is sent if there is no tunnel.
+    GetTunnelParameters(oneshot::Sender<Option<TunnelParameters>>),
     /// Bypass a socket, allowing traffic to flow through outside the tunnel.
     #[cfg(target_os = "android")]
     BypassSocket(RawFd, oneshot::Sender<()>),
@@ -251,6 +282,7 @@ struct TunnelStateMachineInitArgs<G: TunnelParametersGenerator> {
     settings: InitialTunnelState,
     command_tx: std::sync::Weak<mpsc::UnboundedSender<TunnelCommand>>,
     offline_state_tx: mpsc::UnboundedSender<Connectivity>,
+    tunnel_notification_tx: Box<dyn Sender<TunnelNotification> + Send>,
     tunnel_parameters_generator: G,
     tun_provider: TunProvider,
     log_dir: Option<PathBuf>,
@@ -307,7 +339,7 @@ impl TunnelStateMachine {
             initial_state: InitialFirewallState::None,
             allow_lan: args.settings.allow_lan,
             #[cfg(target_os = "linux")]
-            fwmark: args.linux_ids.fwmark,
+            linux_ids: args.linux_ids,
         };
 
         let firewall = Firewall::from_args(fw_args).map_err(Error::InitFirewallError)?;
@@ -320,6 +352,28 @@ impl TunnelStateMachine {
         )
         .map_err(Error::InitDnsMonitorError)?;
 
+        #[cfg(target_os = "macos")]
+        match args.route_manager.default_route_listener().await {
+            Ok(mut route_events) => {
+                let command_tx = args.command_tx.clone();
+                tokio::spawn(async move {
+                    while let Some(event) = route_events.next().await {
+                        let Some(tx) = command_tx.upgrade() else {
+                            break;
+                        };
+                        let _ = tx.unbounded_send(TunnelCommand::DefaultRouteChanged(event));
+                    }
+                });
+            }
+            Err(error) => log::warn!(
+                "{}",
+                error.display_chain_with_msg("Failed to listen for default route changes")
+            ),
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        let command_tx = args.command_tx.clone();
+
         let (offline_tx, mut offline_rx) = mpsc::unbounded();
         let initial_offline_state_tx = args.offline_state_tx.clone();
         tokio::spawn(async move {
@@ -387,10 +441,13 @@ impl TunnelStateMachine {
             tun_provider: Arc::new(Mutex::new(args.tun_provider)),
             log_dir: args.log_dir,
             resource_dir: args.resource_dir,
+            tunnel_notification_tx: args.tunnel_notification_tx,
             #[cfg(target_os = "linux")]
             connectivity_check_was_enabled: None,
             #[cfg(target_os = "macos")]
             filtering_resolver,
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            command_tx,
         };
 
         tokio::task::spawn_blocking(move || {
@@ -487,6 +544,8 @@ struct SharedTunnelStateValues {
     log_dir: Option<PathBuf>,
     /// Resource directory path.
     resource_dir: PathBuf,
+    /// Listener for tunnel events that do not cause a state transition.
+    tunnel_notification_tx: Box<dyn Sender<TunnelNotification> + Send>,
 
     /// NetworkManager's connecitivity check state.
     #[cfg(target_os = "linux")]
@@ -495,6 +554,10 @@ struct SharedTunnelStateValues {
     /// Filtering resolver handle
     #[cfg(target_os = "macos")]
     filtering_resolver: crate::resolver::ResolverHandle,
+
+    /// Sender used by background tasks to report back to the state machine.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    command_tx: std::sync::Weak<mpsc::UnboundedSender<TunnelCommand>>,
 }
 
 impl SharedTunnelStateValues {
@@ -556,6 +619,23 @@ impl SharedTunnelStateValues {
             .map_err(|error| ErrorStateCause::from(&error))
     }
 
+    #[cfg(target_os = "linux")]
+    pub fn set_log_blocked_packets(&mut self, enabled: bool) {
+        if let Err(error) = self.firewall.set_log_blocked(enabled) {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to update logging of blocked packets")
+            );
+        }
+    }
+
+    /// Forward an event that does not affect the tunnel state to the listener.
+    pub fn notify_tunnel_listener(&self, notification: TunnelNotification) {
+        if self.tunnel_notification_tx.send(notification).is_err() {
+            log::warn!("Tunnel notification listener is closed");
+        }
+    }
+
     pub fn set_allow_lan(&mut self, allow_lan: bool) -> bool {
         if self.allow_lan != allow_lan {
             self.allow_lan = allow_lan;
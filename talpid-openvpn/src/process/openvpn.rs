@@ -312,8 +312,8 @@ impl OpenVpnCommand {
             }
             Some(CustomProxy::Socks5Remote(ref remote_proxy)) => {
                 args.push("--socks-proxy".to_owned());
-                args.push(remote_proxy.endpoint.ip().to_string());
-                args.push(remote_proxy.endpoint.port().to_string());
+                args.push(remote_proxy.endpoint.host.to_string());
+                args.push(remote_proxy.endpoint.port.to_string());
 
                 if let Some(ref _auth) = remote_proxy.auth {
                     if let Some(ref auth_file) = self.proxy_auth_path {
@@ -324,7 +324,26 @@ impl OpenVpnCommand {
                 }
 
                 args.push("--route".to_owned());
-                args.push(remote_proxy.endpoint.ip().to_string());
+                args.push(remote_proxy.endpoint.host.to_string());
+                args.push("255.255.255.255".to_owned());
+                args.push("net_gateway".to_owned());
+            }
+            Some(CustomProxy::HttpConnect(ref http_proxy)) => {
+                args.push("--http-proxy".to_owned());
+                args.push(http_proxy.endpoint.host.to_string());
+                args.push(http_proxy.endpoint.port.to_string());
+
+                if let Some(ref _auth) = http_proxy.auth {
+                    if let Some(ref auth_file) = self.proxy_auth_path {
+                        args.push(auth_file.to_string_lossy().to_string());
+                        args.push("basic".to_owned());
+                    } else {
+                        log::error!("Proxy credentials present but credentials file missing");
+                    }
+                }
+
+                args.push("--route".to_owned());
+                args.push(http_proxy.endpoint.host.to_string());
                 args.push("255.255.255.255".to_owned());
                 args.push("net_gateway".to_owned());
             }
@@ -339,7 +358,7 @@ impl OpenVpnCommand {
                 }
 
                 args.push("--route".to_owned());
-                args.push(ss.endpoint.ip().to_string());
+                args.push(ss.endpoint.host.to_string());
                 args.push("255.255.255.255".to_owned());
                 args.push("net_gateway".to_owned());
             }
@@ -13,6 +13,9 @@ pub enum Error {
 
     #[error("I/O error")]
     Io(io::Error),
+
+    #[error("OpenVPN does not support connecting to HTTP proxies over TLS")]
+    HttpProxyTlsUnsupported,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -53,7 +56,16 @@ pub async fn start_proxy(
         CustomProxy::Socks5Remote(remote_settings) => {
             // These are generic proxy settings with the proxy client not managed by us.
             Ok(Box::new(noop::NoopProxyMonitor::start(
-                remote_settings.endpoint.port(),
+                remote_settings.endpoint.port,
+            )?))
+        }
+        CustomProxy::HttpConnect(http_settings) => {
+            if http_settings.tls.is_some() {
+                return Err(Error::HttpProxyTlsUnsupported);
+            }
+            // These are generic proxy settings with the proxy client not managed by us.
+            Ok(Box::new(noop::NoopProxyMonitor::start(
+                http_settings.endpoint.port,
             )?))
         }
         CustomProxy::Shadowsocks(ss_settings) => Ok(Box::new(
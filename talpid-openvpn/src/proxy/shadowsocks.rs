@@ -17,7 +17,10 @@ use shadowsocks_service::{
 };
 
 use super::{Error, ProxyMonitor, ProxyMonitorCloseHandle};
-use talpid_types::{net::proxy::Shadowsocks, ErrorExt};
+use talpid_types::{
+    net::proxy::{ProxyHost, Shadowsocks},
+    ErrorExt,
+};
 
 pub struct ShadowsocksProxyMonitor {
     port: u16,
@@ -58,8 +61,16 @@ impl ShadowsocksProxyMonitor {
             .local
             .push(LocalInstanceConfig::with_local_config(local));
 
+        let server_addr = match &settings.endpoint.host {
+            ProxyHost::Ip(ip) => {
+                ServerAddr::SocketAddr(SocketAddr::new(*ip, settings.endpoint.port))
+            }
+            ProxyHost::Hostname(hostname) => {
+                ServerAddr::DomainName(hostname.clone(), settings.endpoint.port)
+            }
+        };
         let server = ServerConfig::new(
-            settings.endpoint,
+            server_addr,
             settings.password.clone(),
             settings.cipher.parse().map_err(|_| {
                 io::Error::new(
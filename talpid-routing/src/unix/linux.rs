@@ -125,6 +125,16 @@ pub enum Error {
     #[error("Cannot find a free routing table ID")]
     NoFreeRoutingTableId,
 
+    /// An existing routing rule uses the same routing table ID or firewall mark.
+    #[error(
+        "Existing routing rule conflicts with table ID {table_id} or fwmark {fwmark:#x}: {rule}"
+    )]
+    ConflictingRoutingRule {
+        table_id: u32,
+        fwmark: u32,
+        rule: String,
+    },
+
     #[error("Shutting down route manager")]
     Shutdown,
 }
@@ -172,11 +182,26 @@ impl RouteManagerImpl {
             fwmark,
         };
 
+        monitor.check_conflicting_rules().await?;
         monitor.clear_routing_rules().await?;
 
         Ok(monitor)
     }
 
+    /// Fail if a routing rule that we did not create references our routing table or fwmark.
+    /// Rules left behind by a previous instance are not considered to be conflicts.
+    async fn check_conflicting_rules(&mut self) -> Result<()> {
+        let rules = self.get_rules().await?;
+        match find_conflicting_rule(&rules, self.fwmark, self.table_id) {
+            Some(rule) => Err(Error::ConflictingRoutingRule {
+                table_id: self.table_id,
+                fwmark: self.fwmark,
+                rule: format!("{rule:?}"),
+            }),
+            None => Ok(()),
+        }
+    }
+
     async fn create_routing_rules(&mut self, enable_ipv6: bool) -> Result<()> {
         use netlink_packet_route::constants::*;
 
@@ -208,25 +233,7 @@ impl RouteManagerImpl {
             // `RTM_DELRULE` is way too picky about which rules are considered the same.
             // So iterate over all rules and ignore irrelevant attributes.
             for found_rule in &rules {
-                // Match header
-                if found_rule.header.family != rule.header.family {
-                    continue;
-                }
-                if found_rule.header.action != rule.header.action {
-                    continue;
-                }
-                if (found_rule.header.flags & rule.header.flags) != rule.header.flags {
-                    continue;
-                }
-                // Match NLAs
-                let mut contains_nlas = true;
-                for nla in &rule.nlas {
-                    if !found_rule.nlas.contains(nla) {
-                        contains_nlas = false;
-                        break;
-                    }
-                }
-                if contains_nlas {
+                if rule_matches(found_rule, &rule) {
                     log::trace!("Existing routing rule matched: {:?}", found_rule);
                     matching_rule = Some(found_rule);
                     break;
@@ -846,6 +853,45 @@ impl RouteManagerImpl {
     }
 }
 
+/// Returns whether `found_rule` is equivalent to `rule`, ignoring attributes that the kernel adds
+/// on its own.
+fn rule_matches(found_rule: &RuleMessage, rule: &RuleMessage) -> bool {
+    // Match header
+    if found_rule.header.family != rule.header.family {
+        return false;
+    }
+    if found_rule.header.action != rule.header.action {
+        return false;
+    }
+    if (found_rule.header.flags & rule.header.flags) != rule.header.flags {
+        return false;
+    }
+    // Match NLAs
+    rule.nlas.iter().all(|nla| found_rule.nlas.contains(nla))
+}
+
+/// Find a rule that references `table_id` or `fwmark` but is not one of the rules added by the
+/// route manager itself.
+fn find_conflicting_rule(
+    rules: &[RuleMessage],
+    fwmark: u32,
+    table_id: u32,
+) -> Option<&RuleMessage> {
+    let own_rules = all_rules(fwmark, table_id);
+    rules.iter().find(|found_rule| {
+        let uses_ids = found_rule.nlas.iter().any(|nla| match nla {
+            RuleNla::Table(table) => *table == table_id,
+            RuleNla::FwMark(mark) => *mark == fwmark,
+            _ => false,
+        }) || (table_id <= 255 && u32::from(found_rule.header.table) == table_id);
+
+        uses_ids
+            && !own_rules
+                .iter()
+                .any(|own_rule| rule_matches(found_rule, own_rule))
+    })
+}
+
 fn ip_to_bytes(addr: IpAddr) -> Vec<u8> {
     match addr {
         IpAddr::V4(addr) => addr.octets().to_vec(),
@@ -934,4 +980,31 @@ mod test {
         });
         std::mem::drop(manager);
     }
+
+    /// Rules added by the route manager must not be reported as conflicts, but any other rule
+    /// using the same table or fwmark must be.
+    #[test]
+    fn test_conflicting_rules() {
+        const FWMARK: u32 = 0x1234;
+        const TABLE_ID: u32 = 0x5678;
+
+        let own_rules = all_rules(FWMARK, TABLE_ID);
+        assert!(find_conflicting_rule(&own_rules, FWMARK, TABLE_ID).is_none());
+
+        let unrelated_rule = no_fwmark_rule_v4(0x1, 0x2);
+        assert!(find_conflicting_rule(&[unrelated_rule], FWMARK, TABLE_ID).is_none());
+
+        let mut vrf_rule = RuleMessage::default();
+        vrf_rule.header.family = AF_INET as u8;
+        vrf_rule.header.action = FR_ACT_TO_TBL;
+        vrf_rule.nlas.push(RuleNla::Table(TABLE_ID));
+        assert!(find_conflicting_rule(&[vrf_rule], FWMARK, TABLE_ID).is_some());
+
+        let mut mark_rule = RuleMessage::default();
+        mark_rule.header.family = AF_INET6 as u8;
+        mark_rule.header.action = FR_ACT_TO_TBL;
+        mark_rule.nlas.push(RuleNla::FwMark(FWMARK));
+        mark_rule.nlas.push(RuleNla::Table(100));
+        assert!(find_conflicting_rule(&[mark_rule], FWMARK, TABLE_ID).is_some());
+    }
 }
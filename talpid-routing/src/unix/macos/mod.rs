@@ -235,6 +235,11 @@ impl RouteManagerImpl {
                             log::debug!("Adding routes: {routes:?}");
                             let _ = tx.send(self.add_required_routes(routes).await);
                         }
+                        Some(RouteManagerCommand::RemoveRoutes(routes, tx)) => {
+                            log::debug!("Removing routes: {routes:?}");
+                            self.remove_required_routes(routes).await;
+                            let _ = tx.send(());
+                        }
                         Some(RouteManagerCommand::ClearRoutes) => {
                             if let Err(err) = self.cleanup_routes().await {
                                 log::error!("Failed to clean up rotues: {err}");
@@ -625,6 +630,19 @@ impl RouteManagerImpl {
         Ok(())
     }
 
+    /// Remove routes previously added by [Self::add_required_routes].
+    async fn remove_required_routes(&mut self, routes: HashSet<RequiredRoute>) {
+        let prefixes: HashSet<IpNetwork> = routes.into_iter().map(|route| route.prefix).collect();
+        self.non_tunnel_routes
+            .retain(|prefix| !prefixes.contains(prefix));
+        self.remove_applied_routes(|route| {
+            route
+                .destination_ip()
+                .is_ok_and(|destination| prefixes.contains(&destination))
+        })
+        .await;
+    }
+
     /// Remove all applied routes for which `filter` returns true
     async fn remove_applied_routes(&mut self, filter: impl Fn(&RouteMessage) -> bool) {
         let mut deleted_routes = vec![];
@@ -118,6 +118,7 @@ pub(crate) enum RouteManagerCommand {
         HashSet<RequiredRoute>,
         oneshot::Sender<Result<(), PlatformError>>,
     ),
+    RemoveRoutes(HashSet<RequiredRoute>, oneshot::Sender<()>),
     ClearRoutes,
     Shutdown(oneshot::Sender<()>),
     RefreshRoutes,
@@ -175,10 +176,10 @@ impl RouteManagerHandle {
         let (manage_tx, manage_rx) = mpsc::unbounded();
         let manage_tx = Arc::new(manage_tx);
         let manager = imp::RouteManagerImpl::new(
-            #[cfg(target_os = "linux")]
-            fwmark,
             #[cfg(target_os = "linux")]
             table_id,
+            #[cfg(target_os = "linux")]
+            fwmark,
             #[cfg(target_os = "macos")]
             Arc::downgrade(&manage_tx),
             #[cfg(target_os = "android")]
@@ -236,6 +237,17 @@ impl RouteManagerHandle {
             .map_err(|_| Error::ManagerChannelDown)
     }
 
+    /// Removes the given routes, which were previously applied in
+    /// [`RouteManagerHandle::add_routes`].
+    #[cfg(target_os = "macos")]
+    pub async fn remove_routes(&self, routes: HashSet<RequiredRoute>) -> Result<(), Error> {
+        let (result_tx, result_rx) = oneshot::channel();
+        self.tx
+            .unbounded_send(RouteManagerCommand::RemoveRoutes(routes, result_tx))
+            .map_err(|_| Error::RouteManagerDown)?;
+        result_rx.await.map_err(|_| Error::ManagerChannelDown)
+    }
+
     /// Removes all routes previously applied in [`RouteManagerHandle::add_routes`].
     #[cfg(not(target_os = "android"))]
     pub fn clear_routes(&self) -> Result<(), Error> {
@@ -26,8 +26,8 @@ async fn main() {
         tuncfg_server_ip,
         public_key, // Parent connection's public key.
         ephemeral_private_key.public_key(),
-        true,  // Whether to negotiate a "PQ-safe" PSK.
-        false, // Whether to use DAITA (Does not work with Linux kernel WireGuard.)
+        true, // Whether to negotiate a "PQ-safe" PSK.
+        None, // DAITA level to request, if any (Does not work with Linux kernel WireGuard.)
     )
     .await
     .unwrap();
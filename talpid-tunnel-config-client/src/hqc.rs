@@ -2,7 +2,7 @@ use pqcrypto_hqc::hqc256;
 use pqcrypto_traits::kem::{Ciphertext as _, PublicKey as _, SharedSecret as _};
 use sha2::{Digest as _, Sha256};
 
-const ALGORITHM_NAME: &str = "HQC-256";
+pub const ALGORITHM_NAME: &str = "HQC-256";
 
 pub struct Keypair {
     public_key: hqc256::PublicKey,
@@ -54,3 +54,17 @@ pub fn keypair() -> Keypair {
         secret_key,
     }
 }
+
+/// Generates a random shared secret and encapsulates it to the given public key. Returns the
+/// ciphertext to send to the owner of the key, along with the hashed shared secret.
+///
+/// This is the server side of the key exchange, and is only used by the reference server.
+#[cfg(any(test, feature = "server"))]
+pub fn encapsulate(public_key: &[u8]) -> Option<(Vec<u8>, [u8; 32])> {
+    let public_key = hqc256::PublicKey::from_bytes(public_key).ok()?;
+    let (shared_secret, ciphertext) = hqc256::encapsulate(&public_key);
+
+    // Hash the shared secret the same way as in `Keypair::decapsulate`.
+    let output_shared_secret = Sha256::digest(shared_secret.as_bytes());
+    Some((ciphertext.as_bytes().to_vec(), output_shared_secret.into()))
+}
@@ -5,7 +5,7 @@ use std::net::SocketAddr;
 #[cfg(not(target_os = "ios"))]
 use std::net::{IpAddr, Ipv4Addr};
 use std::time::Instant;
-use talpid_types::net::wireguard::{PresharedKey, PublicKey};
+use talpid_types::net::wireguard::{DaitaLevel, PresharedKey, PublicKey};
 use tonic::transport::Channel;
 #[cfg(not(target_os = "ios"))]
 use tonic::transport::Endpoint;
@@ -15,6 +15,8 @@ use zeroize::Zeroize;
 
 mod hqc;
 mod ml_kem;
+#[cfg(any(test, feature = "server"))]
+pub mod server;
 #[cfg(not(target_os = "ios"))]
 mod socket;
 
@@ -39,6 +41,10 @@ pub enum Error {
         actual: usize,
     },
     MissingDaitaResponse,
+    InvalidDaitaResponse {
+        max_padding_frac: f64,
+        max_blocking_frac: f64,
+    },
     #[cfg(target_os = "ios")]
     TcpConnectionOpen,
     #[cfg(target_os = "ios")]
@@ -64,6 +70,13 @@ impl std::fmt::Display for Error {
                 write!(f, "Expected 2 ciphertext in the response, got {actual}")
             }
             MissingDaitaResponse => "Expected DAITA configuration in response".fmt(f),
+            InvalidDaitaResponse {
+                max_padding_frac,
+                max_blocking_frac,
+            } => write!(
+                f,
+                "Invalid DAITA limits in response: max padding {max_padding_frac}, max blocking {max_blocking_frac}"
+            ),
             #[cfg(target_os = "ios")]
             TcpConnectionOpen => "Failed to open TCP connection".fmt(f),
             #[cfg(target_os = "ios")]
@@ -86,25 +99,28 @@ pub type RelayConfigService = proto::ephemeral_peer_client::EphemeralPeerClient<
 /// Port used by the tunnel config service.
 pub const CONFIG_SERVICE_PORT: u16 = 1337;
 
+#[derive(Debug)]
 pub struct EphemeralPeer {
     pub psk: Option<PresharedKey>,
     pub daita: Option<DaitaSettings>,
 }
 
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct DaitaSettings {
     pub client_machines: Vec<String>,
     pub max_padding_frac: f64,
     pub max_blocking_frac: f64,
 }
 
-/// Negotiate a short-lived peer with a PQ-safe PSK or with DAITA enabled.
+/// Negotiate a short-lived peer with a PQ-safe PSK or with DAITA enabled. DAITA is enabled by
+/// passing the level to request.
 #[cfg(not(target_os = "ios"))]
 pub async fn request_ephemeral_peer(
     service_address: Ipv4Addr,
     parent_pubkey: PublicKey,
     ephemeral_pubkey: PublicKey,
     enable_post_quantum: bool,
-    enable_daita: bool,
+    daita_level: Option<DaitaLevel>,
 ) -> Result<EphemeralPeer, Error> {
     log::debug!("Connecting to relay config service at {service_address}");
     let client = connect_relay_config_client(service_address).await?;
@@ -115,7 +131,7 @@ pub async fn request_ephemeral_peer(
         parent_pubkey,
         ephemeral_pubkey,
         enable_post_quantum,
-        enable_daita,
+        daita_level,
     )
     .await
 }
@@ -125,7 +141,7 @@ pub async fn request_ephemeral_peer_with(
     parent_pubkey: PublicKey,
     ephemeral_pubkey: PublicKey,
     enable_quantum_resistant: bool,
-    enable_daita: bool,
+    daita_level: Option<DaitaLevel>,
 ) -> Result<EphemeralPeer, Error> {
     let (pq_request, kem_keypairs) = if enable_quantum_resistant {
         let start = Instant::now();
@@ -145,11 +161,11 @@ pub async fn request_ephemeral_peer_with(
             wg_ephemeral_peer_pubkey: ephemeral_pubkey.as_bytes().to_vec(),
             post_quantum: pq_request,
             daita: None,
-            daita_v2: enable_daita.then(|| {
+            daita_v2: daita_level.map(|level| {
                 let platform = get_platform();
-                log::trace!("DAITA v2 platform: {platform:?}");
+                log::trace!("DAITA v2 platform: {platform:?}, level: {level}");
                 proto::DaitaRequestV2 {
-                    level: i32::from(proto::DaitaLevel::LevelDefault),
+                    level: i32::from(level.get()),
                     platform: i32::from(platform),
                     version: DAITA_VERSION,
                 }
@@ -209,8 +225,18 @@ pub async fn request_ephemeral_peer_with(
         max_padding_frac: daita.max_padding_frac,
         max_blocking_frac: daita.max_blocking_frac,
     });
-    if daita.is_none() && enable_daita {
-        return Err(Error::MissingDaitaResponse);
+    match &daita {
+        None if daita_level.is_some() => return Err(Error::MissingDaitaResponse),
+        Some(settings)
+            if !(0.0..=1.0).contains(&settings.max_padding_frac)
+                || !(0.0..=1.0).contains(&settings.max_blocking_frac) =>
+        {
+            return Err(Error::InvalidDaitaResponse {
+                max_padding_frac: settings.max_padding_frac,
+                max_blocking_frac: settings.max_blocking_frac,
+            });
+        }
+        _ => (),
     }
     Ok(EphemeralPeer { psk, daita })
 }
@@ -355,3 +381,149 @@ mod socket_sniffer {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use server::EphemeralPeerService;
+    use talpid_types::net::wireguard::PrivateKey;
+    use tokio::net::TcpListener;
+
+    /// Serve `service` on a random local port and return a client connected to it.
+    async fn connect_to(service: EphemeralPeerService) -> RelayConfigService {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(service.serve(listener));
+        RelayConfigService::connect(format!("http://{addr}"))
+            .await
+            .unwrap()
+    }
+
+    fn daita_settings() -> DaitaSettings {
+        DaitaSettings {
+            client_machines: vec!["02eNAQAAAAAAAAA=".to_owned()],
+            max_padding_frac: 0.5,
+            max_blocking_frac: 0.25,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_quantum_psk() {
+        let service = EphemeralPeerService::new();
+        let client = connect_to(service.clone()).await;
+        let parent_pubkey = PrivateKey::new_from_random().public_key();
+        let ephemeral_pubkey = PrivateKey::new_from_random().public_key();
+
+        let peer = request_ephemeral_peer_with(
+            client,
+            parent_pubkey.clone(),
+            ephemeral_pubkey.clone(),
+            true,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let server_peer = service.peer(&parent_pubkey).unwrap();
+        assert_eq!(server_peer.ephemeral_pubkey, ephemeral_pubkey);
+        assert_eq!(peer.psk.unwrap(), server_peer.psk.unwrap());
+        assert!(peer.daita.is_none());
+        assert!(server_peer.daita.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_daita() {
+        let service = EphemeralPeerService::new().with_daita(daita_settings());
+        let client = connect_to(service.clone()).await;
+        let parent_pubkey = PrivateKey::new_from_random().public_key();
+        let ephemeral_pubkey = PrivateKey::new_from_random().public_key();
+
+        let peer = request_ephemeral_peer_with(
+            client,
+            parent_pubkey.clone(),
+            ephemeral_pubkey,
+            false,
+            Some(DaitaLevel::MAX),
+        )
+        .await
+        .unwrap();
+
+        assert!(peer.psk.is_none());
+        assert_eq!(peer.daita, Some(daita_settings()));
+        let server_peer = service.peer(&parent_pubkey).unwrap();
+        assert_eq!(server_peer.daita, peer.daita);
+        assert_eq!(server_peer.daita_level, Some(DaitaLevel::MAX));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_daita_limits() {
+        let service = EphemeralPeerService::new().with_daita(DaitaSettings {
+            max_padding_frac: 1.5,
+            ..daita_settings()
+        });
+        let client = connect_to(service).await;
+
+        let error = request_ephemeral_peer_with(
+            client,
+            PrivateKey::new_from_random().public_key(),
+            PrivateKey::new_from_random().public_key(),
+            false,
+            Some(DaitaLevel::DEFAULT),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(error, Error::InvalidDaitaResponse { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_post_quantum_psk_and_daita() {
+        let service = EphemeralPeerService::new().with_daita(daita_settings());
+        let client = connect_to(service.clone()).await;
+        let parent_pubkey = PrivateKey::new_from_random().public_key();
+        let ephemeral_pubkey = PrivateKey::new_from_random().public_key();
+
+        let peer = request_ephemeral_peer_with(
+            client,
+            parent_pubkey.clone(),
+            ephemeral_pubkey,
+            true,
+            Some(DaitaLevel::DEFAULT),
+        )
+        .await
+        .unwrap();
+
+        let server_peer = service.peer(&parent_pubkey).unwrap();
+        assert_eq!(peer.psk.unwrap(), server_peer.psk.unwrap());
+        assert_eq!(peer.daita, Some(daita_settings()));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_kem_algorithm() {
+        let mut client = connect_to(EphemeralPeerService::new()).await;
+
+        let status = client
+            .register_peer_v1(proto::EphemeralPeerRequestV1 {
+                wg_parent_pubkey: PrivateKey::new_from_random()
+                    .public_key()
+                    .as_bytes()
+                    .to_vec(),
+                wg_ephemeral_peer_pubkey: PrivateKey::new_from_random()
+                    .public_key()
+                    .as_bytes()
+                    .to_vec(),
+                post_quantum: Some(proto::PostQuantumRequestV1 {
+                    kem_pubkeys: vec![proto::KemPubkeyV1 {
+                        algorithm_name: "Classic-McEliece-8192128f".to_owned(),
+                        key_data: vec![0; 32],
+                    }],
+                }),
+                daita: None,
+                daita_v2: None,
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
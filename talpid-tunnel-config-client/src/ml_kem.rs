@@ -4,7 +4,7 @@ use ml_kem::{Ciphertext, EncodedSizeUser, KemCore, MlKem1024, MlKem1024Params};
 
 /// Use the strongest variant of ML-KEM. It is fast and the keys are small, so there is no practical
 /// benefit of going with anything lower. The servers also only supports the strongest variant.
-const ALGORITHM_NAME: &str = "ML-KEM-1024";
+pub const ALGORITHM_NAME: &str = "ML-KEM-1024";
 
 /// The number of bytes in an ML-KEM 1024 ciphertext.
 const CIPHERTEXT_LEN: usize = <MlKem1024 as KemCore>::CiphertextSize::USIZE;
@@ -67,3 +67,23 @@ pub fn keypair() -> Keypair {
         decapsulation_key,
     }
 }
+
+/// Generates a random shared secret and encapsulates it to the given encapsulation key. Returns the
+/// ciphertext to send to the owner of the key, along with the shared secret.
+///
+/// This is the server side of the key exchange, and is only used by the reference server.
+#[cfg(any(test, feature = "server"))]
+pub fn encapsulate(encapsulation_key: &[u8]) -> Option<(Vec<u8>, [u8; 32])> {
+    use ml_kem::{kem::Encapsulate, Encoded};
+
+    type EncapsulationKey = <MlKem1024 as KemCore>::EncapsulationKey;
+
+    let encapsulation_key_array = <Encoded<EncapsulationKey>>::try_from(encapsulation_key).ok()?;
+    let encapsulation_key = EncapsulationKey::from_bytes(&encapsulation_key_array);
+
+    // Encapsulation is infallible, see the comment in `Keypair::decapsulate`.
+    let (ciphertext, shared_secret) = encapsulation_key
+        .encapsulate(&mut rand::thread_rng())
+        .unwrap();
+    Some((ciphertext.to_vec(), shared_secret.into()))
+}
@@ -0,0 +1,179 @@
+//! A reference implementation of the ephemeral peer config service.
+//!
+//! The server performs the same key encapsulation as the relays, so the PSK it derives is the
+//! same one that the client ends up with. This makes it possible to negotiate PQ-safe and DAITA
+//! peers against a local WireGuard peer in end-to-end tests, without access to a real relay.
+
+use crate::{hqc, ml_kem, proto, xor_assign, DaitaSettings};
+use proto::{
+    ephemeral_peer_server::{EphemeralPeer, EphemeralPeerServer},
+    EphemeralPeerRequestV1, EphemeralPeerResponseV1,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use talpid_types::net::wireguard::{DaitaLevel, PresharedKey, PublicKey};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{transport::Server, Request, Response, Status};
+use zeroize::Zeroize;
+
+type PeerCallback = dyn Fn(&NegotiatedPeer) + Send + Sync;
+
+/// An ephemeral peer negotiated by [`EphemeralPeerService`].
+#[derive(Debug, Clone)]
+pub struct NegotiatedPeer {
+    /// Public key of the peer that the ephemeral peer was negotiated through.
+    pub parent_pubkey: PublicKey,
+    /// Public key that the client will use for the ephemeral peer.
+    pub ephemeral_pubkey: PublicKey,
+    /// PSK derived from the KEM shared secrets, if PQ was requested.
+    pub psk: Option<PresharedKey>,
+    /// DAITA configuration sent to the client, if DAITA was requested.
+    pub daita: Option<DaitaSettings>,
+    /// DAITA level requested by the client. Always the default level for DAITA v1 requests.
+    pub daita_level: Option<DaitaLevel>,
+}
+
+/// Ephemeral peer config service. Cloning the service returns a handle to the same set of
+/// negotiated peers.
+#[derive(Clone, Default)]
+pub struct EphemeralPeerService {
+    daita: DaitaSettings,
+    peers: Arc<Mutex<HashMap<PublicKey, NegotiatedPeer>>>,
+    on_peer: Option<Arc<PeerCallback>>,
+}
+
+impl EphemeralPeerService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the DAITA configuration to return to clients that request DAITA. By default, no
+    /// machines are returned.
+    pub fn with_daita(mut self, daita: DaitaSettings) -> Self {
+        self.daita = daita;
+        self
+    }
+
+    /// Call `on_peer` every time a peer has been negotiated, e.g. to add it to a local WireGuard
+    /// interface.
+    pub fn on_peer(mut self, on_peer: impl Fn(&NegotiatedPeer) + Send + Sync + 'static) -> Self {
+        self.on_peer = Some(Arc::new(on_peer));
+        self
+    }
+
+    /// Returns the most recent ephemeral peer negotiated through `parent_pubkey`, if any.
+    pub fn peer(&self, parent_pubkey: &PublicKey) -> Option<NegotiatedPeer> {
+        self.peers.lock().unwrap().get(parent_pubkey).cloned()
+    }
+
+    /// Serve requests on `listener` until an error occurs.
+    pub async fn serve(self, listener: TcpListener) -> Result<(), tonic::transport::Error> {
+        Server::builder()
+            .add_service(EphemeralPeerServer::new(self))
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+    }
+
+    /// Encapsulate a shared secret to each KEM public key, and mix them all into a single PSK.
+    /// Returns the ciphertexts in the same order as the keys, along with the PSK.
+    fn encapsulate(
+        kem_pubkeys: Vec<proto::KemPubkeyV1>,
+    ) -> Result<(Vec<Vec<u8>>, PresharedKey), Status> {
+        let mut ciphertexts = Vec::with_capacity(kem_pubkeys.len());
+        let mut psk_data = Box::new([0u8; 32]);
+
+        for kem_pubkey in kem_pubkeys {
+            let algorithm = kem_pubkey.algorithm_name.as_str();
+            let encapsulate: fn(&[u8]) -> Option<(Vec<u8>, [u8; 32])> = match algorithm {
+                ml_kem::ALGORITHM_NAME => ml_kem::encapsulate,
+                hqc::ALGORITHM_NAME => hqc::encapsulate,
+                _ => {
+                    return Err(Status::invalid_argument(format!(
+                        "Unsupported KEM algorithm: {algorithm}"
+                    )))
+                }
+            };
+            let (ciphertext, mut shared_secret) =
+                encapsulate(&kem_pubkey.key_data).ok_or_else(|| {
+                    Status::invalid_argument(format!("Invalid {algorithm} public key"))
+                })?;
+            xor_assign(&mut psk_data, &shared_secret);
+            shared_secret.zeroize();
+            ciphertexts.push(ciphertext);
+        }
+
+        Ok((ciphertexts, PresharedKey::from(psk_data)))
+    }
+}
+
+#[tonic::async_trait]
+impl EphemeralPeer for EphemeralPeerService {
+    async fn register_peer_v1(
+        &self,
+        request: Request<EphemeralPeerRequestV1>,
+    ) -> Result<Response<EphemeralPeerResponseV1>, Status> {
+        let request = request.into_inner();
+
+        let parse_pubkey = |key: &[u8]| {
+            PublicKey::try_from(key)
+                .map_err(|_| Status::invalid_argument("Invalid WireGuard public key"))
+        };
+        let parent_pubkey = parse_pubkey(&request.wg_parent_pubkey)?;
+        let ephemeral_pubkey = parse_pubkey(&request.wg_ephemeral_peer_pubkey)?;
+
+        let (post_quantum, psk) = match request.post_quantum {
+            Some(post_quantum) => {
+                let (ciphertexts, psk) = Self::encapsulate(post_quantum.kem_pubkeys)?;
+                (
+                    Some(proto::PostQuantumResponseV1 { ciphertexts }),
+                    Some(psk),
+                )
+            }
+            None => (None, None),
+        };
+
+        let daita_level = match request.daita_v2 {
+            Some(daita_v2) => Some(
+                u8::try_from(daita_v2.level)
+                    .ok()
+                    .and_then(DaitaLevel::new)
+                    .ok_or_else(|| Status::invalid_argument("Invalid DAITA level"))?,
+            ),
+            None => request
+                .daita
+                .is_some_and(|daita_v1| daita_v1.activate_daita)
+                .then_some(DaitaLevel::DEFAULT),
+        };
+        let daita = daita_level.map(|_| self.daita.clone());
+
+        let peer = NegotiatedPeer {
+            parent_pubkey: parent_pubkey.clone(),
+            ephemeral_pubkey,
+            psk,
+            daita: daita.clone(),
+            daita_level,
+        };
+        log::debug!(
+            "Negotiated ephemeral peer {} for {parent_pubkey} (PQ: {}, DAITA: {})",
+            peer.ephemeral_pubkey,
+            peer.psk.is_some(),
+            peer.daita.is_some(),
+        );
+        if let Some(on_peer) = &self.on_peer {
+            on_peer(&peer);
+        }
+        self.peers.lock().unwrap().insert(parent_pubkey, peer);
+
+        Ok(Response::new(EphemeralPeerResponseV1 {
+            post_quantum,
+            daita: daita.map(|daita| proto::DaitaResponseV2 {
+                client_machines: daita.client_machines,
+                max_padding_frac: daita.max_padding_frac,
+                max_blocking_frac: daita.max_blocking_frac,
+            }),
+        }))
+    }
+}
@@ -17,7 +17,13 @@ use futures::{
     SinkExt,
 };
 use talpid_routing::RouteManagerHandle;
-use talpid_types::net::AllowedTunnelTraffic;
+use talpid_types::{
+    net::{
+        wireguard::{DaitaParameters, WireguardBackend},
+        AllowedTunnelTraffic,
+    },
+    tunnel::TunnelNotification,
+};
 use tun_provider::TunProvider;
 
 /// Size of IPv4 header in bytes
@@ -80,6 +86,10 @@ pub struct TunnelMetadata {
     pub ipv4_gateway: Ipv4Addr,
     /// The IP to the IPv6 default gateway on the tunnel interface.
     pub ipv6_gateway: Option<Ipv6Addr>,
+    /// DAITA parameters negotiated with the relay, if DAITA is enabled.
+    pub daita: Option<DaitaParameters>,
+    /// The WireGuard implementation driving the tunnel, if it is a WireGuard tunnel.
+    pub wireguard_backend: Option<WireguardBackend>,
 }
 
 impl TunnelMetadata {
@@ -104,4 +114,6 @@ pub enum TunnelEvent {
     Up(TunnelMetadata),
     /// Sent when the tunnel goes down, but before destroying the tunnel device.
     Down,
+    /// Sent when the tunnel has something to report that does not affect its state.
+    Notification(TunnelNotification),
 }
@@ -0,0 +1,84 @@
+use std::{fmt, net::IpAddr, time::SystemTime};
+
+/// Firewall chain in which a packet was blocked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FirewallChain {
+    Input,
+    Output,
+    Forward,
+}
+
+impl fmt::Display for FirewallChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chain = match self {
+            FirewallChain::Input => "input",
+            FirewallChain::Output => "output",
+            FirewallChain::Forward => "forward",
+        };
+        f.write_str(chain)
+    }
+}
+
+/// Describes a packet that was dropped or rejected by the firewall.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedPacket {
+    /// Time at which the packet was blocked.
+    pub timestamp: SystemTime,
+    /// Chain that blocked the packet.
+    pub chain: FirewallChain,
+    /// IP protocol number of the transport layer, e.g. 6 for TCP.
+    pub protocol: u8,
+    pub source: IpAddr,
+    /// Source port, if the packet is TCP or UDP.
+    pub source_port: Option<u16>,
+    pub destination: IpAddr,
+    /// Destination port, if the packet is TCP or UDP.
+    pub destination_port: Option<u16>,
+    /// Owner of the socket that sent the packet, if it is a local socket.
+    pub uid: Option<u32>,
+    /// Group of the socket that sent the packet, if it is a local socket.
+    pub gid: Option<u32>,
+    /// net_cls class ID of the cgroup of the process that sent the packet, if it is a local socket
+    /// in such a cgroup.
+    pub cgroup: Option<u32>,
+}
+
+impl BlockedPacket {
+    /// Returns a human-readable name of the transport protocol.
+    pub fn protocol_name(&self) -> String {
+        match self.protocol {
+            1 => "ICMP".to_owned(),
+            6 => "TCP".to_owned(),
+            17 => "UDP".to_owned(),
+            58 => "ICMPv6".to_owned(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for BlockedPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ", self.chain, self.protocol_name())?;
+        fmt_address(f, self.source, self.source_port)?;
+        f.write_str(" -> ")?;
+        fmt_address(f, self.destination, self.destination_port)?;
+        if let Some(uid) = self.uid {
+            write!(f, " uid={uid}")?;
+        }
+        if let Some(gid) = self.gid {
+            write!(f, " gid={gid}")?;
+        }
+        if let Some(cgroup) = self.cgroup {
+            write!(f, " cgroup={cgroup:#x}")?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_address(f: &mut fmt::Formatter<'_>, address: IpAddr, port: Option<u16>) -> fmt::Result {
+    match (address, port) {
+        (IpAddr::V4(address), Some(port)) => write!(f, "{address}:{port}"),
+        (IpAddr::V6(address), Some(port)) => write!(f, "[{address}]:{port}"),
+        (address, None) => write!(f, "{address}"),
+    }
+}
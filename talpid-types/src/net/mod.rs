@@ -13,9 +13,11 @@ use std::{
     sync::LazyLock,
 };
 
+pub mod firewall_log;
 pub mod obfuscation;
 pub mod openvpn;
 pub mod proxy;
+pub mod stats;
 pub mod wireguard;
 
 /// When "allow local network" is enabled the app will allow traffic to and from these networks.
@@ -70,12 +72,16 @@ impl TunnelParameters {
                 proxy: params
                     .proxy
                     .as_ref()
-                    .map(|proxy| proxy.get_remote_endpoint()),
+                    .and_then(|proxy| proxy.get_remote_endpoint()),
                 obfuscation: None,
                 entry_endpoint: None,
                 tunnel_interface: None,
+                tunnel_ips: vec![],
                 #[cfg(daita)]
                 daita: false,
+                #[cfg(daita)]
+                daita_parameters: None,
+                wireguard_backend: None,
             },
             TunnelParameters::Wireguard(params) => TunnelEndpoint {
                 tunnel_type: TunnelType::Wireguard,
@@ -91,8 +97,12 @@ impl TunnelParameters {
                     .get_exit_endpoint()
                     .map(|_| params.connection.get_endpoint()),
                 tunnel_interface: None,
+                tunnel_ips: vec![],
                 #[cfg(daita)]
                 daita: params.options.daita,
+                #[cfg(daita)]
+                daita_parameters: None,
+                wireguard_backend: None,
             },
         }
     }
@@ -103,7 +113,8 @@ impl TunnelParameters {
             TunnelParameters::OpenVpn(params) => params
                 .proxy
                 .as_ref()
-                .map(|proxy| proxy.get_remote_endpoint().endpoint)
+                .and_then(|proxy| proxy.get_remote_endpoint())
+                .map(|proxy| proxy.endpoint)
                 .unwrap_or(params.config.endpoint),
             TunnelParameters::Wireguard(params) => params.get_next_hop_endpoint(),
         }
@@ -203,8 +214,18 @@ pub struct TunnelEndpoint {
     pub obfuscation: Option<ObfuscationEndpoint>,
     pub entry_endpoint: Option<Endpoint>,
     pub tunnel_interface: Option<String>,
+    /// IPs of the tunnel interface. Only set once the tunnel is connected.
+    #[serde(default)]
+    pub tunnel_ips: Vec<IpAddr>,
     #[cfg(daita)]
     pub daita: bool,
+    /// DAITA parameters negotiated with the relay. Only set once the tunnel is connected.
+    #[cfg(daita)]
+    #[serde(default)]
+    pub daita_parameters: Option<wireguard::DaitaParameters>,
+    /// WireGuard implementation in use. Only set once the tunnel is connected.
+    #[serde(default)]
+    pub wireguard_backend: Option<wireguard::WireguardBackend>,
 }
 
 impl fmt::Display for TunnelEndpoint {
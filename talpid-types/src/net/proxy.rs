@@ -1,14 +1,48 @@
 use crate::net::Endpoint;
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
 use serde::{Deserialize, Serialize};
-use std::{fmt, net::SocketAddr};
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
 
 use super::TransportProtocol;
 
+/// Placeholder for passwords that are withheld from a client.
+const REDACTED: &str = "<redacted>";
+
+/// Engine for Shadowsocks 2022 keys. Padding is optional, as in the reference implementation.
+const KEY_ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     /// Validation of SOCKS5 username or password failed.
     #[error("Invalid SOCKS5 authentication credentials: {0}")]
     InvalidSocksAuthValues(&'static str),
+
+    /// Validation of HTTP proxy username or password failed.
+    #[error("Invalid HTTP proxy authentication credentials: {0}")]
+    InvalidHttpAuthValues(&'static str),
+
+    /// The password is not a valid pre-shared key for a Shadowsocks 2022 cipher.
+    #[error("Invalid Shadowsocks 2022 key: {0}")]
+    InvalidShadowsocksKey(String),
+
+    /// The host of a proxy is neither an IP address nor a valid hostname.
+    #[error("Invalid proxy host: {0}")]
+    InvalidHost(String),
+
+    /// The address of a proxy is not of the form `host:port`.
+    #[error("Invalid proxy address: {0}")]
+    InvalidAddress(String),
 }
 
 /// Types of bridges that can be used to proxy a connection to a tunnel
@@ -44,23 +78,116 @@ pub enum CustomProxy {
     Shadowsocks(Shadowsocks),
     Socks5Local(Socks5Local),
     Socks5Remote(Socks5Remote),
+    HttpConnect(HttpConnect),
 }
 
 impl CustomProxy {
-    pub fn get_remote_endpoint(&self) -> ProxyEndpoint {
+    /// Return the endpoint of the proxy, or `None` if its hostname has not been resolved.
+    pub fn get_remote_endpoint(&self) -> Option<ProxyEndpoint> {
+        let (address, proxy_type) = match self {
+            CustomProxy::Socks5Local(settings) => {
+                return Some(ProxyEndpoint {
+                    endpoint: settings.remote_endpoint,
+                    proxy_type: ProxyType::Custom,
+                });
+            }
+            CustomProxy::Socks5Remote(settings) => (&settings.endpoint, ProxyType::Custom),
+            CustomProxy::Shadowsocks(settings) => (&settings.endpoint, ProxyType::Shadowsocks),
+            CustomProxy::HttpConnect(settings) => (&settings.endpoint, ProxyType::Custom),
+        };
+        Some(ProxyEndpoint {
+            endpoint: Endpoint::from_socket_address(address.socket_addr()?, TransportProtocol::Tcp),
+            proxy_type,
+        })
+    }
+
+    /// Hostname of the proxy, if it has to be resolved before it can be connected to.
+    pub fn hostname(&self) -> Option<&str> {
         match self {
-            CustomProxy::Socks5Local(settings) => ProxyEndpoint {
-                endpoint: settings.remote_endpoint,
-                proxy_type: ProxyType::Custom,
-            },
-            CustomProxy::Socks5Remote(settings) => ProxyEndpoint {
-                endpoint: Endpoint::from_socket_address(settings.endpoint, TransportProtocol::Tcp),
-                proxy_type: ProxyType::Custom,
-            },
-            CustomProxy::Shadowsocks(settings) => ProxyEndpoint {
-                endpoint: Endpoint::from_socket_address(settings.endpoint, TransportProtocol::Tcp),
-                proxy_type: ProxyType::Shadowsocks,
-            },
+            CustomProxy::Shadowsocks(settings) => settings.endpoint.hostname(),
+            CustomProxy::Socks5Remote(settings) => settings.endpoint.hostname(),
+            CustomProxy::HttpConnect(settings) => settings.endpoint.hostname(),
+            CustomProxy::Socks5Local(_) => None,
+        }
+    }
+
+    /// Return a copy of this proxy which connects to `ip` instead of looking up its hostname.
+    ///
+    /// The certificate of a TLS-enabled [`HttpConnect`] proxy is verified against the hostname,
+    /// unless a server name has been set explicitly.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::{CustomProxy, ProxyAddress, Socks5Remote};
+    ///
+    /// let address: ProxyAddress = "proxy.example.com:1080".parse().unwrap();
+    /// let proxy = CustomProxy::from(Socks5Remote::new(address));
+    /// assert_eq!(proxy.hostname(), Some("proxy.example.com"));
+    /// assert_eq!(proxy.get_remote_endpoint(), None);
+    ///
+    /// let resolved = proxy.resolve([198, 51, 100, 1].into());
+    /// assert_eq!(resolved.hostname(), None);
+    /// assert_eq!(
+    ///     resolved.get_remote_endpoint().unwrap().endpoint.address,
+    ///     ([198, 51, 100, 1], 1080).into()
+    /// );
+    /// ```
+    pub fn resolve(&self, ip: IpAddr) -> CustomProxy {
+        let mut resolved = self.clone();
+        match &mut resolved {
+            CustomProxy::Shadowsocks(settings) => settings.endpoint.host = ProxyHost::Ip(ip),
+            CustomProxy::Socks5Remote(settings) => settings.endpoint.host = ProxyHost::Ip(ip),
+            CustomProxy::HttpConnect(settings) => {
+                if let Some(tls) = &mut settings.tls {
+                    let hostname = settings.endpoint.hostname().map(str::to_owned);
+                    tls.server_name = tls.server_name.take().or(hostname);
+                }
+                settings.endpoint.host = ProxyHost::Ip(ip);
+            }
+            CustomProxy::Socks5Local(_) => (),
+        }
+        resolved
+    }
+
+    /// Replace all passwords and keys with placeholders, so that the proxy can be shown to
+    /// clients that may not see them. The placeholders are still valid secrets.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Remote, SocksAuth};
+    ///
+    /// let key = "AAECAwQFBgcICQoLDA0ODw==".to_string();
+    /// let shadowsocks = Shadowsocks::new(([192, 0, 2, 1], 443), "2022-blake3-aes-128-gcm".to_string(), key.clone());
+    /// let mut proxy = CustomProxy::from(shadowsocks.unwrap());
+    /// proxy.redact_secrets();
+    /// let CustomProxy::Shadowsocks(redacted) = &proxy else { unreachable!() };
+    /// assert_ne!(redacted.password, key);
+    /// assert!(Shadowsocks::new(redacted.endpoint.clone(), redacted.cipher.clone(), redacted.password.clone()).is_ok());
+    ///
+    /// let auth = SocksAuth::new("user".to_string(), "hunter2".to_string()).unwrap();
+    /// let mut proxy = CustomProxy::from(Socks5Remote::new_with_authentication(([192, 0, 2, 1], 1080), auth));
+    /// proxy.redact_secrets();
+    /// let CustomProxy::Socks5Remote(redacted) = &proxy else { unreachable!() };
+    /// assert_eq!(redacted.auth.as_ref().unwrap().username(), "user");
+    /// assert_ne!(redacted.auth.as_ref().unwrap().password(), "hunter2");
+    /// ```
+    pub fn redact_secrets(&mut self) {
+        match self {
+            CustomProxy::Shadowsocks(settings) => {
+                settings.password = match shadowsocks_2022_key_len(&settings.cipher) {
+                    Some((key_len, _)) => KEY_ENGINE.encode(vec![0u8; key_len]),
+                    None => REDACTED.to_owned(),
+                };
+            }
+            CustomProxy::Socks5Remote(settings) => {
+                if let Some(auth) = &mut settings.auth {
+                    auth.password = REDACTED.to_owned();
+                }
+            }
+            CustomProxy::HttpConnect(settings) => {
+                if let Some(auth) = &mut settings.auth {
+                    auth.password = REDACTED.to_owned();
+                }
+            }
+            CustomProxy::Socks5Local(_) => (),
         }
     }
 }
@@ -83,9 +210,16 @@ impl From<Shadowsocks> for CustomProxy {
     }
 }
 
+impl From<HttpConnect> for CustomProxy {
+    fn from(value: HttpConnect) -> Self {
+        CustomProxy::HttpConnect(value)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Shadowsocks {
-    pub endpoint: SocketAddr,
+    pub endpoint: ProxyAddress,
+    /// Password, or for the 2022 ciphers, the base64 encoded pre-shared key.
     pub password: String,
     /// One of [`SHADOWSOCKS_CIPHERS`].
     /// Gets validated at a later stage. Is assumed to be valid.
@@ -101,10 +235,98 @@ pub struct Socks5Local {
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Socks5Remote {
-    pub endpoint: SocketAddr,
+    pub endpoint: ProxyAddress,
     pub auth: Option<SocksAuth>,
 }
 
+/// HTTP proxy which is asked to open a tunnel to the destination using the `CONNECT` method.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct HttpConnect {
+    pub endpoint: ProxyAddress,
+    pub auth: Option<HttpAuth>,
+    /// Connect to the proxy over TLS. If unset, the proxy is connected to over plain TCP.
+    pub tls: Option<HttpProxyTls>,
+}
+
+/// TLS settings for connecting to an [`HttpConnect`] proxy.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct HttpProxyTls {
+    /// Name to verify the certificate of the proxy against. If unset, the certificate is verified
+    /// against the hostname of the proxy, or its IP address if it has no hostname.
+    pub server_name: Option<String>,
+}
+
+/// Credentials for HTTP basic authentication against a proxy, according to
+/// RFC 7617: <https://datatracker.ietf.org/doc/html/rfc7617>.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(try_from = "UnvalidatedHttpAuth")]
+pub struct HttpAuth {
+    username: String,
+    password: String,
+}
+
+/// Deserialized [`HttpAuth`] that has not yet been validated by [`HttpAuth::new`].
+#[derive(Deserialize)]
+struct UnvalidatedHttpAuth {
+    username: String,
+    password: String,
+}
+
+impl TryFrom<UnvalidatedHttpAuth> for HttpAuth {
+    type Error = Error;
+
+    fn try_from(auth: UnvalidatedHttpAuth) -> Result<Self, Self::Error> {
+        HttpAuth::new(auth.username, auth.password)
+    }
+}
+
+impl HttpAuth {
+    /// Validate HTTP basic authentication credentials.
+    ///
+    /// The username must not be empty or contain a colon, since that separates it from the
+    /// password.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::HttpAuth;
+    ///
+    /// assert!(HttpAuth::new("FooBar".to_string(), "hunter2".to_string()).is_ok());
+    /// assert!(HttpAuth::new("FooBar".to_string(), "".to_string()).is_ok());
+    /// assert!(HttpAuth::new("".to_string(), "hunter2".to_string()).is_err());
+    /// assert!(HttpAuth::new("Foo:Bar".to_string(), "hunter2".to_string()).is_err());
+    /// ```
+    pub fn new(username: String, password: String) -> Result<Self, Error> {
+        if username.is_empty() {
+            return Err(Error::InvalidHttpAuthValues("Username must not be empty"));
+        }
+        if username.contains(':') {
+            return Err(Error::InvalidHttpAuthValues(
+                "Username must not contain a colon",
+            ));
+        }
+        if username
+            .chars()
+            .chain(password.chars())
+            .any(char::is_control)
+        {
+            return Err(Error::InvalidHttpAuthValues(
+                "Username and password must not contain control characters",
+            ));
+        }
+
+        Ok(HttpAuth { username, password })
+    }
+
+    /// Read the username.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Read the password.
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
 /// A valid SOCKS5 username/password authentication according to
 /// RFC 1929: <https://datatracker.ietf.org/doc/html/rfc1929>.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -185,13 +407,73 @@ impl SocksAuth {
 }
 
 impl Shadowsocks {
-    pub fn new<I: Into<SocketAddr>>(endpoint: I, cipher: String, password: String) -> Self {
-        Shadowsocks {
+    /// Create Shadowsocks proxy settings. For the 2022 ciphers, the password must be a base64
+    /// encoded key of the length used by the cipher. For the AES ciphers, identity keys may
+    /// precede the user key, separated by colons.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::Shadowsocks;
+    ///
+    /// let endpoint = ([192, 0, 2, 1], 443);
+    /// let aes128 = "2022-blake3-aes-128-gcm".to_string();
+    /// let key16 = "AAECAwQFBgcICQoLDA0ODw==".to_string();
+    /// let key32 = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=".to_string();
+    ///
+    /// assert!(Shadowsocks::new(endpoint, aes128.clone(), key16.clone()).is_ok());
+    /// assert!(Shadowsocks::new(endpoint, aes128.clone(), format!("{key16}:{key16}")).is_ok());
+    /// assert!(Shadowsocks::new(endpoint, aes128.clone(), key32.clone()).is_err());
+    /// assert!(Shadowsocks::new(endpoint, aes128, "hunter2".to_string()).is_err());
+    /// assert!(Shadowsocks::new(endpoint, "aes-256-gcm".to_string(), "hunter2".to_string()).is_ok());
+    /// ```
+    pub fn new<I: Into<ProxyAddress>>(
+        endpoint: I,
+        cipher: String,
+        password: String,
+    ) -> Result<Self, Error> {
+        validate_shadowsocks_2022_key(&cipher, &password)?;
+        Ok(Shadowsocks {
             endpoint: endpoint.into(),
             password,
             cipher,
+        })
+    }
+}
+
+/// Returns the key length of `cipher`, and whether it supports identity keys, if it is one of
+/// the Shadowsocks 2022 ciphers.
+fn shadowsocks_2022_key_len(cipher: &str) -> Option<(usize, bool)> {
+    match cipher {
+        "2022-blake3-aes-128-gcm" => Some((16, true)),
+        "2022-blake3-aes-256-gcm" => Some((32, true)),
+        "2022-blake3-chacha20-poly1305" => Some((32, false)),
+        _ => None,
+    }
+}
+
+/// Check that `password` is a valid key if `cipher` is one of the Shadowsocks 2022 ciphers.
+/// Passwords for other ciphers are not checked.
+fn validate_shadowsocks_2022_key(cipher: &str, password: &str) -> Result<(), Error> {
+    let Some((key_len, supports_identity_keys)) = shadowsocks_2022_key_len(cipher) else {
+        return Ok(());
+    };
+
+    let keys: Vec<&str> = if supports_identity_keys {
+        password.split(':').collect()
+    } else {
+        vec![password]
+    };
+    for key in keys {
+        let decoded = KEY_ENGINE.decode(key).map_err(|_| {
+            Error::InvalidShadowsocksKey(format!("{cipher} requires a base64 encoded key"))
+        })?;
+        if decoded.len() != key_len {
+            return Err(Error::InvalidShadowsocksKey(format!(
+                "{cipher} requires a {key_len} byte key, but got {} bytes",
+                decoded.len()
+            )));
         }
     }
+    Ok(())
 }
 
 impl Socks5Local {
@@ -215,14 +497,14 @@ impl Socks5Local {
 }
 
 impl Socks5Remote {
-    pub fn new<I: Into<SocketAddr>>(endpoint: I) -> Self {
+    pub fn new<I: Into<ProxyAddress>>(endpoint: I) -> Self {
         Self {
             endpoint: endpoint.into(),
             auth: None,
         }
     }
 
-    pub fn new_with_authentication<I: Into<SocketAddr>>(
+    pub fn new_with_authentication<I: Into<ProxyAddress>>(
         endpoint: I,
         authentication: SocksAuth,
     ) -> Self {
@@ -233,8 +515,174 @@ impl Socks5Remote {
     }
 }
 
+impl HttpConnect {
+    pub fn new<I: Into<ProxyAddress>>(endpoint: I) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            auth: None,
+            tls: None,
+        }
+    }
+}
+
+/// Host of a remote proxy: either an IP address, or a hostname which is looked up when connecting
+/// to the proxy.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ProxyHost {
+    Ip(IpAddr),
+    Hostname(String),
+}
+
+impl From<IpAddr> for ProxyHost {
+    fn from(ip: IpAddr) -> Self {
+        ProxyHost::Ip(ip)
+    }
+}
+
+impl FromStr for ProxyHost {
+    type Err = Error;
+
+    /// Parse an IP address or a hostname. Hostnames must consist of dot-separated labels of at
+    /// most 63 ASCII letters, digits and hyphens, which neither start nor end with a hyphen.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::ProxyHost;
+    ///
+    /// assert!(matches!("192.0.2.1".parse(), Ok(ProxyHost::Ip(_))));
+    /// assert!(matches!("2001:db8::1".parse(), Ok(ProxyHost::Ip(_))));
+    /// assert!(matches!("proxy.example.com".parse(), Ok(ProxyHost::Hostname(_))));
+    /// assert!(matches!("proxy.example.com.".parse(), Ok(ProxyHost::Hostname(_))));
+    /// assert!("".parse::<ProxyHost>().is_err());
+    /// assert!("proxy..example.com".parse::<ProxyHost>().is_err());
+    /// assert!("-proxy.example.com".parse::<ProxyHost>().is_err());
+    /// assert!("proxy.example.com:1080".parse::<ProxyHost>().is_err());
+    /// ```
+    fn from_str(host: &str) -> Result<Self, Self::Err> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(ProxyHost::Ip(ip));
+        }
+
+        let is_valid_label = |label: &str| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        };
+        let name = host.strip_suffix('.').unwrap_or(host);
+        if name.len() > 253 || !name.split('.').all(is_valid_label) {
+            return Err(Error::InvalidHost(host.to_owned()));
+        }
+        Ok(ProxyHost::Hostname(host.to_owned()))
+    }
+}
+
+impl fmt::Display for ProxyHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyHost::Ip(ip) => ip.fmt(f),
+            ProxyHost::Hostname(hostname) => hostname.fmt(f),
+        }
+    }
+}
+
+/// Address of a remote proxy. It is written as `host:port`, with IPv6 addresses in brackets, so
+/// proxies given by IP address are (de)serialized like a [`SocketAddr`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ProxyAddress {
+    pub host: ProxyHost,
+    pub port: u16,
+}
+
+impl ProxyAddress {
+    pub fn new(host: ProxyHost, port: u16) -> Self {
+        Self { host, port }
+    }
+
+    /// Return the socket address of the proxy, or `None` if its hostname has to be resolved
+    /// first.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self.host {
+            ProxyHost::Ip(ip) => Some(SocketAddr::new(ip, self.port)),
+            ProxyHost::Hostname(_) => None,
+        }
+    }
+
+    /// Return the hostname of the proxy, unless it is given by IP address.
+    pub fn hostname(&self) -> Option<&str> {
+        match &self.host {
+            ProxyHost::Ip(_) => None,
+            ProxyHost::Hostname(hostname) => Some(hostname),
+        }
+    }
+}
+
+impl From<SocketAddr> for ProxyAddress {
+    fn from(addr: SocketAddr) -> Self {
+        Self::new(ProxyHost::Ip(addr.ip()), addr.port())
+    }
+}
+
+impl<I: Into<IpAddr>> From<(I, u16)> for ProxyAddress {
+    fn from((ip, port): (I, u16)) -> Self {
+        Self::new(ProxyHost::Ip(ip.into()), port)
+    }
+}
+
+impl FromStr for ProxyAddress {
+    type Err = Error;
+
+    /// Parse a socket address, or a hostname followed by a port.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::{ProxyAddress, ProxyHost};
+    ///
+    /// let address: ProxyAddress = "proxy.example.com:1080".parse().unwrap();
+    /// assert_eq!(address.host, ProxyHost::Hostname("proxy.example.com".to_owned()));
+    /// assert_eq!(address.port, 1080);
+    /// assert!("[2001:db8::1]:1080".parse::<ProxyAddress>().unwrap().socket_addr().is_some());
+    /// assert!("2001:db8::1:1080".parse::<ProxyAddress>().is_err());
+    /// assert!("proxy.example.com".parse::<ProxyAddress>().is_err());
+    /// ```
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        if let Ok(addr) = address.parse::<SocketAddr>() {
+            return Ok(Self::from(addr));
+        }
+        let invalid = || Error::InvalidAddress(address.to_owned());
+        let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+        match host.parse().map_err(|_| invalid())? {
+            // IP addresses must be valid socket addresses on their own
+            ProxyHost::Ip(_) => Err(invalid()),
+            host => Ok(Self::new(host, port.parse().map_err(|_| invalid())?)),
+        }
+    }
+}
+
+impl TryFrom<String> for ProxyAddress {
+    type Error = Error;
+
+    fn try_from(address: String) -> Result<Self, Self::Error> {
+        address.parse()
+    }
+}
+
+impl From<ProxyAddress> for String {
+    fn from(address: ProxyAddress) -> Self {
+        address.to_string()
+    }
+}
+
+impl fmt::Display for ProxyAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.host {
+            ProxyHost::Ip(ip) => SocketAddr::new(*ip, self.port).fmt(f),
+            ProxyHost::Hostname(hostname) => write!(f, "{hostname}:{}", self.port),
+        }
+    }
+}
+
 /// List of ciphers usable by a Shadowsocks proxy.
-pub const SHADOWSOCKS_CIPHERS: [&str; 19] = [
+pub const SHADOWSOCKS_CIPHERS: [&str; 22] = [
     // Stream ciphers.
     "aes-128-cfb",
     "aes-128-cfb1",
@@ -256,4 +704,70 @@ pub const SHADOWSOCKS_CIPHERS: [&str; 19] = [
     "xchacha20-ietf-poly1305",
     "aes-128-pmac-siv",
     "aes-256-pmac-siv",
+    // AEAD 2022 ciphers.
+    "2022-blake3-aes-128-gcm",
+    "2022-blake3-aes-256-gcm",
+    "2022-blake3-chacha20-poly1305",
 ];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_proxy_host() {
+        assert_eq!(
+            "192.0.2.1".parse::<ProxyHost>().unwrap(),
+            ProxyHost::Ip([192, 0, 2, 1].into())
+        );
+        assert_eq!(
+            "proxy-1.example.com".parse::<ProxyHost>().unwrap(),
+            ProxyHost::Hostname("proxy-1.example.com".to_owned())
+        );
+        assert!("localhost".parse::<ProxyHost>().is_ok());
+        assert!(format!("{}.com", "a".repeat(63))
+            .parse::<ProxyHost>()
+            .is_ok());
+        assert!(format!("{}.com", "a".repeat(64))
+            .parse::<ProxyHost>()
+            .is_err());
+        assert!(["a"; 127].join(".").parse::<ProxyHost>().is_ok());
+        assert!(["a"; 128].join(".").parse::<ProxyHost>().is_err());
+        assert!("proxy-.example.com".parse::<ProxyHost>().is_err());
+        assert!("proxy_1.example.com".parse::<ProxyHost>().is_err());
+        assert!("proxy.example.com/".parse::<ProxyHost>().is_err());
+        assert!("[2001:db8::1]".parse::<ProxyHost>().is_err());
+        assert!(".".parse::<ProxyHost>().is_err());
+    }
+
+    #[test]
+    fn test_proxy_address() {
+        for address in [
+            "192.0.2.1:1080",
+            "[2001:db8::1]:1080",
+            "proxy.example.com:443",
+        ] {
+            let parsed = address.parse::<ProxyAddress>().unwrap();
+            assert_eq!(parsed.to_string(), address);
+            assert_eq!(
+                String::from(parsed.clone())
+                    .parse::<ProxyAddress>()
+                    .unwrap(),
+                parsed
+            );
+        }
+
+        let address = "192.0.2.1:1080".parse::<ProxyAddress>().unwrap();
+        assert_eq!(address.socket_addr(), Some(([192, 0, 2, 1], 1080).into()));
+        assert_eq!(address.hostname(), None);
+
+        let address = "proxy.example.com:443".parse::<ProxyAddress>().unwrap();
+        assert_eq!(address.socket_addr(), None);
+        assert_eq!(address.hostname(), Some("proxy.example.com"));
+
+        assert!("proxy.example.com:".parse::<ProxyAddress>().is_err());
+        assert!("proxy.example.com:65536".parse::<ProxyAddress>().is_err());
+        assert!(":1080".parse::<ProxyAddress>().is_err());
+        assert!("192.0.2.1".parse::<ProxyAddress>().is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use super::wireguard::PublicKey;
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+/// A snapshot of the traffic statistics of an active tunnel.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TunnelStats {
+    /// Time at which the statistics were sampled.
+    pub timestamp: SystemTime,
+    /// Statistics for each WireGuard peer.
+    pub peers: Vec<PeerStats>,
+    /// Upper bound on the round-trip time of the most recent ping answered by the connectivity
+    /// monitor.
+    ///
+    /// The monitor only observes that traffic was received, so the actual round-trip time may be
+    /// shorter by up to the monitor's polling interval.
+    pub ping_rtt_upper_bound: Option<Duration>,
+    /// Counters kept by the obfuscator, if one is used and supports them.
+    pub obfuscator: Option<ObfuscatorStats>,
+}
+
+/// Traffic statistics for a single WireGuard peer.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeerStats {
+    pub public_key: PublicKey,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+    /// Time of the most recent completed handshake, if any.
+    pub last_handshake: Option<SystemTime>,
+    /// Outgoing throughput in bytes per second, if it has been computed.
+    pub tx_rate: Option<f64>,
+    /// Incoming throughput in bytes per second, if it has been computed.
+    pub rx_rate: Option<f64>,
+}
+
+/// Traffic counters of an obfuscation proxy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ObfuscatorStats {
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub fragmented_rx_packets: u64,
+    pub fragmented_tx_packets: u64,
+    pub fragmented_rx_bytes: u64,
+    pub fragmented_tx_bytes: u64,
+}
+
+impl TunnelStats {
+    /// Compute the throughput of each peer from the difference to an earlier sample.
+    ///
+    /// Peers that are missing from `previous`, or whose counters went backwards, e.g. because the
+    /// tunnel was reconfigured, are left without a rate.
+    pub fn compute_rates(&mut self, previous: &TunnelStats) {
+        let Ok(elapsed) = self.timestamp.duration_since(previous.timestamp) else {
+            return;
+        };
+        let elapsed = elapsed.as_secs_f64();
+        if elapsed <= 0.0 {
+            return;
+        }
+
+        for peer in &mut self.peers {
+            let Some(prev_peer) = previous
+                .peers
+                .iter()
+                .find(|prev_peer| prev_peer.public_key == peer.public_key)
+            else {
+                continue;
+            };
+            let rate = |new: u64, old: u64| Some(new.checked_sub(old)? as f64 / elapsed);
+            peer.tx_rate = rate(peer.tx_bytes, prev_peer.tx_bytes);
+            peer.rx_rate = rate(peer.rx_bytes, prev_peer.rx_bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::net::wireguard::PrivateKey;
+
+    fn peer(public_key: &PublicKey, tx_bytes: u64, rx_bytes: u64) -> PeerStats {
+        PeerStats {
+            public_key: public_key.clone(),
+            tx_bytes,
+            rx_bytes,
+            last_handshake: None,
+            tx_rate: None,
+            rx_rate: None,
+        }
+    }
+
+    #[test]
+    fn test_compute_rates() {
+        let key_a = PrivateKey::new_from_random().public_key();
+        let key_b = PrivateKey::new_from_random().public_key();
+        let start = SystemTime::UNIX_EPOCH;
+
+        let previous = TunnelStats {
+            timestamp: start,
+            peers: vec![peer(&key_a, 1000, 5000)],
+            ping_rtt_upper_bound: None,
+            obfuscator: None,
+        };
+        let mut current = TunnelStats {
+            timestamp: start + Duration::from_secs(2),
+            peers: vec![peer(&key_a, 3000, 4000), peer(&key_b, 100, 100)],
+            ping_rtt_upper_bound: None,
+            obfuscator: None,
+        };
+        current.compute_rates(&previous);
+
+        assert_eq!(current.peers[0].tx_rate, Some(1000.0));
+        // Counters were reset, so no rate can be computed
+        assert_eq!(current.peers[0].rx_rate, None);
+        // Unknown peer
+        assert_eq!(current.peers[1].tx_rate, None);
+        assert_eq!(current.peers[1].rx_rate, None);
+    }
+}
@@ -6,6 +6,7 @@ use std::{
     cmp, fmt,
     hash::{Hash, Hasher},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
 };
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -27,6 +28,24 @@ impl TunnelParameters {
             .map(|proxy| proxy.get_obfuscator_endpoint())
             .unwrap_or_else(|| self.connection.get_endpoint())
     }
+
+    /// Returns whether a running tunnel using these parameters can be moved to `other` by only
+    /// replacing its peer, without recreating the tunnel. This requires that only the relay
+    /// differs, and that neither uses obfuscation or multihop.
+    pub fn can_switch_peer_to(&self, other: &TunnelParameters) -> bool {
+        let switchable = |params: &TunnelParameters| {
+            params.obfuscation.is_none() && params.connection.exit_peer.is_none()
+        };
+        if !switchable(self) || !switchable(other) {
+            return false;
+        }
+
+        let mut switched = self.clone();
+        switched.connection.peer = other.connection.peer.clone();
+        // Whether switching is enabled does not affect the tunnel itself
+        switched.options.make_before_break = other.options.make_before_break;
+        switched == *other
+    }
 }
 
 /// Connection-specific configuration in [`TunnelParameters`].
@@ -66,11 +85,14 @@ pub struct PeerConfig {
     pub allowed_ips: Vec<IpNetwork>,
     /// IP address of the WireGuard server.
     pub endpoint: SocketAddr,
-    /// Preshared key (PSK). The PSK should never be persisted, so it does not serialize
-    /// or deserialize. A PSK is only used with quantum-resistant tunnels and are then
-    /// ephemeral and living in memory only.
+    /// Preshared key (PSK). Never serialized, since PSKs negotiated for quantum-resistant tunnels
+    /// must never be persisted. Static PSKs of custom relays are persisted separately by the
+    /// owner of the relay config.
     #[serde(skip)]
     pub psk: Option<PresharedKey>,
+    /// Interval in seconds at which to send keepalive packets to the peer. Disabled if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent_keepalive: Option<u16>,
     /// Enable constant packet sizes for `entry_peer``
     #[cfg(daita)]
     #[serde(skip)]
@@ -94,6 +116,251 @@ pub struct TunnelOptions {
     /// Enable DAITA during tunnel config
     #[cfg(daita)]
     pub daita: bool,
+    /// DAITA level to request from the relay, if DAITA is enabled
+    #[cfg(daita)]
+    pub daita_level: DaitaLevel,
+    /// Periodically negotiate a new PQ-safe PSK at this interval, without reconnecting
+    pub rekey_interval: Option<Duration>,
+    /// Parameters of the connectivity monitor
+    pub connectivity: ConnectivityCheckOptions,
+    /// WireGuard implementation to try first when creating the tunnel device
+    pub backend: WireguardBackend,
+    /// Establish the tunnel to a new relay before leaving the current one, when possible
+    pub make_before_break: bool,
+}
+
+/// WireGuard implementation used to create the tunnel device. Which implementations are
+/// available depends on the platform and on how the daemon was built.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireguardBackend {
+    /// Prefer the kernel implementation where it is available, and fall back on the userspace
+    /// implementation otherwise.
+    #[default]
+    Auto,
+    /// The kernel module on Linux, or WireGuardNT on Windows.
+    Kernel,
+    /// wireguard-go, in userspace.
+    WireguardGo,
+    /// boringtun, in userspace.
+    Boringtun,
+}
+
+impl std::str::FromStr for WireguardBackend {
+    type Err = InvalidWireguardBackend;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(WireguardBackend::Auto),
+            "kernel" => Ok(WireguardBackend::Kernel),
+            "wireguard-go" => Ok(WireguardBackend::WireguardGo),
+            "boringtun" => Ok(WireguardBackend::Boringtun),
+            _ => Err(InvalidWireguardBackend),
+        }
+    }
+}
+
+impl fmt::Display for WireguardBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WireguardBackend::Auto => "auto",
+            WireguardBackend::Kernel => "kernel",
+            WireguardBackend::WireguardGo => "wireguard-go",
+            WireguardBackend::Boringtun => "boringtun",
+        })
+    }
+}
+
+/// Returned when a string is not a known WireGuard backend.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("WireGuard backend must be one of 'auto', 'kernel', 'wireguard-go', or 'boringtun'")]
+pub struct InvalidWireguardBackend;
+
+/// DAITA level to request from the relay. Higher levels add more padding and blocking, which
+/// provides more protection at the cost of more bandwidth overhead.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct DaitaLevel(u8);
+
+impl DaitaLevel {
+    /// Let the relay decide which level to use.
+    pub const DEFAULT: DaitaLevel = DaitaLevel(0);
+    /// Highest level supported by the relays.
+    pub const MAX: DaitaLevel = DaitaLevel(10);
+
+    /// Returns the given level, or `None` if it is higher than [`DaitaLevel::MAX`]. `0` means that
+    /// the relay decides.
+    pub const fn new(level: u8) -> Option<Self> {
+        if level <= Self::MAX.0 {
+            Some(DaitaLevel(level))
+        } else {
+            None
+        }
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_default(self) -> bool {
+        self.0 == Self::DEFAULT.0
+    }
+}
+
+impl From<DaitaLevel> for u8 {
+    fn from(level: DaitaLevel) -> Self {
+        level.0
+    }
+}
+
+impl TryFrom<u8> for DaitaLevel {
+    type Error = InvalidDaitaLevel;
+
+    fn try_from(level: u8) -> Result<Self, Self::Error> {
+        DaitaLevel::new(level).ok_or(InvalidDaitaLevel)
+    }
+}
+
+impl std::str::FromStr for DaitaLevel {
+    type Err = InvalidDaitaLevel;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("default") {
+            return Ok(DaitaLevel::DEFAULT);
+        }
+        s.parse::<u8>()
+            .map_err(|_| InvalidDaitaLevel)
+            .and_then(DaitaLevel::try_from)
+    }
+}
+
+impl fmt::Display for DaitaLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_default() {
+            f.write_str("default")
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+/// Returned when a DAITA level is out of range.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("DAITA level must be 'default' or between 1 and 10")]
+pub struct InvalidDaitaLevel;
+
+/// DAITA parameters in use by an active tunnel, as negotiated with the relay.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct DaitaParameters {
+    /// The level that was requested.
+    pub level: DaitaLevel,
+    /// Largest fraction of the traffic that may consist of padding.
+    pub max_padding_frac: f64,
+    /// Largest fraction of the time that traffic may be blocked.
+    pub max_blocking_frac: f64,
+}
+
+impl DaitaParameters {
+    /// Returns whether both fractions are within `0.0..=1.0`.
+    pub fn is_valid(&self) -> bool {
+        (0.0..=1.0).contains(&self.max_padding_frac)
+            && (0.0..=1.0).contains(&self.max_blocking_frac)
+    }
+}
+
+// The fractions are never NaN, since they are validated before being used.
+impl Eq for DaitaParameters {}
+
+impl Hash for DaitaParameters {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.level.hash(state);
+        self.max_padding_frac.to_bits().hash(state);
+        self.max_blocking_frac.to_bits().hash(state);
+    }
+}
+
+/// Parameters that decide when the connectivity monitor considers a tunnel to be dead.
+///
+/// Values outside of the bounds given by [`ConnectivityCheckOptions::MIN`] and
+/// [`ConnectivityCheckOptions::MAX`] are clamped when the monitor is started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConnectivityCheckOptions {
+    /// Start pinging if outgoing traffic has not been answered within this time.
+    pub rx_timeout: Duration,
+    /// Start pinging if no traffic has been sent or received within this time.
+    pub traffic_timeout: Duration,
+    /// Consider the tunnel dead if no traffic has been received this long after the first ping.
+    pub ping_timeout: Duration,
+    /// Time between pings.
+    pub ping_interval: Duration,
+    /// Initial timeout for receiving traffic when establishing a connection. It is doubled on
+    /// each failed attempt, up to `ping_timeout`.
+    pub establish_timeout: Duration,
+    /// Address to ping inside the tunnel. The tunnel gateway is used if unset.
+    pub ping_target: Option<Ipv4Addr>,
+}
+
+impl ConnectivityCheckOptions {
+    /// Smallest allowed values.
+    pub const MIN: ConnectivityCheckOptions = ConnectivityCheckOptions {
+        rx_timeout: Duration::from_secs(1),
+        traffic_timeout: Duration::from_secs(10),
+        ping_timeout: Duration::from_secs(3),
+        ping_interval: Duration::from_millis(500),
+        establish_timeout: Duration::from_secs(1),
+        ping_target: None,
+    };
+
+    /// Largest allowed values.
+    pub const MAX: ConnectivityCheckOptions = ConnectivityCheckOptions {
+        rx_timeout: Duration::from_secs(60),
+        traffic_timeout: Duration::from_secs(10 * 60),
+        ping_timeout: Duration::from_secs(2 * 60),
+        ping_interval: Duration::from_secs(30),
+        establish_timeout: Duration::from_secs(60),
+        ping_target: None,
+    };
+
+    /// Return a copy where every value is within bounds, and where pings are sent more than once
+    /// before timing out.
+    pub fn clamped(&self) -> ConnectivityCheckOptions {
+        let (min, max) = (Self::MIN, Self::MAX);
+        let ping_timeout = self.ping_timeout.clamp(min.ping_timeout, max.ping_timeout);
+        ConnectivityCheckOptions {
+            rx_timeout: self.rx_timeout.clamp(min.rx_timeout, max.rx_timeout),
+            traffic_timeout: self
+                .traffic_timeout
+                .clamp(min.traffic_timeout, max.traffic_timeout),
+            ping_timeout,
+            ping_interval: self
+                .ping_interval
+                .clamp(min.ping_interval, max.ping_interval.min(ping_timeout / 2)),
+            establish_timeout: self.establish_timeout.clamp(
+                min.establish_timeout,
+                max.establish_timeout.min(ping_timeout),
+            ),
+            ping_target: self.ping_target,
+        }
+    }
+
+    /// Return whether all values are within bounds.
+    pub fn is_valid(&self) -> bool {
+        self.clamped() == *self
+    }
+}
+
+impl Default for ConnectivityCheckOptions {
+    fn default() -> Self {
+        ConnectivityCheckOptions {
+            rx_timeout: Duration::from_secs(5),
+            traffic_timeout: Duration::from_secs(120),
+            ping_timeout: Duration::from_secs(15),
+            ping_interval: Duration::from_secs(3),
+            establish_timeout: Duration::from_secs(4),
+            ping_target: None,
+        }
+    }
 }
 
 /// Wireguard x25519 private key
@@ -262,7 +529,8 @@ impl fmt::Display for PublicKey {
     }
 }
 
-/// A WireGuard preshared key (PSK). Used to make the tunnel quantum-resistant.
+/// A WireGuard preshared key (PSK). Used to make the tunnel quantum-resistant, or configured
+/// statically for custom relays.
 #[derive(Clone, PartialEq, Eq, Hash, Zeroize, ZeroizeOnDrop)]
 pub struct PresharedKey(Box<[u8; 32]>);
 
@@ -273,6 +541,14 @@ impl PresharedKey {
     pub fn as_bytes(&self) -> &[u8; 32] {
         &self.0
     }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.as_bytes())
+    }
+
+    pub fn from_base64(key: &str) -> Result<Self, InvalidKey> {
+        key_from_base64(key)
+    }
 }
 
 impl From<Box<[u8; 32]>> for PresharedKey {
@@ -281,6 +557,30 @@ impl From<Box<[u8; 32]>> for PresharedKey {
     }
 }
 
+impl From<[u8; 32]> for PresharedKey {
+    fn from(key: [u8; 32]) -> PresharedKey {
+        PresharedKey(Box::new(key))
+    }
+}
+
+impl Serialize for PresharedKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_key(self.as_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PresharedKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_key(deserializer)
+    }
+}
+
 impl fmt::Debug for PresharedKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", &STANDARD.encode(self.as_bytes()))
@@ -314,3 +614,134 @@ fn key_from_base64<K: From<[u8; 32]>>(key: &str) -> Result<K, InvalidKey> {
     key.copy_from_slice(&bytes);
     Ok(From::from(key))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wireguard_backend() {
+        for backend in [
+            WireguardBackend::Auto,
+            WireguardBackend::Kernel,
+            WireguardBackend::WireguardGo,
+            WireguardBackend::Boringtun,
+        ] {
+            assert_eq!(backend.to_string().parse(), Ok(backend));
+        }
+        assert_eq!(
+            "wireguard_go".parse::<WireguardBackend>(),
+            Err(InvalidWireguardBackend)
+        );
+    }
+
+    #[test]
+    fn test_daita_level() {
+        assert_eq!("default".parse(), Ok(DaitaLevel::DEFAULT));
+        assert_eq!("0".parse(), Ok(DaitaLevel::DEFAULT));
+        assert_eq!("10".parse(), Ok(DaitaLevel::MAX));
+        assert_eq!("11".parse::<DaitaLevel>(), Err(InvalidDaitaLevel));
+        assert_eq!("-1".parse::<DaitaLevel>(), Err(InvalidDaitaLevel));
+        assert_eq!(DaitaLevel::try_from(11), Err(InvalidDaitaLevel));
+    }
+
+    #[test]
+    fn test_connectivity_check_options_clamped() {
+        let default = ConnectivityCheckOptions::default();
+        assert!(default.is_valid());
+
+        let options = ConnectivityCheckOptions {
+            rx_timeout: Duration::ZERO,
+            traffic_timeout: Duration::from_secs(24 * 60 * 60),
+            ping_timeout: Duration::from_secs(4),
+            ping_interval: Duration::from_secs(3),
+            establish_timeout: Duration::from_secs(10),
+            ping_target: Some(Ipv4Addr::new(10, 64, 0, 1)),
+        };
+        assert!(!options.is_valid());
+
+        let clamped = options.clamped();
+        assert_eq!(clamped.rx_timeout, ConnectivityCheckOptions::MIN.rx_timeout);
+        assert_eq!(
+            clamped.traffic_timeout,
+            ConnectivityCheckOptions::MAX.traffic_timeout
+        );
+        assert_eq!(clamped.ping_timeout, Duration::from_secs(4));
+        // At least two pings must be sent before timing out
+        assert_eq!(clamped.ping_interval, Duration::from_secs(2));
+        // Establishing a connection must not take longer than the ping timeout
+        assert_eq!(clamped.establish_timeout, Duration::from_secs(4));
+        assert_eq!(clamped.ping_target, options.ping_target);
+        assert!(clamped.is_valid());
+    }
+
+    fn tunnel_parameters(relay: &str) -> TunnelParameters {
+        let peer = |endpoint: &str| PeerConfig {
+            public_key: PrivateKey::from([relay.as_bytes()[0]; 32]).public_key(),
+            allowed_ips: crate::net::all_of_the_internet(),
+            endpoint: endpoint.parse().unwrap(),
+            psk: None,
+            persistent_keepalive: None,
+            #[cfg(daita)]
+            constant_packet_size: false,
+        };
+        TunnelParameters {
+            connection: ConnectionConfig {
+                tunnel: TunnelConfig {
+                    private_key: PrivateKey::from([1; 32]),
+                    addresses: vec![Ipv4Addr::new(10, 64, 0, 2).into()],
+                },
+                peer: peer(relay),
+                exit_peer: None,
+                ipv4_gateway: Ipv4Addr::new(10, 64, 0, 1),
+                ipv6_gateway: None,
+                #[cfg(target_os = "linux")]
+                fwmark: None,
+            },
+            options: TunnelOptions {
+                mtu: None,
+                quantum_resistant: false,
+                #[cfg(daita)]
+                daita: false,
+                #[cfg(daita)]
+                daita_level: DaitaLevel::DEFAULT,
+                rekey_interval: None,
+                connectivity: ConnectivityCheckOptions::default(),
+                backend: WireguardBackend::Auto,
+                make_before_break: true,
+            },
+            generic_options: GenericTunnelOptions { enable_ipv6: false },
+            obfuscation: None,
+        }
+    }
+
+    #[test]
+    fn test_can_switch_peer_to() {
+        let current = tunnel_parameters("1.2.3.4:51820");
+        let other = tunnel_parameters("5.6.7.8:51820");
+        assert!(current.can_switch_peer_to(&other));
+
+        let mut multihop = other.clone();
+        multihop.connection.exit_peer = Some(current.connection.peer.clone());
+        assert!(!current.can_switch_peer_to(&multihop));
+
+        // Ephemeral peers are negotiated with the new relay
+        let mut quantum_resistant = current.clone();
+        quantum_resistant.options.quantum_resistant = true;
+        let mut other_quantum_resistant = other.clone();
+        other_quantum_resistant.options.quantum_resistant = true;
+        assert!(quantum_resistant.can_switch_peer_to(&other_quantum_resistant));
+        assert!(!current.can_switch_peer_to(&other_quantum_resistant));
+
+        let mut obfuscated = other.clone();
+        obfuscated.obfuscation = Some(crate::net::obfuscation::ObfuscatorConfig::Udp2Tcp {
+            endpoint: "5.6.7.8:443".parse().unwrap(),
+        });
+        assert!(!current.can_switch_peer_to(&obfuscated));
+
+        // Anything but the peer differing requires a new tunnel
+        let mut new_mtu = other;
+        new_mtu.options.mtu = Some(1280);
+        assert!(!current.can_switch_peer_to(&new_mtu));
+    }
+}
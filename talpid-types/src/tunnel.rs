@@ -27,6 +27,19 @@ pub enum TunnelStateTransition {
     Error(ErrorState),
 }
 
+/// Event emitted by an active tunnel that does not cause the tunnel state machine to change
+/// state.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TunnelNotification {
+    /// The tunnel MTU was changed after re-probing the path MTU.
+    MtuChanged(u16),
+    /// The connectivity monitor has not received any traffic from the relay for a while, in
+    /// response to traffic being sent. The tunnel is reconnected if it does not recover.
+    ConnectivityDegraded,
+    /// Traffic is being received from the relay again after [`Self::ConnectivityDegraded`].
+    ConnectivityRestored,
+}
+
 /// Action that will be taken after disconnection is complete.
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
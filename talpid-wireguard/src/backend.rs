@@ -0,0 +1,82 @@
+//! Selection of the WireGuard implementation that drives the tunnel device.
+
+use talpid_types::net::wireguard::WireguardBackend;
+
+/// The userspace implementation included in this build.
+#[cfg(not(feature = "boringtun"))]
+pub const USERSPACE: WireguardBackend = WireguardBackend::WireguardGo;
+/// The userspace implementation included in this build.
+#[cfg(feature = "boringtun")]
+pub const USERSPACE: WireguardBackend = WireguardBackend::Boringtun;
+
+/// Returns the implementations to try, in order, when creating the tunnel device.
+///
+/// The preferred implementation is tried first, followed by every other available implementation.
+/// If the preferred implementation is unavailable, the order used for
+/// [`WireguardBackend::Auto`] is returned instead. `prefer_userspace` only affects automatic
+/// selection, and makes it skip the kernel implementation altogether.
+#[cfg(not(target_os = "android"))]
+pub fn candidates(
+    preferred: WireguardBackend,
+    kernel_available: bool,
+    prefer_userspace: bool,
+) -> Vec<WireguardBackend> {
+    let available: Vec<_> = kernel_available
+        .then_some(WireguardBackend::Kernel)
+        .into_iter()
+        .chain(std::iter::once(USERSPACE))
+        .collect();
+
+    match preferred {
+        WireguardBackend::Auto => (),
+        preferred if available.contains(&preferred) => {
+            return std::iter::once(preferred)
+                .chain(
+                    available
+                        .into_iter()
+                        .filter(|backend| *backend != preferred),
+                )
+                .collect();
+        }
+        preferred => {
+            log::warn!("WireGuard backend {preferred} is unavailable, selecting one automatically")
+        }
+    }
+
+    if prefer_userspace {
+        vec![USERSPACE]
+    } else {
+        available
+    }
+}
+
+#[cfg(all(test, not(target_os = "android")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        use WireguardBackend::*;
+
+        assert_eq!(candidates(Auto, true, false), [Kernel, USERSPACE]);
+        assert_eq!(candidates(Auto, true, true), [USERSPACE]);
+        assert_eq!(candidates(Auto, false, false), [USERSPACE]);
+
+        assert_eq!(candidates(Kernel, true, true), [Kernel, USERSPACE]);
+        assert_eq!(candidates(Kernel, false, false), [USERSPACE]);
+
+        assert_eq!(candidates(USERSPACE, true, false), [USERSPACE, Kernel]);
+        assert_eq!(candidates(USERSPACE, false, false), [USERSPACE]);
+
+        let other_userspace = if USERSPACE == WireguardGo {
+            Boringtun
+        } else {
+            WireguardGo
+        };
+        assert_eq!(
+            candidates(other_userspace, true, false),
+            [Kernel, USERSPACE]
+        );
+        assert_eq!(candidates(other_userspace, true, true), [USERSPACE]);
+    }
+}
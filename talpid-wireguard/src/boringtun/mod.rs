@@ -17,6 +17,7 @@ use std::{
     future::Future,
     ops::Deref,
     sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
 };
 use talpid_tunnel::tun_provider::{self, Tun, TunProvider};
 use talpid_tunnel_config_client::DaitaSettings;
@@ -156,6 +157,15 @@ impl Tunnel for BoringTun {
                     Stats {
                         tx_bytes: peer.tx_bytes.unwrap_or_default(),
                         rx_bytes: peer.rx_bytes.unwrap_or_default(),
+                        last_handshake: peer
+                            .last_handshake_time_sec
+                            .zip(peer.last_handshake_time_nsec)
+                            .filter(|&(sec, nsec)| sec != 0 || nsec != 0)
+                            .and_then(|(sec, nsec)| {
+                                let since_epoch = Duration::from_secs(sec.into())
+                                    + Duration::from_nanos(nsec.into());
+                                SystemTime::UNIX_EPOCH.checked_add(since_epoch)
+                            }),
                     },
                 )
             },
@@ -213,6 +223,7 @@ async fn set_boringtun_config(
         if let Some(psk) = &peer.psk {
             boring_peer.preshared_key = Some(SetUnset::Set((*psk.as_bytes()).into()));
         }
+        boring_peer.persistent_keepalive_interval = peer.persistent_keepalive;
 
         let boring_peer = SetPeer::builder().peer(boring_peer).build();
 
@@ -2,7 +2,9 @@ use std::{
     borrow::Cow,
     ffi::CString,
     net::{Ipv4Addr, Ipv6Addr},
+    time::Duration,
 };
+use talpid_tunnel_config_client::DaitaSettings;
 use talpid_types::net::wireguard::{PeerConfig, PrivateKey};
 use talpid_types::net::{obfuscation::ObfuscatorConfig, wireguard, GenericTunnelOptions};
 
@@ -37,6 +39,18 @@ pub struct Config {
     pub quantum_resistant: bool,
     /// Enable DAITA
     pub daita: bool,
+    /// DAITA level to request from the relay
+    pub daita_level: wireguard::DaitaLevel,
+    /// DAITA parameters negotiated with the relay
+    pub daita_parameters: Option<wireguard::DaitaParameters>,
+    /// DAITA machines negotiated with the relay, used to restart DAITA when the peers are replaced
+    pub daita_settings: Option<DaitaSettings>,
+    /// Interval at which to negotiate a new ephemeral peer, if `quantum_resistant` is enabled
+    pub rekey_interval: Option<Duration>,
+    /// Parameters of the connectivity monitor
+    pub connectivity: wireguard::ConnectivityCheckOptions,
+    /// Preferred WireGuard implementation
+    pub backend: wireguard::WireguardBackend,
 }
 
 /// Configuration errors
@@ -106,6 +120,15 @@ impl Config {
             daita: wg_options.daita,
             #[cfg(not(daita))]
             daita: false,
+            #[cfg(daita)]
+            daita_level: wg_options.daita_level,
+            #[cfg(not(daita))]
+            daita_level: wireguard::DaitaLevel::DEFAULT,
+            daita_parameters: None,
+            daita_settings: None,
+            rekey_interval: wg_options.rekey_interval,
+            connectivity: wg_options.connectivity,
+            backend: wg_options.backend,
         };
 
         for peer in config.peers_mut() {
@@ -254,6 +277,12 @@ fn write_peer_to_config(wg_conf: &mut WgConfigBuffer, peer: &PeerConfig) {
     if let Some(ref psk) = peer.psk {
         wg_conf.add::<&[u8]>("preshared_key", psk.as_bytes().as_ref());
     }
+    if let Some(interval) = peer.persistent_keepalive {
+        wg_conf.add(
+            "persistent_keepalive_interval",
+            interval.to_string().as_str(),
+        );
+    }
     for addr in &peer.allowed_ips {
         wg_conf.add("allowed_ip", addr.to_string().as_str());
     }
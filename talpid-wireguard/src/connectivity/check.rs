@@ -2,10 +2,11 @@ use std::{
     net::Ipv4Addr,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     time::Duration,
 };
+use talpid_types::net::wireguard::ConnectivityCheckOptions;
 use tokio::{sync::broadcast, time::Instant};
 
 use super::{constants::*, error::Error, pinger};
@@ -26,16 +27,19 @@ use pinger::Pinger;
 /// timeout. A connection is considered to be established the first time an increase in incoming
 /// traffic is observed.
 ///
-/// The connectivity monitor will start sending pings and start the countdown to `PING_TIMEOUT` in
+/// The connectivity monitor will start sending pings and start the countdown to `ping_timeout` in
 /// the following cases:
 /// - In case that we have observed a bump in the outgoing traffic but no corresponding incoming
-///   traffic for longer than `BYTES_RX_TIMEOUT`, then the monitor will start pinging.
+///   traffic for longer than `rx_timeout`, then the monitor will start pinging.
 /// - In case that no increase in outgoing or incoming traffic has been observed for longer than
-///   `TRAFFIC_TIMEOUT`, then the monitor will start pinging as well.
+///   `traffic_timeout`, then the monitor will start pinging as well.
 ///
 /// Once a connection established, a connection is only considered broken once the connectivity
-/// monitor has started pinging and no traffic has been received for a duration of `PING_TIMEOUT`.
+/// monitor has started pinging and no traffic has been received for a duration of `ping_timeout`.
+///
+/// The timeouts are given by [`ConnectivityCheckOptions`].
 pub struct Check {
+    options: ConnectivityCheckOptions,
     conn_state: ConnState,
     ping_state: PingState,
     cancel_receiver: CancelReceiver,
@@ -93,16 +97,24 @@ impl CancelToken {
 }
 
 impl Check {
+    /// Create a new [Check]. Pings are sent to `gateway` unless the options specify another
+    /// target.
     pub fn new(
-        addr: Ipv4Addr,
+        gateway: Ipv4Addr,
         #[cfg(any(target_os = "macos", target_os = "linux"))] interface: String,
+        options: ConnectivityCheckOptions,
         retry_attempt: u32,
         cancel_receiver: CancelReceiver,
     ) -> Result<Check, Error> {
+        let clamped = options.clamped();
+        if clamped != options {
+            log::warn!("Connectivity monitor parameters are out of bounds, using {clamped:?}");
+        }
         Ok(Check {
+            options: clamped,
             conn_state: ConnState::new(Instant::now(), Default::default()),
             ping_state: PingState::new(
-                addr,
+                clamped.ping_target.unwrap_or(gateway),
                 #[cfg(any(target_os = "macos", target_os = "linux"))]
                 interface,
             )?,
@@ -113,10 +125,15 @@ impl Check {
 
     #[cfg(test)]
     /// Create a new [Check] with a custom initial state.
-    pub(super) fn mock(conn_state: ConnState, ping_state: PingState) -> (Self, CancelToken) {
+    pub(super) fn mock(
+        options: ConnectivityCheckOptions,
+        conn_state: ConnState,
+        ping_state: PingState,
+    ) -> (Self, CancelToken) {
         let (cancel_token, cancel_receiver) = CancelToken::new();
         (
             Check {
+                options,
                 conn_state,
                 ping_state,
                 retry_attempt: 0,
@@ -138,16 +155,23 @@ impl Check {
             .send_icmp()
             .await
             .map_err(Error::PingError)?;
+        self.ping_state.last_ping_timestamp = Some(Instant::now());
         self.establish_connectivity_inner(
             self.retry_attempt,
-            ESTABLISH_TIMEOUT,
+            self.options.establish_timeout,
             ESTABLISH_TIMEOUT_MULTIPLIER,
-            MAX_ESTABLISH_TIMEOUT,
+            self.options.ping_timeout,
             tunnel_handle,
         )
         .await
     }
 
+    /// Returns a handle to an upper bound on the round-trip time of the most recent ping that was
+    /// answered. See [`PingRtt`].
+    pub fn ping_rtt(&self) -> PingRtt {
+        self.ping_state.rtt.clone()
+    }
+
     pub(crate) async fn reset(&mut self, current_iteration: Instant) {
         self.ping_state.reset().await;
         self.conn_state.reset_after_suspension(current_iteration);
@@ -172,6 +196,7 @@ impl Check {
         let poll_check = async {
             loop {
                 if Self::check_connectivity_interval(
+                    &self.options,
                     &mut self.conn_state,
                     &mut self.ping_state,
                     Instant::now(),
@@ -216,6 +241,12 @@ impl Check {
         }
     }
 
+    /// Returns true if traffic has been sent through an established tunnel, but nothing has
+    /// been received in response for `rx_timeout`.
+    pub(crate) fn is_degraded(&self) -> bool {
+        self.conn_state.connected() && self.conn_state.rx_timed_out(&self.options)
+    }
+
     pub(crate) fn should_shut_down(&self) -> bool {
         self.cancel_receiver.closed()
     }
@@ -227,10 +258,11 @@ impl Check {
         tunnel_handle: &dyn Tunnel,
     ) -> Result<bool, Error> {
         Self::check_connectivity_interval(
+            &self.options,
             &mut self.conn_state,
             &mut self.ping_state,
             now,
-            PING_TIMEOUT,
+            self.options.ping_timeout,
             tunnel_handle,
         )
         .await
@@ -238,6 +270,7 @@ impl Check {
 
     /// Returns true if connection is established
     async fn check_connectivity_interval(
+        options: &ConnectivityCheckOptions,
         conn_state: &mut ConnState,
         ping_state: &mut PingState,
         now: Instant,
@@ -251,11 +284,12 @@ impl Check {
             None => Ok(false),
             Some(new_stats) => {
                 if conn_state.update(now, new_stats) {
+                    ping_state.record_rtt(now);
                     ping_state.reset().await;
                     return Ok(true);
                 }
 
-                Self::maybe_send_ping(conn_state, ping_state, now).await?;
+                Self::maybe_send_ping(options, conn_state, ping_state, now).await?;
                 Ok(!ping_state.ping_timed_out(timeout) && conn_state.connected())
             }
         }
@@ -274,18 +308,20 @@ impl Check {
     }
 
     async fn maybe_send_ping(
+        options: &ConnectivityCheckOptions,
         conn_state: &mut ConnState,
         ping_state: &mut PingState,
         now: Instant,
     ) -> Result<(), Error> {
         // Only send out a ping if we haven't received a byte in a while or no traffic has flowed
-        // in the last 2 minutes, but if a ping already has been sent out, only send one out every
-        // 3 seconds.
-        if (conn_state.rx_timed_out() || conn_state.traffic_timed_out())
+        // in a long time, but if a ping already has been sent out, only send one out every
+        // `ping_interval`.
+        if (conn_state.rx_timed_out(options) || conn_state.traffic_timed_out(options))
             && ping_state
                 .initial_ping_timestamp
                 .map(|initial_ping_timestamp| {
-                    initial_ping_timestamp.elapsed() / ping_state.num_pings_sent < SECONDS_PER_PING
+                    initial_ping_timestamp.elapsed() / ping_state.num_pings_sent
+                        < options.ping_interval
                 })
                 .unwrap_or(true)
         {
@@ -297,16 +333,26 @@ impl Check {
             if ping_state.initial_ping_timestamp.is_none() {
                 ping_state.initial_ping_timestamp = Some(now);
             }
+            ping_state.last_ping_timestamp = Some(now);
             ping_state.num_pings_sent += 1;
         }
         Ok(())
     }
 }
 
+/// Round-trip time of the most recent ping that was answered by the connectivity monitor.
+///
+/// The pinger does not read replies, so the reply is assumed to be the first incoming traffic
+/// observed after the ping was sent. The value is therefore an upper bound, limited by how often
+/// the tunnel stats are polled.
+pub type PingRtt = Arc<Mutex<Option<Duration>>>;
+
 pub(super) struct PingState {
     initial_ping_timestamp: Option<Instant>,
+    last_ping_timestamp: Option<Instant>,
     num_pings_sent: u32,
     pinger: Box<dyn Pinger>,
+    rtt: PingRtt,
 }
 
 impl PingState {
@@ -327,8 +373,10 @@ impl PingState {
     pub(super) fn new_with(pinger: Box<dyn Pinger>) -> Self {
         Self {
             initial_ping_timestamp: None,
+            last_ping_timestamp: None,
             num_pings_sent: 0,
             pinger,
+            rtt: PingRtt::default(),
         }
     }
 
@@ -338,9 +386,17 @@ impl PingState {
             .unwrap_or(false)
     }
 
+    /// Record the time since the last ping as its round-trip time, if one is outstanding.
+    fn record_rtt(&mut self, now: Instant) {
+        if let Some(sent) = self.last_ping_timestamp.take() {
+            *self.rtt.lock().unwrap() = Some(now.saturating_duration_since(sent));
+        }
+    }
+
     /// Reset timeouts - assume that the last time bytes were received is now.
     async fn reset(&mut self) {
         self.initial_ping_timestamp = None;
+        self.last_ping_timestamp = None;
         self.num_pings_sent = 0;
         self.pinger.reset().await;
     }
@@ -437,9 +493,9 @@ impl ConnState {
     }
 
     // check if last time data was received is too long ago
-    pub fn rx_timed_out(&self) -> bool {
+    pub fn rx_timed_out(&self, options: &ConnectivityCheckOptions) -> bool {
         match self {
-            ConnState::Connecting { start, .. } => start.elapsed() >= BYTES_RX_TIMEOUT,
+            ConnState::Connecting { start, .. } => start.elapsed() >= options.rx_timeout,
             ConnState::Connected {
                 rx_timestamp,
                 tx_timestamp,
@@ -447,23 +503,23 @@ impl ConnState {
             } => {
                 // if last sent bytes were sent after or at the same time as last received bytes
                 tx_timestamp >= rx_timestamp &&
-                    // and the response hasn't been seen for BYTES_RX_TIMEOUT
-                    rx_timestamp.elapsed() >= BYTES_RX_TIMEOUT
+                    // and the response hasn't been seen for rx_timeout
+                    rx_timestamp.elapsed() >= options.rx_timeout
             }
         }
     }
 
     // check if no bytes have been sent or received in a while
-    pub fn traffic_timed_out(&self) -> bool {
+    pub fn traffic_timed_out(&self, options: &ConnectivityCheckOptions) -> bool {
         match self {
-            ConnState::Connecting { .. } => self.rx_timed_out(),
+            ConnState::Connecting { .. } => self.rx_timed_out(options),
             ConnState::Connected {
                 rx_timestamp,
                 tx_timestamp,
                 ..
             } => {
-                rx_timestamp.elapsed() >= TRAFFIC_TIMEOUT
-                    || tx_timestamp.elapsed() >= TRAFFIC_TIMEOUT
+                rx_timestamp.elapsed() >= options.traffic_timeout
+                    || tx_timestamp.elapsed() >= options.traffic_timeout
             }
         }
     }
@@ -480,6 +536,10 @@ mod test {
     use super::*;
     use crate::connectivity::mock::*;
 
+    fn options() -> ConnectivityCheckOptions {
+        ConnectivityCheckOptions::default()
+    }
+
     /// Test if a newly created ConnState won't have timed out or consider itself connected
     #[test]
     fn test_conn_state_no_timeout_on_start() {
@@ -487,20 +547,20 @@ mod test {
         let conn_state = ConnState::new(now, Default::default());
 
         assert!(!conn_state.connected());
-        assert!(!conn_state.rx_timed_out());
-        assert!(!conn_state.traffic_timed_out());
+        assert!(!conn_state.rx_timed_out(&options()));
+        assert!(!conn_state.traffic_timed_out(&options()));
     }
 
     /// Test if ConnState::Connecting will timeout after not receiving any traffic after
-    /// BYTES_RX_TIMEOUT
+    /// the rx timeout
     #[test]
     fn test_conn_state_timeout_after_rx_timeout() {
-        let now = Instant::now().checked_sub(BYTES_RX_TIMEOUT).unwrap();
+        let now = Instant::now().checked_sub(options().rx_timeout).unwrap();
         let conn_state = ConnState::new(now, Default::default());
 
         assert!(!conn_state.connected());
-        assert!(conn_state.rx_timed_out());
-        assert!(conn_state.traffic_timed_out());
+        assert!(conn_state.rx_timed_out(&options()));
+        assert!(conn_state.traffic_timed_out(&options()));
     }
 
     /// Test if ConnState::Connecting correctly transitions into ConnState::Connected if traffic is
@@ -515,46 +575,50 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                last_handshake: None,
             },
         );
         conn_state.update(Instant::now(), stats);
 
         assert!(conn_state.connected());
-        assert!(!conn_state.rx_timed_out());
-        assert!(!conn_state.traffic_timed_out());
+        assert!(!conn_state.rx_timed_out(&options()));
+        assert!(!conn_state.traffic_timed_out(&options()));
     }
 
-    /// Test if ConnState::Connected correctly times out after TRAFFIC_TIMEOUT when no traffic is
+    /// Test if ConnState::Connected correctly times out after the traffic timeout when no traffic is
     /// observed
     #[test]
     fn test_conn_state_traffic_times_out_after_connecting() {
         let start = Instant::now()
-            .checked_sub(TRAFFIC_TIMEOUT + Duration::from_secs(1))
+            .checked_sub(options().traffic_timeout + Duration::from_secs(1))
             .unwrap();
         let mut conn_state = ConnState::new(start, Default::default());
 
-        let connect_time = Instant::now().checked_sub(TRAFFIC_TIMEOUT).unwrap();
+        let connect_time = Instant::now()
+            .checked_sub(options().traffic_timeout)
+            .unwrap();
         let mut stats = StatsMap::new();
         stats.insert(
             [0u8; 32],
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                last_handshake: None,
             },
         );
         conn_state.update(connect_time, stats);
 
         assert!(conn_state.connected());
-        assert!(!conn_state.rx_timed_out());
-        assert!(conn_state.traffic_timed_out());
+        assert!(!conn_state.rx_timed_out(&options()));
+        assert!(conn_state.traffic_timed_out(&options()));
     }
 
-    /// Test if ConnState::Connected correctly times out after BYTES_RX_TIMEOUT when no incoming
+    /// Test if ConnState::Connected correctly times out after the rx timeout when no incoming
     /// traffic is observed
     #[test]
     fn test_conn_state_rx_times_out_after_connecting() {
         let start = Instant::now()
-            .checked_sub(BYTES_RX_TIMEOUT + Duration::from_secs(1))
+            .checked_sub(options().rx_timeout + Duration::from_secs(1))
             .unwrap();
         let mut conn_state = ConnState::new(start, Default::default());
 
@@ -564,50 +628,111 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                last_handshake: None,
             },
         );
         conn_state.update(start, stats);
 
-        let update_time = Instant::now().checked_sub(BYTES_RX_TIMEOUT).unwrap();
+        let update_time = Instant::now().checked_sub(options().rx_timeout).unwrap();
         let mut stats = StatsMap::new();
         stats.insert(
             [0u8; 32],
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 1,
+                last_handshake: None,
             },
         );
         conn_state.update(update_time, stats);
 
         assert!(conn_state.connected());
-        assert!(conn_state.rx_timed_out());
-        assert!(!conn_state.traffic_timed_out());
+        assert!(conn_state.rx_timed_out(&options()));
+        assert!(!conn_state.traffic_timed_out(&options()));
     }
 
     #[tokio::test]
     /// Verify that `check_connectivity()` returns `false` if the tunnel is connected and traffic is
-    /// not flowing after `BYTES_RX_TIMEOUT` and `PING_TIMEOUT`.
+    /// not flowing after the rx timeout and ping timeout.
     async fn test_ping_times_out() {
         let tunnel = MockTunnel::never_incrementing().boxed();
         let pinger = MockPinger::default();
         let now = Instant::now();
         let start = now
-            .checked_sub(BYTES_RX_TIMEOUT + PING_TIMEOUT + Duration::from_secs(10))
+            .checked_sub(options().rx_timeout + options().ping_timeout + Duration::from_secs(10))
             .unwrap();
         let (mut checker, _cancel_token) = mock_checker(start, Box::new(pinger));
 
         // Mock the state - connectivity has been established
         checker.conn_state = connected_state(start);
         // A ping was sent to verify connectivity
-        Check::maybe_send_ping(&mut checker.conn_state, &mut checker.ping_state, start)
-            .await
-            .unwrap();
+        Check::maybe_send_ping(
+            &checker.options,
+            &mut checker.conn_state,
+            &mut checker.ping_state,
+            start,
+        )
+        .await
+        .unwrap();
         assert!(!checker
             .check_connectivity(now, tunnel.as_ref())
             .await
             .unwrap())
     }
 
+    #[test]
+    /// Verify that connectivity is only considered degraded once connected, and nothing has been
+    /// received in response to sent traffic for the rx timeout.
+    fn test_is_degraded() {
+        let now = Instant::now();
+        let (mut checker, _cancel_token) = mock_checker(now, Box::new(MockPinger::default()));
+        assert!(!checker.is_degraded());
+
+        checker.conn_state = connected_state(now);
+        assert!(!checker.is_degraded());
+
+        let start = now
+            .checked_sub(checker.options.rx_timeout + Duration::from_secs(1))
+            .unwrap();
+        checker.conn_state = connected_state(start);
+        assert!(checker.is_degraded());
+    }
+
+    #[tokio::test]
+    /// Verify that a custom ping timeout is honored.
+    async fn test_custom_ping_timeout() {
+        let custom = ConnectivityCheckOptions {
+            ping_timeout: Duration::from_secs(3),
+            ping_interval: Duration::from_secs(1),
+            ..options()
+        };
+        let tunnel = MockTunnel::never_incrementing().boxed();
+        let now = Instant::now();
+        let start = now
+            .checked_sub(custom.rx_timeout + Duration::from_secs(5))
+            .unwrap();
+        let ping_sent = now.checked_sub(Duration::from_secs(4)).unwrap();
+
+        for (options, timed_out) in [(custom, true), (options(), false)] {
+            let (mut checker, _cancel_token) =
+                mock_checker_with_options(options, start, Box::new(MockPinger::default()));
+            checker.conn_state = connected_state(start);
+            Check::maybe_send_ping(
+                &checker.options,
+                &mut checker.conn_state,
+                &mut checker.ping_state,
+                ping_sent,
+            )
+            .await
+            .unwrap();
+
+            let connected = checker
+                .check_connectivity(now, tunnel.as_ref())
+                .await
+                .unwrap();
+            assert_eq!(connected, !timed_out);
+        }
+    }
+
     #[tokio::test]
     /// Verify that `check_connectivity()` returns `true` if the tunnel is connected and traffic is
     /// flowing constantly.
@@ -643,6 +768,28 @@ mod test {
             .unwrap())
     }
 
+    #[tokio::test]
+    /// Verify that the time between a ping and the next incoming traffic is recorded as the RTT.
+    async fn test_ping_rtt_recorded() {
+        let tunnel = MockTunnel::always_incrementing().boxed();
+        let pinger = MockPinger::default();
+        let now = Instant::now();
+        let start = now.checked_sub(Duration::from_secs(1)).unwrap();
+        let ping_sent = now.checked_sub(Duration::from_millis(300)).unwrap();
+        let (mut checker, _cancel_token) = mock_checker(start, Box::new(pinger));
+        let rtt = checker.ping_rtt();
+
+        checker.conn_state = connected_state(start);
+        checker.ping_state.last_ping_timestamp = Some(ping_sent);
+
+        assert!(checker
+            .check_connectivity(now, tunnel.as_ref())
+            .await
+            .unwrap());
+        assert_eq!(*rtt.lock().unwrap(), Some(Duration::from_millis(300)));
+        assert!(checker.ping_state.last_ping_timestamp.is_none());
+    }
+
     #[tokio::test(start_paused = true)]
     /// Verify that the timeout for setting up a tunnel works as expected.
     async fn test_establish_timeout() {
@@ -665,6 +812,7 @@ mod test {
                     Stats {
                         tx_bytes: 0,
                         rx_bytes: 0,
+                        last_handshake: None,
                     },
                 );
                 MockTunnel::new(move || Ok(tunnel_stats.clone())).boxed()
@@ -8,6 +8,7 @@ use super::Check;
 
 use crate::{Config, Tunnel, TunnelError};
 use pinger::Pinger;
+use talpid_types::net::wireguard::ConnectivityCheckOptions;
 
 // Convenient re-exports
 pub use crate::stats::{Stats, StatsMap};
@@ -19,12 +20,21 @@ pub(crate) struct MockPinger {
 
 pub(crate) struct MockTunnel {
     on_get_stats: Box<dyn Fn() -> Result<StatsMap, TunnelError> + Send + Sync>,
+    on_set_config: Option<Box<dyn FnMut(Config) + Send + Sync>>,
 }
 
 pub fn mock_checker(now: Instant, pinger: Box<dyn Pinger>) -> (Check, CancelToken) {
+    mock_checker_with_options(ConnectivityCheckOptions::default(), now, pinger)
+}
+
+pub fn mock_checker_with_options(
+    options: ConnectivityCheckOptions,
+    now: Instant,
+    pinger: Box<dyn Pinger>,
+) -> (Check, CancelToken) {
     let conn_state = ConnState::new(now, Default::default());
     let ping_state = PingState::new_with(pinger);
-    Check::mock(conn_state, ping_state)
+    Check::mock(options, conn_state, ping_state)
 }
 
 pub fn connected_state(timestamp: Instant) -> ConnState {
@@ -35,6 +45,7 @@ pub fn connected_state(timestamp: Instant) -> ConnState {
         Stats {
             tx_bytes: 0,
             rx_bytes: 0,
+            last_handshake: None,
         },
     );
     ConnState::Connected {
@@ -50,9 +61,16 @@ impl MockTunnel {
     pub fn new<F: Fn() -> Result<StatsMap, TunnelError> + Send + Sync + 'static>(f: F) -> Self {
         Self {
             on_get_stats: Box::new(f),
+            on_set_config: None,
         }
     }
 
+    /// Call `f` with every config that is applied to the tunnel.
+    pub fn on_set_config<F: FnMut(Config) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.on_set_config = Some(Box::new(f));
+        self
+    }
+
     /// Convert self to the more general [TunnelType].
     pub fn boxed(self) -> Box<dyn Tunnel> {
         Box::new(self)
@@ -65,6 +83,7 @@ impl MockTunnel {
             Stats {
                 tx_bytes: 0,
                 rx_bytes: 0,
+                last_handshake: None,
             },
         );
         let peers = std::sync::Mutex::new(map);
@@ -77,6 +96,7 @@ impl MockTunnel {
                 }
                 Ok(peers.clone())
             }),
+            on_set_config: None,
         }
     }
 
@@ -89,10 +109,12 @@ impl MockTunnel {
                     Stats {
                         tx_bytes: 0,
                         rx_bytes: 0,
+                        last_handshake: None,
                     },
                 );
                 Ok(map)
             }),
+            on_set_config: None,
         }
     }
 }
@@ -113,8 +135,11 @@ impl Tunnel for MockTunnel {
 
     fn set_config(
         &mut self,
-        _config: Config,
+        config: Config,
     ) -> Pin<Box<dyn Future<Output = std::result::Result<(), TunnelError>> + Send>> {
+        if let Some(callback) = self.on_set_config.as_mut() {
+            (callback)(config);
+        }
         Box::pin(async { Ok(()) })
     }
 
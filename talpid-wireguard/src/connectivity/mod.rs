@@ -8,6 +8,10 @@ mod pinger;
 
 #[cfg(all(target_os = "android", not(feature = "boringtun")))]
 pub use check::CancelReceiver;
-pub use check::{CancelToken, Check};
+pub use check::{CancelToken, Check, PingRtt};
 pub use error::Error;
+#[cfg(test)]
+pub(crate) use mock::MockTunnel;
 pub use monitor::Monitor;
+#[cfg(not(target_os = "android"))]
+pub use pinger::{new_pinger, Error as PingerError};
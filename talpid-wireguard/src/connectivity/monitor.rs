@@ -1,5 +1,7 @@
 use std::{sync::Weak, time::Duration};
 
+use talpid_tunnel::{EventHook, TunnelEvent};
+use talpid_types::tunnel::TunnelNotification;
 use tokio::{
     sync::Mutex,
     time::{Instant, MissedTickBehavior},
@@ -17,11 +19,23 @@ const SUSPEND_TIMEOUT: Duration = Duration::from_secs(6);
 
 pub struct Monitor {
     connectivity_check: Check,
+    event_hook: Option<EventHook>,
+    degraded: bool,
 }
 
 impl Monitor {
     pub fn init(connectivity_check: Check) -> Self {
-        Self { connectivity_check }
+        Self {
+            connectivity_check,
+            event_hook: None,
+            degraded: false,
+        }
+    }
+
+    /// Report when connectivity degrades or recovers to `event_hook`.
+    pub fn with_event_hook(mut self, event_hook: EventHook) -> Self {
+        self.event_hook = Some(event_hook);
+        self
     }
 
     pub async fn run(
@@ -47,11 +61,33 @@ impl Monitor {
             } else if !self.tunnel_exists_and_is_connected(&tunnel_handle).await? {
                 return Ok(());
             }
+            self.report_degradation().await;
 
             interval.tick().await;
         }
     }
 
+    async fn report_degradation(&mut self) {
+        let degraded = self.connectivity_check.is_degraded();
+        if degraded == self.degraded {
+            return;
+        }
+        self.degraded = degraded;
+
+        let notification = if degraded {
+            log::warn!("No traffic received from the relay in response to sent traffic");
+            TunnelNotification::ConnectivityDegraded
+        } else {
+            log::info!("Receiving traffic from the relay again");
+            TunnelNotification::ConnectivityRestored
+        };
+        if let Some(event_hook) = &mut self.event_hook {
+            event_hook
+                .on_event(TunnelEvent::Notification(notification))
+                .await;
+        }
+    }
+
     async fn tunnel_exists_and_is_connected(
         &mut self,
         tunnel_handle: &Weak<Mutex<Option<TunnelType>>>,
@@ -86,7 +122,8 @@ mod test {
 
     use tokio::sync::{mpsc, Mutex};
 
-    use crate::connectivity::{constants::*, mock::*};
+    use crate::connectivity::mock::*;
+    use talpid_types::net::wireguard::ConnectivityCheckOptions;
 
     #[tokio::test(start_paused = true)]
     /// Verify that the connectivity monitor doesn't fail if the tunnel constantly sends traffic,
@@ -120,7 +157,7 @@ mod test {
 
     #[tokio::test(start_paused = true)]
     /// Verify that the connectivity monitor detects the tunnel timing out after no longer than
-    /// `BYTES_RX_TIMEOUT` and `PING_TIMEOUT` combined.
+    /// the rx timeout and ping timeout combined.
     async fn test_wait_loop_timeout() {
         let stop_bytes_rx = Arc::new(AtomicBool::new(false));
         let stop_bytes_rx_inner = stop_bytes_rx.clone();
@@ -131,6 +168,7 @@ mod test {
             Stats {
                 tx_bytes: 0,
                 rx_bytes: 0,
+                last_handshake: None,
             },
         );
         let tunnel_stats = std::sync::Mutex::new(map);
@@ -178,8 +216,9 @@ mod test {
                 .unwrap()
         );
         stop_bytes_rx.store(true, Ordering::SeqCst);
+        let options = ConnectivityCheckOptions::default();
         assert!(tokio::time::timeout(
-            BYTES_RX_TIMEOUT + PING_TIMEOUT + Duration::from_secs(2),
+            options.rx_timeout + options.ping_timeout + Duration::from_secs(2),
             result_rx.recv()
         )
         .await
@@ -1,12 +1,15 @@
 //! This module takes care of obtaining ephemeral peers, updating the WireGuard configuration and
 //! restarting obfuscation and WG tunnels when necessary.
 
-use super::{config::Config, obfuscation::ObfuscatorHandle, CloseMsg, Error, TunnelType};
+use super::{
+    config::Config, obfuscation::ObfuscatorHandle, CloseMsg, Error, TunnelError, TunnelType,
+};
 
 #[cfg(target_os = "android")]
 use std::sync::Mutex;
 use std::{
-    net::IpAddr,
+    future::{self, Future},
+    net::{IpAddr, Ipv4Addr},
     sync::{mpsc as sync_mpsc, Arc},
     time::Duration,
 };
@@ -14,14 +17,50 @@ use std::{
 use talpid_tunnel::tun_provider::TunProvider;
 
 use ipnetwork::IpNetwork;
-use talpid_tunnel_config_client::EphemeralPeer;
-use talpid_types::net::wireguard::{PrivateKey, PublicKey};
+use talpid_future::retry::{ExponentialBackoff, Jittered};
+use talpid_tunnel_config_client::{DaitaSettings, EphemeralPeer};
+use talpid_types::{
+    net::wireguard::{DaitaLevel, DaitaParameters, PrivateKey, PublicKey},
+    ErrorExt,
+};
 use tokio::sync::Mutex as AsyncMutex;
 
-const INITIAL_PSK_EXCHANGE_TIMEOUT: Duration = Duration::from_secs(8);
+pub(crate) const INITIAL_PSK_EXCHANGE_TIMEOUT: Duration = Duration::from_secs(8);
 const MAX_PSK_EXCHANGE_TIMEOUT: Duration = Duration::from_secs(48);
 const PSK_EXCHANGE_TIMEOUT_MULTIPLIER: u32 = 2;
 
+const INITIAL_REKEY_RETRY_DELAY: Duration = Duration::from_secs(15);
+const MAX_REKEY_RETRY_DELAY: Duration = Duration::from_secs(10 * 60);
+const REKEY_RETRY_DELAY_MULTIPLIER: u32 = 2;
+
+/// Errors that can occur while re-keying the ephemeral peers of a running tunnel.
+#[derive(thiserror::Error, Debug)]
+enum RekeyError {
+    /// Timed out while negotiating ephemeral peer
+    #[error("Timed out while negotiating ephemeral peer")]
+    Timeout,
+
+    /// Failed while negotiating ephemeral peer
+    #[error("Failed while negotiating ephemeral peer")]
+    Negotiation(#[source] talpid_tunnel_config_client::Error),
+
+    /// Failed to apply the new peer config to the tunnel
+    #[error("Failed to update tunnel config")]
+    SetConfig(#[source] TunnelError),
+
+    /// Failed to restart DAITA with the new peer config
+    #[error("Failed to restart DAITA")]
+    StartDaita(#[source] TunnelError),
+
+    /// The peers were changed by someone else while negotiating
+    #[error("The tunnel peers changed while negotiating ephemeral peer")]
+    PeersChanged,
+
+    /// Failed to put the old peer config back on the tunnel. The tunnel must be torn down
+    #[error("Failed to restore tunnel config after failed re-key")]
+    Restore(#[source] TunnelError),
+}
+
 #[cfg(windows)]
 pub async fn config_ephemeral_peers(
     tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
@@ -167,6 +206,8 @@ async fn config_ephemeral_peers_inner(
         let Some(daita) = daita else {
             unreachable!("missing DAITA settings");
         };
+        config.daita_parameters = Some(daita_parameters(config, &daita));
+        config.daita_settings = Some(daita.clone());
 
         // Start local DAITA machines
         let mut tunnel = tunnel.lock().await;
@@ -184,7 +225,7 @@ async fn config_ephemeral_peers_inner(
 #[cfg(target_os = "android")]
 /// Reconfigures the tunnel to use the provided config while potentially modifying the config
 /// and restarting the obfuscation provider. Returns the new config used by the new tunnel.
-async fn reconfigure_tunnel(
+pub(crate) async fn reconfigure_tunnel(
     tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
     mut config: Config,
     obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
@@ -221,7 +262,7 @@ async fn reconfigure_tunnel(
 #[cfg(not(target_os = "android"))]
 /// Reconfigures the tunnel to use the provided config while potentially modifying the config
 /// and restarting the obfuscation provider. Returns the new config used by the new tunnel.
-async fn reconfigure_tunnel(
+pub(crate) async fn reconfigure_tunnel(
     tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
     mut config: Config,
     obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
@@ -274,7 +315,7 @@ async fn request_ephemeral_peer(
             config.tunnel.private_key.public_key(),
             wg_psk_pubkey,
             enable_pq,
-            enable_daita,
+            enable_daita.then_some(config.daita_level),
         ),
     )
     .await
@@ -287,3 +328,607 @@ async fn request_ephemeral_peer(
 
     Ok(ephemeral)
 }
+
+/// Periodically negotiates new ephemeral peers over the running tunnel and swaps them in, without
+/// changing the tunnel state. `config` must hold the config the tunnel is currently using, and is
+/// updated after every re-key. Each new set of peers is negotiated on behalf of the current
+/// ephemeral key.
+///
+/// Failed attempts are retried with backoff. The tunnel keeps using the current peers until a
+/// new set of peers has been negotiated. Only returns if the current peers could not be restored
+/// after a failed attempt, in which case the tunnel must be closed.
+pub async fn rekey_ephemeral_peers(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &AsyncMutex<Config>,
+) -> CloseMsg {
+    let interval = {
+        let config = config.lock().await;
+        config.rekey_interval.filter(|_| config.quantum_resistant)
+    };
+    let Some(interval) = interval else {
+        return future::pending().await;
+    };
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let mut retry_delays = Jittered::jitter(
+            ExponentialBackoff::new(INITIAL_REKEY_RETRY_DELAY, REKEY_RETRY_DELAY_MULTIPLIER)
+                .max_delay(Some(MAX_REKEY_RETRY_DELAY)),
+        );
+        loop {
+            log::debug!("Re-keying ephemeral peer");
+            match rekey(tunnel, config).await {
+                Ok(()) => break,
+                Err(RekeyError::Restore(error)) => {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to restore tunnel after re-key")
+                    );
+                    return CloseMsg::SetupError(Error::TunnelError(error));
+                }
+                Err(error) => {
+                    let delay = retry_delays.next().unwrap_or(MAX_REKEY_RETRY_DELAY);
+                    log::warn!(
+                        "{}",
+                        error.display_chain_with_msg(&format!(
+                            "Failed to re-key ephemeral peer. Retrying in {} seconds",
+                            delay.as_secs()
+                        ))
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+        log::info!("Re-keyed ephemeral peer");
+    }
+}
+
+#[cfg(windows)]
+async fn rekey(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &AsyncMutex<Config>,
+) -> Result<(), RekeyError> {
+    let iface_name = {
+        let tunnel = tunnel.lock().await;
+        let tunnel = tunnel.as_ref().unwrap();
+        tunnel.get_interface_name()
+    };
+
+    // Lower the MTU for the duration of the exchange, for the same reason as in
+    // `config_ephemeral_peers`.
+    log::trace!("Temporarily lowering tunnel MTU before re-keying ephemeral peer");
+    try_set_ipv4_mtu(&iface_name, talpid_tunnel::MIN_IPV4_MTU);
+
+    let result = rekey_inner(
+        tunnel,
+        config,
+        talpid_tunnel_config_client::request_ephemeral_peer,
+    )
+    .await;
+
+    log::trace!("Resetting tunnel MTU");
+    let mtu = config.lock().await.mtu;
+    try_set_ipv4_mtu(&iface_name, mtu);
+
+    result
+}
+
+#[cfg(not(windows))]
+async fn rekey(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &AsyncMutex<Config>,
+) -> Result<(), RekeyError> {
+    rekey_inner(
+        tunnel,
+        config,
+        talpid_tunnel_config_client::request_ephemeral_peer,
+    )
+    .await
+}
+
+/// Negotiates new ephemeral peers using a fresh key, and applies them to the tunnel. The new key
+/// and PSKs are then stored in `config`. On failure, the tunnel is left with `config`.
+///
+/// `config` is not locked during the negotiation, so that it can be updated by others meanwhile.
+/// Only the key, PSKs and DAITA parameters are replaced once the negotiation has finished. The
+/// re-key is aborted if the peers were replaced meanwhile, since the PSKs belong to the old ones.
+///
+/// `request_peer` has the signature of [`talpid_tunnel_config_client::request_ephemeral_peer`].
+async fn rekey_inner<F, Fut>(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &AsyncMutex<Config>,
+    request_peer: F,
+) -> Result<(), RekeyError>
+where
+    F: Fn(Ipv4Addr, PublicKey, PublicKey, bool, Option<DaitaLevel>) -> Fut,
+    Fut: Future<Output = Result<EphemeralPeer, talpid_tunnel_config_client::Error>>,
+{
+    let ephemeral_private_key = PrivateKey::new_from_random();
+    let current_config = config.lock().await.clone();
+
+    let exit_should_have_daita = current_config.daita && !current_config.is_multihop();
+    let exit_ephemeral_peer = negotiate_ephemeral_peer(
+        &request_peer,
+        &current_config,
+        ephemeral_private_key.public_key(),
+        exit_should_have_daita,
+    )
+    .await?;
+    let exit_psk = exit_ephemeral_peer.psk;
+    let mut entry_psk = None;
+    let mut daita = exit_ephemeral_peer.daita;
+
+    if current_config.is_multihop() {
+        // Route the config service of the gateway through the entry peer while the exit peer
+        // remains in place, so that other traffic keeps flowing through the tunnel.
+        let mut entry_config = current_config.clone();
+        entry_config
+            .entry_peer
+            .allowed_ips
+            .push(IpNetwork::new(IpAddr::V4(current_config.ipv4_gateway), 32).unwrap());
+        if let Err(error) = set_tunnel_config(tunnel, entry_config).await {
+            restore_tunnel_config(tunnel, &*config.lock().await)
+                .await
+                .map_err(RekeyError::Restore)?;
+            return Err(RekeyError::SetConfig(error));
+        }
+
+        let entry_ephemeral_peer = negotiate_ephemeral_peer(
+            &request_peer,
+            &current_config,
+            ephemeral_private_key.public_key(),
+            current_config.daita,
+        )
+        .await;
+        let entry_ephemeral_peer = match entry_ephemeral_peer {
+            Ok(peer) => peer,
+            Err(error) => {
+                restore_tunnel_config(tunnel, &*config.lock().await)
+                    .await
+                    .map_err(RekeyError::Restore)?;
+                return Err(error);
+            }
+        };
+
+        entry_psk = entry_ephemeral_peer.psk;
+        daita = entry_ephemeral_peer.daita;
+    }
+
+    let mut config = config.lock().await;
+    if !same_peers(&config, &current_config) {
+        if current_config.is_multihop() {
+            restore_tunnel_config(tunnel, &config)
+                .await
+                .map_err(RekeyError::Restore)?;
+        }
+        return Err(RekeyError::PeersChanged);
+    }
+
+    let mut new_config = config.clone();
+    new_config.tunnel.private_key = ephemeral_private_key;
+    new_config.exit_peer_mut().psk = exit_psk;
+    if new_config.is_multihop() {
+        new_config.entry_peer.psk = entry_psk;
+    }
+    new_config.daita_parameters = daita.as_ref().map(|daita| daita_parameters(&config, daita));
+    new_config.daita_settings = daita;
+    if let Err(error) = set_tunnel_config(tunnel, new_config.clone()).await {
+        restore_tunnel_config(tunnel, &config)
+            .await
+            .map_err(RekeyError::Restore)?;
+        return Err(RekeyError::SetConfig(error));
+    }
+
+    // Replacing the peers stops DAITA, so it has to be started again.
+    if let Err(error) = start_daita(tunnel, &new_config).await {
+        restore_tunnel_config(tunnel, &config)
+            .await
+            .map_err(RekeyError::Restore)?;
+        return Err(RekeyError::StartDaita(error));
+    }
+
+    *config = new_config;
+    Ok(())
+}
+
+/// Returns whether `a` and `b` use the same key and the same peers, in the same hops.
+pub(crate) fn same_peers(a: &Config, b: &Config) -> bool {
+    a.tunnel.private_key.public_key() == b.tunnel.private_key.public_key()
+        && a.is_multihop() == b.is_multihop()
+        && a.peers()
+            .map(|peer| &peer.public_key)
+            .eq(b.peers().map(|peer| &peer.public_key))
+}
+
+/// Negotiates an ephemeral peer on behalf of the key that `config` is currently using.
+async fn negotiate_ephemeral_peer<F, Fut>(
+    request_peer: &F,
+    config: &Config,
+    ephemeral_pubkey: PublicKey,
+    enable_daita: bool,
+) -> Result<EphemeralPeer, RekeyError>
+where
+    F: Fn(Ipv4Addr, PublicKey, PublicKey, bool, Option<DaitaLevel>) -> Fut,
+    Fut: Future<Output = Result<EphemeralPeer, talpid_tunnel_config_client::Error>>,
+{
+    tokio::time::timeout(
+        MAX_PSK_EXCHANGE_TIMEOUT,
+        request_peer(
+            config.ipv4_gateway,
+            config.tunnel.private_key.public_key(),
+            ephemeral_pubkey,
+            config.quantum_resistant,
+            enable_daita.then_some(config.daita_level),
+        ),
+    )
+    .await
+    .map_err(|_timeout_err| RekeyError::Timeout)?
+    .map_err(RekeyError::Negotiation)
+}
+
+/// Returns the DAITA parameters that were negotiated with the relay, for reporting to the user.
+pub(crate) fn daita_parameters(config: &Config, daita: &DaitaSettings) -> DaitaParameters {
+    DaitaParameters {
+        level: config.daita_level,
+        max_padding_frac: daita.max_padding_frac,
+        max_blocking_frac: daita.max_blocking_frac,
+    }
+}
+
+async fn set_tunnel_config(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: Config,
+) -> Result<(), TunnelError> {
+    let mut tunnel = tunnel.lock().await;
+    if let Some(tunnel) = tunnel.as_mut() {
+        tunnel.set_config(config).await?;
+    }
+    Ok(())
+}
+
+/// Starts DAITA on the tunnel with the machines in `config`, if DAITA is enabled.
+pub(crate) async fn start_daita(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &Config,
+) -> Result<(), TunnelError> {
+    #[cfg(daita)]
+    if config.daita {
+        let Some(daita) = config.daita_settings.clone() else {
+            unreachable!("missing DAITA settings");
+        };
+        let mut tunnel = tunnel.lock().await;
+        if let Some(tunnel) = tunnel.as_mut() {
+            tunnel.start_daita(daita)?;
+        }
+    }
+    Ok(())
+}
+
+/// Puts `config` back on the tunnel after failing to replace its peers, and restarts DAITA since
+/// replacing the peers stops it. If this fails, the tunnel is not in a known state and must be
+/// torn down.
+pub(crate) async fn restore_tunnel_config(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &Config,
+) -> Result<(), TunnelError> {
+    set_tunnel_config(tunnel, config.clone()).await?;
+    start_daita(tunnel, config).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{stats::StatsMap, Tunnel};
+    use std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicU8, Ordering},
+            Mutex,
+        },
+    };
+    use talpid_types::net::wireguard::{PeerConfig, PresharedKey, TunnelConfig};
+
+    /// What has been done to a [`RecordingTunnel`].
+    #[derive(Default)]
+    struct TunnelState {
+        /// Every config applied to the tunnel.
+        configs: Vec<Config>,
+        /// DAITA settings that the tunnel is running with. Replacing the peers stops DAITA.
+        daita: Option<DaitaSettings>,
+        /// Number of upcoming attempts to start DAITA that fail.
+        daita_failures: usize,
+    }
+
+    /// Tunnel that records every config applied to it.
+    struct RecordingTunnel {
+        state: Arc<Mutex<TunnelState>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Tunnel for RecordingTunnel {
+        fn get_interface_name(&self) -> String {
+            "recording-tunnel".to_string()
+        }
+
+        fn stop(self: Box<Self>) -> Result<(), TunnelError> {
+            Ok(())
+        }
+
+        async fn get_tunnel_stats(&self) -> Result<StatsMap, TunnelError> {
+            Ok(StatsMap::new())
+        }
+
+        fn set_config(
+            &mut self,
+            config: Config,
+        ) -> Pin<Box<dyn Future<Output = Result<(), TunnelError>> + Send>> {
+            let mut state = self.state.lock().unwrap();
+            state.configs.push(config);
+            state.daita = None;
+            Box::pin(async { Ok(()) })
+        }
+
+        #[cfg(daita)]
+        fn start_daita(&mut self, settings: DaitaSettings) -> Result<(), TunnelError> {
+            let mut state = self.state.lock().unwrap();
+            if state.daita_failures > 0 {
+                state.daita_failures -= 1;
+                return Err(TunnelError::SetConfigError);
+            }
+            state.daita = Some(settings);
+            Ok(())
+        }
+    }
+
+    /// Fake config service which hands out a new PSK on every request, and records the parent
+    /// and ephemeral keys of every request.
+    #[derive(Default)]
+    struct FakeConfigService {
+        requests: Mutex<Vec<(PublicKey, PublicKey)>>,
+        next_psk: AtomicU8,
+    }
+
+    impl FakeConfigService {
+        fn request_peer(
+            &self,
+            parent_pubkey: PublicKey,
+            ephemeral_pubkey: PublicKey,
+            daita_level: Option<DaitaLevel>,
+        ) -> future::Ready<Result<EphemeralPeer, talpid_tunnel_config_client::Error>> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((parent_pubkey, ephemeral_pubkey));
+            let psk = self.next_psk.fetch_add(1, Ordering::Relaxed);
+            future::ready(Ok(EphemeralPeer {
+                psk: Some(PresharedKey::from([psk; 32])),
+                daita: daita_level.map(|_| DaitaSettings {
+                    client_machines: vec![format!("machine {psk}")],
+                    ..Default::default()
+                }),
+            }))
+        }
+    }
+
+    type SharedTunnel = Arc<AsyncMutex<Option<TunnelType>>>;
+
+    fn tunnel(daita_failures: usize) -> (SharedTunnel, Arc<Mutex<TunnelState>>) {
+        let state = Arc::new(Mutex::new(TunnelState {
+            daita_failures,
+            ..Default::default()
+        }));
+        let tunnel = RecordingTunnel {
+            state: state.clone(),
+        };
+        (
+            Arc::new(AsyncMutex::new(Some(Box::new(tunnel) as TunnelType))),
+            state,
+        )
+    }
+
+    fn peer() -> PeerConfig {
+        PeerConfig {
+            public_key: PrivateKey::new_from_random().public_key(),
+            allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
+            endpoint: "1.2.3.4:51820".parse().unwrap(),
+            psk: None,
+            persistent_keepalive: None,
+            constant_packet_size: false,
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            tunnel: TunnelConfig {
+                private_key: PrivateKey::new_from_random(),
+                addresses: vec![],
+            },
+            entry_peer: peer(),
+            exit_peer: None,
+            ipv4_gateway: "10.64.0.1".parse().unwrap(),
+            ipv6_gateway: None,
+            mtu: 1380,
+            #[cfg(target_os = "linux")]
+            fwmark: None,
+            #[cfg(target_os = "linux")]
+            enable_ipv6: false,
+            obfuscator_config: None,
+            quantum_resistant: true,
+            daita: false,
+            daita_level: Default::default(),
+            daita_parameters: None,
+            daita_settings: None,
+            rekey_interval: Some(Duration::from_secs(60)),
+            connectivity: Default::default(),
+            backend: Default::default(),
+        }
+    }
+
+    /// Config with DAITA running on the tunnel.
+    fn daita_config() -> Config {
+        let mut config = config();
+        config.daita = true;
+        config.entry_peer.psk = Some(PresharedKey::from([0xff; 32]));
+        config.daita_settings = Some(DaitaSettings {
+            client_machines: vec!["original machine".to_owned()],
+            ..Default::default()
+        });
+        config
+    }
+
+    /// Each re-key must be negotiated on behalf of the key that the tunnel is currently using,
+    /// and must replace both the key and the PSK.
+    #[tokio::test]
+    async fn test_rekey_negotiates_from_current_key() {
+        let (tunnel, state) = tunnel(0);
+        let service = FakeConfigService::default();
+        let request_peer =
+            |_, parent, ephemeral, _, daita| service.request_peer(parent, ephemeral, daita);
+
+        let original = config();
+        let current = AsyncMutex::new(original.clone());
+        rekey_inner(&tunnel, &current, &request_peer).await.unwrap();
+        let first = current.lock().await.clone();
+        rekey_inner(&tunnel, &current, &request_peer).await.unwrap();
+        let second = current.lock().await.clone();
+
+        let requests = service.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].0, original.tunnel.private_key.public_key());
+        assert_eq!(requests[0].1, first.tunnel.private_key.public_key());
+        assert_eq!(requests[1].0, first.tunnel.private_key.public_key());
+        assert_eq!(requests[1].1, second.tunnel.private_key.public_key());
+
+        assert!(first.entry_peer.psk.is_some());
+        assert!(second.entry_peer.psk.is_some());
+        assert_ne!(original.entry_peer.psk, first.entry_peer.psk);
+        assert_ne!(first.entry_peer.psk, second.entry_peer.psk);
+
+        let state = state.lock().unwrap();
+        let applied = state.configs.last().unwrap();
+        assert_eq!(applied.entry_peer.psk, second.entry_peer.psk);
+        assert_eq!(
+            applied.tunnel.private_key.public_key(),
+            second.tunnel.private_key.public_key()
+        );
+    }
+
+    /// DAITA must be restarted with the newly negotiated machines after a re-key.
+    #[cfg(daita)]
+    #[tokio::test]
+    async fn test_rekey_restarts_daita() {
+        let (tunnel, state) = tunnel(0);
+        let service = FakeConfigService::default();
+        let request_peer =
+            |_, parent, ephemeral, _, daita| service.request_peer(parent, ephemeral, daita);
+
+        let current = AsyncMutex::new(daita_config());
+        rekey_inner(&tunnel, &current, &request_peer).await.unwrap();
+
+        let rekeyed = current.lock().await.clone();
+        assert!(rekeyed.daita_settings.is_some());
+        assert_eq!(state.lock().unwrap().daita, rekeyed.daita_settings);
+    }
+
+    /// If DAITA cannot be started with the new peers, the tunnel must be put back on the old
+    /// config, with DAITA running again.
+    #[cfg(daita)]
+    #[tokio::test]
+    async fn test_rekey_restores_config_on_daita_failure() {
+        let (tunnel, state) = tunnel(1);
+        let service = FakeConfigService::default();
+        let request_peer =
+            |_, parent, ephemeral, _, daita| service.request_peer(parent, ephemeral, daita);
+
+        let original = daita_config();
+        let current = AsyncMutex::new(original.clone());
+        let result = rekey_inner(&tunnel, &current, &request_peer).await;
+        assert!(matches!(result, Err(RekeyError::StartDaita(_))));
+        assert_eq!(current.lock().await.entry_peer.psk, original.entry_peer.psk);
+
+        let state = state.lock().unwrap();
+        let applied = state.configs.last().unwrap();
+        assert_eq!(applied.entry_peer.psk, original.entry_peer.psk);
+        assert_eq!(
+            applied.tunnel.private_key.public_key(),
+            original.tunnel.private_key.public_key()
+        );
+        assert_eq!(state.daita, original.daita_settings);
+    }
+
+    /// If DAITA cannot be restarted on the old config either, the re-key must fail with an error
+    /// that closes the tunnel.
+    #[cfg(daita)]
+    #[tokio::test]
+    async fn test_rekey_fails_if_daita_cannot_be_restored() {
+        let (tunnel, state) = tunnel(2);
+        let service = FakeConfigService::default();
+        let request_peer =
+            |_, parent, ephemeral, _, daita| service.request_peer(parent, ephemeral, daita);
+
+        let current = AsyncMutex::new(daita_config());
+        let result = rekey_inner(&tunnel, &current, &request_peer).await;
+        assert!(matches!(result, Err(RekeyError::Restore(_))));
+        assert_eq!(state.lock().unwrap().daita, None);
+    }
+
+    /// The config must not be locked while negotiating, and changes made to it meanwhile must be
+    /// kept.
+    #[tokio::test]
+    async fn test_rekey_keeps_concurrent_config_changes() {
+        let (tunnel, state) = tunnel(0);
+        let service = FakeConfigService::default();
+
+        let original = config();
+        let current = AsyncMutex::new(original.clone());
+        let new_endpoint: std::net::SocketAddr = "5.6.7.8:51820".parse().unwrap();
+        let request_peer = |_, parent, ephemeral, _, daita| {
+            current
+                .try_lock()
+                .expect("config must not be locked while negotiating")
+                .entry_peer
+                .endpoint = new_endpoint;
+            service.request_peer(parent, ephemeral, daita)
+        };
+        rekey_inner(&tunnel, &current, &request_peer).await.unwrap();
+
+        let rekeyed = current.lock().await.clone();
+        assert_eq!(rekeyed.entry_peer.endpoint, new_endpoint);
+        assert_ne!(rekeyed.entry_peer.psk, original.entry_peer.psk);
+        assert_ne!(
+            rekeyed.tunnel.private_key.public_key(),
+            original.tunnel.private_key.public_key()
+        );
+
+        let state = state.lock().unwrap();
+        assert_eq!(
+            state.configs.last().unwrap().entry_peer.endpoint,
+            new_endpoint
+        );
+    }
+
+    /// The PSKs must not be applied if the peers they were negotiated for have been replaced
+    /// meanwhile.
+    #[tokio::test]
+    async fn test_rekey_aborts_if_peers_change() {
+        let service = FakeConfigService::default();
+        let changes: [fn(&mut Config); 2] = [
+            |config| config.entry_peer.public_key = peer().public_key,
+            |config| config.exit_peer = Some(peer()),
+        ];
+
+        for change in changes {
+            let (tunnel, state) = tunnel(0);
+            let current = AsyncMutex::new(config());
+            let request_peer = |_, parent, ephemeral, _, daita| {
+                change(&mut current.try_lock().unwrap());
+                service.request_peer(parent, ephemeral, daita)
+            };
+            let result = rekey_inner(&tunnel, &current, &request_peer).await;
+            assert!(matches!(result, Err(RekeyError::PeersChanged)));
+            assert!(current.lock().await.peers().all(|peer| peer.psk.is_none()));
+            assert!(state.lock().unwrap().configs.is_empty());
+        }
+    }
+}
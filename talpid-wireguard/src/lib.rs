@@ -14,6 +14,7 @@ use std::{
     path::Path,
     pin::Pin,
     sync::{mpsc as sync_mpsc, Arc},
+    time::SystemTime,
 };
 #[cfg(not(target_os = "android"))]
 use std::{env, sync::LazyLock};
@@ -24,11 +25,16 @@ use talpid_tunnel::{tun_provider, EventHook, TunnelArgs, TunnelEvent, TunnelMeta
 #[cfg(daita)]
 use talpid_tunnel_config_client::DaitaSettings;
 use talpid_types::{
-    net::{wireguard::TunnelParameters, AllowedTunnelTraffic, Endpoint, TransportProtocol},
+    net::{
+        stats::{PeerStats, TunnelStats},
+        wireguard::{TunnelParameters, WireguardBackend},
+        AllowedTunnelTraffic, Endpoint, TransportProtocol,
+    },
     BoxedError, ErrorExt,
 };
 use tokio::sync::Mutex as AsyncMutex;
 
+mod backend;
 #[cfg(feature = "boringtun")]
 mod boringtun;
 
@@ -41,6 +47,10 @@ mod connectivity;
 mod ephemeral;
 mod logging;
 mod obfuscation;
+#[cfg(not(target_os = "android"))]
+mod relay_switch;
+#[cfg(target_os = "macos")]
+mod roaming;
 mod stats;
 #[cfg(target_os = "linux")]
 pub(crate) mod wireguard_kernel;
@@ -50,6 +60,9 @@ mod wireguard_nt;
 #[cfg(not(target_os = "android"))]
 mod mtu_detection;
 
+#[cfg(not(target_os = "android"))]
+pub use relay_switch::{Error as RelaySwitchError, RelaySwitchHandle};
+
 type TunnelType = Box<dyn Tunnel>;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -138,6 +151,9 @@ pub struct WireguardMonitor {
     close_msg_receiver: sync_mpsc::Receiver<CloseMsg>,
     pinger_stop_sender: connectivity::CancelToken,
     obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
+    ping_rtt: connectivity::PingRtt,
+    #[cfg(not(target_os = "android"))]
+    relay_switch: RelaySwitchHandle,
 }
 
 #[cfg(not(target_os = "android"))]
@@ -183,28 +199,54 @@ impl WireguardMonitor {
             config.mtu = clamp_mtu(params, config.mtu);
         }
 
-        // NOTE: We force userspace WireGuard while boringtun is enabled to more easily test
-        // the implementation, as DAITA is not currently supported by boringtun.
+        // The kernel implementations do not support DAITA.
+        let kernel_available =
+            cfg!(any(target_os = "linux", target_os = "windows")) && !config.daita;
+        // NOTE: We prefer userspace WireGuard while boringtun is enabled to more easily test
+        // the implementation.
         // TODO: Remove `cfg!(feature = "boringtun")`.
-        let userspace_wireguard =
-            *FORCE_USERSPACE_WIREGUARD || config.daita || cfg!(feature = "boringtun");
+        let prefer_userspace = *FORCE_USERSPACE_WIREGUARD || cfg!(feature = "boringtun");
+        let mut backends =
+            backend::candidates(config.backend, kernel_available, prefer_userspace).into_iter();
 
         #[cfg(target_os = "windows")]
         let (setup_done_tx, setup_done_rx) = mpsc::channel(0);
-        let tunnel = Self::open_tunnel(
-            args.runtime.clone(),
-            &config,
-            #[cfg(target_os = "windows")]
-            args.resource_dir,
-            #[cfg(not(all(target_os = "windows", not(feature = "boringtun"))))]
-            args.tun_provider.clone(),
-            #[cfg(all(windows, not(feature = "boringtun")))]
-            args.route_manager.clone(),
-            #[cfg(target_os = "windows")]
-            setup_done_tx,
-            userspace_wireguard,
-            _log_path,
-        )?;
+        let (tunnel, backend) = loop {
+            let backend = backends
+                .next()
+                .expect("there is always at least one backend to try");
+            let result = Self::open_tunnel(
+                args.runtime.clone(),
+                &config,
+                #[cfg(target_os = "windows")]
+                args.resource_dir,
+                #[cfg(not(all(target_os = "windows", not(feature = "boringtun"))))]
+                args.tun_provider.clone(),
+                #[cfg(all(windows, not(feature = "boringtun")))]
+                args.route_manager.clone(),
+                #[cfg(target_os = "windows")]
+                setup_done_tx.clone(),
+                backend,
+                _log_path,
+            );
+            match result {
+                Ok(tunnel) => break (tunnel, backend),
+                Err(error) if !backends.as_slice().is_empty() => {
+                    log::warn!(
+                        "{}",
+                        error.display_chain_with_msg(&format!(
+                            "Failed to create tunnel device using {backend}, trying the next \
+                             WireGuard backend"
+                        ))
+                    );
+                }
+                Err(error) => return Err(error),
+            }
+        };
+        // Only the tunnel may hold on to the sender, so that setup fails if the tunnel goes away.
+        #[cfg(target_os = "windows")]
+        drop(setup_done_tx);
+        log::info!("Using WireGuard backend: {backend}");
         let iface_name = tunnel.get_interface_name();
 
         let obfuscator = Arc::new(AsyncMutex::new(obfuscator));
@@ -215,11 +257,14 @@ impl WireguardMonitor {
             gateway,
             #[cfg(any(target_os = "macos", target_os = "linux"))]
             iface_name.clone(),
+            config.connectivity,
             args.retry_attempt,
             cancel_receiver,
         )
         .map_err(Error::ConnectivityMonitorError)?;
 
+        let (relay_switch, relay_switch_requests) = RelaySwitchHandle::new();
+
         let monitor = WireguardMonitor {
             runtime: args.runtime.clone(),
             tunnel: Arc::new(AsyncMutex::new(Some(tunnel))),
@@ -227,19 +272,20 @@ impl WireguardMonitor {
             close_msg_receiver: close_obfs_listener,
             pinger_stop_sender: cancel_token,
             obfuscator,
+            ping_rtt: connectivity_monitor.ping_rtt(),
+            relay_switch,
         };
 
         let mut event_hook = args.event_hook.clone();
         let moved_tunnel = monitor.tunnel.clone();
         let moved_close_obfs_sender = close_obfs_sender.clone();
         let moved_obfuscator = monitor.obfuscator.clone();
-        let detect_mtu = params.options.mtu.is_none();
         let tunnel_fut = async move {
             let tunnel = moved_tunnel;
             let close_obfs_sender: sync_mpsc::Sender<CloseMsg> = moved_close_obfs_sender;
             let obfuscator = moved_obfuscator;
             #[cfg(windows)]
-            if cfg!(feature = "boringtun") && userspace_wireguard {
+            if backend == WireguardBackend::Boringtun {
                 // NOTE: For boringtun, we use the `tun` crate to create our tunnel interface.
                 // It will automatically configure the IP address and DNS servers using `netsh`.
                 // This is quite slow, so we need to wait for the interface to be created.
@@ -249,7 +295,7 @@ impl WireguardMonitor {
                     .await?;
             }
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
+            let metadata = Self::tunnel_metadata(&iface_name, &config, backend);
             let allowed_traffic = Self::allowed_traffic_during_tunnel_config(&config);
             event_hook
                 .on_event(TunnelEvent::InterfaceUp(metadata.clone(), allowed_traffic))
@@ -292,7 +338,7 @@ impl WireguardMonitor {
                     return Err(e);
                 }
 
-                let metadata = Self::tunnel_metadata(&iface_name, &config);
+                let metadata = Self::tunnel_metadata(&iface_name, &config, backend);
                 event_hook
                     .on_event(TunnelEvent::InterfaceUp(
                         metadata,
@@ -301,36 +347,6 @@ impl WireguardMonitor {
                     .await;
             }
 
-            if detect_mtu {
-                let config = config.clone();
-                let iface_name = iface_name.clone();
-                tokio::task::spawn(async move {
-                    if config.daita {
-                        // TODO: For now, we assume the MTU during the tunnel lifetime.
-                        // We could instead poke maybenot whenever we detect changes to it.
-                        log::warn!("MTU detection is not supported with DAITA. Skipping");
-                        return;
-                    }
-
-                    if let Err(e) = mtu_detection::automatic_mtu_correction(
-                        gateway,
-                        iface_name,
-                        config.mtu,
-                        #[cfg(windows)]
-                        config.ipv6_gateway.is_some(),
-                    )
-                    .await
-                    {
-                        log::error!(
-                            "{}",
-                            e.display_chain_with_msg(
-                                "Failed to automatically adjust MTU based on dropped packets"
-                            )
-                        );
-                    };
-                });
-            }
-
             let lock = tunnel.lock().await;
             let borrowed_tun = lock.as_ref().expect("The tunnel was dropped unexpectedly");
             match connectivity_monitor
@@ -359,17 +375,85 @@ impl WireguardMonitor {
                 .map_err(Error::SetupRoutingError)
                 .map_err(CloseMsg::SetupError)?;
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
+            let metadata = Self::tunnel_metadata(&iface_name, &config, backend);
             event_hook.on_event(TunnelEvent::Up(metadata)).await;
 
-            if let Err(error) = connectivity::Monitor::init(connectivity_monitor)
-                .run(Arc::downgrade(&tunnel))
-                .await
-            {
-                log::error!(
-                    "{}",
-                    error.display_chain_with_msg("Connectivity monitor failed")
-                );
+            let connectivity_monitor = connectivity::Monitor::init(connectivity_monitor)
+                .with_event_hook(event_hook.clone())
+                .run(Arc::downgrade(&tunnel));
+            // The configured MTU is used as the upper bound. It is either set by the user or
+            // already reduced by the obfuscation overhead.
+            let mtu_monitor = {
+                let iface_name = iface_name.clone();
+                let max_tunnel_mtu = config.mtu;
+                let daita = config.daita;
+                #[cfg(windows)]
+                let ipv6 = config.ipv6_gateway.is_some();
+                let event_hook = event_hook.clone();
+                async move {
+                    if daita {
+                        // TODO: For now, we assume the MTU during the tunnel lifetime.
+                        // We could instead poke maybenot whenever we detect changes to it.
+                        log::warn!("MTU detection is not supported with DAITA. Skipping");
+                        return futures::future::pending().await;
+                    }
+                    mtu_detection::monitor_mtu(
+                        gateway,
+                        iface_name,
+                        max_tunnel_mtu,
+                        #[cfg(windows)]
+                        ipv6,
+                        event_hook,
+                    )
+                    .await
+                }
+            };
+            let config = AsyncMutex::new(config);
+            let rekey = ephemeral::rekey_ephemeral_peers(&tunnel, &config);
+            #[cfg(target_os = "macos")]
+            let roaming = async {
+                match args.route_manager.default_route_listener().await {
+                    Ok(route_events) => {
+                        roaming::follow_default_route(
+                            route_events,
+                            &tunnel,
+                            &config,
+                            obfuscator.clone(),
+                            close_obfs_sender.clone(),
+                        )
+                        .await
+                    }
+                    Err(error) => {
+                        log::warn!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to listen for route changes")
+                        );
+                        futures::future::pending().await
+                    }
+                }
+            };
+            #[cfg(not(target_os = "macos"))]
+            let roaming = futures::future::pending::<CloseMsg>();
+            let relay_switch = relay_switch::serve_requests(
+                relay_switch_requests,
+                &tunnel,
+                &config,
+                &args.route_manager,
+                &iface_name,
+            );
+            tokio::select! {
+                result = connectivity_monitor => {
+                    if let Err(error) = result {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Connectivity monitor failed")
+                        );
+                    }
+                }
+                close_msg = rekey => return Err(close_msg),
+                never = mtu_monitor => match never {},
+                close_msg = roaming => return Err(close_msg),
+                never = relay_switch => match never {},
             }
 
             Err::<Infallible, CloseMsg>(CloseMsg::PingErr)
@@ -432,6 +516,7 @@ impl WireguardMonitor {
         #[allow(unused_mut)]
         let mut connectivity_monitor = connectivity::Check::new(
             config.ipv4_gateway,
+            config.connectivity,
             args.retry_attempt,
             cancel_receiver.clone(),
         )
@@ -473,6 +558,7 @@ impl WireguardMonitor {
             close_msg_receiver: close_obfs_listener,
             pinger_stop_sender: cancel_token,
             obfuscator: Arc::new(AsyncMutex::new(obfuscator)),
+            ping_rtt: connectivity_monitor.ping_rtt(),
         };
 
         let moved_close_obfs_sender = close_obfs_sender.clone();
@@ -481,7 +567,7 @@ impl WireguardMonitor {
             let close_obfs_sender: sync_mpsc::Sender<CloseMsg> = moved_close_obfs_sender;
             let obfuscator = moved_obfuscator;
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
+            let metadata = Self::tunnel_metadata(&iface_name, &config, backend::USERSPACE);
             let allowed_traffic = Self::allowed_traffic_during_tunnel_config(&config);
             event_hook
                 .on_event(TunnelEvent::InterfaceUp(metadata.clone(), allowed_traffic))
@@ -531,7 +617,7 @@ impl WireguardMonitor {
                     return Err(e);
                 }
 
-                let metadata = Self::tunnel_metadata(&iface_name, &config);
+                let metadata = Self::tunnel_metadata(&iface_name, &config, backend::USERSPACE);
                 event_hook
                     .on_event(TunnelEvent::InterfaceUp(
                         metadata,
@@ -540,17 +626,24 @@ impl WireguardMonitor {
                     .await;
             }
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
+            let metadata = Self::tunnel_metadata(&iface_name, &config, backend::USERSPACE);
             event_hook.on_event(TunnelEvent::Up(metadata)).await;
 
-            if let Err(error) = connectivity::Monitor::init(connectivity_monitor)
-                .run(Arc::downgrade(&tunnel))
-                .await
-            {
-                log::error!(
-                    "{}",
-                    error.display_chain_with_msg("Connectivity monitor failed")
-                );
+            let connectivity_monitor = connectivity::Monitor::init(connectivity_monitor)
+                .with_event_hook(event_hook.clone())
+                .run(Arc::downgrade(&tunnel));
+            let config = AsyncMutex::new(config);
+            let rekey = ephemeral::rekey_ephemeral_peers(&tunnel, &config);
+            tokio::select! {
+                result = connectivity_monitor => {
+                    if let Err(error) = result {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Connectivity monitor failed")
+                        );
+                    }
+                }
+                close_msg = rekey => return Err(close_msg),
             }
 
             Err::<Infallible, CloseMsg>(CloseMsg::PingErr)
@@ -675,12 +768,12 @@ impl WireguardMonitor {
         >,
         #[cfg(not(feature = "boringtun"))] route_manager: talpid_routing::RouteManagerHandle,
         setup_done_tx: mpsc::Sender<std::result::Result<(), BoxedError>>,
-        userspace_wireguard: bool,
+        backend: WireguardBackend,
         _log_path: Option<&Path>,
     ) -> Result<TunnelType> {
         log::debug!("Tunnel MTU: {}", config.mtu);
 
-        if userspace_wireguard {
+        if backend != WireguardBackend::Kernel {
             log::debug!("Using userspace WireGuard implementation");
 
             #[cfg(feature = "boringtun")]
@@ -712,7 +805,7 @@ impl WireguardMonitor {
         runtime: tokio::runtime::Handle,
         config: &Config,
         tun_provider: Arc<std::sync::Mutex<tun_provider::TunProvider>>,
-        _userspace_wireguard: bool,
+        _backend: WireguardBackend,
         _log_path: Option<&Path>,
     ) -> Result<TunnelType> {
         log::debug!("Tunnel MTU: {}", config.mtu);
@@ -740,12 +833,25 @@ impl WireguardMonitor {
         runtime: tokio::runtime::Handle,
         config: &Config,
         tun_provider: Arc<std::sync::Mutex<tun_provider::TunProvider>>,
-        userspace_wireguard: bool,
+        backend: WireguardBackend,
         _log_path: Option<&Path>,
     ) -> Result<TunnelType> {
         log::debug!("Tunnel MTU: {}", config.mtu);
 
-        if userspace_wireguard {
+        if backend == WireguardBackend::Kernel {
+            let res = if will_nm_manage_dns() {
+                log::debug!("Using kernel WireGuard implementation through NetworkManager");
+                wireguard_kernel::NetworkManagerTunnel::new(runtime, config)
+                    .map(|tunnel| Box::new(tunnel) as TunnelType)
+            } else {
+                log::debug!("Using kernel WireGuard implementation through netlink");
+                wireguard_kernel::NetlinkTunnel::new(runtime, config)
+                    .map(|tunnel| Box::new(tunnel) as TunnelType)
+            };
+            res.map_err(|error| {
+                Error::TunnelError(TunnelError::FatalStartWireguardError(Box::new(error)))
+            })
+        } else {
             log::debug!("Using userspace WireGuard implementation");
 
             #[cfg(not(feature = "boringtun"))]
@@ -756,37 +862,6 @@ impl WireguardMonitor {
 
             let tunnel = runtime.block_on(f).map(Box::new)?;
             Ok(tunnel)
-        } else {
-            let res = if will_nm_manage_dns() {
-                log::debug!("Using kernel WireGuard implementation through NetworkManager");
-                wireguard_kernel::NetworkManagerTunnel::new(runtime.clone(), config)
-                    .map(|tunnel| Box::new(tunnel) as TunnelType)
-            } else {
-                log::debug!("Using kernel WireGuard implementation through netlink");
-                wireguard_kernel::NetlinkTunnel::new(runtime.clone(), config)
-                    .map(|tunnel| Box::new(tunnel) as TunnelType)
-            };
-
-            res.or_else(|err| {
-                    log::warn!("Failed to initialize kernel WireGuard tunnel, falling back to userspace WireGuard implementation:\n{}",err.display_chain() );
-
-                    #[cfg(not(feature = "boringtun"))]
-                    {
-                        Ok(runtime
-                            .block_on(wireguard_go::open_wireguard_go_tunnel(
-                                config,
-                                _log_path,
-                                tun_provider,
-                            ))
-                            .map(Box::new)?)
-                    }
-                    #[cfg(feature = "boringtun")]
-                    {
-                        Ok(runtime
-                            .block_on(boringtun::open_boringtun_tunnel(config, tun_provider))
-                            .map(Box::new)?)
-                    }
-                })
         }
     }
 
@@ -812,6 +887,22 @@ impl WireguardMonitor {
         wait_result
     }
 
+    /// Returns a handle that can be used to read the traffic statistics of the tunnel while it
+    /// is running.
+    pub fn stats_handle(&self) -> TunnelStatsHandle {
+        TunnelStatsHandle {
+            tunnel: self.tunnel.clone(),
+            obfuscator: self.obfuscator.clone(),
+            ping_rtt: self.ping_rtt.clone(),
+        }
+    }
+
+    /// Returns a handle that can be used to move the tunnel to another relay while it is running.
+    #[cfg(not(target_os = "android"))]
+    pub fn relay_switch_handle(&self) -> RelaySwitchHandle {
+        self.relay_switch.clone()
+    }
+
     /// Tear down the tunnel.
     ///
     /// NOTE: will panic if called from within a tokio runtime.
@@ -963,12 +1054,18 @@ impl WireguardMonitor {
         }
     }
 
-    fn tunnel_metadata(interface_name: &str, config: &Config) -> TunnelMetadata {
+    fn tunnel_metadata(
+        interface_name: &str,
+        config: &Config,
+        backend: WireguardBackend,
+    ) -> TunnelMetadata {
         TunnelMetadata {
             interface: interface_name.to_string(),
             ips: config.tunnel.addresses.clone(),
             ipv4_gateway: config.ipv4_gateway,
             ipv6_gateway: config.ipv6_gateway,
+            daita: config.daita_parameters,
+            wireguard_backend: Some(backend),
         }
     }
 }
@@ -997,6 +1094,51 @@ async fn log_tunnel_data_usage(config: &Config, tunnel: &Arc<AsyncMutex<Option<T
     }
 }
 
+/// Handle for reading the traffic statistics of a [`WireguardMonitor`].
+#[derive(Clone)]
+pub struct TunnelStatsHandle {
+    tunnel: Arc<AsyncMutex<Option<TunnelType>>>,
+    obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
+    ping_rtt: connectivity::PingRtt,
+}
+
+impl TunnelStatsHandle {
+    /// Sample the current tunnel statistics. Returns `None` if the tunnel has been stopped or its
+    /// stats could not be read.
+    pub async fn get(&self) -> Option<TunnelStats> {
+        let peer_stats = {
+            let tunnel = self.tunnel.lock().await;
+            tunnel.as_ref()?.get_tunnel_stats().await.ok()?
+        };
+        let timestamp = SystemTime::now();
+        let peers = peer_stats
+            .into_iter()
+            .map(|(public_key, stats)| PeerStats {
+                public_key: public_key.into(),
+                tx_bytes: stats.tx_bytes,
+                rx_bytes: stats.rx_bytes,
+                last_handshake: stats.last_handshake,
+                tx_rate: None,
+                rx_rate: None,
+            })
+            .collect();
+        let obfuscator = self
+            .obfuscator
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|obfuscator| obfuscator.stats());
+        let ping_rtt_upper_bound = *self.ping_rtt.lock().unwrap();
+
+        Some(TunnelStats {
+            timestamp,
+            peers,
+            ping_rtt_upper_bound,
+            obfuscator,
+        })
+    }
+}
+
 #[derive(Debug)]
 enum CloseMsg {
     Stop,
@@ -1,8 +1,15 @@
-use std::{io, net::IpAddr, time::Duration};
+use std::{
+    convert::Infallible,
+    io,
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
 
 use futures::{future, stream::FuturesUnordered, Future, TryStreamExt};
 use surge_ping::{Client, Config, PingIdentifier, PingSequence, SurgeError};
-use talpid_tunnel::{ICMP_HEADER_SIZE, IPV4_HEADER_SIZE, MIN_IPV4_MTU};
+use talpid_tunnel::{EventHook, TunnelEvent, ICMP_HEADER_SIZE, IPV4_HEADER_SIZE, MIN_IPV4_MTU};
+use talpid_types::{tunnel::TunnelNotification, ErrorExt};
+use tokio::time::Instant;
 use tokio_stream::StreamExt;
 
 #[derive(thiserror::Error, Debug)]
@@ -35,37 +42,236 @@ const PING_TIMEOUT: Duration = Duration::from_secs(10);
 /// considered dropped, so we return the largest collected packet size.
 const PING_OFFSET_TIMEOUT: Duration = Duration::from_secs(2);
 const MTU_STEP_SIZE: u16 = 20;
+/// How often to verify that the current tunnel MTU does not cause dropped packets.
+const MONITOR_INTERVAL: Duration = Duration::from_secs(60);
+/// How often to try raising the tunnel MTU, if it is below the upper bound.
+const RAISE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// What to do during the next iteration of [`monitor_mtu`].
+#[derive(Debug, PartialEq, Eq)]
+enum Probe {
+    /// Send a single ping of the current MTU.
+    Verify,
+    /// Search for the largest MTU that does not cause dropped packets, up to `upper_bound`.
+    Search { upper_bound: u16 },
+}
+
+impl Probe {
+    fn next(current_mtu: u16, max_tunnel_mtu: u16, since_last_search: Duration) -> Self {
+        if current_mtu < max_tunnel_mtu && since_last_search >= RAISE_INTERVAL {
+            Probe::Search {
+                upper_bound: max_tunnel_mtu,
+            }
+        } else {
+            Probe::Verify
+        }
+    }
+}
 
-/// Verify that the current MTU doesn't cause dropped packets, otherwise lower it to the
-/// largest value which doesn't.
+/// Continuously verify that the tunnel MTU doesn't cause dropped packets, and adjust it to the
+/// largest value which doesn't. The tunnel MTU is never raised above `max_tunnel_mtu`, which is
+/// expected to be the MTU that the tunnel was configured with. That value already takes any user
+/// override and obfuscation overhead into account.
+///
+/// The path is probed once immediately. After that, a ping of the current MTU is sent every
+/// [`MONITOR_INTERVAL`], and the path is probed again if it is dropped. If the MTU has been
+/// lowered, an attempt to raise it is made every [`RAISE_INTERVAL`]. Whenever the MTU changes,
+/// [`TunnelNotification::MtuChanged`] is sent to `event_hook`.
 ///
 /// Note: This does not take fragmentation into account, so it should only be used as an extra
 /// safety measure after the normal MTU calculation using header sizes and safety margins.
-pub async fn automatic_mtu_correction(
-    gateway: std::net::Ipv4Addr,
+pub async fn monitor_mtu(
+    gateway: Ipv4Addr,
     iface_name: String,
-    current_tunnel_mtu: u16,
+    max_tunnel_mtu: u16,
     #[cfg(windows)] ipv6: bool,
-) -> Result<(), Error> {
+    mut event_hook: EventHook,
+) -> Infallible {
     log::debug!("Starting MTU detection");
-    let verified_mtu = detect_mtu(
+    let mut path = PingPath {
         gateway,
-        #[cfg(any(target_os = "macos", target_os = "linux"))]
-        iface_name.clone(),
-        current_tunnel_mtu,
-    )
-    .await?;
+        iface_name,
+        #[cfg(windows)]
+        ipv6,
+    };
+    let mut monitor = MtuMonitor::new(max_tunnel_mtu);
+
+    loop {
+        if let Some(mtu) = monitor.probe(&mut path).await {
+            event_hook
+                .on_event(TunnelEvent::Notification(TunnelNotification::MtuChanged(
+                    mtu,
+                )))
+                .await;
+        }
+
+        tokio::time::sleep(MONITOR_INTERVAL).await;
+        monitor.schedule_next_probe();
+    }
+}
 
-    if verified_mtu != current_tunnel_mtu {
-        log::warn!("Lowering MTU from {} to {verified_mtu}", current_tunnel_mtu);
+/// The tunnel path whose MTU is monitored by [`MtuMonitor`].
+#[async_trait::async_trait]
+trait Path: Send {
+    /// Verifies that a single ping of size `mtu` is not dropped.
+    async fn verify_mtu(&mut self, mtu: u16) -> Result<(), Error>;
 
-        #[cfg(any(target_os = "linux", target_os = "macos"))]
-        talpid_net::unix::set_mtu(&iface_name, verified_mtu).map_err(Error::SetMtu)?;
-        #[cfg(windows)]
-        set_mtu_windows(verified_mtu, iface_name, ipv6).map_err(Error::SetMtu)?;
-    } else {
-        log::debug!("MTU {verified_mtu} verified to not drop packets");
+    /// Detects the largest MTU up to `upper_bound` that does not cause dropped packets, and sets
+    /// the tunnel MTU to it. Returns the MTU the tunnel is using afterwards.
+    async fn search_mtu(&mut self, current_mtu: u16, upper_bound: u16) -> Result<u16, Error>;
+}
+
+/// The tunnel path, probed by pinging the gateway.
+struct PingPath {
+    gateway: Ipv4Addr,
+    iface_name: String,
+    #[cfg(windows)]
+    ipv6: bool,
+}
+
+#[async_trait::async_trait]
+impl Path for PingPath {
+    async fn verify_mtu(&mut self, mtu: u16) -> Result<(), Error> {
+        verify_mtu(self.gateway, &self.iface_name, mtu).await
+    }
+
+    async fn search_mtu(&mut self, current_mtu: u16, upper_bound: u16) -> Result<u16, Error> {
+        search_mtu(
+            self.gateway,
+            &self.iface_name,
+            current_mtu,
+            upper_bound,
+            #[cfg(windows)]
+            self.ipv6,
+        )
+        .await
+    }
+}
+
+/// State kept by [`monitor_mtu`] between probes.
+struct MtuMonitor {
+    current_mtu: u16,
+    max_tunnel_mtu: u16,
+    last_search: Instant,
+    next_probe: Probe,
+}
+
+impl MtuMonitor {
+    fn new(max_tunnel_mtu: u16) -> Self {
+        Self {
+            current_mtu: max_tunnel_mtu,
+            max_tunnel_mtu,
+            last_search: Instant::now(),
+            next_probe: Probe::Search {
+                upper_bound: max_tunnel_mtu,
+            },
+        }
+    }
+
+    /// Runs the scheduled probe. Returns the new MTU if it was changed.
+    async fn probe(&mut self, path: &mut impl Path) -> Option<u16> {
+        let current_mtu = self.current_mtu;
+
+        if self.next_probe == Probe::Verify {
+            match path.verify_mtu(current_mtu).await {
+                Ok(()) => log::trace!("MTU {current_mtu} verified to not drop packets"),
+                Err(Error::MtuDetectionAllDropped) => {
+                    log::debug!("Ping of size {current_mtu} was dropped, probing path MTU");
+                    self.next_probe = Probe::Search {
+                        upper_bound: current_mtu,
+                    };
+                }
+                Err(error) => log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to verify tunnel MTU")
+                ),
+            }
+        }
+
+        let Probe::Search { upper_bound } = self.next_probe else {
+            return None;
+        };
+        self.last_search = Instant::now();
+        match path.search_mtu(current_mtu, upper_bound).await {
+            Ok(verified_mtu) if verified_mtu != current_mtu => {
+                if verified_mtu < current_mtu {
+                    log::warn!("Lowering MTU from {current_mtu} to {verified_mtu}");
+                } else {
+                    log::info!("Raising MTU from {current_mtu} to {verified_mtu}");
+                }
+                self.current_mtu = verified_mtu;
+                Some(verified_mtu)
+            }
+            Ok(verified_mtu) => {
+                log::debug!("MTU {verified_mtu} verified to not drop packets");
+                None
+            }
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg(
+                        "Failed to automatically adjust MTU based on dropped packets"
+                    )
+                );
+                None
+            }
+        }
+    }
+
+    fn schedule_next_probe(&mut self) {
+        self.next_probe = Probe::next(
+            self.current_mtu,
+            self.max_tunnel_mtu,
+            self.last_search.elapsed(),
+        );
+    }
+}
+
+/// Detect the largest MTU up to `upper_bound` that does not cause dropped packets, and set the
+/// tunnel MTU to it. Returns the MTU the tunnel is using afterwards.
+async fn search_mtu(
+    gateway: Ipv4Addr,
+    iface_name: &str,
+    current_mtu: u16,
+    upper_bound: u16,
+    #[cfg(windows)] ipv6: bool,
+) -> Result<u16, Error> {
+    let set_mtu = |mtu| {
+        set_tunnel_mtu(
+            iface_name,
+            mtu,
+            #[cfg(windows)]
+            ipv6,
+        )
     };
+
+    // Pings cannot be larger than the MTU of the interface they are sent on, so it must be
+    // raised temporarily in order to probe for a larger MTU.
+    if upper_bound > current_mtu {
+        set_mtu(upper_bound)?;
+    }
+
+    match detect_mtu(gateway, iface_name, upper_bound).await {
+        Ok(verified_mtu) => {
+            if verified_mtu != upper_bound {
+                set_mtu(verified_mtu)?;
+            }
+            Ok(verified_mtu)
+        }
+        Err(error) => {
+            if upper_bound > current_mtu {
+                set_mtu(current_mtu)?;
+            }
+            Err(error)
+        }
+    }
+}
+
+fn set_tunnel_mtu(iface_name: &str, mtu: u16, #[cfg(windows)] ipv6: bool) -> Result<(), Error> {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    talpid_net::unix::set_mtu(iface_name, mtu).map_err(Error::SetMtu)?;
+    #[cfg(windows)]
+    set_mtu_windows(mtu, iface_name.to_owned(), ipv6).map_err(Error::SetMtu)?;
     Ok(())
 }
 
@@ -92,18 +298,52 @@ fn set_mtu_windows(verified_mtu: u16, iface_name: String, ipv6: bool) -> io::Res
 /// The detection works by sending evenly spread out range of pings between 576 and the given
 /// current tunnel MTU, and returning the maximum packet size that was returned within a
 /// timeout.
-async fn detect_mtu(
-    gateway: std::net::Ipv4Addr,
-    #[cfg(any(target_os = "macos", target_os = "linux"))] iface_name: String,
-    current_mtu: u16,
-) -> Result<u16, Error> {
+async fn detect_mtu(gateway: Ipv4Addr, iface_name: &str, current_mtu: u16) -> Result<u16, Error> {
     let linspace = mtu_spacing(MIN_IPV4_MTU, current_mtu, MTU_STEP_SIZE);
+    max_ping_size_on_path(gateway, iface_name, linspace).await
+}
+
+/// Verifies that a single ping of size `mtu` is not dropped.
+async fn verify_mtu(gateway: Ipv4Addr, iface_name: &str, mtu: u16) -> Result<(), Error> {
+    max_ping_size_on_path(gateway, iface_name, vec![mtu])
+        .await
+        .map(|_| ())
+}
+
+/// Sends a ping of each of the given sizes to `gateway` and returns the largest one that
+/// was returned within a timeout. The pings have the DF bit set where supported, so that they are
+/// dropped rather than fragmented by the local network stack.
+async fn max_ping_size_on_path(
+    gateway: Ipv4Addr,
+    #[cfg_attr(windows, allow(unused_variables))] iface_name: &str,
+    sizes: Vec<u16>,
+) -> Result<u16, Error> {
+    let largest_size = sizes.iter().copied().max().unwrap_or(MIN_IPV4_MTU);
 
     let config_builder = Config::builder().kind(surge_ping::ICMP::V4);
     #[cfg(any(target_os = "macos", target_os = "linux"))]
-    let config_builder = config_builder.interface(&iface_name);
+    let config_builder = config_builder.interface(iface_name);
     let client = Client::new(&config_builder.build()).map_err(Error::MtuDetectionSetupSocket)?;
 
+    #[cfg(target_os = "linux")]
+    {
+        let fd = client.get_socket().get_native_sock();
+        let pmtu_discover: libc::c_int = libc::IP_PMTUDISC_PROBE;
+        // SAFETY: `fd` is a valid socket, and the option value is a `c_int` of the given size
+        let result = unsafe {
+            libc::setsockopt(
+                fd,
+                libc::IPPROTO_IP,
+                libc::IP_MTU_DISCOVER,
+                &pmtu_discover as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if result != 0 {
+            return Err(Error::MtuDetectionSetupSocket(io::Error::last_os_error()));
+        }
+    }
+
     // For macos, the default socket receive buffer size seems to be too small to handle the
     // data we are sending here. The consequence will be dropped packets causing the MTU
     // detection to set a low value. Here we manually increase this value, which fixes
@@ -113,16 +353,16 @@ async fn detect_mtu(
     {
         use nix::sys::socket::{setsockopt, sockopt};
         let fd = client.get_socket().get_native_sock();
-        let buf_size = linspace.iter().map(|sz| usize::from(*sz)).sum();
+        let buf_size = sizes.iter().map(|sz| usize::from(*sz)).sum();
         setsockopt(fd, sockopt::SndBuf, &buf_size).map_err(Error::MtuSetBufferSize)?;
         setsockopt(fd, sockopt::RcvBuf, &buf_size).map_err(Error::MtuSetBufferSize)?;
     }
 
     // Shared buffer to reduce allocations
-    let payload_buf = vec![0; current_mtu as usize];
+    let payload_buf = vec![0; largest_size as usize];
 
     // Send a ping for each MTU in the linspace
-    let ping_stream = linspace
+    let ping_stream = sizes
         .into_iter()
         .enumerate()
         .map(|(sequence, mtu)| {
@@ -208,6 +448,7 @@ fn mtu_spacing(mtu_min: u16, mtu_max: u16, step_size: u16) -> Vec<u16> {
 mod tests {
     use super::*;
     use proptest::prelude::*;
+    use std::collections::VecDeque;
 
     proptest! {
         #[test]
@@ -226,6 +467,116 @@ mod tests {
         }
     }
 
+    /// The MTU should only be searched for again if it is below the upper bound and enough time
+    /// has passed since the last search.
+    #[test]
+    fn next_probe() {
+        assert_eq!(Probe::next(1380, 1380, RAISE_INTERVAL), Probe::Verify);
+        assert_eq!(Probe::next(1300, 1380, MONITOR_INTERVAL), Probe::Verify);
+        assert_eq!(
+            Probe::next(1300, 1380, RAISE_INTERVAL),
+            Probe::Search { upper_bound: 1380 }
+        );
+    }
+
+    /// Path which returns scripted probe results, and records the searches made.
+    #[derive(Default)]
+    struct ScriptedPath {
+        verify_results: VecDeque<Result<(), Error>>,
+        search_results: VecDeque<Result<u16, Error>>,
+        searches: Vec<(u16, u16)>,
+    }
+
+    #[async_trait::async_trait]
+    impl Path for ScriptedPath {
+        async fn verify_mtu(&mut self, _mtu: u16) -> Result<(), Error> {
+            self.verify_results.pop_front().expect("unexpected verify")
+        }
+
+        async fn search_mtu(&mut self, current_mtu: u16, upper_bound: u16) -> Result<u16, Error> {
+            self.searches.push((current_mtu, upper_bound));
+            self.search_results.pop_front().expect("unexpected search")
+        }
+    }
+
+    /// Wait until the next probe, like [`monitor_mtu`] does.
+    async fn wait_for_next_probe(monitor: &mut MtuMonitor, interval: Duration) {
+        tokio::time::advance(interval).await;
+        monitor.schedule_next_probe();
+    }
+
+    /// A dropped verification ping should lower the MTU, and the MTU should be raised again once
+    /// the path allows it.
+    #[tokio::test(start_paused = true)]
+    async fn dropped_ping_lowers_mtu() {
+        let mut path = ScriptedPath {
+            verify_results: [Ok(()), Err(Error::MtuDetectionAllDropped), Ok(())].into(),
+            search_results: [Ok(1380), Ok(1300), Ok(1380)].into(),
+            ..Default::default()
+        };
+        let mut monitor = MtuMonitor::new(1380);
+
+        assert_eq!(monitor.probe(&mut path).await, None);
+        wait_for_next_probe(&mut monitor, MONITOR_INTERVAL).await;
+        assert_eq!(monitor.probe(&mut path).await, None);
+        wait_for_next_probe(&mut monitor, MONITOR_INTERVAL).await;
+        assert_eq!(monitor.probe(&mut path).await, Some(1300));
+        wait_for_next_probe(&mut monitor, MONITOR_INTERVAL).await;
+        assert_eq!(monitor.probe(&mut path).await, None);
+        wait_for_next_probe(&mut monitor, RAISE_INTERVAL).await;
+        assert_eq!(monitor.probe(&mut path).await, Some(1380));
+
+        assert_eq!(path.searches, [(1380, 1380), (1380, 1380), (1300, 1380)]);
+        assert!(path.verify_results.is_empty());
+    }
+
+    /// Verification failing for any reason other than dropped pings should not trigger a search.
+    #[tokio::test(start_paused = true)]
+    async fn failed_verification_keeps_mtu() {
+        let mut path = ScriptedPath {
+            verify_results: [Err(Error::MtuDetectionSetupSocket(io::Error::other(
+                "test",
+            )))]
+            .into(),
+            search_results: [Ok(1380)].into(),
+            ..Default::default()
+        };
+        let mut monitor = MtuMonitor::new(1380);
+
+        assert_eq!(monitor.probe(&mut path).await, None);
+        wait_for_next_probe(&mut monitor, MONITOR_INTERVAL).await;
+        assert_eq!(monitor.probe(&mut path).await, None);
+
+        assert_eq!(monitor.current_mtu, 1380);
+        assert_eq!(path.searches, [(1380, 1380)]);
+    }
+
+    /// A failed search should leave the MTU as is, and not be retried until the raise interval has
+    /// passed again.
+    #[tokio::test(start_paused = true)]
+    async fn failed_search_keeps_mtu() {
+        let mut path = ScriptedPath {
+            verify_results: [Ok(())].into(),
+            search_results: [Ok(1300), Err(Error::MtuDetectionAllDropped), Ok(1340)].into(),
+            ..Default::default()
+        };
+        let mut monitor = MtuMonitor::new(1380);
+
+        assert_eq!(monitor.probe(&mut path).await, Some(1300));
+        wait_for_next_probe(&mut monitor, RAISE_INTERVAL).await;
+        assert_eq!(monitor.probe(&mut path).await, None);
+        assert_eq!(monitor.current_mtu, 1300);
+
+        wait_for_next_probe(&mut monitor, MONITOR_INTERVAL).await;
+        assert_eq!(monitor.next_probe, Probe::Verify);
+        assert_eq!(monitor.probe(&mut path).await, None);
+
+        wait_for_next_probe(&mut monitor, RAISE_INTERVAL).await;
+        assert_eq!(monitor.probe(&mut path).await, Some(1340));
+
+        assert_eq!(path.searches, [(1380, 1380), (1300, 1380), (1300, 1380)]);
+    }
+
     /// Tests for the timeout behavior described by [`PING_OFFSET_TIMEOUT`] and [`PING_TIMEOUT`].
     ///
     /// Note that time is mocked using [`tokio::time::pause`]. When all current tasks are sleeping,
@@ -3,17 +3,20 @@
 use super::{Error, Result};
 use crate::{config::Config, CloseMsg};
 #[cfg(target_os = "android")]
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 use std::{
     net::{Ipv4Addr, Ipv6Addr, SocketAddr},
-    sync::mpsc as sync_mpsc,
+    sync::{mpsc as sync_mpsc, Arc},
 };
 #[cfg(target_os = "android")]
 use talpid_tunnel::tun_provider::TunProvider;
-use talpid_types::{net::obfuscation::ObfuscatorConfig, ErrorExt};
+use talpid_types::{
+    net::{obfuscation::ObfuscatorConfig, stats::ObfuscatorStats},
+    ErrorExt,
+};
 
 use tunnel_obfuscation::{
-    create_obfuscator, quic, shadowsocks, udp2tcp, Settings as ObfuscationSettings,
+    create_obfuscator, quic, shadowsocks, udp2tcp, Settings as ObfuscationSettings, Stats,
 };
 
 /// Begin running obfuscation machine, if configured. This function will patch `config`'s endpoint
@@ -40,6 +43,7 @@ pub async fn apply_obfuscation_config(
         .map_err(Error::ObfuscationError)?;
 
     let packet_overhead = obfuscator.packet_overhead();
+    let stats = obfuscator.stats();
 
     #[cfg(target_os = "android")]
     bypass_vpn(tun_provider, obfuscator.remote_socket_fd()).await;
@@ -65,6 +69,7 @@ pub async fn apply_obfuscation_config(
     Ok(Some(ObfuscatorHandle {
         obfuscation_task,
         packet_overhead,
+        stats,
     }))
 }
 
@@ -128,6 +133,7 @@ async fn bypass_vpn(
 pub struct ObfuscatorHandle {
     obfuscation_task: tokio::task::JoinHandle<()>,
     packet_overhead: u16,
+    stats: Option<Arc<Stats>>,
 }
 
 impl ObfuscatorHandle {
@@ -138,6 +144,21 @@ impl ObfuscatorHandle {
     pub fn packet_overhead(&self) -> u16 {
         self.packet_overhead
     }
+
+    /// Returns the current traffic counters of the obfuscator, if it keeps any.
+    pub fn stats(&self) -> Option<ObfuscatorStats> {
+        let stats = self.stats.as_ref()?.snapshot();
+        Some(ObfuscatorStats {
+            rx_packets: stats.rx_packets as u64,
+            tx_packets: stats.tx_packets as u64,
+            rx_bytes: stats.rx_bytes as u64,
+            tx_bytes: stats.tx_bytes as u64,
+            fragmented_rx_packets: stats.fragmented_rx_packets as u64,
+            fragmented_tx_packets: stats.fragmented_tx_packets as u64,
+            fragmented_rx_bytes: stats.fragmented_rx_bytes as u64,
+            fragmented_tx_bytes: stats.fragmented_tx_bytes as u64,
+        })
+    }
 }
 
 impl Drop for ObfuscatorHandle {
@@ -0,0 +1,678 @@
+//! Move a running tunnel to another relay without taking it down.
+//!
+//! Every relay hands out the same tunnel addresses and gateway, so a tunnel can be moved to another
+//! relay by replacing its peer. The new peer is first added next to the current one, routing only
+//! the gateway, which is pinged until the new relay has responded. Only then is the current peer
+//! removed. If the new relay does not respond, the tunnel keeps using the current peer.
+//!
+//! Tunnels using ephemeral peers, for quantum resistance or DAITA, use a key that only the current
+//! relay knows of. The new relay is therefore reached using the device key, and a new ephemeral
+//! peer is negotiated with it before the current peer is replaced. Traffic other than to the
+//! gateway is paused meanwhile, rather than sent to the current relay without its PSK.
+//!
+//! Updating the peers makes WireGuard redo its handshakes, which only interrupts traffic for a
+//! round trip to the relay. Multihop and obfuscated tunnels cannot be moved.
+
+#[cfg(target_os = "macos")]
+use crate::WireguardMonitor;
+use crate::{config::Config, connectivity, ephemeral, TunnelError, TunnelType};
+use futures::{
+    channel::{mpsc, oneshot},
+    StreamExt,
+};
+use std::{
+    convert::Infallible,
+    future::{self, Future},
+    net::Ipv4Addr,
+    sync::Arc,
+    time::Duration,
+};
+use talpid_routing::RouteManagerHandle;
+use talpid_tunnel_config_client::EphemeralPeer;
+use talpid_types::net::wireguard::{DaitaLevel, PeerConfig, PrivateKey, PublicKey};
+#[cfg(target_os = "macos")]
+use talpid_types::ErrorExt;
+use tokio::sync::Mutex as AsyncMutex;
+
+/// How long to wait for the new relay to respond before giving up.
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Interval at which the gateway is pinged through the new relay.
+const PING_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Errors that can occur while moving a tunnel to another relay.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The tunnel uses a feature that requires a new tunnel to switch relays
+    #[error("The tunnel cannot switch relays without reconnecting")]
+    Unsupported,
+
+    /// The tunnel is no longer running
+    #[error("The tunnel is not running")]
+    TunnelDown,
+
+    /// Failed to add a route to the new relay
+    #[error("Failed to add route to the new relay")]
+    AddRoute(#[source] talpid_routing::Error),
+
+    /// Failed to update the peers of the tunnel
+    #[error("Failed to update tunnel config")]
+    SetConfig(#[source] TunnelError),
+
+    /// Failed to read the peer statistics of the tunnel
+    #[error("Failed to read tunnel stats")]
+    Stats(#[source] TunnelError),
+
+    /// Failed to ping the gateway through the new relay
+    #[error("Failed to ping the gateway through the new relay")]
+    Ping(#[source] connectivity::PingerError),
+
+    /// The new relay did not respond in time
+    #[error("Timed out waiting for the new relay to respond")]
+    Timeout,
+
+    /// Failed to negotiate an ephemeral peer with the new relay
+    #[error("Failed to negotiate ephemeral peer with the new relay")]
+    Negotiation(#[source] talpid_tunnel_config_client::Error),
+
+    /// Failed to start DAITA with the new relay
+    #[error("Failed to start DAITA")]
+    StartDaita(#[source] TunnelError),
+
+    /// The peers were replaced by someone else while switching relays
+    #[error("The tunnel peers changed while switching relays")]
+    PeersChanged,
+
+    /// Failed to put the current relay back on the tunnel after a failed switch
+    #[error("Failed to restore tunnel config after failed relay switch")]
+    Restore(#[source] TunnelError),
+}
+
+/// Handle for moving the tunnel of a [`WireguardMonitor`](crate::WireguardMonitor) to another
+/// relay.
+#[derive(Clone)]
+pub struct RelaySwitchHandle {
+    tx: mpsc::UnboundedSender<Request>,
+}
+
+pub(crate) struct Request {
+    peer: PeerConfig,
+    device_key: PrivateKey,
+    result_tx: oneshot::Sender<Result<(), Error>>,
+}
+
+impl RelaySwitchHandle {
+    pub(crate) fn new() -> (Self, mpsc::UnboundedReceiver<Request>) {
+        let (tx, rx) = mpsc::unbounded();
+        (Self { tx }, rx)
+    }
+
+    /// Replace the peer of the tunnel with `peer`, once the relay has been verified to respond.
+    /// `device_key` is the private key of the device, which is used to negotiate a new ephemeral
+    /// peer with the relay if the tunnel uses one. If this fails, the tunnel keeps using its
+    /// current peer.
+    pub async fn switch(&self, peer: PeerConfig, device_key: PrivateKey) -> Result<(), Error> {
+        let (result_tx, result_rx) = oneshot::channel();
+        self.tx
+            .unbounded_send(Request {
+                peer,
+                device_key,
+                result_tx,
+            })
+            .map_err(|_| Error::TunnelDown)?;
+        result_rx.await.map_err(|_| Error::TunnelDown)?
+    }
+}
+
+/// Serves requests to switch relays for as long as the tunnel is running. `config` must hold the
+/// config the tunnel is currently using, and is updated whenever the peer has been replaced. It is
+/// only locked while reading it and while committing the new peer.
+pub async fn serve_requests(
+    mut requests: mpsc::UnboundedReceiver<Request>,
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &AsyncMutex<Config>,
+    route_manager: &RouteManagerHandle,
+    iface_name: &str,
+) -> Infallible {
+    while let Some(request) = requests.next().await {
+        let result = switch_peer(
+            tunnel,
+            config,
+            request.peer,
+            request.device_key,
+            route_manager,
+            iface_name,
+        )
+        .await;
+        let _ = request.result_tx.send(result);
+    }
+
+    future::pending().await
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+async fn switch_peer(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &AsyncMutex<Config>,
+    peer: PeerConfig,
+    device_key: PrivateKey,
+    route_manager: &RouteManagerHandle,
+    iface_name: &str,
+) -> Result<(), Error> {
+    let current_config = config.lock().await.clone();
+
+    // Obfuscators only know of a single relay, and the exit relay of a multihop tunnel can only be
+    // reached through the entry relay.
+    if current_config.is_multihop() || current_config.obfuscator_config.is_some() {
+        return Err(Error::Unsupported);
+    }
+
+    // On Linux, relays are reached outside the tunnel using policy routing.
+    #[cfg(target_os = "macos")]
+    route_manager
+        .add_routes(WireguardMonitor::get_endpoint_routes(&[peer.endpoint.ip()]).collect())
+        .await
+        .map_err(Error::AddRoute)?;
+
+    let verify = verify_peer(tunnel, &peer, current_config.ipv4_gateway, iface_name);
+    let result = replace_peer(
+        tunnel,
+        config,
+        &current_config,
+        peer.clone(),
+        device_key,
+        verify,
+        talpid_tunnel_config_client::request_ephemeral_peer,
+    )
+    .await;
+
+    // Remove the route to whichever relay is no longer used
+    #[cfg(target_os = "macos")]
+    {
+        let used_endpoint = config.lock().await.entry_peer.endpoint.ip();
+        for unused_endpoint in [current_config.entry_peer.endpoint.ip(), peer.endpoint.ip()] {
+            if unused_endpoint == used_endpoint {
+                continue;
+            }
+            let routes = WireguardMonitor::get_endpoint_routes(&[unused_endpoint]).collect();
+            if let Err(error) = route_manager.remove_routes(routes).await {
+                log::warn!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to remove route to unused relay")
+                );
+            }
+        }
+    }
+
+    result
+}
+
+/// Moves the tunnel from the relay in `current_config` to `peer`, once `verify` succeeds. `config`
+/// is updated with the new peer, unless its peers were replaced meanwhile. On failure, the tunnel
+/// is put back on the config in `config`.
+///
+/// `peer` is first added next to the current peer, routing only the gateway to it. If the tunnel
+/// uses ephemeral peers, the current peer is paused instead and a new ephemeral peer is negotiated
+/// with the new relay on behalf of `device_key`, using `request_peer`. `request_peer` has the
+/// signature of [`talpid_tunnel_config_client::request_ephemeral_peer`].
+async fn replace_peer<F, Fut>(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &AsyncMutex<Config>,
+    current_config: &Config,
+    peer: PeerConfig,
+    device_key: PrivateKey,
+    verify: impl Future<Output = Result<(), Error>>,
+    request_peer: F,
+) -> Result<(), Error>
+where
+    F: Fn(Ipv4Addr, PublicKey, PublicKey, bool, Option<DaitaLevel>) -> Fut,
+    Fut: Future<Output = Result<EphemeralPeer, talpid_tunnel_config_client::Error>>,
+{
+    let uses_ephemeral_peer = current_config.quantum_resistant || current_config.daita;
+
+    // Route only the gateway to the new relay, so that the new relay can be verified before any
+    // other traffic is sent to it.
+    let gateway_peer = PeerConfig {
+        allowed_ips: vec![ipnetwork::Ipv4Network::from(current_config.ipv4_gateway).into()],
+        psk: None,
+        ..peer.clone()
+    };
+    let mut transition_config = current_config.clone();
+    if uses_ephemeral_peer {
+        // The current relay only accepts the ephemeral key together with its PSK.
+        transition_config.tunnel.private_key = device_key.clone();
+        transition_config.entry_peer = gateway_peer;
+    } else {
+        // The exit peer is merely used to hold the additional peer.
+        transition_config.exit_peer = Some(gateway_peer);
+    }
+
+    let switched = async {
+        set_config(tunnel, transition_config).await?;
+        verify.await?;
+        if uses_ephemeral_peer {
+            negotiate_ephemeral_peer(&request_peer, current_config, &device_key)
+                .await
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+    .await;
+
+    let mut config = config.lock().await;
+    let result = match switched {
+        Ok(_) if !ephemeral::same_peers(&config, current_config) => Err(Error::PeersChanged),
+        Ok(ephemeral_peer) => {
+            log::debug!("New relay responded. Replacing the current relay");
+            let new_config = switched_config(&config, peer, ephemeral_peer);
+            apply_config(tunnel, &new_config).await.map(|()| new_config)
+        }
+        Err(error) => Err(error),
+    };
+
+    match result {
+        Ok(new_config) => {
+            *config = new_config;
+            Ok(())
+        }
+        Err(error) => {
+            ephemeral::restore_tunnel_config(tunnel, &config)
+                .await
+                .map_err(Error::Restore)?;
+            Err(error)
+        }
+    }
+}
+
+/// Negotiates an ephemeral peer with the relay that the gateway is currently routed to, on behalf
+/// of `device_key`. Returns the ephemeral key along with the peer.
+async fn negotiate_ephemeral_peer<F, Fut>(
+    request_peer: &F,
+    config: &Config,
+    device_key: &PrivateKey,
+) -> Result<(PrivateKey, EphemeralPeer), Error>
+where
+    F: Fn(Ipv4Addr, PublicKey, PublicKey, bool, Option<DaitaLevel>) -> Fut,
+    Fut: Future<Output = Result<EphemeralPeer, talpid_tunnel_config_client::Error>>,
+{
+    let ephemeral_private_key = PrivateKey::new_from_random();
+    let ephemeral_peer = tokio::time::timeout(
+        ephemeral::INITIAL_PSK_EXCHANGE_TIMEOUT,
+        request_peer(
+            config.ipv4_gateway,
+            device_key.public_key(),
+            ephemeral_private_key.public_key(),
+            config.quantum_resistant,
+            config.daita.then_some(config.daita_level),
+        ),
+    )
+    .await
+    .map_err(|_timeout_err| Error::Timeout)?
+    .map_err(Error::Negotiation)?;
+    Ok((ephemeral_private_key, ephemeral_peer))
+}
+
+/// Returns `config` with its peer replaced by `peer`, using `ephemeral_peer` if one was negotiated.
+fn switched_config(
+    config: &Config,
+    peer: PeerConfig,
+    ephemeral_peer: Option<(PrivateKey, EphemeralPeer)>,
+) -> Config {
+    let mut new_config = config.clone();
+    new_config.entry_peer = peer;
+    if let Some((ephemeral_private_key, ephemeral_peer)) = ephemeral_peer {
+        new_config.tunnel.private_key = ephemeral_private_key;
+        new_config.entry_peer.psk = ephemeral_peer.psk;
+        new_config.entry_peer.constant_packet_size = new_config.daita;
+        new_config.daita_parameters = ephemeral_peer
+            .daita
+            .as_ref()
+            .map(|daita| ephemeral::daita_parameters(&new_config, daita));
+        new_config.daita_settings = ephemeral_peer.daita;
+    }
+    new_config
+}
+
+/// Applies `config` to the tunnel, and starts DAITA since replacing the peers stops it.
+async fn apply_config(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &Config,
+) -> Result<(), Error> {
+    set_config(tunnel, config.clone()).await?;
+    ephemeral::start_daita(tunnel, config)
+        .await
+        .map_err(Error::StartDaita)
+}
+
+async fn set_config(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: Config,
+) -> Result<(), Error> {
+    let mut tunnel = tunnel.lock().await;
+    let tunnel = tunnel.as_mut().ok_or(Error::TunnelDown)?;
+    tunnel.set_config(config).await.map_err(Error::SetConfig)
+}
+
+/// Pings `gateway` until `peer` has responded, or until [`VERIFY_TIMEOUT`] has elapsed.
+#[cfg_attr(target_os = "windows", allow(unused_variables))]
+async fn verify_peer(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    peer: &PeerConfig,
+    gateway: Ipv4Addr,
+    iface_name: &str,
+) -> Result<(), Error> {
+    let mut pinger = connectivity::new_pinger(
+        gateway,
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        iface_name.to_string(),
+    )
+    .map_err(Error::Ping)?;
+
+    let verify = async {
+        loop {
+            pinger.send_icmp().await.map_err(Error::Ping)?;
+            tokio::time::sleep(PING_INTERVAL).await;
+            if peer_has_responded(tunnel, peer).await? {
+                return Ok(());
+            }
+        }
+    };
+    let result = tokio::time::timeout(VERIFY_TIMEOUT, verify)
+        .await
+        .unwrap_or(Err(Error::Timeout));
+
+    pinger.reset().await;
+    result
+}
+
+/// Returns whether a handshake has completed with `peer`, and whether any data has been received
+/// from it.
+async fn peer_has_responded(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    peer: &PeerConfig,
+) -> Result<bool, Error> {
+    let tunnel = tunnel.lock().await;
+    let tunnel = tunnel.as_ref().ok_or(Error::TunnelDown)?;
+    let stats = tunnel.get_tunnel_stats().await.map_err(Error::Stats)?;
+    Ok(stats
+        .get(peer.public_key.as_bytes())
+        .is_some_and(|stats| stats.last_handshake.is_some() && stats.rx_bytes > 0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{connectivity::MockTunnel, stats::StatsMap};
+    use std::sync::Mutex;
+    use talpid_types::net::wireguard::{PresharedKey, TunnelConfig};
+
+    type SharedTunnel = Arc<AsyncMutex<Option<TunnelType>>>;
+
+    /// Returns a tunnel that records every config applied to it.
+    fn tunnel() -> (SharedTunnel, Arc<Mutex<Vec<Config>>>) {
+        let configs = Arc::new(Mutex::new(vec![]));
+        let recorded_configs = configs.clone();
+        let tunnel = MockTunnel::new(|| Ok(StatsMap::new()))
+            .on_set_config(move |config| recorded_configs.lock().unwrap().push(config))
+            .boxed();
+        (Arc::new(AsyncMutex::new(Some(tunnel))), configs)
+    }
+
+    /// Config service for tunnels that do not use ephemeral peers.
+    fn no_config_service(
+        _: Ipv4Addr,
+        _: PublicKey,
+        _: PublicKey,
+        _: bool,
+        _: Option<DaitaLevel>,
+    ) -> future::Ready<Result<EphemeralPeer, talpid_tunnel_config_client::Error>> {
+        panic!("No ephemeral peer should be negotiated");
+    }
+
+    fn peer(endpoint: &str) -> PeerConfig {
+        PeerConfig {
+            public_key: PrivateKey::new_from_random().public_key(),
+            allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
+            endpoint: endpoint.parse().unwrap(),
+            psk: None,
+            persistent_keepalive: None,
+            constant_packet_size: false,
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            tunnel: TunnelConfig {
+                private_key: PrivateKey::new_from_random(),
+                addresses: vec![],
+            },
+            entry_peer: peer("1.2.3.4:51820"),
+            exit_peer: None,
+            ipv4_gateway: "10.64.0.1".parse().unwrap(),
+            ipv6_gateway: None,
+            mtu: 1380,
+            #[cfg(target_os = "linux")]
+            fwmark: None,
+            #[cfg(target_os = "linux")]
+            enable_ipv6: false,
+            obfuscator_config: None,
+            quantum_resistant: false,
+            daita: false,
+            daita_level: Default::default(),
+            daita_parameters: None,
+            daita_settings: None,
+            rekey_interval: None,
+            connectivity: Default::default(),
+            backend: Default::default(),
+        }
+    }
+
+    fn gateway_route(config: &Config) -> Vec<ipnetwork::IpNetwork> {
+        vec![ipnetwork::Ipv4Network::from(config.ipv4_gateway).into()]
+    }
+
+    /// Asserts that `config` routes only the gateway to `peer`, next to the current peer.
+    fn assert_transition_config(config: &Config, current: &Config, peer: &PeerConfig) {
+        assert_eq!(config.entry_peer, current.entry_peer);
+        let exit_peer = config.exit_peer.as_ref().unwrap();
+        assert_eq!(exit_peer.public_key, peer.public_key);
+        assert_eq!(exit_peer.allowed_ips, gateway_route(current));
+    }
+
+    /// Once the new relay has responded, it replaces the current relay.
+    #[tokio::test]
+    async fn test_swap_peer() {
+        let (tunnel, configs) = tunnel();
+        let original = config();
+        let config = AsyncMutex::new(original.clone());
+        let new_peer = peer("5.6.7.8:51820");
+
+        let verify = async {
+            // The new relay must be reachable while it is verified
+            let configs = configs.lock().unwrap();
+            assert_eq!(configs.len(), 1);
+            assert_transition_config(&configs[0], &original, &new_peer);
+            Ok(())
+        };
+        replace_peer(
+            &tunnel,
+            &config,
+            &original,
+            new_peer.clone(),
+            original.tunnel.private_key.clone(),
+            verify,
+            no_config_service,
+        )
+        .await
+        .unwrap();
+
+        let config = config.lock().await;
+        assert_eq!(config.entry_peer, new_peer);
+        assert!(config.exit_peer.is_none());
+        let configs = configs.lock().unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[1].entry_peer, new_peer);
+        assert!(configs[1].exit_peer.is_none());
+    }
+
+    /// Tunnels using ephemeral peers reach the new relay using the device key, and use a new
+    /// ephemeral peer negotiated with it. Traffic is not sent to the current relay meanwhile.
+    #[tokio::test]
+    async fn test_swap_ephemeral_peer() {
+        let (tunnel, configs) = tunnel();
+        let mut original = config();
+        original.quantum_resistant = true;
+        original.entry_peer.psk = Some(PresharedKey::from([1; 32]));
+        let config = AsyncMutex::new(original.clone());
+        let device_key = PrivateKey::new_from_random();
+        let new_peer = peer("5.6.7.8:51820");
+
+        let negotiated_keys = Mutex::new(vec![]);
+        let request_peer = |_, parent_pubkey, ephemeral_pubkey, _, _| {
+            negotiated_keys
+                .lock()
+                .unwrap()
+                .push((parent_pubkey, ephemeral_pubkey));
+            future::ready(Ok(EphemeralPeer {
+                psk: Some(PresharedKey::from([2; 32])),
+                daita: None,
+            }))
+        };
+        let verify = async {
+            let configs = configs.lock().unwrap();
+            assert_eq!(configs.len(), 1);
+            let transition = &configs[0];
+            assert_eq!(
+                transition.tunnel.private_key.public_key(),
+                device_key.public_key()
+            );
+            assert!(transition.exit_peer.is_none());
+            assert_eq!(transition.entry_peer.public_key, new_peer.public_key);
+            assert_eq!(transition.entry_peer.allowed_ips, gateway_route(&original));
+            assert!(transition.entry_peer.psk.is_none());
+            Ok(())
+        };
+        replace_peer(
+            &tunnel,
+            &config,
+            &original,
+            new_peer.clone(),
+            device_key.clone(),
+            verify,
+            request_peer,
+        )
+        .await
+        .unwrap();
+
+        let negotiated_keys = negotiated_keys.into_inner().unwrap();
+        assert_eq!(negotiated_keys.len(), 1);
+        let (parent_pubkey, ephemeral_pubkey) = &negotiated_keys[0];
+        assert_eq!(*parent_pubkey, device_key.public_key());
+
+        let config = config.lock().await;
+        assert_eq!(config.tunnel.private_key.public_key(), *ephemeral_pubkey);
+        assert_eq!(config.entry_peer.public_key, new_peer.public_key);
+        assert_eq!(config.entry_peer.allowed_ips, new_peer.allowed_ips);
+        assert_eq!(config.entry_peer.psk, Some(PresharedKey::from([2; 32])));
+        let configs = configs.lock().unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(
+            configs[1].tunnel.private_key.public_key(),
+            *ephemeral_pubkey
+        );
+        assert_eq!(configs[1].entry_peer, config.entry_peer);
+    }
+
+    /// If the new relay does not respond, the tunnel is put back on the current relay.
+    #[tokio::test]
+    async fn test_keep_peer_on_failed_verification() {
+        let (tunnel, configs) = tunnel();
+        let original = config();
+        let config = AsyncMutex::new(original.clone());
+        let new_peer = peer("5.6.7.8:51820");
+
+        let result = replace_peer(
+            &tunnel,
+            &config,
+            &original,
+            new_peer.clone(),
+            original.tunnel.private_key.clone(),
+            async { Err(Error::Timeout) },
+            no_config_service,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::Timeout)));
+        let config = config.lock().await;
+        assert_eq!(config.entry_peer, original.entry_peer);
+        assert!(config.exit_peer.is_none());
+        let configs = configs.lock().unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_transition_config(&configs[0], &original, &new_peer);
+        assert_eq!(configs[1].entry_peer, original.entry_peer);
+        assert!(configs[1].exit_peer.is_none());
+    }
+
+    /// If the peers are replaced by someone else during the switch, the switch is abandoned and
+    /// the tunnel is put on the new peers.
+    #[tokio::test]
+    async fn test_abort_if_peers_changed() {
+        let (tunnel, configs) = tunnel();
+        let original = config();
+        let config = AsyncMutex::new(original.clone());
+        let new_peer = peer("5.6.7.8:51820");
+
+        let mut rekeyed = original.clone();
+        rekeyed.tunnel.private_key = PrivateKey::new_from_random();
+        let verify = async {
+            *config.lock().await = rekeyed.clone();
+            Ok(())
+        };
+        let result = replace_peer(
+            &tunnel,
+            &config,
+            &original,
+            new_peer,
+            original.tunnel.private_key.clone(),
+            verify,
+            no_config_service,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::PeersChanged)));
+        let config = config.lock().await;
+        assert_eq!(
+            config.tunnel.private_key.public_key(),
+            rekeyed.tunnel.private_key.public_key()
+        );
+        let configs = configs.lock().unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(
+            configs[1].tunnel.private_key.public_key(),
+            rekeyed.tunnel.private_key.public_key()
+        );
+        assert_eq!(configs[1].entry_peer, original.entry_peer);
+    }
+
+    /// The peer is only replaced while the tunnel is running.
+    #[tokio::test]
+    async fn test_tunnel_down() {
+        let tunnel: SharedTunnel = Arc::new(AsyncMutex::new(None));
+        let original = config();
+        let config = AsyncMutex::new(original.clone());
+
+        let result = replace_peer(
+            &tunnel,
+            &config,
+            &original,
+            peer("5.6.7.8:51820"),
+            original.tunnel.private_key.clone(),
+            async { Ok(()) },
+            no_config_service,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::TunnelDown)));
+        assert_eq!(config.lock().await.entry_peer, original.entry_peer);
+    }
+}
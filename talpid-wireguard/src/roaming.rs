@@ -0,0 +1,58 @@
+//! Keep the tunnel working when the non-tunnel default route changes, without reconnecting.
+//!
+//! WireGuard follows a new default route by itself, since it sends every packet to the peer
+//! endpoint anew. Obfuscators on the other hand may have a connection that is tied to the old
+//! route, so they are restarted. If the relay turns out to be unreachable, the connectivity
+//! monitor closes the tunnel as usual.
+//!
+//! Only macOS reports default route changes to the tunnel for now. On other platforms, a network
+//! change results in a reconnect.
+
+use crate::{config::Config, ephemeral, obfuscation::ObfuscatorHandle, CloseMsg, TunnelType};
+use futures::{future, Stream, StreamExt};
+use std::sync::{mpsc as sync_mpsc, Arc};
+use talpid_routing::DefaultRouteEvent;
+use tokio::sync::Mutex as AsyncMutex;
+
+/// Restarts the obfuscator, if there is one, whenever a non-tunnel default route is added or
+/// changed. `config` must hold the config the tunnel is currently using, and is updated with the
+/// new obfuscator endpoint. Only returns if the obfuscator could not be restarted.
+pub async fn follow_default_route(
+    mut route_events: impl Stream<Item = DefaultRouteEvent> + Unpin,
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &AsyncMutex<Config>,
+    obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
+    close_obfs_sender: sync_mpsc::Sender<CloseMsg>,
+) -> CloseMsg {
+    while let Some(event) = route_events.next().await {
+        if let DefaultRouteEvent::RemovedV4 | DefaultRouteEvent::RemovedV6 = event {
+            continue;
+        }
+        if obfuscator.lock().await.is_none() {
+            log::debug!("Default route changed. Letting WireGuard roam");
+            continue;
+        }
+        let mut config = config.lock().await;
+        if config.daita {
+            // Replacing the peers stops DAITA, and the DAITA settings negotiated with the relay
+            // are not kept around.
+            log::debug!("Default route changed. Not restarting obfuscator since DAITA is enabled");
+            continue;
+        }
+
+        log::debug!("Default route changed. Restarting obfuscator");
+        match ephemeral::reconfigure_tunnel(
+            tunnel,
+            config.clone(),
+            obfuscator.clone(),
+            close_obfs_sender.clone(),
+        )
+        .await
+        {
+            Ok(new_config) => *config = new_config,
+            Err(close_msg) => return close_msg,
+        }
+    }
+
+    future::pending().await
+}
@@ -1,8 +1,12 @@
+use std::time::SystemTime;
+
 /// Contains bytes sent and received through a tunnel
 #[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Stats {
     pub tx_bytes: u64,
     pub rx_bytes: u64,
+    /// Time of the most recent handshake, or `None` if no handshake has completed.
+    pub last_handshake: Option<SystemTime>,
 }
 
 /// A map from peer pubkeys to peer stats.
@@ -681,10 +681,11 @@ impl WgGoTunnel {
         Ok(())
     }
     async fn ensure_tunnel_is_running(&self) -> Result<()> {
-        let addr = self.handle().config.ipv4_gateway;
+        let config = &self.handle().config;
         let cancel_receiver = self.handle().cancel_receiver.clone();
-        let mut check = connectivity::Check::new(addr, 0, cancel_receiver)
-            .map_err(|err| TunnelError::RecoverableStartWireguardError(Box::new(err)))?;
+        let mut check =
+            connectivity::Check::new(config.ipv4_gateway, config.connectivity, 0, cancel_receiver)
+                .map_err(|err| TunnelError::RecoverableStartWireguardError(Box::new(err)))?;
 
         // TODO: retry attempt?
 
@@ -760,6 +761,7 @@ impl Tunnel for WgGoTunnel {
 
 mod stats {
     use super::{Stats, StatsMap};
+    use std::time::{Duration, SystemTime};
 
     #[derive(thiserror::Error, Debug, PartialEq)]
     pub enum Error {
@@ -777,6 +779,8 @@ mod stats {
             let mut peer = None;
             let mut tx_bytes = None;
             let mut rx_bytes = None;
+            let mut handshake_sec = 0;
+            let mut handshake_nsec = 0;
 
             // parts iterates over keys and values
             let parts = config.split('\n').filter_map(|line| {
@@ -795,6 +799,20 @@ mod stats {
                         peer = Some(buffer);
                         tx_bytes = None;
                         rx_bytes = None;
+                        handshake_sec = 0;
+                        handshake_nsec = 0;
+                    }
+                    "last_handshake_time_sec" => {
+                        handshake_sec = value
+                            .trim()
+                            .parse()
+                            .map_err(|err| Error::IntParse(value.to_string(), err))?;
+                    }
+                    "last_handshake_time_nsec" => {
+                        handshake_nsec = value
+                            .trim()
+                            .parse()
+                            .map_err(|err| Error::IntParse(value.to_string(), err))?;
                     }
                     "rx_bytes" => {
                         rx_bytes = Some(
@@ -824,20 +842,32 @@ mod stats {
                         Self {
                             tx_bytes: tx_bytes_val,
                             rx_bytes: rx_bytes_val,
+                            last_handshake: last_handshake(handshake_sec, handshake_nsec),
                         },
                     );
                     peer = None;
                     tx_bytes = None;
                     rx_bytes = None;
+                    handshake_sec = 0;
+                    handshake_nsec = 0;
                 }
             }
             Ok(map)
         }
     }
 
+    /// A zero timestamp means that no handshake has been completed.
+    fn last_handshake(sec: u64, nsec: u32) -> Option<SystemTime> {
+        if sec == 0 && nsec == 0 {
+            return None;
+        }
+        SystemTime::UNIX_EPOCH.checked_add(Duration::new(sec, nsec))
+    }
+
     #[cfg(test)]
     mod test {
         use super::super::stats::{Error, Stats};
+        use std::time::{Duration, SystemTime};
 
         #[test]
         fn test_parsing() {
@@ -850,6 +880,17 @@ mod stats {
             assert_eq!(actual_keys, [pubkey]);
             assert_eq!(stats[&pubkey].rx_bytes, 2396);
             assert_eq!(stats[&pubkey].tx_bytes, 2740);
+            assert_eq!(
+                stats[&pubkey].last_handshake,
+                Some(SystemTime::UNIX_EPOCH + Duration::new(1578420649, 369416131))
+            );
+        }
+
+        #[test]
+        fn test_parsing_no_handshake() {
+            let input = "public_key=0000000000000000000000000000000000000000000000000000000000000000\nlast_handshake_time_sec=0\nlast_handshake_time_nsec=0\ntx_bytes=148\nrx_bytes=0\n";
+            let stats = Stats::parse_config_str(input).expect("Failed to parse valid input");
+            assert_eq!(stats[&[0u8; 32]].last_handshake, None);
         }
 
         #[test]
@@ -1,5 +1,7 @@
 use super::wg_message::{DeviceMessage, DeviceNla, PeerNla};
 use crate::stats::{Stats, StatsMap};
+use nix::sys::time::TimeSpec;
+use std::time::{Duration, SystemTime};
 
 impl Stats {
     pub fn parse_device_message(message: &DeviceMessage) -> StatsMap {
@@ -11,17 +13,28 @@ impl Stats {
                     let mut tx_bytes = 0;
                     let mut rx_bytes = 0;
                     let mut pub_key = None;
+                    let mut last_handshake = None;
 
                     for nla in &msg.0 {
                         match nla {
                             PeerNla::TxBytes(bytes) => tx_bytes = *bytes,
                             PeerNla::RxBytes(bytes) => rx_bytes = *bytes,
                             PeerNla::PublicKey(key) => pub_key = Some(*key),
+                            PeerNla::LastHandshakeTime(time) => {
+                                last_handshake = handshake_time(time)
+                            }
                             _ => continue,
                         }
                     }
                     if let Some(key) = pub_key {
-                        map.insert(key, Stats { tx_bytes, rx_bytes });
+                        map.insert(
+                            key,
+                            Stats {
+                                tx_bytes,
+                                rx_bytes,
+                                last_handshake,
+                            },
+                        );
                     }
                 }
             }
@@ -30,3 +43,13 @@ impl Stats {
         map
     }
 }
+
+/// The kernel reports the handshake time as a wall-clock time, or zero if there has not been one.
+fn handshake_time(time: &TimeSpec) -> Option<SystemTime> {
+    let secs = u64::try_from(time.tv_sec()).ok()?;
+    let nanos = u32::try_from(time.tv_nsec()).ok()?;
+    if secs == 0 && nanos == 0 {
+        return None;
+    }
+    SystemTime::UNIX_EPOCH.checked_add(Duration::new(secs, nanos))
+}
@@ -24,6 +24,7 @@ use std::{
     pin::Pin,
     ptr,
     sync::{Arc, LazyLock, Mutex},
+    time::{Duration, SystemTime},
 };
 use talpid_types::{BoxedError, ErrorExt};
 use talpid_windows::net;
@@ -330,6 +331,16 @@ bitflags! {
     }
 }
 
+/// Number of 100 ns intervals between 1601-01-01 and the Unix epoch.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// Convert a `FILETIME` value (100 ns intervals since 1601-01-01) to a [`SystemTime`]. A zero
+/// value means that there is no timestamp.
+fn filetime_to_system_time(filetime: u64) -> Option<SystemTime> {
+    let since_epoch = filetime.checked_sub(FILETIME_UNIX_EPOCH)?;
+    SystemTime::UNIX_EPOCH.checked_add(Duration::from_nanos(since_epoch.saturating_mul(100)))
+}
+
 /// See `WIREGUARD_PEER` at <https://git.zx2c4.com/wireguard-nt/tree/api/wireguard.h>.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 #[repr(C, align(8))]
@@ -947,6 +958,9 @@ fn serialize_config(config: &Config) -> Result<Vec<MaybeUninit<u8>>> {
         if peer.psk.is_some() {
             flags |= WgPeerFlag::HAS_PRESHARED_KEY;
         }
+        if peer.persistent_keepalive.is_some() {
+            flags |= WgPeerFlag::HAS_PERSISTENT_KEEPALIVE;
+        }
         #[cfg(daita)]
         let constant_packet_size = if peer.constant_packet_size { 1 } else { 0 };
         let wg_peer = WgPeer {
@@ -958,7 +972,7 @@ fn serialize_config(config: &Config) -> Result<Vec<MaybeUninit<u8>>> {
                 .as_ref()
                 .map(|psk| *psk.as_bytes())
                 .unwrap_or([0u8; WIREGUARD_KEY_LENGTH]),
-            persistent_keepalive: 0,
+            persistent_keepalive: peer.persistent_keepalive.unwrap_or(0),
             endpoint: net::inet_sockaddr_from_socketaddr(peer.endpoint).into(),
             tx_bytes: 0,
             rx_bytes: 0,
@@ -1078,6 +1092,7 @@ impl Tunnel for WgNtTunnel {
                     Stats {
                         tx_bytes: peer.tx_bytes,
                         rx_bytes: peer.rx_bytes,
+                        last_handshake: filetime_to_system_time(peer.last_handshake),
                     },
                 );
             }
@@ -1162,6 +1177,7 @@ mod tests {
             allowed_ips: vec!["1.3.3.0/24".parse().unwrap()],
             endpoint: "1.2.3.4:1234".parse().unwrap(),
             psk: None,
+            persistent_keepalive: None,
             constant_packet_size: false,
         },
         exit_peer: None,
@@ -1171,7 +1187,13 @@ mod tests {
         obfuscator_config: None,
         #[cfg(daita)]
         daita: false,
+        daita_level: Default::default(),
+        daita_parameters: None,
+        daita_settings: None,
         quantum_resistant: false,
+        rekey_interval: None,
+        connectivity: Default::default(),
+        backend: Default::default(),
     });
 
     static WG_STRUCT_CONFIG: LazyLock<Interface> = LazyLock::new(|| Interface {
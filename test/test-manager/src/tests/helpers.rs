@@ -703,6 +703,7 @@ pub fn unreachable_wireguard_tunnel() -> talpid_types::net::wireguard::Connectio
             ],
             endpoint: "1.3.3.7:1234".parse().unwrap(),
             psk: None,
+            persistent_keepalive: None,
             constant_packet_size: false,
         },
         exit_peer: None,
@@ -367,7 +367,10 @@ pub async fn test_connected_state(
                     obfuscation: _,
                     entry_endpoint: None,
                     tunnel_interface: _,
+                    tunnel_ips: _,
                     daita: _,
+                    daita_parameters: _,
+                    wireguard_backend: _,
                 },
             ..
         } => {
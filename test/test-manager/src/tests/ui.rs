@@ -209,11 +209,11 @@ async fn test_custom_access_methods_gui(
         [
             (
                 "SHADOWSOCKS_SERVER_IP",
-                access_method.endpoint.ip().to_string().as_ref(),
+                access_method.endpoint.host.to_string().as_ref(),
             ),
             (
                 "SHADOWSOCKS_SERVER_PORT",
-                access_method.endpoint.port().to_string().as_ref(),
+                access_method.endpoint.port.to_string().as_ref(),
             ),
             ("SHADOWSOCKS_SERVER_CIPHER", access_method.cipher.as_ref()),
             (
@@ -263,11 +263,11 @@ async fn test_custom_bridge_gui(
         [
             (
                 "SHADOWSOCKS_SERVER_IP",
-                custom_proxy.endpoint.ip().to_string().as_ref(),
+                custom_proxy.endpoint.host.to_string().as_ref(),
             ),
             (
                 "SHADOWSOCKS_SERVER_PORT",
-                custom_proxy.endpoint.port().to_string().as_ref(),
+                custom_proxy.endpoint.port.to_string().as_ref(),
             ),
             ("SHADOWSOCKS_SERVER_CIPHER", custom_proxy.cipher.as_ref()),
             (
@@ -1,5 +1,7 @@
 use async_trait::async_trait;
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc};
+
+pub use mullvad_masque_proxy::stats::{Stats, StatsSnapshot};
 
 pub mod quic;
 pub mod shadowsocks;
@@ -43,6 +45,11 @@ pub trait Obfuscator: Send {
     ///
     /// This is used when deciding on MTUs.
     fn packet_overhead(&self) -> u16;
+
+    /// Returns the traffic counters of this obfuscator, if it keeps any.
+    fn stats(&self) -> Option<Arc<Stats>> {
+        None
+    }
 }
 
 #[derive(Debug)]
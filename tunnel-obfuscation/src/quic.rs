@@ -2,9 +2,11 @@
 
 use async_trait::async_trait;
 use mullvad_masque_proxy::client::{Client, ClientConfig};
+use mullvad_masque_proxy::stats::Stats;
 use std::{
     io,
     net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
 };
 use tokio::net::UdpSocket;
 
@@ -26,6 +28,7 @@ pub enum Error {
 pub struct Quic {
     local_endpoint: SocketAddr,
     task: tokio::task::JoinHandle<Result<()>>,
+    stats: Arc<Stats>,
 }
 
 #[derive(Debug)]
@@ -49,13 +52,16 @@ impl Quic {
 
         let local_endpoint = local_socket.local_addr().unwrap();
 
+        let stats = Arc::new(Stats::default());
+
         let config_builder = ClientConfig::builder()
             .client_socket(local_socket)
             .local_addr((Ipv4Addr::UNSPECIFIED, 0).into())
             .server_addr(settings.quic_endpoint)
             .server_host(settings.hostname.clone())
             .target_addr(settings.wireguard_endpoint)
-            .auth_header(Some(AUTH_HEADER.to_owned()));
+            .auth_header(Some(AUTH_HEADER.to_owned()))
+            .stats(stats.clone());
 
         #[cfg(target_os = "linux")]
         let config_builder = config_builder.fwmark(settings.fwmark);
@@ -70,6 +76,7 @@ impl Quic {
         Ok(Quic {
             local_endpoint,
             task,
+            stats,
         })
     }
 }
@@ -91,6 +98,10 @@ impl Obfuscator for Quic {
         0 // FIXME
     }
 
+    fn stats(&self) -> Option<Arc<Stats>> {
+        Some(self.stats.clone())
+    }
+
     #[cfg(target_os = "android")]
     fn remote_socket_fd(&self) -> std::os::unix::io::RawFd {
         unimplemented!()